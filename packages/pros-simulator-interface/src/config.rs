@@ -0,0 +1,453 @@
+//! Robot configuration: a description of the hardware that makes up the simulated robot.
+//!
+//! Robot configurations are usually stored as TOML or JSON files:
+//!
+//! ```toml
+//! [[smart_ports]]
+//! port = 1
+//! type = "Motor"
+//! gearset = "Blue"
+//!
+//! [[smart_ports]]
+//! port = 5
+//! type = "Imu"
+//! drift = 0.5
+//!
+//! [[smart_ports]]
+//! port = 6
+//! type = "Rotation"
+//! tracking_wheel = { diameter = 0.07, left = 0.1 }
+//!
+//! [[adi_ports]]
+//! port = "A"
+//! type = "LimitSwitch"
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::MotorGearset;
+
+/// The hardware plugged into the simulated robot's brain.
+///
+/// Ports that aren't listed are empty, so robot code that tries to use them will fail the same
+/// way it would on a real robot.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct RobotConfig {
+    /// Devices plugged into the brain's smart ports.
+    pub smart_ports: Vec<SmartPortConfig>,
+    /// Devices plugged into the brain's built-in three-wire ports.
+    pub adi_ports: Vec<AdiPortConfig>,
+    /// The motors that drive the robot around the field, if any. Without a drivetrain, the robot
+    /// only moves when told to by `SetRobotPose` and `SetRobotVelocity` messages.
+    pub drivetrain: Option<DrivetrainConfig>,
+    /// The outline of the robot, which it uses to collide with the field and the objects on it.
+    pub size: RobotSize,
+    /// Mechanisms that pick up game pieces.
+    pub intakes: Vec<IntakeConfig>,
+}
+
+impl RobotConfig {
+    /// Returns the device plugged into the given smart port (1-21), if any.
+    pub fn smart_device(&self, port: u8) -> Option<&SmartDevice> {
+        self.smart_ports
+            .iter()
+            .find(|config| config.port == port)
+            .map(|config| &config.device)
+    }
+
+    /// The model of a motor plugged into the given smart port, adjusted by the drivetrain's
+    /// deprecated `motor_curve` if the motor is part of the drivetrain.
+    pub fn motor_model(&self, port: u8, model: MotorModel) -> MotorModel {
+        match &self.drivetrain {
+            #[allow(deprecated)]
+            Some(drivetrain) if drivetrain.layout.motors().contains(&port) => {
+                drivetrain.motor_curve.apply(model)
+            }
+            _ => model,
+        }
+    }
+
+    /// Returns the device plugged into the given built-in three-wire port (A-H), if any.
+    pub fn adi_device(&self, port: char) -> Option<&AdiDevice> {
+        find_adi_device(&self.adi_ports, port)
+    }
+}
+
+fn find_adi_device(ports: &[AdiPortConfig], port: char) -> Option<&AdiDevice> {
+    ports
+        .iter()
+        .find(|config| config.port.eq_ignore_ascii_case(&port))
+        .map(|config| &config.device)
+}
+
+/// A device plugged into a smart port.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SmartPortConfig {
+    /// The smart port number, from 1 to 21.
+    pub port: u8,
+    #[serde(flatten)]
+    pub device: SmartDevice,
+}
+
+/// A device plugged into a three-wire port.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AdiPortConfig {
+    /// The three-wire port letter, from A to H.
+    pub port: char,
+    #[serde(flatten)]
+    pub device: AdiDevice,
+}
+
+/// A V5 device that connects to the brain using a smart cable.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum SmartDevice {
+    /// A V5 Smart Motor.
+    Motor {
+        /// The gear cartridge installed in the motor.
+        #[serde(default)]
+        gearset: MotorGearset,
+        /// How the motor behaves under load. Uses the published specifications by default.
+        #[serde(default)]
+        model: MotorModel,
+    },
+    /// A V5 Inertial Sensor.
+    Imu {
+        /// How quickly the sensor's heading drifts away from the robot's real heading, in degrees
+        /// per minute.
+        #[serde(default)]
+        drift: f64,
+        /// The standard deviation of the random noise added to each angle the sensor reports, in
+        /// degrees.
+        #[serde(default)]
+        noise: f64,
+    },
+    /// A V5 Rotation Sensor.
+    Rotation {
+        /// The unpowered tracking wheel the sensor measures, if any. Tracking wheels turn as the
+        /// robot moves across the field.
+        #[serde(default)]
+        tracking_wheel: Option<TrackingWheel>,
+    },
+    /// A V5 Distance Sensor.
+    Distance {
+        /// Where the sensor is mounted on the robot. If this is set, the sensor measures the
+        /// distance to the field perimeter in the direction it's facing.
+        #[serde(default)]
+        mount: Option<SensorMount>,
+    },
+    /// A V5 Optical Sensor.
+    Optical,
+    /// A V5 Vision Sensor.
+    Vision,
+    /// A V5 GPS Sensor.
+    Gps {
+        /// Where the sensor is mounted on the robot. The robot code still has to tell the sensor
+        /// its offset from the center of turning with `gps_set_offset`, like on a real robot.
+        #[serde(default)]
+        mount: SensorMount,
+        /// The RMS error of the position the sensor reports, in meters.
+        #[serde(default)]
+        error: f64,
+    },
+    /// A V5 Robot Radio.
+    Radio,
+    /// A V5 Three-Wire Expander, which adds 8 more three-wire ports.
+    AdiExpander {
+        /// Devices plugged into the expander.
+        #[serde(default)]
+        adi_ports: Vec<AdiPortConfig>,
+    },
+}
+
+impl SmartDevice {
+    /// If this device is a three-wire expander, returns the device plugged into the given port
+    /// (A-H) on it.
+    pub fn adi_device(&self, port: char) -> Option<&AdiDevice> {
+        match self {
+            SmartDevice::AdiExpander { adi_ports } => find_adi_device(adi_ports, port),
+            _ => None,
+        }
+    }
+}
+
+/// Where a sensor is mounted on the robot, relative to the robot's center of rotation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct SensorMount {
+    /// How far in front of the center the sensor is, in meters.
+    #[serde(default)]
+    pub forward: f64,
+    /// How far to the left of the center the sensor is, in meters.
+    #[serde(default)]
+    pub left: f64,
+    /// The direction the sensor faces, in degrees clockwise from the front of the robot.
+    #[serde(default)]
+    pub heading: f64,
+}
+
+/// An unpowered wheel that rolls along the field as the robot moves.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct TrackingWheel {
+    /// The wheel's diameter, in meters.
+    pub diameter: f64,
+    /// Where the wheel touches the field. `heading` is the direction the wheel has to roll in
+    /// to make the sensor's position increase (for example, 0 for a wheel that measures forward
+    /// movement).
+    #[serde(flatten)]
+    pub mount: SensorMount,
+}
+
+/// A set of powered wheels that move the robot around the field.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DrivetrainConfig {
+    /// How the wheels are arranged, and which motors turn them.
+    #[serde(flatten)]
+    pub layout: DrivetrainLayout,
+    /// The diameter of the wheels, in meters.
+    #[serde(default = "DrivetrainConfig::default_wheel_diameter")]
+    pub wheel_diameter: f64,
+    /// The distance between the left and right wheels, in meters.
+    #[serde(default = "DrivetrainConfig::default_track_width")]
+    pub track_width: f64,
+    /// The distance between the front and back wheels, in meters. Only used by X-drives and
+    /// mecanum drives.
+    #[serde(default = "DrivetrainConfig::default_wheelbase")]
+    pub wheelbase: f64,
+    /// The number of times the wheels turn for each turn of the motors' output shafts.
+    #[serde(default = "DrivetrainConfig::default_gear_ratio")]
+    pub gear_ratio: f64,
+    /// The mass of the robot, in kilograms.
+    #[serde(default = "DrivetrainConfig::default_mass")]
+    pub mass: f64,
+    /// How much torque the drive motors produce. This scales the stall torque of each drive
+    /// motor's [`MotorModel`], so the default leaves them unchanged.
+    #[deprecated(note = "set the `model` of each drive motor in `smart_ports` instead")]
+    #[serde(default)]
+    pub motor_curve: MotorCurve,
+}
+
+impl DrivetrainConfig {
+    /// 4 inch omni wheels.
+    fn default_wheel_diameter() -> f64 {
+        0.1016
+    }
+
+    fn default_track_width() -> f64 {
+        0.3
+    }
+
+    fn default_wheelbase() -> f64 {
+        0.3
+    }
+
+    fn default_gear_ratio() -> f64 {
+        1.0
+    }
+
+    fn default_mass() -> f64 {
+        6.0
+    }
+}
+
+/// How a drivetrain's wheels are arranged. Each wheel is turned by one or more motors, listed by
+/// their smart port numbers. Motors should be reversed in the robot code so that positive
+/// voltages drive their wheels forwards.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum DrivetrainLayout {
+    /// Wheels on the left and right sides of the robot that all point forwards.
+    Tank { left: Vec<u8>, right: Vec<u8> },
+    /// Omni wheels in each corner of the robot, angled at 45 degrees.
+    XDrive {
+        front_left: Vec<u8>,
+        front_right: Vec<u8>,
+        back_left: Vec<u8>,
+        back_right: Vec<u8>,
+    },
+    /// Mecanum wheels in each corner of the robot, which point forwards but can push the robot
+    /// sideways.
+    Mecanum {
+        front_left: Vec<u8>,
+        front_right: Vec<u8>,
+        back_left: Vec<u8>,
+        back_right: Vec<u8>,
+    },
+}
+
+impl DrivetrainLayout {
+    /// The smart ports of every motor in the drivetrain.
+    pub fn motors(&self) -> Vec<u8> {
+        match self {
+            Self::Tank { left, right } => [left.as_slice(), right].concat(),
+            Self::XDrive {
+                front_left,
+                front_right,
+                back_left,
+                back_right,
+            }
+            | Self::Mecanum {
+                front_left,
+                front_right,
+                back_left,
+                back_right,
+            } => [front_left.as_slice(), front_right, back_left, back_right].concat(),
+        }
+    }
+}
+
+/// The physical properties of a V5 Smart Motor, which is simulated as a DC motor with a current
+/// limit. Torques are measured at the output shaft of a motor with the 100 RPM (red) gearset;
+/// faster gearsets trade torque for speed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct MotorModel {
+    /// The torque produced when the motor is stalled at 12 volts without a current limit, in
+    /// newton meters.
+    pub stall_torque: f64,
+    /// The current drawn when the motor is stalled at 12 volts without a current limit, in amps.
+    pub stall_current: f64,
+    /// The current needed to overcome the motor's internal friction, in amps.
+    pub free_current: f64,
+    /// The moment of inertia of whatever the motor turns, at its output shaft, in kilogram square
+    /// meters. Ignored for drivetrain motors, whose load is the robot itself.
+    pub inertia: f64,
+    /// Proportional gain of the motor's internal velocity controller, in RPM of extra output per
+    /// RPM of error.
+    pub velocity_kp: f64,
+    /// Integral gain of the motor's internal velocity controller, in RPM of extra output per RPM
+    /// second of accumulated error.
+    pub velocity_ki: f64,
+    /// How many degrees Celsius the motor heats up above room temperature for each watt it
+    /// loses as heat, once it has settled.
+    pub thermal_resistance: f64,
+    /// The energy needed to heat the motor by one degree Celsius, in joules.
+    pub heat_capacity: f64,
+}
+
+impl Default for MotorModel {
+    /// The published 11W V5 Smart Motor curve: 2.1 newton meters at the 2.5 amp current limit.
+    fn default() -> Self {
+        Self {
+            stall_torque: 4.2,
+            stall_current: 5.0,
+            free_current: 0.1,
+            inertia: 0.005,
+            velocity_kp: 2.0,
+            velocity_ki: 20.0,
+            thermal_resistance: 5.0,
+            heat_capacity: 25.0,
+        }
+    }
+}
+
+/// A motor's torque-speed curve, from before motors had a [`MotorModel`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct MotorCurve {
+    /// The torque at the output shaft of a motor with the 100 RPM (red) gearset when it's
+    /// stalled at full voltage, in newton meters. Faster gearsets trade torque for speed.
+    #[serde(default = "MotorCurve::default_stall_torque")]
+    pub stall_torque: f64,
+}
+
+impl MotorCurve {
+    /// The stall torque of a V5 Smart Motor.
+    fn default_stall_torque() -> f64 {
+        2.1
+    }
+
+    /// Scales a motor model's torque so that it stalls with this curve's stall torque instead of
+    /// the default one.
+    pub fn apply(&self, model: MotorModel) -> MotorModel {
+        MotorModel {
+            stall_torque: model.stall_torque * self.stall_torque / Self::default_stall_torque(),
+            ..model
+        }
+    }
+}
+
+impl Default for MotorCurve {
+    fn default() -> Self {
+        Self {
+            stall_torque: Self::default_stall_torque(),
+        }
+    }
+}
+
+/// The size of the robot's rectangular outline, centered on its center of rotation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RobotSize {
+    /// The size of the robot from side to side, in meters.
+    pub width: f64,
+    /// The size of the robot from front to back, in meters.
+    pub length: f64,
+}
+
+impl Default for RobotSize {
+    /// The largest robot allowed at the start of a match: 18 inches by 18 inches.
+    fn default() -> Self {
+        Self {
+            width: 0.4572,
+            length: 0.4572,
+        }
+    }
+}
+
+/// A mechanism that picks up game pieces when its motors spin forwards, and spits them back out
+/// when they spin backwards.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IntakeConfig {
+    /// The smart ports of the motors that run the intake. Motors should be reversed in the robot
+    /// code so that positive voltages pull game pieces in.
+    pub motors: Vec<u8>,
+    /// The middle of the intake's opening, facing the way game pieces go in.
+    #[serde(default)]
+    pub mount: SensorMount,
+    /// The width of the intake's opening, in meters.
+    #[serde(default = "IntakeConfig::default_width")]
+    pub width: f64,
+    /// How many game pieces the intake can hold at once.
+    #[serde(default = "IntakeConfig::default_capacity")]
+    pub capacity: usize,
+}
+
+impl IntakeConfig {
+    fn default_width() -> f64 {
+        0.3
+    }
+
+    fn default_capacity() -> usize {
+        1
+    }
+}
+
+/// A legacy sensor or actuator that connects to a three-wire port.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum AdiDevice {
+    /// A digital sensor, like a limit switch or bumper switch.
+    #[serde(alias = "LimitSwitch", alias = "Bumper")]
+    DigitalIn,
+    /// A digital output, like a pneumatic solenoid or an LED.
+    #[serde(alias = "Solenoid", alias = "Led")]
+    DigitalOut,
+    /// An analog sensor, like a potentiometer or light sensor.
+    #[serde(alias = "Potentiometer", alias = "LightSensor")]
+    AnalogIn,
+    /// A line tracker, which is an analog sensor that reads lower values over lighter surfaces.
+    LineTracker {
+        /// Where the sensor is mounted on the robot, facing down at the field. Mounted line
+        /// trackers see the tape lines on the field; otherwise they read values sent by the
+        /// simulator client like other analog sensors.
+        #[serde(default)]
+        mount: Option<SensorMount>,
+    },
+    /// An analog output.
+    AnalogOut,
+    /// A quadrature encoder. The encoder's top wire goes in this port, and its bottom wire goes
+    /// in the next one.
+    Encoder,
+    /// An ultrasonic rangefinder. The ping (output) wire goes in this port, and the echo (input)
+    /// wire goes in the next one.
+    Ultrasonic,
+}
@@ -1,9 +1,28 @@
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 
+pub mod config;
+pub mod world;
+
 pub const LCD_HEIGHT: u32 = 8;
 pub const LCD_WIDTH: u32 = 40;
 pub type LcdLines = [String; LCD_HEIGHT as usize];
 
+pub const SCREEN_WIDTH: u32 = 480;
+pub const SCREEN_HEIGHT: u32 = 272;
+
+pub const CONTROLLER_SCREEN_HEIGHT: u32 = 3;
+pub const CONTROLLER_SCREEN_WIDTH: u32 = 19;
+pub type ControllerScreenLines = [String; CONTROLLER_SCREEN_HEIGHT as usize];
+
+/// Which of the two controllers connected to the robot something refers to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ControllerId {
+    Master,
+    Partner,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct DigitalControllerState {
     pub l1: bool,
@@ -41,9 +60,207 @@ pub struct CompetitionPhase {
     pub is_competition: bool,
 }
 
+/// The internal gear cartridge installed in a V5 Smart Motor.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MotorGearset {
+    /// 36:1, 100 RPM
+    Red,
+    /// 18:1, 200 RPM
+    #[default]
+    Green,
+    /// 6:1, 600 RPM
+    Blue,
+}
+
+impl MotorGearset {
+    /// The maximum speed of the motor's output shaft in RPM.
+    pub fn max_rpm(&self) -> u32 {
+        match self {
+            MotorGearset::Red => 100,
+            MotorGearset::Green => 200,
+            MotorGearset::Blue => 600,
+        }
+    }
+}
+
+/// What a V5 Smart Motor does when it is told to stop.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MotorBrakeMode {
+    /// Stop driving the motor and let it spin freely.
+    #[default]
+    Coast,
+    /// Short the motor's windings to slow it down.
+    Brake,
+    /// Actively hold the motor at its current position.
+    Hold,
+}
+
+/// The output most recently requested from a V5 Smart Motor by the robot code.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum MotorCommand {
+    /// Stop the motor using the provided brake mode.
+    Brake(MotorBrakeMode),
+    /// Apply a fixed voltage to the motor, in millivolts (-12000 to 12000).
+    Voltage(i32),
+    /// Spin the motor at the given velocity, in RPM at the output shaft.
+    Velocity(i32),
+    /// Move the motor to an absolute position, in degrees at the output shaft, without exceeding
+    /// the given velocity (RPM).
+    Position { target: f64, velocity: i32 },
+}
+
+/// How fast simulated time passes compared to real time.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SimulationSpeed {
+    /// Simulated time passes this many times faster than real time. For example, `2.0` runs the
+    /// robot code twice as fast as a real brain would, and `0.5` runs it in slow motion.
+    Factor(f64),
+    /// Simulated time passes as fast as the robot code can be run.
+    Max,
+}
+
+impl SimulationSpeed {
+    /// Returns whether this speed can be used by the simulator. Factors must be positive and
+    /// finite.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            SimulationSpeed::Factor(factor) => factor.is_finite() && *factor > 0.0,
+            SimulationSpeed::Max => true,
+        }
+    }
+}
+
+impl Default for SimulationSpeed {
+    /// Real time.
+    fn default() -> Self {
+        SimulationSpeed::Factor(1.0)
+    }
+}
+
+impl FromStr for SimulationSpeed {
+    type Err = String;
+
+    /// Parses either `max` or a speed factor like `2` or `0.5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("max") {
+            return Ok(SimulationSpeed::Max);
+        }
+        let speed = s
+            .parse()
+            .map(SimulationSpeed::Factor)
+            .map_err(|_| format!("invalid speed `{s}`: expected a number or `max`"))?;
+        if !speed.is_valid() {
+            return Err(format!("invalid speed `{s}`: must be greater than zero"));
+        }
+        Ok(speed)
+    }
+}
+
+/// The position and orientation of the simulated robot on the field.
+///
+/// The origin is the center of the field, and the y axis points away from the red alliance
+/// station (the same coordinate system used by the V5 GPS Sensor).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct RobotPose {
+    /// Distance from the center of the field, in meters.
+    pub x: f64,
+    /// Distance from the center of the field, in meters.
+    pub y: f64,
+    /// The direction the robot is facing, in degrees clockwise from the positive y axis. This
+    /// value isn't wrapped, so a robot that has spun around twice has a heading of 720.
+    pub heading: f64,
+}
+
+/// How fast the simulated robot is moving across the field.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct RobotVelocity {
+    /// Velocity along the field's x axis, in meters per second.
+    pub x: f64,
+    /// Velocity along the field's y axis, in meters per second.
+    pub y: f64,
+    /// Rate of turning, in degrees per second clockwise.
+    pub angular: f64,
+}
+
+/// What a distance sensor sees in front of it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct DistanceReading {
+    /// Distance to the object, in millimeters. Use 9999 if there's nothing in range.
+    pub distance: u32,
+    /// How confident the sensor is in the reading, from 0 to 63.
+    #[serde(default = "DistanceReading::default_confidence")]
+    pub confidence: u32,
+    /// A guess at the relative size of the object, from 0 to 400. An 18" x 30" grey card reads
+    /// as about 75.
+    #[serde(default)]
+    pub object_size: u32,
+    /// How fast the distance to the object is changing, in meters per second.
+    #[serde(default)]
+    pub object_velocity: f64,
+}
+
+impl DistanceReading {
+    fn default_confidence() -> u32 {
+        63
+    }
+}
+
+/// What an optical sensor sees in front of it.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct OpticalReading {
+    /// Hue of the detected color, in degrees from 0 to 360.
+    pub hue: f64,
+    /// Saturation of the detected color, from 0 to 1.
+    pub saturation: f64,
+    /// Brightness of the detected color, from 0 to 1.
+    pub brightness: f64,
+    /// How close the object is, from 0 (far away) to 255 (touching the sensor).
+    #[serde(default)]
+    pub proximity: u8,
+}
+
+/// A hand gesture that an optical sensor can detect.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum OpticalGesture {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// The kind of object a vision sensor has detected.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VisionObjectType {
+    /// An object matching a single signature.
+    #[default]
+    Normal,
+    /// An object matching a color code (a combination of signatures).
+    ColorCode,
+    Line,
+}
+
+/// An object detected by a vision sensor.
+///
+/// Coordinates are in pixels, measured from the top left corner of the sensor's 316x212 field of
+/// view.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct VisionObject {
+    /// The signature ID (1-7) the object matches, or the color code for color code objects.
+    pub signature: u16,
+    #[serde(default, rename = "type")]
+    pub object_type: VisionObjectType,
+    pub left: i16,
+    pub top: i16,
+    pub width: i16,
+    pub height: i16,
+    /// The angle of color code objects, in tenths of a degree.
+    #[serde(default)]
+    pub angle: i16,
+}
+
 /// An event that happens inside the simulator that the API consumer might want to know about.
 /// Use this to monitor robot code progress, simulated LCD updates, log messages, and more.
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum SimulatorEvent {
     /// A warning message has been emitted by the simulator backend. The robot code is likely using the PROS API incorrectly.
     Warning(String),
@@ -58,21 +275,97 @@ pub enum SimulatorEvent {
     RobotCodeFinished,
     /// The robot code has panicked or otherwise faulted.
     RobotCodeError { message: String, backtrace: String },
+    /// The simulator has been paused, either because it was asked to or because it finished a
+    /// step. `time` is the simulated time in milliseconds at which it stopped.
+    Paused { time: u32 },
 
     /// The LCD has been initialized and may be updated in the future.
     LcdInitialized,
     /// The LCD has been updated and should be redrawn.
     LcdUpdated(LcdLines),
-    /// The robot code has requested that the LCD color change to the provided foreground/background (RGBA).
+    /// The robot code has requested that the LCD color change to the provided foreground/background, in the `0xAARRGGBB` format used by the PROS `COLOR_*` constants.
     LcdColorsUpdated { foreground: u32, background: u32 },
     /// The LCD has shut down and should be blanked.
     LcdShutdown,
+
+    /// The robot code has drawn on the brain's screen. Each region is a rectangle of pixels that
+    /// changed since the last update. Sent at most once per simulator tick (every 2 simulated
+    /// milliseconds).
+    ScreenUpdated { regions: Vec<ScreenRegion> },
+
+    /// The robot code has changed the text on a controller's screen, which should be redrawn.
+    /// Each line is padded with spaces to the width of the screen.
+    ControllerScreenUpdated {
+        controller: ControllerId,
+        lines: ControllerScreenLines,
+    },
+    /// The robot code has asked a controller to rumble. The pattern is made of `.` for short
+    /// rumbles, `-` for long rumbles and ` ` for pauses.
+    ControllerRumble {
+        controller: ControllerId,
+        pattern: String,
+    },
+
+    /// The robot code has changed the output of the motor on the given smart port (1-21).
+    /// Command values describe the motor's physical direction, with its reversed flag already
+    /// applied.
+    MotorCommanded { port: u8, command: MotorCommand },
+
+    /// The robot code has changed the output of a three-wire port, like a solenoid, LED or legacy
+    /// motor. `port` is the port letter (A-H), and `expander` is the smart port (1-21) of the
+    /// three-wire expander it belongs to, or `None` for the brain's built-in ports.
+    ///
+    /// Digital outputs are 0 or 1, analog outputs range from 0 to 4095, and legacy motors and
+    /// servos range from -127 to 127.
+    AdiOutput {
+        port: char,
+        expander: Option<u8>,
+        value: i32,
+    },
+
+    /// The robot has moved. `x` and `y` are in meters from the center of the field, and
+    /// `heading` is in degrees clockwise from the positive y axis. Sent at most every 20
+    /// simulated milliseconds while the robot is moving.
+    RobotPose { x: f64, y: f64, heading: f64 },
+
+    /// A snapshot of the robot and the game pieces on the field, sent after every physics tick
+    /// (every 2 simulated milliseconds) in which something moved. Game pieces are listed in the
+    /// same order as in the world description.
+    WorldState {
+        robot: RobotPose,
+        game_pieces: Vec<GamePieceState>,
+    },
+}
+
+/// A rectangle of pixels on the brain's screen, as sent in [`SimulatorEvent::ScreenUpdated`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ScreenRegion {
+    /// Distance of the left edge from the left of the screen, in pixels.
+    pub x: u32,
+    /// Distance of the top edge from the top of the screen, in pixels.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// The color of each pixel in `0x00RRGGBB` format, row by row from the top left corner.
+    pub pixels: Vec<u32>,
+}
+
+/// Where a game piece is, as reported in [`SimulatorEvent::WorldState`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct GamePieceState {
+    /// Distance from the center of the field, in meters.
+    pub x: f64,
+    /// Distance from the center of the field, in meters.
+    pub y: f64,
+    /// Whether the game piece is being held by one of the robot's intakes. Held game pieces move
+    /// with the robot.
+    pub held: bool,
 }
 
 /// A message sent to the simulator to control the robot code environment.
 /// The `pros-simulator` API accepts these over an async stream, and API consumers can use
 /// them to simulate changes in robot hardware (like controller input and LCD touch events).
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum SimulatorMessage {
     /// Master and Partner controllers have updated (in that order). None = disconnected.
     ControllerUpdate(Option<ControllerState>, Option<ControllerState>),
@@ -83,4 +376,74 @@ pub enum SimulatorMessage {
     LcdButtonsUpdate([bool; 3]), // {"LcdButtonsUpdate": [true, false, false]}
     /// The robot has switched competition modes (opcontrol or autonomous or disabled).
     PhaseChange(CompetitionPhase),
+    /// Change how fast simulated time passes.
+    SetSpeed(SimulationSpeed), // {"SetSpeed": {"Factor": 2.0}} or {"SetSpeed": "Max"}
+    /// Stop running robot code and freeze the simulated clock. The simulator will respond with a
+    /// `Paused` event.
+    Pause,
+    /// Continue running robot code after a `Pause` or `Step`.
+    Resume,
+    /// Run the simulation for exactly this many milliseconds of simulated time, then pause again.
+    /// The simulator will respond with a `Paused` event once the step is finished.
+    Step { millis: u32 }, // {"Step": {"millis": 100}}
+    /// Stop running robot code and end the simulation. The simulator will respond with a
+    /// `RobotCodeFinished` event.
+    Stop,
+    /// Move the robot to a new position on the field. Sensors like the inertial sensor will see
+    /// the robot jump there instantly.
+    SetRobotPose(RobotPose), // {"SetRobotPose": {"x": 0.0, "y": 0.0, "heading": 90.0}}
+    /// A three-wire sensor has a new reading. `port` is the port letter (A-H), and `expander` is
+    /// the smart port (1-21) of the three-wire expander it belongs to, if any.
+    ///
+    /// Digital inputs are 0 or 1, analog inputs range from 0 to 4095, encoders take the total
+    /// number of ticks counted (on the encoder's top port), and ultrasonic sensors take the
+    /// distance to the nearest object in tenths of a millimeter (on the ping port).
+    AdiInput {
+        port: char,
+        #[serde(default)]
+        expander: Option<u8>,
+        value: i32,
+    }, // {"AdiInput": {"port": "A", "value": 1}}
+    /// Make the robot move across the field at a constant velocity until told otherwise.
+    SetRobotVelocity(RobotVelocity), // {"SetRobotVelocity": {"x": 0.0, "y": 0.5, "angular": 0.0}}
+    /// Turn the shaft of a rotation sensor. `position` is the total number of degrees the shaft
+    /// has turned, and the shaft keeps turning at `velocity` degrees per second until the next
+    /// `RotationInput`. If the sensor is measuring a tracking wheel, the wheel's movement is added
+    /// on top of this.
+    RotationInput {
+        port: u8,
+        position: f64,
+        #[serde(default)]
+        velocity: f64,
+    }, // {"RotationInput": {"port": 1, "position": 90.0}}
+    /// A distance sensor has a new reading, which replaces the reading it would take
+    /// automatically from the robot's position on the field. Send `null` to go back to automatic
+    /// readings.
+    DistanceInput {
+        port: u8,
+        reading: Option<DistanceReading>,
+    }, // {"DistanceInput": {"port": 1, "reading": {"distance": 500}}}
+    /// An optical sensor sees a new color.
+    OpticalInput { port: u8, reading: OpticalReading }, // {"OpticalInput": {"port": 1, "reading": {"hue": 120.0, "saturation": 1.0, "brightness": 0.5}}}
+    /// An optical sensor detects a hand gesture. Gestures are ignored unless the robot code has
+    /// enabled gesture detection.
+    OpticalGesture { port: u8, gesture: OpticalGesture }, // {"OpticalGesture": {"port": 1, "gesture": "Up"}}
+    /// Replace the list of objects a vision sensor can see.
+    VisionObjects {
+        port: u8,
+        objects: Vec<VisionObject>,
+    }, // {"VisionObjects": {"port": 1, "objects": [{"signature": 1, "left": 10, "top": 20, "width": 30, "height": 40}]}}
+    /// The brain's touch screen is being touched at the given position in pixels, or has been
+    /// released. Moving a touch while it's pressed counts as holding it.
+    ScreenTouch { x: i16, y: i16, pressed: bool }, // {"ScreenTouch": {"x": 240, "y": 136, "pressed": true}}
+    /// Change the state of the robot's battery, for testing how the robot code handles a low
+    /// battery. `capacity` is the remaining charge as a percentage and `temperature` is in
+    /// degrees Celsius. Anything left out keeps its current value. The battery's voltage follows
+    /// from its charge and the current the motors draw.
+    SetBattery {
+        #[serde(default)]
+        capacity: Option<f64>,
+        #[serde(default)]
+        temperature: Option<f64>,
+    }, // {"SetBattery": {"capacity": 10.0}}
 }
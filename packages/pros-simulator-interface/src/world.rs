@@ -0,0 +1,115 @@
+//! World description: the objects on the field that the simulated robot can run into.
+//!
+//! Worlds are usually stored as TOML or JSON files. Positions are in meters from the center of
+//! the field, and headings are in degrees clockwise from the positive y axis:
+//!
+//! ```toml
+//! [[obstacles]]
+//! x = 0.0
+//! y = 0.0
+//! shape = "Rectangle"
+//! width = 0.6
+//! length = 0.6
+//! heading = 45.0
+//!
+//! [[game_pieces]]
+//! kind = "Ball"
+//! x = 0.6
+//! y = -0.6
+//! radius = 0.08
+//!
+//! [[lines]]
+//! start = [-1.8288, 0.0]
+//! end = [1.8288, 0.0]
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// Everything on the field besides the robot. The field perimeter is always present, so the
+/// default world is an empty field.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct World {
+    /// Fixed objects that can't be moved, like goals and barriers.
+    pub obstacles: Vec<Obstacle>,
+    /// Objects that the robot can push around and pick up with its intakes.
+    pub game_pieces: Vec<GamePiece>,
+    /// Tape lines on the field tiles, which line trackers can see.
+    pub lines: Vec<FieldLine>,
+}
+
+/// The outline of an object on the field.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "shape")]
+pub enum Shape {
+    Circle {
+        /// The radius of the circle, in meters.
+        radius: f64,
+    },
+    Rectangle {
+        /// The size of the rectangle from side to side, in meters.
+        width: f64,
+        /// The size of the rectangle from front to back, in meters.
+        length: f64,
+    },
+}
+
+/// A fixed object on the field.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Obstacle {
+    pub x: f64,
+    pub y: f64,
+    /// The direction the front of the obstacle faces. Only matters for rectangles.
+    #[serde(default)]
+    pub heading: f64,
+    #[serde(flatten)]
+    pub shape: Shape,
+}
+
+/// A round object that can be pushed around the field, like a ball or a disc.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GamePiece {
+    /// A name for the kind of game piece, for telling different kinds apart.
+    #[serde(default)]
+    pub kind: String,
+    pub x: f64,
+    pub y: f64,
+    /// The radius of the game piece, in meters.
+    pub radius: f64,
+    /// The mass of the game piece, in kilograms.
+    #[serde(default = "GamePiece::default_mass")]
+    pub mass: f64,
+    /// How quickly the game piece slows down as it slides or rolls across the tiles, as a
+    /// coefficient of friction.
+    #[serde(default = "GamePiece::default_friction")]
+    pub friction: f64,
+}
+
+impl GamePiece {
+    fn default_mass() -> f64 {
+        0.1
+    }
+
+    fn default_friction() -> f64 {
+        0.3
+    }
+}
+
+/// A straight line of tape on the field.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct FieldLine {
+    /// One end of the line, as x and y coordinates in meters.
+    pub start: (f64, f64),
+    /// The other end of the line, as x and y coordinates in meters.
+    pub end: (f64, f64),
+    /// The width of the tape, in meters.
+    #[serde(default = "FieldLine::default_width")]
+    pub width: f64,
+}
+
+impl FieldLine {
+    /// 2 inch tape.
+    fn default_width() -> f64 {
+        0.0508
+    }
+}
@@ -1,13 +1,23 @@
 use std::{
-    io::{stdin, stdout, BufReader},
-    path::PathBuf,
+    error::Error,
+    fs,
+    io::{stdin, stdout, BufRead, BufReader},
+    path::{Path, PathBuf},
     process::exit,
     sync::mpsc,
 };
 
-use clap::Parser;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::{Parser, ValueEnum};
 use jsonl::{read, write, ReadError};
-use pros_simulator_interface::SimulatorMessage;
+use pros_simulator::{
+    snapshot::{Display, Snapshots},
+    RobotCode, Simulator,
+};
+use pros_simulator_interface::{
+    config::RobotConfig, world::World, SimulationSpeed, SimulatorEvent, SimulatorMessage,
+};
+use serde::de::DeserializeOwned;
 
 /// Simulate a VEX V5 robot using the PROS API interface.
 #[derive(Parser, Debug)]
@@ -17,39 +27,190 @@ struct Args {
     #[clap(long)]
     stdio: bool,
 
-    /// The robot code to simulate (WASM file).
+    /// A TOML or JSON file describing the devices plugged into the simulated robot.
+    #[clap(long)]
+    robot_config: Option<PathBuf>,
+
+    /// A TOML or JSON file describing the obstacles, game pieces and tape lines on the field.
+    #[clap(long)]
+    world: Option<PathBuf>,
+
+    /// How fast simulated time passes: a multiple of real time (like `2` or `0.5`), or `max` to
+    /// run as fast as possible.
+    #[clap(long, value_name = "FACTOR|max", default_value = "1")]
+    speed: SimulationSpeed,
+
+    /// A directory to cache compiled robot code in, so that it only needs to be compiled once.
+    /// Only use a directory that you trust: cached code is run without being checked.
+    #[clap(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// A directory to save a PNG screenshot of the LCD or brain screen to every time robot code
+    /// changes what it displays.
+    #[clap(long)]
+    screenshot_dir: Option<PathBuf>,
+
+    /// How robot code is encoded when it is read from stdin.
+    #[clap(long, value_enum, default_value_t = StdinFormat::Base64)]
+    stdin_format: StdinFormat,
+
+    /// The robot code to simulate (WASM file), or `-` to read it from stdin before any messages.
     robot_code: PathBuf,
 }
 
+/// An encoding for sending robot code over stdin.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum StdinFormat {
+    /// A single line of base64 text.
+    Base64,
+    /// A 32-bit little-endian byte count, followed by that many bytes.
+    LengthPrefixed,
+}
+
+/// Reads robot code sent over stdin, leaving the rest of the input (the message stream) unread.
+fn read_robot_code(reader: &mut impl BufRead, format: StdinFormat) -> Result<Vec<u8>, String> {
+    match format {
+        StdinFormat::Base64 => {
+            let mut line = String::new();
+            reader.read_line(&mut line).map_err(|err| err.to_string())?;
+            STANDARD
+                .decode(line.trim_end())
+                .map_err(|err| err.to_string())
+        }
+        StdinFormat::LengthPrefixed => {
+            let mut len = [0; 4];
+            reader.read_exact(&mut len).map_err(|err| err.to_string())?;
+            let mut wasm = vec![0; u32::from_le_bytes(len) as usize];
+            reader
+                .read_exact(&mut wasm)
+                .map_err(|err| err.to_string())?;
+            Ok(wasm)
+        }
+    }
+}
+
+/// Loads a robot configuration or world file, choosing a format based on its extension.
+fn load_config<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&contents).map_err(|err| err.to_string())
+    } else {
+        toml::from_str(&contents).map_err(|err| err.to_string())
+    }
+}
+
+/// Saves numbered screenshots of the robot's displays as the events that change them are sent.
+struct Screenshots {
+    dir: PathBuf,
+    snapshots: Snapshots,
+    lcd_count: u32,
+    screen_count: u32,
+}
+
+impl Screenshots {
+    fn new(dir: PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+        Ok(Self {
+            dir,
+            snapshots: Snapshots::default(),
+            lcd_count: 0,
+            screen_count: 0,
+        })
+    }
+
+    fn update(&mut self, event: &SimulatorEvent) -> Result<(), String> {
+        let Some(display) = self.snapshots.update(event) else {
+            return Ok(());
+        };
+        let (name, count) = match display {
+            Display::Lcd => ("lcd", &mut self.lcd_count),
+            Display::Screen => ("screen", &mut self.screen_count),
+        };
+        *count += 1;
+        let path = self.dir.join(format!("{name}-{count:05}.png"));
+        fs::write(&path, self.snapshots.render(display))
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
     let args = Args::parse();
 
+    let robot_config = match &args.robot_config {
+        Some(path) => load_config(path).unwrap_or_else(|err| {
+            eprintln!("Error loading robot config: {}", err);
+            exit(1);
+        }),
+        None => RobotConfig::default(),
+    };
+    let world = args.world.as_deref().map(|path| {
+        load_config::<World>(path).unwrap_or_else(|err| {
+            eprintln!("Error loading world: {}", err);
+            exit(1);
+        })
+    });
+
+    let mut screenshots = args.screenshot_dir.clone().map(|dir| {
+        Screenshots::new(dir).unwrap_or_else(|err| {
+            eprintln!("Error creating screenshot directory: {}", err);
+            exit(1);
+        })
+    });
+
+    let mut reader = BufReader::new(stdin());
+    let robot_code = if args.robot_code == Path::new("-") {
+        let wasm = read_robot_code(&mut reader, args.stdin_format).unwrap_or_else(|err| {
+            eprintln!("Error reading robot code from stdin: {}", err);
+            exit(1);
+        });
+        RobotCode::Bytes(wasm)
+    } else {
+        RobotCode::Path(args.robot_code)
+    };
+
     if args.stdio {
         let (tx, rx) = mpsc::channel::<SimulatorMessage>();
-        tokio::task::spawn_blocking(move || {
-            let mut reader = BufReader::new(stdin().lock());
-            loop {
-                let event = read(&mut reader);
-                match event {
-                    Ok(message) => _ = tx.send(message),
-                    Err(ReadError::Eof) => break,
-                    Err(err) => {
-                        eprintln!("Error reading from stdio: {}", err);
-                        exit(1);
-                    }
+        tokio::task::spawn_blocking(move || loop {
+            let event = read(&mut reader);
+            match event {
+                Ok(message) => _ = tx.send(message),
+                Err(ReadError::Eof) => break,
+                Err(err) => {
+                    eprintln!("Error reading from stdio: {}", err);
+                    exit(1);
                 }
             }
         });
-        pros_simulator::simulate(
-            &args.robot_code,
-            move |event| {
+        let mut simulator = Simulator::builder()
+            .robot_code(robot_code)
+            .robot_config(robot_config)
+            .speed(args.speed)
+            .interface(move |event: SimulatorEvent| {
+                if let Some(screenshots) = &mut screenshots {
+                    if let Err(err) = screenshots.update(&event) {
+                        eprintln!("Error saving screenshot: {}", err);
+                    }
+                }
                 write(stdout().lock(), &event).unwrap();
-            },
-            rx,
-        )
-        .await
-        .unwrap();
+            })
+            .messages(rx);
+        if let Some(world) = world {
+            simulator = simulator.world(world);
+        }
+        if let Some(cache_dir) = &args.cache_dir {
+            simulator = simulator.cache_dir(cache_dir);
+        }
+        let result = simulator.start().await;
+        if let Err(err) = result {
+            eprintln!("Error: {}", err);
+            let mut source = err.source();
+            while let Some(cause) = source {
+                eprintln!("Caused by: {}", cause);
+                source = cause.source();
+            }
+            exit(1);
+        }
     } else {
         panic!("No connection method: append the --stdio flag to use stdin/stdout.")
     }
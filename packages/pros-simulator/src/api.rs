@@ -2,10 +2,19 @@ use wasmtime::{Linker, SharedMemory, Store};
 
 use crate::host::Host;
 
+mod adi;
+mod distance;
 mod generic_io;
+mod gps;
+mod imu;
 mod llemu;
 mod misc;
+mod motors;
+mod optical;
+mod rotation;
 mod rtos_facilities;
+mod screen;
+mod vision;
 
 pub fn configure_api(
     linker: &mut Linker<Host>,
@@ -14,9 +23,18 @@ pub fn configure_api(
 ) -> anyhow::Result<()> {
     linker.define(&mut *store, "env", "memory", shared_memory.clone())?;
 
+    adi::configure_adi_api(&mut *linker)?;
+    distance::configure_distance_api(&mut *linker)?;
+    gps::configure_gps_api(&mut *linker)?;
+    imu::configure_imu_api(&mut *linker)?;
     llemu::configure_llemu_api(&mut *linker)?;
     misc::configure_misc_api(&mut *linker)?;
+    motors::configure_motors_api(&mut *linker)?;
+    optical::configure_optical_api(&mut *linker)?;
+    rotation::configure_rotation_api(&mut *linker)?;
     rtos_facilities::configure_rtos_facilities_api(&mut *linker)?;
+    screen::configure_screen_api(&mut *linker)?;
+    vision::configure_vision_api(&mut *linker)?;
 
     generic_io::configure_generic_io_api(&mut *linker)?;
 
@@ -0,0 +1,350 @@
+//! ADI (Three-Wire Port) API
+//!
+//! Three-wire ports on the brain and on three-wire expanders are simulated together, so every
+//! function is available in both its `adi_` and `ext_adi_` form. Sensor readings are supplied
+//! with the `AdiInput` simulator message, and changes to outputs are reported with the
+//! `AdiOutput` event.
+//!
+//! ## Reference
+//!
+//! * `adi_analog_calibrate`
+//! * `adi_analog_read`
+//! * `adi_analog_read_calibrated`
+//! * `adi_analog_read_calibrated_HR`
+//! * `adi_digital_get_new_press`
+//! * `adi_digital_read`
+//! * `adi_digital_write`
+//! * `adi_encoder_get`
+//! * `adi_encoder_init`
+//! * `adi_encoder_reset`
+//! * `adi_encoder_shutdown`
+//! * `adi_gyro_get` (not implemented)
+//! * `adi_gyro_init` (not implemented)
+//! * `adi_gyro_reset` (not implemented)
+//! * `adi_gyro_shutdown` (not implemented)
+//! * `adi_led_*` (not implemented)
+//! * `adi_motor_get`
+//! * `adi_motor_set`
+//! * `adi_motor_stop`
+//! * `adi_pin_mode`
+//! * `adi_port_get_config`
+//! * `adi_port_get_value`
+//! * `adi_port_set_config`
+//! * `adi_port_set_value`
+//! * `adi_potentiometer_get_angle`
+//! * `adi_potentiometer_init`
+//! * `adi_potentiometer_type_init`
+//! * `adi_ultrasonic_get`
+//! * `adi_ultrasonic_init`
+//! * `adi_ultrasonic_shutdown`
+
+use std::time::Duration;
+
+use pros_sys::{
+    E_ADI_ANALOG_IN, E_ADI_DIGITAL_IN, E_ADI_DIGITAL_OUT, E_ADI_LEGACY_ENCODER, E_ADI_LEGACY_PWM,
+    E_ADI_LEGACY_ULTRASONIC, E_ADI_POT_EDR, INTERNAL_ADI_PORT, PROS_ERR, PROS_ERR_F,
+};
+use wasmtime::{Caller, Linker};
+
+use crate::host::{adi::Adi, task::TaskPool, Host, HostCtx, ResultExt};
+
+/// How long `adi_analog_calibrate` takes to average its samples.
+const CALIBRATION_TIME: Duration = Duration::from_millis(500);
+
+/// The smart port number used by the `adi_` functions, which only use the brain's built-in ports.
+const INTERNAL: u32 = INTERNAL_ADI_PORT as u32;
+
+/// Defines `adi_{name}(port)` and `ext_adi_{name}(smart_port, port)`, which run `f` on the given
+/// port and return its result, or return `PROS_ERR` and set errno if it fails.
+fn define_port_fn(
+    linker: &mut Linker<Host>,
+    name: &str,
+    f: fn(&mut Adi, u32, u32) -> Result<i32, i32>,
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        &format!("adi_{name}"),
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = f(&mut *caller.adi_lock().await, INTERNAL, port);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    linker.func_wrap2_async(
+        "env",
+        &format!("ext_adi_{name}"),
+        move |mut caller: Caller<'_, Host>, smart_port: u32, port: u32| {
+            Box::new(async move {
+                let res = f(&mut *caller.adi_lock().await, smart_port, port);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines `adi_{name}(port, value)` and `ext_adi_{name}(smart_port, port, value)`, which pass
+/// `value` to `f` and return 1 on success, or return `PROS_ERR` and set errno if it fails.
+fn define_port_setter(
+    linker: &mut Linker<Host>,
+    name: &str,
+    f: fn(&mut Adi, u32, u32, i32) -> Result<(), i32>,
+) -> anyhow::Result<()> {
+    linker.func_wrap2_async(
+        "env",
+        &format!("adi_{name}"),
+        move |mut caller: Caller<'_, Host>, port: u32, value: i32| {
+            Box::new(async move {
+                let res = f(&mut *caller.adi_lock().await, INTERNAL, port, value).map(|_| 1);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    linker.func_wrap3_async(
+        "env",
+        &format!("ext_adi_{name}"),
+        move |mut caller: Caller<'_, Host>, smart_port: u32, port: u32, value: i32| {
+            Box::new(async move {
+                let res = f(&mut *caller.adi_lock().await, smart_port, port, value).map(|_| 1);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines `adi_{name}(handle)` and `ext_adi_{name}(handle)`, which take a handle to a legacy
+/// sensor and return the result of `f`, or return `PROS_ERR` and set errno if it fails.
+fn define_handle_fn(
+    linker: &mut Linker<Host>,
+    name: &str,
+    f: fn(&mut Adi, i32) -> Result<i32, i32>,
+) -> anyhow::Result<()> {
+    for name in [format!("adi_{name}"), format!("ext_adi_{name}")] {
+        linker.func_wrap1_async(
+            "env",
+            &name,
+            move |mut caller: Caller<'_, Host>, handle: i32| {
+                Box::new(async move {
+                    let res = f(&mut *caller.adi_lock().await, handle);
+                    Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+                })
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Sets the value of a port after checking that it has the given configuration.
+fn write_typed(
+    adi: &mut Adi,
+    smart_port: u32,
+    port: u32,
+    config: i32,
+    value: i32,
+) -> Result<(), i32> {
+    adi.typed_port(smart_port, port, config)?;
+    adi.set_value(smart_port, port, value)
+}
+
+/// Waits for an analog sensor to be calibrated, then returns the calibration value.
+async fn analog_calibrate(caller: &mut Caller<'_, Host>, smart_port: u32, port: u32) -> i32 {
+    let res = caller.adi_lock().await.analog_calibrate(smart_port, port);
+    if res.is_ok() {
+        TaskPool::sleep(caller.data(), CALIBRATION_TIME).await;
+    }
+    res.unwrap_or_errno_as(caller, PROS_ERR).await
+}
+
+pub fn configure_adi_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    define_port_fn(linker, "port_get_config", |adi, smart_port, port| {
+        Ok(adi.port(smart_port, port)?.config())
+    })?;
+    define_port_fn(linker, "port_get_value", |adi, smart_port, port| {
+        Ok(adi.port(smart_port, port)?.value())
+    })?;
+    define_port_setter(linker, "port_set_config", Adi::set_config)?;
+    define_port_setter(linker, "port_set_value", Adi::set_value)?;
+    define_port_setter(linker, "pin_mode", Adi::pin_mode)?;
+
+    define_port_fn(linker, "analog_read", |adi, smart_port, port| {
+        Ok(adi.typed_port(smart_port, port, E_ADI_ANALOG_IN)?.value())
+    })?;
+    define_port_fn(linker, "analog_read_calibrated", |adi, smart_port, port| {
+        let port = adi.typed_port(smart_port, port, E_ADI_ANALOG_IN)?;
+        Ok(port.analog_read_calibrated())
+    })?;
+    define_port_fn(
+        linker,
+        "analog_read_calibrated_HR",
+        |adi, smart_port, port| {
+            let port = adi.typed_port(smart_port, port, E_ADI_ANALOG_IN)?;
+            Ok(port.analog_read_calibrated_hr())
+        },
+    )?;
+    linker.func_wrap1_async(
+        "env",
+        "adi_analog_calibrate",
+        |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move { Ok(analog_calibrate(&mut caller, INTERNAL, port).await) })
+        },
+    )?;
+    linker.func_wrap2_async(
+        "env",
+        "ext_adi_analog_calibrate",
+        |mut caller: Caller<'_, Host>, smart_port: u32, port: u32| {
+            Box::new(async move { Ok(analog_calibrate(&mut caller, smart_port, port).await) })
+        },
+    )?;
+
+    define_port_fn(linker, "digital_read", |adi, smart_port, port| {
+        Ok(adi.typed_port(smart_port, port, E_ADI_DIGITAL_IN)?.value())
+    })?;
+    define_port_fn(linker, "digital_get_new_press", Adi::digital_get_new_press)?;
+    define_port_setter(linker, "digital_write", |adi, smart_port, port, value| {
+        write_typed(adi, smart_port, port, E_ADI_DIGITAL_OUT, value)
+    })?;
+
+    define_port_fn(linker, "motor_get", |adi, smart_port, port| {
+        Ok(adi.typed_port(smart_port, port, E_ADI_LEGACY_PWM)?.value())
+    })?;
+    define_port_setter(linker, "motor_set", |adi, smart_port, port, speed| {
+        write_typed(adi, smart_port, port, E_ADI_LEGACY_PWM, speed)
+    })?;
+    define_port_fn(linker, "motor_stop", |adi, smart_port, port| {
+        write_typed(adi, smart_port, port, E_ADI_LEGACY_PWM, 0).map(|_| 1)
+    })?;
+
+    linker.func_wrap3_async(
+        "env",
+        "adi_encoder_init",
+        |mut caller: Caller<'_, Host>, top: u32, bottom: u32, reversed: i32| {
+            Box::new(async move {
+                let mut adi = caller.adi_lock().await;
+                let res = adi.encoder_init(INTERNAL, top, bottom, reversed != 0);
+                drop(adi);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    linker.func_wrap4_async(
+        "env",
+        "ext_adi_encoder_init",
+        |mut caller: Caller<'_, Host>, smart_port: u32, top: u32, bottom: u32, reversed: i32| {
+            Box::new(async move {
+                let mut adi = caller.adi_lock().await;
+                let res = adi.encoder_init(smart_port, top, bottom, reversed != 0);
+                drop(adi);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    define_handle_fn(linker, "encoder_get", |adi, handle| {
+        Ok(adi
+            .handle_port(handle, E_ADI_LEGACY_ENCODER)?
+            .encoder_ticks())
+    })?;
+    define_handle_fn(linker, "encoder_reset", |adi, handle| {
+        adi.handle_port(handle, E_ADI_LEGACY_ENCODER)?
+            .reset_encoder();
+        Ok(1)
+    })?;
+    define_handle_fn(linker, "encoder_shutdown", |adi, handle| {
+        adi.shutdown_pair(handle, E_ADI_LEGACY_ENCODER).map(|_| 1)
+    })?;
+
+    linker.func_wrap2_async(
+        "env",
+        "adi_ultrasonic_init",
+        |mut caller: Caller<'_, Host>, ping: u32, echo: u32| {
+            Box::new(async move {
+                let res = caller
+                    .adi_lock()
+                    .await
+                    .ultrasonic_init(INTERNAL, ping, echo);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    linker.func_wrap3_async(
+        "env",
+        "ext_adi_ultrasonic_init",
+        |mut caller: Caller<'_, Host>, smart_port: u32, ping: u32, echo: u32| {
+            Box::new(async move {
+                let res = caller
+                    .adi_lock()
+                    .await
+                    .ultrasonic_init(smart_port, ping, echo);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    define_handle_fn(linker, "ultrasonic_get", |adi, handle| {
+        Ok(adi.handle_port(handle, E_ADI_LEGACY_ULTRASONIC)?.value())
+    })?;
+    define_handle_fn(linker, "ultrasonic_shutdown", |adi, handle| {
+        adi.shutdown_pair(handle, E_ADI_LEGACY_ULTRASONIC)
+            .map(|_| 1)
+    })?;
+
+    // ext_adi_potentiometer_init always takes a potentiometer type, so it's defined below
+    linker.func_wrap1_async(
+        "env",
+        "adi_potentiometer_init",
+        |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = caller
+                    .adi_lock()
+                    .await
+                    .potentiometer_init(INTERNAL, port, E_ADI_POT_EDR);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    linker.func_wrap2_async(
+        "env",
+        "adi_potentiometer_type_init",
+        |mut caller: Caller<'_, Host>, port: u32, potentiometer_type: i32| {
+            Box::new(async move {
+                let res =
+                    caller
+                        .adi_lock()
+                        .await
+                        .potentiometer_init(INTERNAL, port, potentiometer_type);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    linker.func_wrap3_async(
+        "env",
+        "ext_adi_potentiometer_init",
+        |mut caller: Caller<'_, Host>, smart_port: u32, port: u32, potentiometer_type: i32| {
+            Box::new(async move {
+                let res = caller.adi_lock().await.potentiometer_init(
+                    smart_port,
+                    port,
+                    potentiometer_type,
+                );
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    for name in [
+        "adi_potentiometer_get_angle",
+        "ext_adi_potentiometer_get_angle",
+    ] {
+        linker.func_wrap1_async("env", name, |mut caller: Caller<'_, Host>, handle: i32| {
+            Box::new(async move {
+                let mut adi = caller.adi_lock().await;
+                let res = adi
+                    .handle_port(handle, E_ADI_ANALOG_IN)
+                    .map(|port| port.potentiometer_angle());
+                drop(adi);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR_F).await)
+            })
+        })?;
+    }
+
+    Ok(())
+}
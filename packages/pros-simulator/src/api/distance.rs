@@ -0,0 +1,69 @@
+//! V5 Distance Sensor API
+//!
+//! Distance sensors that have a mount position in the robot configuration measure the distance
+//! to the nearest obstacle, game piece or wall on the field. Readings can also be supplied with
+//! the `DistanceInput` simulator message.
+//!
+//! ## Reference
+//!
+//! * `distance_get`
+//! * `distance_get_confidence`
+//! * `distance_get_object_size`
+//! * `distance_get_object_velocity`
+
+use pros_simulator_interface::DistanceReading;
+use pros_sys::{PROS_ERR, PROS_ERR_F};
+use wasmtime::{Caller, Linker, WasmTy};
+
+use crate::host::{Host, HostCtx, ResultExt};
+
+/// Defines an API function that returns part of the reading of the sensor on the given port, or
+/// returns `error_value` and sets errno if there is no sensor on that port.
+fn define_getter<R>(
+    linker: &mut Linker<Host>,
+    name: &str,
+    error_value: R,
+    getter: fn(&DistanceReading) -> R,
+) -> anyhow::Result<()>
+where
+    R: WasmTy + Copy + Sync + 'static,
+{
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let mut pose = caller.pose_lock().await;
+                pose.update(caller.clock().now());
+                let physics = caller.physics_lock().await;
+                let mut sensors = caller.distance_sensors_lock().await;
+                let res = sensors
+                    .get(port)
+                    .map(|sensor| getter(&sensor.reading(&pose, physics.world())));
+                drop((sensors, physics, pose));
+                Ok(res.unwrap_or_errno_as(&mut caller, error_value).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+pub fn configure_distance_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    define_getter(linker, "distance_get", PROS_ERR, |reading| {
+        reading.distance as i32
+    })?;
+    define_getter(linker, "distance_get_confidence", PROS_ERR, |reading| {
+        reading.confidence as i32
+    })?;
+    define_getter(linker, "distance_get_object_size", PROS_ERR, |reading| {
+        reading.object_size as i32
+    })?;
+    define_getter(
+        linker,
+        "distance_get_object_velocity",
+        PROS_ERR_F,
+        |reading| reading.object_velocity,
+    )?;
+
+    Ok(())
+}
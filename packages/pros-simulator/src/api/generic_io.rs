@@ -5,42 +5,48 @@
 //!
 //! * `__errno`
 //! * `sim_abort`
-//!   This is a simulator-specific function that will print the given message to stderr and exit.
+//!   This is a simulator-specific function that will stop the simulation with the given error
+//!   message.
 //! * `sim_log_backtrace`
 //!   This is a simulator-specific function that will print a backtrace to the debug terminal.
 //! * `exit`
 //! * `puts`
 
-use std::process::exit;
-
 use pros_simulator_interface::SimulatorEvent;
 use wasmtime::{Caller, Linker, WasmBacktrace};
 
-use crate::host::{memory::SharedMemoryExt, task::TaskPool, ContextExt, Host, HostCtx};
+use crate::{
+    error::RobotCodeError,
+    host::{memory::SharedMemoryExt, task::TaskPool, ContextExt, Host, HostCtx},
+};
 
 pub fn configure_generic_io_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
     linker.func_wrap0_async("env", "__errno", |mut caller: Caller<'_, Host>| {
-        Box::new(async move { Ok(caller.errno_address().await) })
+        Box::new(async move { Ok(caller.errno_address().await?) })
     })?;
 
     linker.func_wrap1_async("env", "sim_abort", |caller: Caller<'_, Host>, msg: u32| {
         Box::new(async move {
             let backtrace = WasmBacktrace::force_capture(&caller);
-            let abort_msg = caller.memory().read_c_str(msg).unwrap();
-            eprintln!("{abort_msg}");
-            eprintln!("{backtrace}");
-            exit(1);
+            let abort_msg = caller.memory().read_c_str(msg)?;
+            Err::<(), _>(
+                RobotCodeError {
+                    message: abort_msg,
+                    backtrace: backtrace.to_string(),
+                }
+                .into(),
+            )
         })
     })?;
 
     linker.func_wrap1_async("env", "puts", |caller: Caller<'_, Host>, buffer: u32| {
         Box::new(async move {
-            let mut console_message = caller.memory().read_c_str(buffer).unwrap();
+            let mut console_message = caller.memory().read_c_str(buffer)?;
             console_message.push('\n');
             caller
                 .interface()
                 .send(SimulatorEvent::ConsoleMessage(console_message));
-            u32::from(true)
+            Ok(u32::from(true))
         })
     })?;
 
@@ -61,7 +67,7 @@ pub fn configure_generic_io_api(linker: &mut Linker<Host>) -> anyhow::Result<()>
                 let buffer = caller
                     .memory()
                     .read_relaxed(buffer as usize, count as usize)?;
-                let buffer_string = String::from_utf8(buffer).unwrap();
+                let buffer_string = String::from_utf8_lossy(&buffer).into_owned();
                 caller
                     .interface()
                     .send(SimulatorEvent::ConsoleMessage(buffer_string));
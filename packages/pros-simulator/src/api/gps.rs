@@ -0,0 +1,240 @@
+//! V5 GPS Sensor API
+//!
+//! GPS sensors measure the simulated robot's pose on a 12ft by 12ft field, which can be changed
+//! with the `SetRobotPose` and `SetRobotVelocity` simulator messages. The simulated sensor can
+//! always see the field strips, so the initial position passed to `gps_initialize_full` and
+//! `gps_set_position` is ignored.
+//!
+//! ## Reference
+//!
+//! * `gps_get_accel`
+//! * `gps_get_error`
+//! * `gps_get_gyro_rate`
+//! * `gps_get_heading`
+//! * `gps_get_heading_raw`
+//! * `gps_get_offset`
+//! * `gps_get_rotation`
+//! * `gps_get_status`
+//! * `gps_initialize_full`
+//! * `gps_set_data_rate`
+//! * `gps_set_offset`
+//! * `gps_set_position`
+//! * `gps_set_rotation`
+//! * `gps_tare_rotation`
+
+use pros_sys::{PROS_ERR, PROS_ERR_F};
+use snafu::OptionExt;
+use wasmtime::{Caller, Linker};
+
+use crate::{
+    error::{InvalidPointerSnafu, SimulatorError},
+    host::{
+        gps::Gps, memory::SharedMemoryExt, pose::PoseModel, ContextExt, Host, HostCtx, ResultExt,
+    },
+};
+
+/// Runs `f` with the GPS sensor on the given port and the robot's current pose.
+async fn with_gps<R>(
+    caller: &Caller<'_, Host>,
+    port: u32,
+    f: impl FnOnce(&mut Gps, &PoseModel) -> R,
+) -> Result<R, i32> {
+    let now = caller.clock().now();
+    let mut pose = caller.pose_lock().await;
+    pose.update(now);
+    let mut sensors = caller.gps_sensors_lock().await;
+    Ok(f(sensors.get(port)?, &pose))
+}
+
+/// Writes an array of `f64`s to the robot code's memory, for returning structs like
+/// `gps_status_s_t`.
+fn write_f64s(
+    caller: &Caller<'_, Host>,
+    pointer: u32,
+    values: &[f64],
+) -> Result<(), SimulatorError> {
+    let bytes = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+    caller
+        .memory()
+        .write_relaxed(pointer as usize, &bytes)
+        .ok()
+        .context(InvalidPointerSnafu { pointer })
+}
+
+/// Defines an API function that reads a value from the sensor on the given port, or returns
+/// `PROS_ERR_F` and sets errno if that fails.
+fn define_getter(
+    linker: &mut Linker<Host>,
+    name: &str,
+    getter: fn(&mut Gps, &PoseModel) -> f64,
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_gps(&caller, port, getter).await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR_F).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that returns a struct of `N` `f64`s read from the sensor on the
+/// given port. If that fails, every field is set to `PROS_ERR_F` and errno is set.
+///
+/// Structs are returned by writing them to a pointer passed as the first argument.
+fn define_struct_getter<const N: usize>(
+    linker: &mut Linker<Host>,
+    name: &str,
+    getter: fn(&mut Gps, &PoseModel) -> [f64; N],
+) -> anyhow::Result<()> {
+    linker.func_wrap2_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32| {
+            Box::new(async move {
+                let res = with_gps(&caller, port, getter).await;
+                let values = res.unwrap_or_errno_as(&mut caller, [PROS_ERR_F; N]).await;
+                write_f64s(&caller, ret_ptr, &values)?;
+                Ok(())
+            })
+        },
+    )?;
+    Ok(())
+}
+
+pub fn configure_gps_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    define_getter(linker, "gps_get_heading", |gps, pose| gps.heading(pose))?;
+    define_getter(linker, "gps_get_heading_raw", |gps, pose| {
+        gps.heading_raw(pose)
+    })?;
+    define_getter(linker, "gps_get_rotation", |gps, pose| gps.rotation(pose))?;
+    define_getter(linker, "gps_get_error", |gps, _| gps.error())?;
+
+    define_struct_getter(linker, "gps_get_status", Gps::status)?;
+    define_struct_getter(linker, "gps_get_gyro_rate", |gps, pose| gps.gyro_rate(pose))?;
+    define_struct_getter(linker, "gps_get_accel", |gps, pose| gps.accel(pose))?;
+
+    linker.func_wrap6_async(
+        "env",
+        "gps_initialize_full",
+        |mut caller: Caller<'_, Host>,
+         port: u32,
+         _x_initial: f64,
+         _y_initial: f64,
+         _heading_initial: f64,
+         x_offset: f64,
+         y_offset: f64| {
+            Box::new(async move {
+                let res = with_gps(&caller, port, |gps, _| {
+                    gps.set_offset(x_offset, y_offset);
+                    1
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    linker.func_wrap4_async(
+        "env",
+        "gps_set_position",
+        |mut caller: Caller<'_, Host>,
+         port: u32,
+         _x_initial: f64,
+         _y_initial: f64,
+         _heading_initial: f64| {
+            Box::new(async move {
+                let res = with_gps(&caller, port, |_, _| 1).await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    // pros-sys declares this function without a return value.
+    linker.func_wrap3_async(
+        "env",
+        "gps_set_offset",
+        |mut caller: Caller<'_, Host>, port: u32, x_offset: f64, y_offset: f64| {
+            Box::new(async move {
+                let res =
+                    with_gps(&caller, port, |gps, _| gps.set_offset(x_offset, y_offset)).await;
+                res.unwrap_or_errno(&mut caller).await;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap3_async(
+        "env",
+        "gps_get_offset",
+        |mut caller: Caller<'_, Host>, port: u32, x_ptr: u32, y_ptr: u32| {
+            Box::new(async move {
+                let res = with_gps(&caller, port, |gps, _| gps.offset()).await;
+                match res {
+                    Ok((x, y)) => {
+                        write_f64s(&caller, x_ptr, &[x])?;
+                        write_f64s(&caller, y_ptr, &[y])?;
+                        Ok(1)
+                    }
+                    Err(code) => {
+                        caller.set_errno(code).await;
+                        Ok(PROS_ERR)
+                    }
+                }
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "gps_set_rotation",
+        |mut caller: Caller<'_, Host>, port: u32, rotation: f64| {
+            Box::new(async move {
+                let res = with_gps(&caller, port, |gps, pose| {
+                    gps.set_rotation(pose, rotation);
+                    1
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "gps_tare_rotation",
+        |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_gps(&caller, port, |gps, pose| {
+                    gps.set_rotation(pose, 0.0);
+                    1
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "gps_set_data_rate",
+        |mut caller: Caller<'_, Host>, port: u32, rate: u32| {
+            Box::new(async move {
+                let res = with_gps(&caller, port, |gps, _| {
+                    gps.set_data_rate(rate);
+                    1
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    Ok(())
+}
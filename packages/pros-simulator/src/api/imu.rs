@@ -0,0 +1,318 @@
+//! V5 Inertial Sensor API
+//!
+//! Inertial sensors measure the simulated robot's pose, which can be changed with the
+//! `SetRobotPose` and `SetRobotVelocity` simulator messages. Like on a real robot, sensors spend
+//! their first 2 seconds calibrating, and fail with `EAGAIN` until they're done.
+//!
+//! ## Reference
+//!
+//! * `imu_get_accel`
+//! * `imu_get_euler`
+//! * `imu_get_gyro_rate`
+//! * `imu_get_heading`
+//! * `imu_get_pitch`
+//! * `imu_get_quaternion`
+//! * `imu_get_roll`
+//! * `imu_get_rotation`
+//! * `imu_get_status`
+//! * `imu_get_yaw`
+//! * `imu_reset`
+//! * `imu_reset_blocking`
+//! * `imu_set_data_rate`
+//! * `imu_set_euler`
+//! * `imu_set_heading`
+//! * `imu_set_pitch`
+//! * `imu_set_roll`
+//! * `imu_set_rotation`
+//! * `imu_set_yaw`
+//! * `imu_tare`
+//! * `imu_tare_euler`
+//! * `imu_tare_heading`
+//! * `imu_tare_pitch`
+//! * `imu_tare_roll`
+//! * `imu_tare_rotation`
+//! * `imu_tare_yaw`
+
+use std::time::Duration;
+
+use pros_sys::{E_IMU_STATUS_ERROR, PROS_ERR, PROS_ERR_F};
+use snafu::OptionExt;
+use wasmtime::{Caller, Linker};
+
+use crate::{
+    error::{InvalidPointerSnafu, SimulatorError},
+    host::{
+        imu::{Imu, CALIBRATION_TIME},
+        memory::SharedMemoryExt,
+        pose::PoseModel,
+        task::TaskPool,
+        Host, HostCtx, ResultExt,
+    },
+};
+
+/// How long `imu_reset` blocks for while the sensor starts calibrating.
+const RESET_DELAY: Duration = Duration::from_millis(5);
+
+/// Runs `f` with the inertial sensor on the given port and the robot's current pose.
+///
+/// Unless `allow_calibrating` is set, this fails with `EAGAIN` if the sensor is calibrating.
+async fn with_imu<R>(
+    caller: &Caller<'_, Host>,
+    port: u32,
+    allow_calibrating: bool,
+    f: impl FnOnce(&mut Imu, &PoseModel, Duration) -> R,
+) -> Result<R, i32> {
+    let now = caller.clock().now();
+    let mut pose = caller.pose_lock().await;
+    pose.update(now);
+    let mut imus = caller.imus_lock().await;
+    let imu = if allow_calibrating {
+        imus.get(port)?
+    } else {
+        imus.get_calibrated(port, now)?
+    };
+    Ok(f(imu, &pose, now))
+}
+
+/// Writes an array of `f64`s to the robot code's memory, for returning structs like
+/// `euler_s_t`.
+fn write_f64s(
+    caller: &Caller<'_, Host>,
+    pointer: u32,
+    values: &[f64],
+) -> Result<(), SimulatorError> {
+    let bytes = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+    caller
+        .memory()
+        .write_relaxed(pointer as usize, &bytes)
+        .ok()
+        .context(InvalidPointerSnafu { pointer })
+}
+
+/// Defines an API function that reads an angle from the sensor on the given port, or returns
+/// `PROS_ERR_F` and sets errno if that fails.
+fn define_getter(
+    linker: &mut Linker<Host>,
+    name: &str,
+    getter: fn(&mut Imu, &PoseModel, Duration) -> f64,
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_imu(&caller, port, false, getter).await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR_F).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that returns a struct of `N` `f64`s read from the sensor on the
+/// given port. If that fails, every field is set to `PROS_ERR_F` and errno is set.
+///
+/// Structs are returned by writing them to a pointer passed as the first argument.
+fn define_struct_getter<const N: usize>(
+    linker: &mut Linker<Host>,
+    name: &str,
+    getter: fn(&mut Imu, &PoseModel, Duration) -> [f64; N],
+) -> anyhow::Result<()> {
+    linker.func_wrap2_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32| {
+            Box::new(async move {
+                let res = with_imu(&caller, port, false, getter).await;
+                let values = res.unwrap_or_errno_as(&mut caller, [PROS_ERR_F; N]).await;
+                write_f64s(&caller, ret_ptr, &values)?;
+                Ok(())
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that resets one of the sensor's readings, returning 1 on success or
+/// `PROS_ERR` and setting errno if the operation fails.
+fn define_tare(
+    linker: &mut Linker<Host>,
+    name: &str,
+    tare: fn(&mut Imu, &PoseModel, Duration),
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_imu(&caller, port, false, tare).await.map(|_| 1);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that changes one of the sensor's readings to the given value,
+/// returning 1 on success or `PROS_ERR` and setting errno if the operation fails.
+fn define_setter(
+    linker: &mut Linker<Host>,
+    name: &str,
+    setter: fn(&mut Imu, &PoseModel, Duration, f64),
+) -> anyhow::Result<()> {
+    linker.func_wrap2_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32, value: f64| {
+            Box::new(async move {
+                let res = with_imu(&caller, port, false, |imu, pose, now| {
+                    setter(imu, pose, now, value);
+                    1
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines `imu_reset` or `imu_reset_blocking`, which start calibrating the sensor and then
+/// block for the given amount of time.
+fn define_reset(
+    linker: &mut Linker<Host>,
+    name: &str,
+    blocking_time: Duration,
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_imu(&caller, port, true, |imu, pose, now| imu.reset(pose, now))
+                    .await
+                    .and_then(|res| res);
+                if res.is_ok() {
+                    TaskPool::sleep(caller.data(), blocking_time).await;
+                }
+                Ok(res
+                    .map(|_| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+pub fn configure_imu_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    define_reset(linker, "imu_reset", RESET_DELAY)?;
+    define_reset(linker, "imu_reset_blocking", CALIBRATION_TIME)?;
+
+    linker.func_wrap1_async(
+        "env",
+        "imu_get_status",
+        |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_imu(&caller, port, true, |imu, _, now| imu.status(now)).await;
+                Ok(res
+                    .unwrap_or_errno_as(&mut caller, E_IMU_STATUS_ERROR)
+                    .await)
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "imu_set_data_rate",
+        |mut caller: Caller<'_, Host>, port: u32, rate: u32| {
+            Box::new(async move {
+                let res = with_imu(&caller, port, false, |imu, _, _| {
+                    imu.set_data_rate(rate);
+                    1
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    define_getter(linker, "imu_get_rotation", Imu::rotation)?;
+    define_getter(linker, "imu_get_heading", Imu::heading)?;
+    define_getter(linker, "imu_get_pitch", |imu, pose, now| {
+        imu.euler(pose, now)[0]
+    })?;
+    define_getter(linker, "imu_get_roll", |imu, pose, now| {
+        imu.euler(pose, now)[1]
+    })?;
+    define_getter(linker, "imu_get_yaw", |imu, pose, now| {
+        imu.euler(pose, now)[2]
+    })?;
+
+    define_struct_getter(linker, "imu_get_quaternion", Imu::quaternion)?;
+    define_struct_getter(linker, "imu_get_euler", Imu::euler)?;
+    define_struct_getter(linker, "imu_get_gyro_rate", |imu, pose, _| {
+        let [x, y, z] = imu.gyro_rate(pose);
+        // imu_gyro_s_t has an unused `w` field
+        [x, y, z, 0.0]
+    })?;
+    define_struct_getter(linker, "imu_get_accel", |imu, pose, _| {
+        let [x, y, z] = imu.accel(pose);
+        [x, y, z, 0.0]
+    })?;
+
+    define_tare(linker, "imu_tare_rotation", |imu, pose, now| {
+        imu.set_rotation(pose, now, 0.0)
+    })?;
+    define_tare(linker, "imu_tare_heading", |imu, pose, now| {
+        imu.set_heading(pose, now, 0.0)
+    })?;
+    define_tare(linker, "imu_tare_pitch", |imu, _, _| imu.set_pitch(0.0))?;
+    define_tare(linker, "imu_tare_roll", |imu, _, _| imu.set_roll(0.0))?;
+    define_tare(linker, "imu_tare_yaw", |imu, pose, now| {
+        imu.set_yaw(pose, now, 0.0)
+    })?;
+    define_tare(linker, "imu_tare_euler", |imu, pose, now| {
+        imu.set_pitch(0.0);
+        imu.set_roll(0.0);
+        imu.set_yaw(pose, now, 0.0);
+    })?;
+    define_tare(linker, "imu_tare", |imu, pose, now| {
+        imu.set_rotation(pose, now, 0.0);
+        imu.set_heading(pose, now, 0.0);
+        imu.set_pitch(0.0);
+        imu.set_roll(0.0);
+        imu.set_yaw(pose, now, 0.0);
+    })?;
+
+    define_setter(linker, "imu_set_rotation", Imu::set_rotation)?;
+    define_setter(linker, "imu_set_heading", Imu::set_heading)?;
+    define_setter(linker, "imu_set_pitch", |imu, _, _, pitch| {
+        imu.set_pitch(pitch)
+    })?;
+    define_setter(linker, "imu_set_roll", |imu, _, _, roll| imu.set_roll(roll))?;
+    define_setter(linker, "imu_set_yaw", Imu::set_yaw)?;
+
+    // Structs passed by value are split into their fields by the wasm32-unknown-unknown ABI.
+    linker.func_wrap4_async(
+        "env",
+        "imu_set_euler",
+        |mut caller: Caller<'_, Host>, port: u32, pitch: f64, roll: f64, yaw: f64| {
+            Box::new(async move {
+                let res = with_imu(&caller, port, false, |imu, pose, now| {
+                    imu.set_pitch(pitch);
+                    imu.set_roll(roll);
+                    imu.set_yaw(pose, now, yaw);
+                    1
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    Ok(())
+}
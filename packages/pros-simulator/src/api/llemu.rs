@@ -1,24 +1,42 @@
 //! Legacy LCD Emulator API
 //!
+//! `lcd_set_background_color` and `lcd_set_text_color` take an `lv_color_t`, which is passed as
+//! a pointer to the color like any other struct.
+//!
 //! ## Reference
 //!
 //! * `lcd_clear`
 //! * `lcd_clear_line`
 //! * `lcd_initialize`
-//! * `lcd_is_initialized` (not implemented)
-//! * `lcd_print` (not implemented)
-//! * `lcd_read_buttons` (not implemented)
+//! * `lcd_is_initialized`
+//! * `lcd_print`
+//! * `lcd_read_buttons`
 //! * `lcd_register_btn0_cb`
 //! * `lcd_register_btn1_cb`
 //! * `lcd_register_btn2_cb`
 //! * `lcd_set_text`
-//! * `lcd_shutdown` (not implemented)
-//! * `lcd_set_background_color` (not implemented)
-//! * `lcd_set_text_color` (not implemented)
+//! * `lcd_shutdown`
+//! * `lcd_set_background_color`
+//! * `lcd_set_text_color`
 
+use snafu::OptionExt;
 use wasmtime::{Caller, Linker};
 
-use crate::host::{memory::SharedMemoryExt, Host, HostCtx, ResultExt};
+use crate::{
+    error::{InvalidPointerSnafu, SimulatorError},
+    host::{memory::SharedMemoryExt, printf::sprintf, Host, HostCtx, ResultExt},
+};
+
+/// Reads an `lv_color_t` from the robot code's memory, as a `0xAARRGGBB` value.
+fn read_color(caller: &Caller<'_, Host>, pointer: u32) -> Result<u32, SimulatorError> {
+    let bytes = caller
+        .memory()
+        .read_relaxed(pointer as usize, 4)
+        .ok()
+        .context(InvalidPointerSnafu { pointer })?;
+    // the struct is laid out as blue, green, red, alpha
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
 
 pub fn configure_llemu_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
     linker.func_wrap0_async("env", "lcd_initialize", |caller: Caller<'_, Host>| {
@@ -40,6 +58,19 @@ pub fn configure_llemu_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
         },
     )?;
 
+    linker.func_wrap3_async(
+        "env",
+        "lcd_print",
+        |mut caller: Caller<'_, Host>, line: i32, format_ptr: u32, args_ptr: u32| {
+            Box::new(async move {
+                let format = caller.memory().read_c_str(format_ptr)?;
+                let text = sprintf(&caller.memory(), &format, args_ptr)?;
+                let res = caller.lcd_lock().await.set_line(line, &text);
+                Ok(u32::from(res.unwrap_or_errno(&mut caller).await))
+            })
+        },
+    )?;
+
     linker.func_wrap1_async(
         "env",
         "lcd_clear_line",
@@ -58,6 +89,50 @@ pub fn configure_llemu_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
         })
     })?;
 
+    linker.func_wrap0_async("env", "lcd_is_initialized", |caller: Caller<'_, Host>| {
+        Box::new(async move { Ok(u32::from(caller.lcd_lock().await.is_initialized())) })
+    })?;
+
+    linker.func_wrap0_async("env", "lcd_shutdown", |mut caller: Caller<'_, Host>| {
+        Box::new(async move {
+            let res = caller.lcd_lock().await.shutdown();
+            Ok(u32::from(res.unwrap_or_errno(&mut caller).await))
+        })
+    })?;
+
+    linker.func_wrap0_async("env", "lcd_read_buttons", |mut caller: Caller<'_, Host>| {
+        Box::new(async move {
+            let res = caller.lcd_lock().await.read_buttons();
+            Ok(u32::from(res.unwrap_or_errno_as(&mut caller, 0).await))
+        })
+    })?;
+
+    linker.func_wrap1_async(
+        "env",
+        "lcd_set_background_color",
+        |mut caller: Caller<'_, Host>, color_ptr: u32| {
+            Box::new(async move {
+                let color = read_color(&caller, color_ptr)?;
+                let res = caller.lcd_lock().await.set_colors(Some(color), None);
+                res.unwrap_or_errno(&mut caller).await;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "lcd_set_text_color",
+        |mut caller: Caller<'_, Host>, color_ptr: u32| {
+            Box::new(async move {
+                let color = read_color(&caller, color_ptr)?;
+                let res = caller.lcd_lock().await.set_colors(None, Some(color));
+                res.unwrap_or_errno(&mut caller).await;
+                Ok(())
+            })
+        },
+    )?;
+
     for lcd_button in 0..3 {
         linker.func_wrap1_async(
             "env",
@@ -1,32 +1,42 @@
 //! Miscellaneous API functions.
 //!
+//! The battery drains as the motors draw current from it, and its voltage drops under load.
+//! Motors can't apply more voltage than the battery supplies. The `SetBattery` simulator message
+//! changes the battery's charge and temperature.
+//!
+//! Controller screens hold 3 lines of 19 characters, and are sent to the simulator interface
+//! with `ControllerScreenUpdated` events whenever they change. Like on a real controller, the
+//! screen and rumble motor only accept one write every 50 milliseconds, and writing more often
+//! fails with `EAGAIN`.
+//!
 //! ## Reference
 //!
-//! * `battery_get_capacity` (not implemented)
-//! * `battery_get_current` (not implemented)
-//! * `battery_get_temperature` (not implemented)
-//! * `battery_get_voltage` (not implemented)
+//! * `battery_get_capacity`
+//! * `battery_get_current`
+//! * `battery_get_temperature`
+//! * `battery_get_voltage`
 //! * `competition_get_status`
 //! * `competition_is_autonomous`
 //! * `competition_is_connected`
 //! * `competition_is_disabled`
-//! * `controller_clear` (not implemented)
-//! * `controller_clear_line` (not implemented)
+//! * `controller_clear`
+//! * `controller_clear_line`
 //! * `controller_get_analog`
 //! * `controller_get_battery_capacity`
 //! * `controller_get_battery_level` (Return value always equal to capacity)
 //! * `controller_get_digital`
 //! * `controller_get_digital_new_press`
 //! * `controller_is_connected`
-//! * `controller_print` (not implemented)
-//! * `controller_rumble` (not implemented)
-//! * `controller_set_text` (not implemented)
+//! * `controller_print`
+//! * `controller_rumble`
+//! * `controller_set_text`
 //! * `usd_is_installed` (not implemented)
 
+use pros_sys::PROS_ERR;
 use wasmtime::{Caller, Linker};
 
 use crate::{
-    host::{Host, HostCtx, ResultExt},
+    host::{memory::SharedMemoryExt, printf::sprintf, Host, HostCtx, ResultExt},
     system::system_daemon::CompetitionPhaseExt,
 };
 
@@ -95,6 +105,105 @@ pub fn configure_misc_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
         |_caller: Caller<'_, Host>, _id: u32| Box::new(async move { Ok(100i32) }),
     )?;
 
+    linker.func_wrap4_async(
+        "env",
+        "controller_set_text",
+        |mut caller: Caller<'_, Host>, id: u32, line: u32, column: u32, text_ptr: u32| {
+            Box::new(async move {
+                let text = caller.memory().read_c_str(text_ptr)?;
+                let now = caller.clock().now();
+                let res = caller.controllers_lock().await.set_text(
+                    id,
+                    now,
+                    line as u8,
+                    column as u8,
+                    &text,
+                );
+                Ok(res
+                    .map(|()| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+
+    linker.func_wrap5_async(
+        "env",
+        "controller_print",
+        |mut caller: Caller<'_, Host>,
+         id: u32,
+         line: u32,
+         column: u32,
+         format_ptr: u32,
+         args_ptr: u32| {
+            Box::new(async move {
+                let format = caller.memory().read_c_str(format_ptr)?;
+                let text = sprintf(&caller.memory(), &format, args_ptr)?;
+                let now = caller.clock().now();
+                let res = caller.controllers_lock().await.set_text(
+                    id,
+                    now,
+                    line as u8,
+                    column as u8,
+                    &text,
+                );
+                Ok(res
+                    .map(|()| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "controller_clear_line",
+        |mut caller: Caller<'_, Host>, id: u32, line: u32| {
+            Box::new(async move {
+                let now = caller.clock().now();
+                let res = caller
+                    .controllers_lock()
+                    .await
+                    .clear_line(id, now, line as u8);
+                Ok(res
+                    .map(|()| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "controller_clear",
+        |mut caller: Caller<'_, Host>, id: u32| {
+            Box::new(async move {
+                let now = caller.clock().now();
+                let res = caller.controllers_lock().await.clear(id, now);
+                Ok(res
+                    .map(|()| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "controller_rumble",
+        |mut caller: Caller<'_, Host>, id: u32, pattern_ptr: u32| {
+            Box::new(async move {
+                let pattern = caller.memory().read_c_str(pattern_ptr)?;
+                let now = caller.clock().now();
+                let res = caller.controllers_lock().await.rumble(id, now, &pattern);
+                Ok(res
+                    .map(|()| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+
     linker.func_wrap0_async(
         "env",
         "competition_get_status",
@@ -139,5 +248,25 @@ pub fn configure_misc_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
         },
     )?;
 
+    linker.func_wrap0_async("env", "battery_get_capacity", |caller: Caller<'_, Host>| {
+        Box::new(async move { Ok(caller.battery_lock().await.capacity()) })
+    })?;
+
+    linker.func_wrap0_async("env", "battery_get_current", |caller: Caller<'_, Host>| {
+        Box::new(async move { Ok((caller.battery_lock().await.current() * 1000.0) as i32) })
+    })?;
+
+    linker.func_wrap0_async(
+        "env",
+        "battery_get_temperature",
+        |caller: Caller<'_, Host>| {
+            Box::new(async move { Ok(caller.battery_lock().await.temperature()) })
+        },
+    )?;
+
+    linker.func_wrap0_async("env", "battery_get_voltage", |caller: Caller<'_, Host>| {
+        Box::new(async move { Ok((caller.battery_lock().await.voltage() * 1000.0) as i32) })
+    })?;
+
     Ok(())
 }
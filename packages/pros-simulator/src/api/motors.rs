@@ -0,0 +1,372 @@
+//! V5 Smart Motor API
+//!
+//! Motors are simulated as DC motors with a current limit, following the published torque-speed
+//! curve of the V5 Smart Motor. Velocity and position commands are handled by an internal
+//! velocity controller, and motors heat up as they draw current, limiting their output once they
+//! are over temperature. The load each motor turns can be changed in the robot configuration.
+//!
+//! ## Reference
+//!
+//! * `motor_brake`
+//! * `motor_get_actual_velocity`
+//! * `motor_get_brake_mode`
+//! * `motor_get_current_draw`
+//! * `motor_get_current_limit`
+//! * `motor_get_direction`
+//! * `motor_get_efficiency`
+//! * `motor_get_encoder_units`
+//! * `motor_get_faults`
+//! * `motor_get_flags`
+//! * `motor_get_gearing`
+//! * `motor_get_pos_pid` (not implemented)
+//! * `motor_get_position`
+//! * `motor_get_power`
+//! * `motor_get_raw_position`
+//! * `motor_get_target_position`
+//! * `motor_get_target_velocity`
+//! * `motor_get_temperature`
+//! * `motor_get_torque`
+//! * `motor_get_vel_pid` (not implemented)
+//! * `motor_get_voltage`
+//! * `motor_get_voltage_limit`
+//! * `motor_get_zero_position_flag` (Always fails with `ENOSYS`, like on a real robot)
+//! * `motor_is_over_current`
+//! * `motor_is_over_temp`
+//! * `motor_is_reversed`
+//! * `motor_is_stopped` (Always fails with `ENOSYS`, like on a real robot)
+//! * `motor_modify_profiled_velocity`
+//! * `motor_move`
+//! * `motor_move_absolute`
+//! * `motor_move_relative`
+//! * `motor_move_velocity`
+//! * `motor_move_voltage`
+//! * `motor_set_brake_mode`
+//! * `motor_set_current_limit`
+//! * `motor_set_encoder_units`
+//! * `motor_set_gearing`
+//! * `motor_set_pos_pid` (not implemented)
+//! * `motor_set_pos_pid_full` (not implemented)
+//! * `motor_set_reversed`
+//! * `motor_set_vel_pid` (not implemented)
+//! * `motor_set_vel_pid_full` (not implemented)
+//! * `motor_set_voltage_limit`
+//! * `motor_set_zero_position`
+//! * `motor_tare_position`
+
+use pros_sys::{
+    E_MOTOR_BRAKE_INVALID, E_MOTOR_ENCODER_INVALID, E_MOTOR_GEARSET_INVALID, PROS_ERR, PROS_ERR_F,
+};
+use tokio::sync::MutexGuard;
+use wasmtime::{Caller, Linker, WasmTy};
+
+use crate::host::{
+    memory::SharedMemoryExt,
+    motors::{
+        brake_mode_from_raw, brake_mode_to_raw, gearset_from_raw, gearset_to_raw, Motor, Motors,
+        MAX_VOLTAGE,
+    },
+    Host, HostCtx, ResultExt,
+};
+
+/// "Function not implemented" error code. Missing from `pros_sys`; this is the value from newlib.
+const ENOSYS: i32 = 88;
+
+/// Locks the motor table, bringing the position of every motor up to date first.
+async fn updated_motors<'a>(caller: &'a Caller<'_, Host>) -> MutexGuard<'a, Motors> {
+    let mut motors = caller.motors_lock().await;
+    motors.update(caller.clock().now());
+    motors
+}
+
+/// Defines an API function that reads a value from the motor on the given port, or returns
+/// `error_value` and sets errno if there is no motor on that port.
+fn define_getter<R>(
+    linker: &mut Linker<Host>,
+    name: &str,
+    error_value: R,
+    getter: fn(&Motor) -> R,
+) -> anyhow::Result<()>
+where
+    R: WasmTy + Copy + Sync + 'static,
+{
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let motors = updated_motors(&caller).await;
+                let res = motors.get(port).map(getter);
+                drop(motors);
+                Ok(res.unwrap_or_errno_as(&mut caller, error_value).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that modifies the motor on the given port, returning 1 on success or
+/// `PROS_ERR` and setting errno if the operation fails.
+fn define_setter<A>(
+    linker: &mut Linker<Host>,
+    name: &str,
+    setter: fn(&mut Motors, u32, A) -> Result<(), i32>,
+) -> anyhow::Result<()>
+where
+    A: WasmTy + 'static,
+{
+    linker.func_wrap2_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32, value: A| {
+            Box::new(async move {
+                let mut motors = updated_motors(&caller).await;
+                let res = setter(&mut motors, port, value).map(|_| 1);
+                drop(motors);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+pub fn configure_motors_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    define_setter(linker, "motor_move", |motors, port, voltage: i32| {
+        motors.move_voltage(port, voltage.clamp(-127, 127) * MAX_VOLTAGE / 127)
+    })?;
+    define_setter(linker, "motor_move_voltage", Motors::move_voltage)?;
+    define_setter(linker, "motor_move_velocity", Motors::move_velocity)?;
+    define_setter(
+        linker,
+        "motor_modify_profiled_velocity",
+        Motors::modify_profiled_velocity,
+    )?;
+
+    linker.func_wrap3_async(
+        "env",
+        "motor_move_absolute",
+        |mut caller: Caller<'_, Host>, port: u32, position: f64, velocity: i32| {
+            Box::new(async move {
+                let mut motors = updated_motors(&caller).await;
+                let res = motors.move_absolute(port, position, velocity).map(|_| 1);
+                drop(motors);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    linker.func_wrap3_async(
+        "env",
+        "motor_move_relative",
+        |mut caller: Caller<'_, Host>, port: u32, distance: f64, velocity: i32| {
+            Box::new(async move {
+                let mut motors = updated_motors(&caller).await;
+                let res = motors.move_relative(port, distance, velocity).map(|_| 1);
+                drop(motors);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "motor_brake",
+        |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let mut motors = updated_motors(&caller).await;
+                let res = motors.brake(port).map(|_| 1);
+                drop(motors);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "motor_tare_position",
+        |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let mut motors = updated_motors(&caller).await;
+                let res = motors.modify(port, |motor| {
+                    motor.tare_position();
+                    Ok(1)
+                });
+                drop(motors);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    define_setter(
+        linker,
+        "motor_set_zero_position",
+        |motors, port, position: f64| {
+            motors.modify(port, |motor| {
+                motor.set_zero_position(position);
+                Ok(())
+            })
+        },
+    )?;
+    define_setter(linker, "motor_set_brake_mode", |motors, port, mode: i32| {
+        let mode = brake_mode_from_raw(mode)?;
+        motors.modify(port, |motor| {
+            motor.set_brake_mode(mode);
+            Ok(())
+        })
+    })?;
+    define_setter(
+        linker,
+        "motor_set_current_limit",
+        |motors, port, limit: i32| {
+            motors.modify(port, |motor| {
+                motor.set_current_limit(limit);
+                Ok(())
+            })
+        },
+    )?;
+    define_setter(
+        linker,
+        "motor_set_encoder_units",
+        |motors, port, units: i32| {
+            let units = units.try_into()?;
+            motors.modify(port, |motor| {
+                motor.set_encoder_units(units);
+                Ok(())
+            })
+        },
+    )?;
+    define_setter(linker, "motor_set_gearing", |motors, port, gearset: i32| {
+        let gearset = gearset_from_raw(gearset)?;
+        motors.modify(port, |motor| {
+            motor.set_gearset(gearset);
+            Ok(())
+        })
+    })?;
+    define_setter(
+        linker,
+        "motor_set_reversed",
+        |motors, port, reversed: u32| {
+            motors.modify(port, |motor| {
+                motor.set_reversed(reversed != 0);
+                Ok(())
+            })
+        },
+    )?;
+    define_setter(
+        linker,
+        "motor_set_voltage_limit",
+        |motors, port, limit: i32| {
+            motors.modify(port, |motor| {
+                motor.set_voltage_limit(limit);
+                Ok(())
+            })
+        },
+    )?;
+
+    define_getter(linker, "motor_get_target_position", PROS_ERR_F, |motor| {
+        motor.target_position()
+    })?;
+    define_getter(linker, "motor_get_target_velocity", PROS_ERR, |motor| {
+        motor.target_velocity()
+    })?;
+    define_getter(linker, "motor_get_actual_velocity", PROS_ERR_F, |motor| {
+        motor.actual_velocity()
+    })?;
+    define_getter(linker, "motor_get_current_draw", PROS_ERR, |motor| {
+        motor.current_draw()
+    })?;
+    define_getter(linker, "motor_get_direction", PROS_ERR, |motor| {
+        motor.direction_of_travel()
+    })?;
+    define_getter(linker, "motor_get_efficiency", PROS_ERR_F, |motor| {
+        motor.efficiency()
+    })?;
+    define_getter(linker, "motor_is_over_current", PROS_ERR, |motor| {
+        i32::from(motor.is_over_current())
+    })?;
+    define_getter(linker, "motor_is_over_temp", PROS_ERR, |motor| {
+        i32::from(motor.is_over_temp())
+    })?;
+    define_getter(linker, "motor_get_faults", PROS_ERR as u32, |motor| {
+        motor.faults()
+    })?;
+    define_getter(linker, "motor_get_flags", PROS_ERR as u32, |motor| {
+        motor.flags()
+    })?;
+    define_getter(linker, "motor_get_position", PROS_ERR_F, |motor| {
+        motor.position()
+    })?;
+    define_getter(linker, "motor_get_power", PROS_ERR_F, |motor| motor.power())?;
+    define_getter(linker, "motor_get_temperature", PROS_ERR_F, |motor| {
+        motor.temperature()
+    })?;
+    define_getter(linker, "motor_get_torque", PROS_ERR_F, |motor| {
+        motor.torque()
+    })?;
+    define_getter(linker, "motor_get_voltage", PROS_ERR, |motor| {
+        motor.voltage()
+    })?;
+    define_getter(
+        linker,
+        "motor_get_brake_mode",
+        E_MOTOR_BRAKE_INVALID,
+        |motor| brake_mode_to_raw(motor.brake_mode()),
+    )?;
+    define_getter(linker, "motor_get_current_limit", PROS_ERR, |motor| {
+        motor.current_limit()
+    })?;
+    define_getter(
+        linker,
+        "motor_get_encoder_units",
+        E_MOTOR_ENCODER_INVALID,
+        |motor| motor.encoder_units().into(),
+    )?;
+    define_getter(
+        linker,
+        "motor_get_gearing",
+        E_MOTOR_GEARSET_INVALID,
+        |motor| gearset_to_raw(motor.gearset()),
+    )?;
+    define_getter(linker, "motor_is_reversed", PROS_ERR, |motor| {
+        i32::from(motor.is_reversed())
+    })?;
+    define_getter(linker, "motor_get_voltage_limit", PROS_ERR, |motor| {
+        motor.voltage_limit()
+    })?;
+
+    linker.func_wrap2_async(
+        "env",
+        "motor_get_raw_position",
+        |mut caller: Caller<'_, Host>, port: u32, timestamp_ptr: u32| {
+            Box::new(async move {
+                let motors = updated_motors(&caller).await;
+                let res = motors.get(port).map(Motor::raw_position);
+                drop(motors);
+
+                if res.is_ok() && timestamp_ptr != 0 {
+                    let timestamp = caller.clock().millis();
+                    caller
+                        .memory()
+                        .write_relaxed(timestamp_ptr as usize, &timestamp.to_le_bytes())?;
+                }
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    for unsupported in ["motor_is_stopped", "motor_get_zero_position_flag"] {
+        linker.func_wrap1_async(
+            "env",
+            unsupported,
+            |mut caller: Caller<'_, Host>, port: u32| {
+                Box::new(async move {
+                    let motors = caller.motors_lock().await;
+                    let res = motors.get(port).and(Err::<i32, _>(ENOSYS));
+                    drop(motors);
+                    Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+                })
+            },
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+//! V5 Optical Sensor API
+//!
+//! Optical sensors report the color set by the `OpticalInput` simulator message, and detect
+//! gestures sent with the `OpticalGesture` message.
+//!
+//! ## Reference
+//!
+//! * `optical_disable_gesture`
+//! * `optical_enable_gesture`
+//! * `optical_get_brightness`
+//! * `optical_get_gesture`
+//! * `optical_get_gesture_raw`
+//! * `optical_get_hue`
+//! * `optical_get_integration_time`
+//! * `optical_get_led_pwm`
+//! * `optical_get_proximity`
+//! * `optical_get_raw` (values are approximated from the detected color)
+//! * `optical_get_rgb`
+//! * `optical_get_saturation`
+//! * `optical_set_integration_time`
+//! * `optical_set_led_pwm`
+
+use pros_sys::{PROS_ERR, PROS_ERR_2_BYTE, PROS_ERR_BYTE, PROS_ERR_F};
+use snafu::OptionExt;
+use wasmtime::{Caller, Linker, WasmTy};
+
+use crate::{
+    error::{InvalidPointerSnafu, SimulatorError},
+    host::{
+        memory::SharedMemoryExt,
+        optical::{GestureData, OpticalSensor},
+        ContextExt, Host, HostCtx, ResultExt,
+    },
+};
+
+/// Runs `f` with the optical sensor on the given port.
+async fn with_sensor<R>(
+    caller: &Caller<'_, Host>,
+    port: u32,
+    f: impl FnOnce(&mut OpticalSensor) -> Result<R, i32>,
+) -> Result<R, i32> {
+    let mut sensors = caller.optical_sensors_lock().await;
+    f(sensors.get(port)?)
+}
+
+/// Writes a struct returned by an API function to the robot code's memory.
+fn write_struct(
+    caller: &Caller<'_, Host>,
+    pointer: u32,
+    bytes: &[u8],
+) -> Result<(), SimulatorError> {
+    caller
+        .memory()
+        .write_relaxed(pointer as usize, bytes)
+        .ok()
+        .context(InvalidPointerSnafu { pointer })
+}
+
+/// Defines an API function that reads a value from the sensor on the given port, or returns
+/// `error_value` and sets errno if there is no sensor on that port.
+fn define_getter<R>(
+    linker: &mut Linker<Host>,
+    name: &str,
+    error_value: R,
+    getter: fn(&OpticalSensor) -> R,
+) -> anyhow::Result<()>
+where
+    R: WasmTy + Copy + Sync + 'static,
+{
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| Ok(getter(sensor))).await;
+                Ok(res.unwrap_or_errno_as(&mut caller, error_value).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that turns gesture detection on or off.
+fn define_gesture_toggle(
+    linker: &mut Linker<Host>,
+    name: &str,
+    enabled: bool,
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| {
+                    sensor.set_gestures_enabled(enabled);
+                    Ok(1)
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+pub fn configure_optical_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    define_getter(linker, "optical_get_hue", PROS_ERR_F, |sensor| {
+        sensor.reading().hue
+    })?;
+    define_getter(linker, "optical_get_saturation", PROS_ERR_F, |sensor| {
+        sensor.reading().saturation
+    })?;
+    define_getter(linker, "optical_get_brightness", PROS_ERR_F, |sensor| {
+        sensor.reading().brightness
+    })?;
+    define_getter(linker, "optical_get_proximity", PROS_ERR, |sensor| {
+        sensor.reading().proximity.into()
+    })?;
+    define_getter(
+        linker,
+        "optical_get_led_pwm",
+        PROS_ERR,
+        OpticalSensor::led_pwm,
+    )?;
+    define_getter(
+        linker,
+        "optical_get_integration_time",
+        PROS_ERR_F,
+        OpticalSensor::integration_time,
+    )?;
+    define_getter(linker, "optical_get_gesture", PROS_ERR, |sensor| {
+        sensor.gesture().direction
+    })?;
+
+    linker.func_wrap2_async(
+        "env",
+        "optical_set_led_pwm",
+        |mut caller: Caller<'_, Host>, port: u32, pwm: i32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| sensor.set_led_pwm(pwm)).await;
+                Ok(res
+                    .map(|_| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "optical_set_integration_time",
+        |mut caller: Caller<'_, Host>, port: u32, time: f64| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| {
+                    sensor.set_integration_time(time);
+                    Ok(1)
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    define_gesture_toggle(linker, "optical_enable_gesture", true)?;
+    define_gesture_toggle(linker, "optical_disable_gesture", false)?;
+
+    // Structs are returned by writing them to a pointer passed as the first argument.
+    linker.func_wrap2_async(
+        "env",
+        "optical_get_rgb",
+        |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| {
+                    let [red, green, blue] = sensor.rgb();
+                    Ok([red, green, blue, sensor.reading().brightness])
+                })
+                .await;
+                let values = res.unwrap_or_errno_as(&mut caller, [PROS_ERR_F; 4]).await;
+                let bytes = values
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect::<Vec<_>>();
+                write_struct(&caller, ret_ptr, &bytes)?;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "optical_get_raw",
+        |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| {
+                    // scale the 8-bit color up to the sensor's 16-bit raw range
+                    let [red, green, blue] = sensor.rgb().map(|channel| channel * 257.0);
+                    let clear = sensor.reading().brightness * 65535.0;
+                    Ok([clear, red, green, blue].map(|value| value as u32))
+                })
+                .await;
+                let values = res
+                    .unwrap_or_errno_as(&mut caller, [PROS_ERR as u32; 4])
+                    .await;
+                let bytes = values
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect::<Vec<_>>();
+                write_struct(&caller, ret_ptr, &bytes)?;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "optical_get_gesture_raw",
+        |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| Ok(sensor.gesture())).await;
+                let mut bytes = [0; 12];
+                match res {
+                    Ok(GestureData {
+                        direction,
+                        count,
+                        time,
+                    }) => {
+                        // udata, ddata, ldata and rdata stay zero
+                        bytes[4] = direction as u8;
+                        bytes[6..8].copy_from_slice(&count.to_le_bytes());
+                        bytes[8..12].copy_from_slice(&time.to_le_bytes());
+                    }
+                    Err(code) => {
+                        caller.set_errno(code).await;
+                        bytes[0..6].fill(PROS_ERR_BYTE);
+                        bytes[6..8].copy_from_slice(&PROS_ERR_2_BYTE.to_le_bytes());
+                        bytes[8..12].copy_from_slice(&PROS_ERR.to_le_bytes());
+                    }
+                }
+                write_struct(&caller, ret_ptr, &bytes)?;
+                Ok(())
+            })
+        },
+    )?;
+
+    Ok(())
+}
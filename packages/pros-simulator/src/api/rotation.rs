@@ -0,0 +1,148 @@
+//! V5 Rotation Sensor API
+//!
+//! Rotation sensors are turned by the `RotationInput` simulator message, and by the robot's
+//! movement if they're attached to a tracking wheel in the robot configuration.
+//!
+//! ## Reference
+//!
+//! * `rotation_get_angle`
+//! * `rotation_get_position`
+//! * `rotation_get_reversed`
+//! * `rotation_get_velocity`
+//! * `rotation_init_reverse`
+//! * `rotation_reset`
+//! * `rotation_reset_position`
+//! * `rotation_reverse`
+//! * `rotation_set_data_rate`
+//! * `rotation_set_position`
+//! * `rotation_set_reversed`
+
+use std::time::Duration;
+
+use pros_sys::PROS_ERR;
+use wasmtime::{Caller, Linker, WasmTy};
+
+use crate::host::{pose::PoseModel, rotation::RotationSensor, Host, HostCtx, ResultExt};
+
+/// Runs `f` with the rotation sensor on the given port and the robot's current pose.
+async fn with_sensor<R>(
+    caller: &Caller<'_, Host>,
+    port: u32,
+    f: impl FnOnce(&mut RotationSensor, &PoseModel, Duration) -> R,
+) -> Result<R, i32> {
+    let now = caller.clock().now();
+    let mut pose = caller.pose_lock().await;
+    pose.update(now);
+    let mut sensors = caller.rotation_sensors_lock().await;
+    Ok(f(sensors.get(port)?, &pose, now))
+}
+
+/// Defines an API function that reads a value from the sensor on the given port, or returns
+/// `PROS_ERR` and sets errno if that fails.
+fn define_getter(
+    linker: &mut Linker<Host>,
+    name: &str,
+    getter: fn(&mut RotationSensor, &PoseModel, Duration) -> i32,
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, getter).await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that modifies the sensor on the given port, returning 1 on success
+/// or `PROS_ERR` and setting errno if the operation fails.
+fn define_action(
+    linker: &mut Linker<Host>,
+    name: &str,
+    action: fn(&mut RotationSensor, &PoseModel, Duration),
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, action).await.map(|_| 1);
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that changes a setting of the sensor on the given port to the given
+/// value, returning 1 on success or `PROS_ERR` and setting errno if the operation fails.
+fn define_setter<A>(
+    linker: &mut Linker<Host>,
+    name: &str,
+    setter: fn(&mut RotationSensor, &PoseModel, Duration, A),
+) -> anyhow::Result<()>
+where
+    A: WasmTy + Send + 'static,
+{
+    linker.func_wrap2_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32, value: A| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor, pose, now| {
+                    setter(sensor, pose, now, value);
+                    1
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+pub fn configure_rotation_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    define_getter(linker, "rotation_get_position", |sensor, pose, now| {
+        sensor.position(pose, now)
+    })?;
+    define_getter(linker, "rotation_get_angle", |sensor, pose, now| {
+        sensor.angle(pose, now)
+    })?;
+    define_getter(linker, "rotation_get_velocity", |sensor, pose, _| {
+        sensor.velocity(pose)
+    })?;
+    define_getter(linker, "rotation_get_reversed", |sensor, _, _| {
+        sensor.is_reversed().into()
+    })?;
+
+    define_action(linker, "rotation_reset", |sensor, pose, now| {
+        let angle = sensor.angle(pose, now);
+        sensor.set_position(pose, now, angle);
+    })?;
+    define_action(linker, "rotation_reset_position", |sensor, pose, now| {
+        sensor.set_position(pose, now, 0)
+    })?;
+    define_setter(
+        linker,
+        "rotation_set_position",
+        |sensor, pose, now, position: u32| sensor.set_position(pose, now, position as i32),
+    )?;
+    define_setter(
+        linker,
+        "rotation_set_data_rate",
+        |sensor, _, _, rate: u32| sensor.set_data_rate(rate),
+    )?;
+    for name in ["rotation_set_reversed", "rotation_init_reverse"] {
+        define_setter(linker, name, |sensor, _, _, reversed: i32| {
+            sensor.set_reversed(reversed != 0)
+        })?;
+    }
+    define_action(linker, "rotation_reverse", |sensor, _, _| {
+        sensor.set_reversed(!sensor.is_reversed())
+    })?;
+
+    Ok(())
+}
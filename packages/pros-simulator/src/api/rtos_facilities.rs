@@ -4,7 +4,7 @@
 //!
 //! * `delay`
 //! * `millis`
-//! * `micros` (not implemented)
+//! * `micros`
 //! * `mutex_create`
 //! * `mutex_delete`
 //! * `mutex_give`
@@ -13,20 +13,20 @@
 //! * `task_delay`
 //! * `task_delay_until`
 //! * `task_delete`
-//! * `task_get_by_name` (not implemented)
-//! * `task_get_count` (not implemented)
+//! * `task_get_by_name`
+//! * `task_get_count`
 //! * `task_get_current` (not implemented)
 //! * `task_get_name`
-//! * `task_get_priority` (not implemented)
-//! * `task_get_state` (not implemented)
-//! * `task_notify` (not implemented)
-//! * `task_notify_clear` (not implemented)
-//! * `task_notify_ext` (not implemented)
-//! * `task_notify_take` (not implemented)
-//! * `task_join` (not implemented)
-//! * `task_resume` (not implemented)
-//! * `task_set_priority` (not implemented)
-//! * `task_suspend` (not implemented)
+//! * `task_get_priority`
+//! * `task_get_state`
+//! * `task_notify`
+//! * `task_notify_clear`
+//! * `task_notify_ext`
+//! * `task_notify_take`
+//! * `task_join`
+//! * `task_resume`
+//! * `task_set_priority`
+//! * `task_suspend`
 //!
 //! ### FreeRTOS
 //!
@@ -36,24 +36,48 @@
 //! * `vTaskSetThreadLocalStoragePointer`
 //! * `xTaskAbortDelay` (not implemented)
 
-use std::{
-    alloc::Layout,
-    ffi::CString,
-    mem::size_of,
-    time::{Duration, Instant},
-};
+use std::{alloc::Layout, ffi::CString, mem::size_of, time::Duration};
 
 use futures_util::Future;
-use pros_sys::TIMEOUT_MAX;
+use pros_sys::{
+    E_TASK_STATE_BLOCKED, E_TASK_STATE_DELETED, E_TASK_STATE_INVALID, E_TASK_STATE_READY,
+    E_TASK_STATE_RUNNING, E_TASK_STATE_SUSPENDED, TASK_PRIORITY_MAX, TASK_PRIORITY_MIN,
+    TIMEOUT_MAX,
+};
+use snafu::{ensure, OptionExt};
 use wasmtime::{Caller, Linker};
 
-use crate::host::{
-    memory::SharedMemoryExt,
-    task::{TaskOptions, TaskPool},
-    thread_local::GetTaskStorage,
-    Host, HostCtx,
+use crate::{
+    error::{InvalidHandleSnafu, InvalidPointerSnafu, SimulatorError},
+    host::{
+        memory::SharedMemoryExt,
+        task::{NotifyAction, TaskHandle, TaskOptions, TaskPool, TaskState},
+        thread_local::GetTaskStorage,
+        ContextExt, Host, HostCtx,
+    },
 };
 
+/// Looks up a task by the handle robot code passed to an API function.
+async fn task_by_handle(
+    caller: &Caller<'_, Host>,
+    task_handle: u32,
+) -> Result<TaskHandle, SimulatorError> {
+    caller
+        .tasks_lock()
+        .await
+        .by_id(task_handle)
+        .context(InvalidHandleSnafu {
+            kind: "task",
+            handle: task_handle,
+        })
+}
+
+/// Converts a PROS task priority (1-16) to a simulator task priority (0-15), clamping it to the
+/// valid range.
+fn task_priority(priority: u32) -> u32 {
+    priority.clamp(TASK_PRIORITY_MIN, TASK_PRIORITY_MAX) - 1
+}
+
 pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
     linker.func_wrap0_async("env", "mutex_create", |caller: Caller<'_, Host>| {
         Box::new(async move {
@@ -67,7 +91,10 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
         "mutex_delete",
         |caller: Caller<'_, Host>, mutex_id: u32| {
             Box::new(async move {
-                caller.mutexes_lock().await.delete_mutex(mutex_id as usize);
+                caller
+                    .mutexes_lock()
+                    .await
+                    .delete_mutex(mutex_id as usize)?;
                 Ok(())
             })
         },
@@ -78,9 +105,8 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
         "mutex_give",
         |caller: Caller<'_, Host>, mutex_id: u32| {
             Box::new(async move {
-                caller.mutexes_lock().await.unlock(mutex_id as usize);
-
-                Ok(u32::from(true))
+                let success = caller.mutexes_lock().await.unlock(mutex_id as usize)?;
+                Ok(u32::from(success))
             })
         },
     )?;
@@ -90,14 +116,18 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
         "mutex_take",
         |caller: Caller<'_, Host>, mutex_id: u32, timeout: u32| {
             Box::new(async move {
-                let timeout = (timeout != TIMEOUT_MAX)
-                    .then(|| Instant::now() + Duration::from_millis(timeout.into()));
-                let success = caller
-                    .mutexes_lock()
-                    .await
-                    .lock(mutex_id as usize, timeout)
-                    .await;
-                Ok(u32::from(success))
+                let deadline = (timeout != TIMEOUT_MAX)
+                    .then(|| caller.clock().now() + Duration::from_millis(timeout.into()));
+                loop {
+                    if caller.mutexes_lock().await.try_lock(mutex_id as usize)? {
+                        break Ok(u32::from(true));
+                    }
+                    if deadline.is_some_and(|deadline| caller.clock().now() >= deadline) {
+                        break Ok(u32::from(false));
+                    }
+                    // check again on the next tick
+                    TaskPool::sleep(caller.data(), Duration::from_millis(1)).await;
+                }
             })
         },
     )?;
@@ -107,8 +137,8 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
         "pvTaskGetThreadLocalStoragePointer",
         |mut caller: Caller<'_, Host>, task_handle: u32, storage_index: i32| {
             Box::new(async move {
-                let storage = caller.task_storage(task_handle).await;
-                Ok(storage.get(caller.memory(), storage_index))
+                let storage = caller.task_storage(task_handle).await?;
+                Ok(storage.get(caller.memory(), storage_index)?)
             })
         },
     )?;
@@ -118,8 +148,8 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
         "vTaskSetThreadLocalStoragePointer",
         |mut caller: Caller<'_, Host>, task_handle: u32, storage_index: i32, value: u32| {
             Box::new(async move {
-                let mut storage = caller.task_storage(task_handle).await;
-                storage.set(caller.memory(), storage_index, value)
+                let mut storage = caller.task_storage(task_handle).await?;
+                Ok(storage.set(caller.memory(), storage_index, value)?)
             })
         },
     )?;
@@ -136,15 +166,12 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
     })?;
 
     fn task_delay(
-        _caller: Caller<'_, Host>,
+        caller: Caller<'_, Host>,
         millis: u32,
     ) -> Box<dyn Future<Output = anyhow::Result<()>> + Send + '_> {
         Box::new(async move {
             if millis > 0 {
-                let end = Instant::now() + Duration::from_millis(millis.into());
-                while Instant::now() < end {
-                    TaskPool::yield_now().await;
-                }
+                TaskPool::sleep(caller.data(), Duration::from_millis(millis.into())).await;
             } else {
                 TaskPool::yield_now().await;
             }
@@ -161,23 +188,27 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
         "task_delay_until",
         |caller: Caller<'_, Host>, prev_time_ptr: u32, delta_ms: u32| {
             Box::new(async move {
-                assert_ne!(prev_time_ptr, 0);
-                assert!(delta_ms > 0);
-
-                let epoch = caller.start_time();
+                ensure!(
+                    prev_time_ptr != 0,
+                    InvalidPointerSnafu {
+                        pointer: prev_time_ptr
+                    }
+                );
 
                 let memory = caller.memory();
-                let u32_bits = memory.read_relaxed(prev_time_ptr as usize, size_of::<u32>())?;
+                let u32_bits = memory
+                    .read_relaxed(prev_time_ptr as usize, size_of::<u32>())
+                    .ok()
+                    .context(InvalidPointerSnafu {
+                        pointer: prev_time_ptr,
+                    })?;
                 let prev_time = u32::from_le_bytes(u32_bits.try_into().unwrap());
 
-                let end = epoch
-                    + Duration::from_millis(prev_time.into())
-                    + Duration::from_millis(delta_ms.into());
+                let wake_time = prev_time.wrapping_add(delta_ms);
+                memory.write_relaxed(prev_time_ptr as usize, &wake_time.to_le_bytes())?;
 
                 TaskPool::yield_now().await;
-                while Instant::now() < end {
-                    TaskPool::yield_now().await;
-                }
+                TaskPool::sleep_until(caller.data(), Duration::from_millis(wake_time.into())).await;
 
                 Ok(())
             })
@@ -201,7 +232,11 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
     })?;
 
     linker.func_wrap0_async("env", "millis", |caller: Caller<'_, Host>| {
-        Box::new(async move { Ok(caller.start_time().elapsed().as_millis() as u32) })
+        Box::new(async move { Ok(caller.clock().read_millis()) })
+    })?;
+
+    linker.func_wrap0_async("env", "micros", |caller: Caller<'_, Host>| {
+        Box::new(async move { Ok(caller.clock().read_micros()) })
     })?;
 
     // task_t task_create ( task_fn_t function,
@@ -217,12 +252,20 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
          parameters: u32,
          priority: u32,
          _stack_depth: u32,
-         _name: u32| {
+         name_ptr: u32| {
             Box::new(async move {
+                let name = if name_ptr != 0 {
+                    Some(caller.memory().read_c_str(name_ptr)?)
+                } else {
+                    None
+                };
                 let mut tasks = caller.tasks_lock().await;
-                let opts =
+                let mut opts =
                     TaskOptions::new_extern(&mut tasks, caller.data(), function, parameters)?
-                        .priority(priority - 1);
+                        .priority(task_priority(priority));
+                if let Some(name) = name {
+                    opts = opts.name(name);
+                }
                 let task = tasks
                     .spawn(opts, &caller.module(), &caller.interface())
                     .await?;
@@ -266,7 +309,7 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
                     let allocator = current_task.allocator();
                     let ptr = allocator
                         .memalign(&mut caller, Layout::for_value(name_bytes))
-                        .await;
+                        .await?;
                     caller.memory().write_relaxed(ptr as usize, name_bytes)?;
 
                     Ok(ptr)
@@ -277,5 +320,166 @@ pub fn configure_rtos_facilities_api(linker: &mut Linker<Host>) -> anyhow::Resul
         },
     )?;
 
+    linker.func_wrap1_async(
+        "env",
+        "task_notify",
+        |caller: Caller<'_, Host>, task_id: u32| {
+            Box::new(async move {
+                let task = task_by_handle(&caller, task_id).await?;
+                task.lock().await.notify(0, NotifyAction::Increment);
+                Ok(u32::from(true))
+            })
+        },
+    )?;
+
+    linker.func_wrap4_async(
+        "env",
+        "task_notify_ext",
+        |mut caller: Caller<'_, Host>,
+         task_id: u32,
+         value: u32,
+         action: u32,
+         prev_value_ptr: u32| {
+            Box::new(async move {
+                let task = task_by_handle(&caller, task_id).await?;
+                let (prev_value, success) = match NotifyAction::try_from(action) {
+                    Ok(action) => task.lock().await.notify(value, action),
+                    Err(code) => {
+                        caller.set_errno(code).await;
+                        return Ok(u32::from(false));
+                    }
+                };
+                if prev_value_ptr != 0 {
+                    caller
+                        .memory()
+                        .write_relaxed(prev_value_ptr as usize, &prev_value.to_le_bytes())
+                        .ok()
+                        .context(InvalidPointerSnafu {
+                            pointer: prev_value_ptr,
+                        })?;
+                }
+                Ok(u32::from(success))
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "task_notify_take",
+        |caller: Caller<'_, Host>, clear_on_exit: u32, timeout: u32| {
+            Box::new(async move {
+                let timeout =
+                    (timeout != TIMEOUT_MAX).then(|| Duration::from_millis(timeout.into()));
+                Ok(TaskPool::notify_take(caller.data(), clear_on_exit != 0, timeout).await)
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "task_notify_clear",
+        |caller: Caller<'_, Host>, task_id: u32| {
+            Box::new(async move {
+                let task = task_by_handle(&caller, task_id).await?;
+                let was_pending = task.lock().await.clear_notification();
+                Ok(u32::from(was_pending))
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "task_suspend",
+        |caller: Caller<'_, Host>, task_id: u32| {
+            Box::new(async move {
+                let task = task_by_handle(&caller, task_id).await?;
+                TaskPool::suspend_task(caller.data(), task).await;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "task_resume",
+        |caller: Caller<'_, Host>, task_id: u32| {
+            Box::new(async move {
+                let task = task_by_handle(&caller, task_id).await?;
+                TaskPool::resume_task(caller.data(), task).await;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "task_set_priority",
+        |caller: Caller<'_, Host>, task_id: u32, priority: u32| {
+            Box::new(async move {
+                let task = task_by_handle(&caller, task_id).await?;
+                TaskPool::set_task_priority(caller.data(), task, task_priority(priority)).await;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "task_get_priority",
+        |caller: Caller<'_, Host>, task_id: u32| {
+            Box::new(async move {
+                let task = task_by_handle(&caller, task_id).await?;
+                let priority = task.lock().await.priority();
+                Ok(priority + 1)
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "task_get_state",
+        |caller: Caller<'_, Host>, task_id: u32| {
+            Box::new(async move {
+                let state = caller.tasks_lock().await.task_state(task_id).await;
+                Ok(match state {
+                    Some(TaskState::Running) => E_TASK_STATE_RUNNING,
+                    Some(TaskState::Ready) => E_TASK_STATE_READY,
+                    Some(TaskState::Blocked) => E_TASK_STATE_BLOCKED,
+                    Some(TaskState::Suspended) => E_TASK_STATE_SUSPENDED,
+                    Some(TaskState::Finished | TaskState::Deleted) => E_TASK_STATE_DELETED,
+                    None => E_TASK_STATE_INVALID,
+                })
+            })
+        },
+    )?;
+
+    linker.func_wrap0_async("env", "task_get_count", |caller: Caller<'_, Host>| {
+        Box::new(async move { Ok(caller.tasks_lock().await.task_count() as u32) })
+    })?;
+
+    linker.func_wrap1_async(
+        "env",
+        "task_get_by_name",
+        |caller: Caller<'_, Host>, name_ptr: u32| {
+            Box::new(async move {
+                let name = caller.memory().read_c_str(name_ptr)?;
+                let task_id = caller.tasks_lock().await.find_by_name(&name).await;
+                // returns NULL if there's no task with that name
+                Ok(task_id.unwrap_or(0))
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "task_join",
+        |caller: Caller<'_, Host>, task_id: u32| {
+            Box::new(async move {
+                TaskPool::join(caller.data(), task_id).await;
+                Ok(())
+            })
+        },
+    )?;
+
     Ok(())
 }
@@ -0,0 +1,335 @@
+//! Brain Screen API
+//!
+//! The 480x272 screen is sent to the simulator interface with `ScreenUpdated` events, which
+//! only contain the parts of the screen that changed. Touches come from `ScreenTouch` simulator
+//! messages. Text is drawn with a 5x7 pixel font, scaled up 1, 2 or 3 times for the small,
+//! medium and large text sizes.
+//!
+//! Coordinates are `int16_t`s in PROS, so they're truncated to 16 bits before drawing.
+//!
+//! ## Reference
+//!
+//! * `screen_copy_area`
+//! * `screen_draw_circle`
+//! * `screen_draw_line`
+//! * `screen_draw_pixel`
+//! * `screen_draw_rect`
+//! * `screen_erase`
+//! * `screen_erase_circle`
+//! * `screen_erase_line`
+//! * `screen_erase_pixel`
+//! * `screen_erase_rect`
+//! * `screen_fill_circle`
+//! * `screen_fill_rect`
+//! * `screen_get_eraser`
+//! * `screen_get_pen`
+//! * `screen_print`
+//! * `screen_print_at`
+//! * `screen_scroll`
+//! * `screen_scroll_area`
+//! * `screen_set_eraser`
+//! * `screen_set_pen`
+//! * `screen_touch_callback`
+//! * `screen_touch_status`
+//! * `screen_vprintf`
+//! * `screen_vprintf_at`
+
+use pros_simulator_interface::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use pros_sys::PROS_ERR;
+use snafu::OptionExt;
+use wasmtime::{Caller, Linker};
+
+use crate::{
+    error::InvalidPointerSnafu,
+    host::{
+        memory::SharedMemoryExt,
+        printf::sprintf,
+        screen::{Screen, TextFormat, TouchEvent},
+        Host, HostCtx, ResultExt,
+    },
+};
+
+/// Defines an API function that draws a shape between two corners or points.
+fn define_two_point(
+    linker: &mut Linker<Host>,
+    name: &str,
+    draw: fn(&mut Screen, i16, i16, i16, i16),
+) -> anyhow::Result<()> {
+    linker.func_wrap4_async(
+        "env",
+        name,
+        move |caller: Caller<'_, Host>, x0: i32, y0: i32, x1: i32, y1: i32| {
+            Box::new(async move {
+                let mut screen = caller.screen_lock().await;
+                draw(&mut screen, x0 as i16, y0 as i16, x1 as i16, y1 as i16);
+                Ok(1u32)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that draws a circle.
+fn define_circle(
+    linker: &mut Linker<Host>,
+    name: &str,
+    draw: fn(&mut Screen, i16, i16, i16),
+) -> anyhow::Result<()> {
+    linker.func_wrap3_async(
+        "env",
+        name,
+        move |caller: Caller<'_, Host>, x: i32, y: i32, radius: i32| {
+            Box::new(async move {
+                let mut screen = caller.screen_lock().await;
+                draw(&mut screen, x as i16, y as i16, radius as i16);
+                Ok(1u32)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+pub fn configure_screen_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        "screen_set_pen",
+        |caller: Caller<'_, Host>, color: u32| {
+            Box::new(async move {
+                caller.screen_lock().await.set_pen(color);
+                Ok(1u32)
+            })
+        },
+    )?;
+
+    linker.func_wrap1_async(
+        "env",
+        "screen_set_eraser",
+        |caller: Caller<'_, Host>, color: u32| {
+            Box::new(async move {
+                caller.screen_lock().await.set_eraser(color);
+                Ok(1u32)
+            })
+        },
+    )?;
+
+    linker.func_wrap0_async("env", "screen_get_pen", |caller: Caller<'_, Host>| {
+        Box::new(async move { Ok(caller.screen_lock().await.pen()) })
+    })?;
+
+    linker.func_wrap0_async("env", "screen_get_eraser", |caller: Caller<'_, Host>| {
+        Box::new(async move { Ok(caller.screen_lock().await.eraser()) })
+    })?;
+
+    linker.func_wrap0_async("env", "screen_erase", |caller: Caller<'_, Host>| {
+        Box::new(async move {
+            caller.screen_lock().await.erase();
+            Ok(1u32)
+        })
+    })?;
+
+    linker.func_wrap2_async(
+        "env",
+        "screen_draw_pixel",
+        |caller: Caller<'_, Host>, x: i32, y: i32| {
+            Box::new(async move {
+                let mut screen = caller.screen_lock().await;
+                screen.draw_pixel(x as i16, y as i16, false);
+                Ok(1u32)
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "screen_erase_pixel",
+        |caller: Caller<'_, Host>, x: i32, y: i32| {
+            Box::new(async move {
+                let mut screen = caller.screen_lock().await;
+                screen.draw_pixel(x as i16, y as i16, true);
+                Ok(1u32)
+            })
+        },
+    )?;
+
+    define_two_point(linker, "screen_draw_line", |screen, x0, y0, x1, y1| {
+        screen.draw_line(x0, y0, x1, y1, false)
+    })?;
+    define_two_point(linker, "screen_erase_line", |screen, x0, y0, x1, y1| {
+        screen.draw_line(x0, y0, x1, y1, true)
+    })?;
+    define_two_point(linker, "screen_draw_rect", |screen, x0, y0, x1, y1| {
+        screen.draw_rect(x0, y0, x1, y1, false)
+    })?;
+    define_two_point(linker, "screen_erase_rect", |screen, x0, y0, x1, y1| {
+        screen.fill_rect(x0, y0, x1, y1, true)
+    })?;
+    define_two_point(linker, "screen_fill_rect", |screen, x0, y0, x1, y1| {
+        screen.fill_rect(x0, y0, x1, y1, false)
+    })?;
+
+    define_circle(linker, "screen_draw_circle", |screen, x, y, radius| {
+        screen.draw_circle(x, y, radius, false)
+    })?;
+    define_circle(linker, "screen_erase_circle", |screen, x, y, radius| {
+        screen.fill_circle(x, y, radius, true)
+    })?;
+    define_circle(linker, "screen_fill_circle", |screen, x, y, radius| {
+        screen.fill_circle(x, y, radius, false)
+    })?;
+
+    linker.func_wrap2_async(
+        "env",
+        "screen_scroll",
+        |caller: Caller<'_, Host>, start_line: i32, lines: i32| {
+            Box::new(async move {
+                let mut screen = caller.screen_lock().await;
+                let (right, bottom) = (SCREEN_WIDTH as i16 - 1, SCREEN_HEIGHT as i16 - 1);
+                screen.scroll_area(0, start_line as i16, right, bottom, lines as i16);
+                Ok(1u32)
+            })
+        },
+    )?;
+
+    linker.func_wrap5_async(
+        "env",
+        "screen_scroll_area",
+        |caller: Caller<'_, Host>, x0: i32, y0: i32, x1: i32, y1: i32, lines: i32| {
+            Box::new(async move {
+                let mut screen = caller.screen_lock().await;
+                screen.scroll_area(x0 as i16, y0 as i16, x1 as i16, y1 as i16, lines as i16);
+                Ok(1u32)
+            })
+        },
+    )?;
+
+    linker.func_wrap6_async(
+        "env",
+        "screen_copy_area",
+        |caller: Caller<'_, Host>,
+         x0: i32,
+         y0: i32,
+         x1: i32,
+         y1: i32,
+         buffer_ptr: u32,
+         stride: i32| {
+            Box::new(async move {
+                // coordinates are int16_t in PROS
+                let (x0, y0, x1, y1) = (x0 as i16, y0 as i16, x1 as i16, y1 as i16);
+                let stride = stride.max(0) as usize;
+                let bytes = Screen::copy_area_len(x0, y0, x1, y1, stride)
+                    .and_then(|len| len.checked_mul(4))
+                    .and_then(|len| caller.memory().read_relaxed(buffer_ptr as usize, len).ok())
+                    .context(InvalidPointerSnafu {
+                        pointer: buffer_ptr,
+                    })?;
+                let pixels = bytes
+                    .chunks_exact(4)
+                    .map(|pixel| u32::from_le_bytes(pixel.try_into().unwrap()))
+                    .collect::<Vec<_>>();
+                caller
+                    .screen_lock()
+                    .await
+                    .copy_area(x0, y0, x1, y1, &pixels, stride);
+                Ok(1u32)
+            })
+        },
+    )?;
+
+    // screen_print and screen_vprintf take a format, line, format string and arguments. The
+    // variadic arguments and the `va_list` are both passed as a pointer to the arguments.
+    for name in ["screen_print", "screen_vprintf"] {
+        linker.func_wrap4_async(
+            "env",
+            name,
+            |mut caller: Caller<'_, Host>, format: i32, line: i32, text_ptr: u32, args_ptr: u32| {
+                Box::new(async move {
+                    let text = caller.memory().read_c_str(text_ptr)?;
+                    let text = sprintf(&caller.memory(), &text, args_ptr)?;
+                    let res = TextFormat::try_from(format);
+                    if let Ok(format) = res {
+                        caller.screen_lock().await.print(format, line as i16, &text);
+                    }
+                    Ok(res
+                        .map(|_| 1)
+                        .unwrap_or_errno_as(&mut caller, PROS_ERR as u32)
+                        .await)
+                })
+            },
+        )?;
+    }
+
+    // screen_print_at and screen_vprintf_at take a format, position, format string and
+    // arguments
+    for name in ["screen_print_at", "screen_vprintf_at"] {
+        linker.func_wrap5_async(
+            "env",
+            name,
+            |mut caller: Caller<'_, Host>,
+             format: i32,
+             x: i32,
+             y: i32,
+             text_ptr: u32,
+             args_ptr: u32| {
+                Box::new(async move {
+                    let text = caller.memory().read_c_str(text_ptr)?;
+                    let text = sprintf(&caller.memory(), &text, args_ptr)?;
+                    let res = TextFormat::try_from(format);
+                    if let Ok(format) = res {
+                        let mut screen = caller.screen_lock().await;
+                        screen.print_at(format, x as i16, y as i16, &text);
+                    }
+                    Ok(res
+                        .map(|_| 1)
+                        .unwrap_or_errno_as(&mut caller, PROS_ERR as u32)
+                        .await)
+                })
+            },
+        )?;
+    }
+
+    linker.func_wrap1_async(
+        "env",
+        "screen_touch_status",
+        |caller: Caller<'_, Host>, ret_ptr: u32| {
+            Box::new(async move {
+                let status = caller.screen_lock().await.touch_status();
+                // screen_touch_status_s_t is returned by writing it to the pointer passed as the
+                // first argument
+                let mut bytes = Vec::with_capacity(16);
+                bytes.extend(i32::from(status.event).to_le_bytes());
+                bytes.extend(status.x.to_le_bytes());
+                bytes.extend(status.y.to_le_bytes());
+                bytes.extend(status.press_count.to_le_bytes());
+                bytes.extend(status.release_count.to_le_bytes());
+                caller
+                    .memory()
+                    .write_relaxed(ret_ptr as usize, &bytes)
+                    .ok()
+                    .context(InvalidPointerSnafu { pointer: ret_ptr })?;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap2_async(
+        "env",
+        "screen_touch_callback",
+        |mut caller: Caller<'_, Host>, callback: u32, event: i32| {
+            Box::new(async move {
+                let res = TouchEvent::try_from(event);
+                if let Ok(event) = res {
+                    caller
+                        .screen_lock()
+                        .await
+                        .register_touch_callback(callback, event);
+                }
+                Ok(res
+                    .map(|_| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR as u32)
+                    .await)
+            })
+        },
+    )?;
+
+    Ok(())
+}
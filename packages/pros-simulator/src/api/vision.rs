@@ -0,0 +1,393 @@
+//! V5 Vision Sensor API
+//!
+//! Vision sensors see the objects set by the `VisionObjects` simulator message. Signatures can
+//! be stored on the sensor, but they aren't used to detect anything; objects are matched to
+//! signatures by the ID they're sent with.
+//!
+//! ## Reference
+//!
+//! * `vision_clear_led`
+//! * `vision_create_color_code`
+//! * `vision_get_by_code`
+//! * `vision_get_by_sig`
+//! * `vision_get_by_size`
+//! * `vision_get_exposure`
+//! * `vision_get_object_count`
+//! * `vision_get_signature`
+//! * `vision_get_white_balance`
+//! * `vision_read_by_code`
+//! * `vision_read_by_sig`
+//! * `vision_read_by_size`
+//! * `vision_set_auto_white_balance`
+//! * `vision_set_exposure`
+//! * `vision_set_led`
+//! * `vision_set_signature`
+//! * `vision_set_white_balance`
+//! * `vision_set_wifi_mode`
+//! * `vision_set_zero_point`
+//! * `vision_signature_from_utility`
+
+use pros_sys::{PROS_ERR, VISION_OBJECT_ERR_SIG};
+use snafu::OptionExt;
+use wasmtime::{Caller, Linker, WasmTy};
+
+use crate::{
+    error::{InvalidPointerSnafu, SimulatorError},
+    host::{
+        memory::SharedMemoryExt,
+        vision::{
+            color_code, error_object_bytes, ObjectFilter, VisionSensor, OBJECT_SIZE, SIGNATURE_SIZE,
+        },
+        ContextExt, Host, HostCtx, ResultExt,
+    },
+};
+
+/// Runs `f` with the vision sensor on the given port.
+async fn with_sensor<R>(
+    caller: &Caller<'_, Host>,
+    port: u32,
+    f: impl FnOnce(&mut VisionSensor) -> Result<R, i32>,
+) -> Result<R, i32> {
+    let mut sensors = caller.vision_sensors_lock().await;
+    f(sensors.get(port)?)
+}
+
+/// Writes a struct returned by an API function to the robot code's memory.
+fn write_struct(
+    caller: &Caller<'_, Host>,
+    pointer: u32,
+    bytes: &[u8],
+) -> Result<(), SimulatorError> {
+    caller
+        .memory()
+        .write_relaxed(pointer as usize, bytes)
+        .ok()
+        .context(InvalidPointerSnafu { pointer })
+}
+
+/// Defines an API function that reads a value from the sensor on the given port, or returns
+/// `PROS_ERR` and sets errno if there is no sensor on that port.
+fn define_getter(
+    linker: &mut Linker<Host>,
+    name: &str,
+    getter: fn(&VisionSensor) -> i32,
+) -> anyhow::Result<()> {
+    linker.func_wrap1_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| Ok(getter(sensor))).await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Defines an API function that changes a setting of the sensor on the given port to the given
+/// value, returning 1 on success or `PROS_ERR` and setting errno if the operation fails.
+fn define_setter<A>(
+    linker: &mut Linker<Host>,
+    name: &str,
+    setter: fn(&mut VisionSensor, A) -> Result<(), i32>,
+) -> anyhow::Result<()>
+where
+    A: WasmTy + Send + 'static,
+{
+    linker.func_wrap2_async(
+        "env",
+        name,
+        move |mut caller: Caller<'_, Host>, port: u32, value: A| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| setter(sensor, value)).await;
+                Ok(res
+                    .map(|_| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// Writes the object at `size_id` to `ret_ptr`, or an object with the signature
+/// `VISION_OBJECT_ERR_SIG` if it doesn't exist.
+async fn get_object(
+    caller: &mut Caller<'_, Host>,
+    ret_ptr: u32,
+    port: u32,
+    filter: Result<ObjectFilter, i32>,
+    size_id: u32,
+) -> anyhow::Result<()> {
+    let res = with_sensor(caller, port, |sensor| {
+        let objects = sensor.read_objects(filter?, size_id, 1)?;
+        Ok(objects[0])
+    })
+    .await;
+    let object = res.unwrap_or_errno_as(caller, error_object_bytes()).await;
+    write_struct(caller, ret_ptr, &object)?;
+    Ok(())
+}
+
+/// Copies up to `count` objects into the array at `array_ptr`, filling the rest of the array with
+/// objects with the signature `VISION_OBJECT_ERR_SIG`. Returns the number of objects copied, or
+/// `PROS_ERR` if there are no objects at `size_id`.
+async fn read_objects(
+    caller: &mut Caller<'_, Host>,
+    port: u32,
+    filter: Result<ObjectFilter, i32>,
+    size_id: u32,
+    count: u32,
+    array_ptr: u32,
+) -> anyhow::Result<i32> {
+    // make sure the whole array fits in memory before building it, since `count` comes from the
+    // robot code
+    (count as usize)
+        .checked_mul(OBJECT_SIZE)
+        .and_then(|len| (array_ptr as usize).checked_add(len))
+        .filter(|&end| end <= caller.memory().data_size())
+        .context(InvalidPointerSnafu { pointer: array_ptr })?;
+
+    let res = with_sensor(caller, port, |sensor| {
+        sensor.read_objects(filter?, size_id, count)
+    })
+    .await;
+    let (objects, return_value) = match res {
+        Ok(objects) => {
+            let copied = objects.len() as i32;
+            (objects, copied)
+        }
+        Err(code) => {
+            caller.set_errno(code).await;
+            (Vec::new(), PROS_ERR)
+        }
+    };
+    let mut bytes = objects.concat();
+    for _ in objects.len()..count as usize {
+        bytes.extend_from_slice(&error_object_bytes());
+    }
+    write_struct(caller, array_ptr, &bytes)?;
+    Ok(return_value)
+}
+
+pub fn configure_vision_api(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    define_getter(linker, "vision_get_object_count", |sensor| {
+        sensor.object_count() as i32
+    })?;
+    define_getter(linker, "vision_get_exposure", VisionSensor::exposure)?;
+    define_getter(
+        linker,
+        "vision_get_white_balance",
+        VisionSensor::white_balance,
+    )?;
+
+    define_setter(linker, "vision_set_exposure", |sensor, exposure: i32| {
+        sensor.set_exposure(exposure as u8 as i32);
+        Ok(())
+    })?;
+    define_setter(linker, "vision_set_white_balance", |sensor, rgb: i32| {
+        sensor.set_white_balance(rgb);
+        Ok(())
+    })?;
+    define_setter(
+        linker,
+        "vision_set_auto_white_balance",
+        |sensor, enabled: u32| sensor.set_auto_white_balance(enabled as u8 as u32),
+    )?;
+    define_setter(linker, "vision_set_led", |sensor, rgb: i32| {
+        sensor.set_led(Some(rgb));
+        Ok(())
+    })?;
+    define_setter(
+        linker,
+        "vision_set_zero_point",
+        VisionSensor::set_zero_point,
+    )?;
+    define_setter(linker, "vision_set_wifi_mode", |sensor, mode: u32| {
+        sensor.set_wifi_enabled(mode as u8 != 0);
+        Ok(())
+    })?;
+
+    linker.func_wrap1_async(
+        "env",
+        "vision_clear_led",
+        |mut caller: Caller<'_, Host>, port: u32| {
+            Box::new(async move {
+                let res = with_sensor(&caller, port, |sensor| {
+                    sensor.set_led(None);
+                    Ok(1)
+                })
+                .await;
+                Ok(res.unwrap_or_errno_as(&mut caller, PROS_ERR).await)
+            })
+        },
+    )?;
+
+    // Structs are returned by writing them to a pointer passed as the first argument.
+    linker.func_wrap3_async(
+        "env",
+        "vision_get_by_size",
+        |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32, size_id: u32| {
+            Box::new(async move {
+                get_object(&mut caller, ret_ptr, port, Ok(ObjectFilter::All), size_id).await
+            })
+        },
+    )?;
+
+    linker.func_wrap4_async(
+        "env",
+        "vision_get_by_sig",
+        |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32, size_id: u32, signature: u32| {
+            Box::new(async move {
+                let filter = ObjectFilter::signature(signature);
+                get_object(&mut caller, ret_ptr, port, filter, size_id).await
+            })
+        },
+    )?;
+
+    linker.func_wrap4_async(
+        "env",
+        "vision_get_by_code",
+        |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32, size_id: u32, code: u32| {
+            Box::new(async move {
+                let filter = Ok(ObjectFilter::ColorCode(code as u16));
+                get_object(&mut caller, ret_ptr, port, filter, size_id).await
+            })
+        },
+    )?;
+
+    linker.func_wrap4_async(
+        "env",
+        "vision_read_by_size",
+        |mut caller: Caller<'_, Host>, port: u32, size_id: u32, count: u32, array_ptr: u32| {
+            Box::new(async move {
+                let filter = Ok(ObjectFilter::All);
+                read_objects(&mut caller, port, filter, size_id, count, array_ptr).await
+            })
+        },
+    )?;
+
+    linker.func_wrap5_async(
+        "env",
+        "vision_read_by_sig",
+        |mut caller: Caller<'_, Host>,
+         port: u32,
+         size_id: u32,
+         signature: u32,
+         count: u32,
+         array_ptr: u32| {
+            Box::new(async move {
+                let filter = ObjectFilter::signature(signature);
+                read_objects(&mut caller, port, filter, size_id, count, array_ptr).await
+            })
+        },
+    )?;
+
+    linker.func_wrap5_async(
+        "env",
+        "vision_read_by_code",
+        |mut caller: Caller<'_, Host>,
+         port: u32,
+         size_id: u32,
+         code: u32,
+         count: u32,
+         array_ptr: u32| {
+            Box::new(async move {
+                let filter = Ok(ObjectFilter::ColorCode(code as u16));
+                read_objects(&mut caller, port, filter, size_id, count, array_ptr).await
+            })
+        },
+    )?;
+
+    linker.func_wrap6_async(
+        "env",
+        "vision_create_color_code",
+        |mut caller: Caller<'_, Host>,
+         port: u32,
+         id1: u32,
+         id2: u32,
+         id3: u32,
+         id4: u32,
+         id5: u32| {
+            Box::new(async move {
+                let res =
+                    with_sensor(&caller, port, |_| color_code([id1, id2, id3, id4, id5])).await;
+                Ok(u32::from(res.unwrap_or_errno_as(&mut caller, 0).await))
+            })
+        },
+    )?;
+
+    linker.func_wrap3_async(
+        "env",
+        "vision_get_signature",
+        |mut caller: Caller<'_, Host>, ret_ptr: u32, port: u32, id: u32| {
+            Box::new(async move {
+                let res =
+                    with_sensor(&caller, port, |sensor| sensor.signature(id as u8 as u32)).await;
+                let mut error_signature = [0; SIGNATURE_SIZE];
+                error_signature[0] = VISION_OBJECT_ERR_SIG as u8;
+                let signature = res.unwrap_or_errno_as(&mut caller, error_signature).await;
+                write_struct(&caller, ret_ptr, &signature)?;
+                Ok(())
+            })
+        },
+    )?;
+
+    linker.func_wrap3_async(
+        "env",
+        "vision_set_signature",
+        |mut caller: Caller<'_, Host>, port: u32, id: u32, signature_ptr: u32| {
+            Box::new(async move {
+                let signature = caller
+                    .memory()
+                    .read_relaxed(signature_ptr as usize, SIGNATURE_SIZE)
+                    .ok()
+                    .context(InvalidPointerSnafu {
+                        pointer: signature_ptr,
+                    })?;
+                let res = with_sensor(&caller, port, |sensor| {
+                    sensor.set_signature(id as u8 as u32, signature.try_into().unwrap())
+                })
+                .await;
+                Ok(res
+                    .map(|_| 1)
+                    .unwrap_or_errno_as(&mut caller, PROS_ERR)
+                    .await)
+            })
+        },
+    )?;
+
+    linker.func_wrap10_async(
+        "env",
+        "vision_signature_from_utility",
+        |caller: Caller<'_, Host>,
+         ret_ptr: u32,
+         id: i32,
+         u_min: i32,
+         u_max: i32,
+         u_mean: i32,
+         v_min: i32,
+         v_max: i32,
+         v_mean: i32,
+         range: f32,
+         signature_type: i32| {
+            Box::new(async move {
+                let mut signature = [0; SIGNATURE_SIZE];
+                signature[0] = id as u8;
+                signature[4..8].copy_from_slice(&range.to_le_bytes());
+                let fields = [u_min, u_max, u_mean, v_min, v_max, v_mean];
+                for (index, field) in fields.into_iter().enumerate() {
+                    let start = 8 + index * 4;
+                    signature[start..start + 4].copy_from_slice(&field.to_le_bytes());
+                }
+                // the rgb field (bytes 32..36) is left as 0
+                signature[36..40].copy_from_slice(&signature_type.to_le_bytes());
+                write_struct(&caller, ret_ptr, &signature)?;
+                Ok(())
+            })
+        },
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! On-disk cache of compiled robot code.
+//!
+//! Compiling robot code with debug info is the slowest part of starting the simulator, so the
+//! compiled module can be saved to a cache directory and loaded on later runs. Cached modules are
+//! keyed by a hash of the robot code, the engine configuration and the simulator version, so
+//! changing any of them causes the robot code to be compiled again.
+//!
+//! Loading a cached module runs the machine code inside it without checking it, so the cache
+//! directory must be trusted: anyone who can write to it can run code in the simulator.
+
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    process,
+};
+
+use sha2::{Digest, Sha256};
+use snafu::ResultExt;
+use wasmtime::{Engine, Module};
+
+use crate::error::{CompileSnafu, SimulatorError};
+
+/// Compiles robot code, or loads it from the cache directory if it has been compiled there before.
+///
+/// Problems with the cache itself are logged and otherwise ignored, since the robot code can
+/// always be compiled from scratch.
+pub(crate) fn load_module(
+    engine: &Engine,
+    wasm: &[u8],
+    cache_dir: Option<&Path>,
+) -> Result<Module, SimulatorError> {
+    let Some(cache_dir) = cache_dir else {
+        return Module::new(engine, wasm).context(CompileSnafu);
+    };
+
+    let cache_path = cache_dir.join(format!("{}.cwasm", cache_key(engine, wasm)));
+    if cache_path.exists() {
+        // SAFETY: The cache directory must be trusted (see `SimulatorBuilder::cache_dir`), since
+        // the machine code in the file is run as-is. wasmtime refuses to load modules that were
+        // compiled for a different engine configuration or wasmtime version.
+        match unsafe { Module::deserialize_file(engine, &cache_path) } {
+            Ok(module) => {
+                tracing::info!("Loaded compiled robot code from {}", cache_path.display());
+                return Ok(module);
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Ignoring invalid cache entry {}: {err}",
+                    cache_path.display()
+                );
+            }
+        }
+    }
+
+    let module = Module::new(engine, wasm).context(CompileSnafu)?;
+    if let Err(err) = save_module(&module, cache_dir, &cache_path) {
+        tracing::warn!("Failed to cache compiled robot code: {err}");
+    }
+    Ok(module)
+}
+
+/// Hashes everything that affects the compiled module.
+fn cache_key(engine: &Engine, wasm: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    engine
+        .precompile_compatibility_hash()
+        .hash(&mut Sha256Hasher(&mut hasher));
+    hasher.update(wasm);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Feeds values that implement [`Hash`] into a SHA-256 hash. Unlike `DefaultHasher`, the result
+/// doesn't change between Rust releases.
+struct Sha256Hasher<'a>(&'a mut Sha256);
+
+impl Hasher for Sha256Hasher<'_> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+fn save_module(module: &Module, cache_dir: &Path, cache_path: &PathBuf) -> anyhow::Result<()> {
+    fs::create_dir_all(cache_dir)?;
+    let serialized = module.serialize()?;
+
+    // Write to a temporary file first so other simulators never see a partially written module.
+    let temp_path = cache_path.with_extension(format!("{}.tmp", process::id()));
+    fs::write(&temp_path, serialized)?;
+    if let Err(err) = fs::rename(&temp_path, cache_path) {
+        _ = fs::remove_file(&temp_path);
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime::Config;
+
+    use super::*;
+
+    #[test]
+    fn cache_key_depends_on_the_code_and_engine() {
+        let engine = Engine::default();
+        let key = cache_key(&engine, b"robot code");
+        assert_eq!(key, cache_key(&Engine::default(), b"robot code"));
+        assert_ne!(key, cache_key(&engine, b"other robot code"));
+
+        let mut config = Config::new();
+        config.debug_info(true);
+        let debug_engine = Engine::new(&config).unwrap();
+        assert_ne!(key, cache_key(&debug_engine, b"robot code"));
+    }
+}
@@ -0,0 +1,128 @@
+//! Errors that can stop a simulation.
+
+use std::{error::Error, string::FromUtf8Error};
+
+use pros_simulator_interface::SimulationSpeed;
+use snafu::{IntoError, Snafu};
+use wasmtime::WasmBacktrace;
+
+use crate::host::thread_local::NUM_THREAD_LOCAL_STORAGE_POINTERS;
+
+type BoxedError = Box<dyn Error + Send + Sync>;
+
+/// An error that stopped the simulator.
+///
+/// Errors caused by the robot code (like passing an invalid pointer to an API function) stop the
+/// simulation instead of crashing the host, and are also reported with a
+/// [`SimulatorEvent::RobotCodeError`](pros_simulator_interface::SimulatorEvent::RobotCodeError)
+/// event.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum SimulatorError {
+    /// The robot configuration describes hardware that can't exist.
+    #[snafu(display("invalid robot config: {message}"))]
+    InvalidRobotConfig { message: String },
+    /// The simulation speed is zero, negative or not a number.
+    #[snafu(display("invalid simulation speed: {speed:?}"))]
+    InvalidSpeed { speed: SimulationSpeed },
+    /// The simulator was started without any robot code to run.
+    #[snafu(display("no robot code was provided"))]
+    MissingRobotCode,
+    /// The robot code couldn't be loaded or isn't valid WebAssembly.
+    #[snafu(display("failed to compile robot code"))]
+    Compile {
+        #[snafu(source(from(anyhow::Error, Into::into)))]
+        source: BoxedError,
+    },
+    /// The robot code doesn't export a function or table that the simulator needs, like
+    /// `initialize` or `wasm_memalign`.
+    #[snafu(display("entrypoint missing: expected `{name}` to be defined"))]
+    MissingEntrypoint { name: String },
+    /// A function exported or referenced by the robot code has the wrong signature.
+    #[snafu(display("invalid `{name}` signature: expected {signature}"))]
+    InvalidEntrypoint { name: String, signature: String },
+    /// The robot code aborted or trapped.
+    #[snafu(display("{source}"), context(false))]
+    RobotCode { source: RobotCodeError },
+    /// The robot code passed a pointer to memory that doesn't exist, or to a string that isn't
+    /// null-terminated.
+    #[snafu(display("invalid pointer: {pointer:#x}"))]
+    InvalidPointer { pointer: u32 },
+    /// The robot code passed a string that isn't valid UTF-8.
+    #[snafu(display("string at {pointer:#x} is not valid UTF-8"))]
+    InvalidString { pointer: u32, source: FromUtf8Error },
+    /// The robot code passed a task or mutex handle that doesn't refer to anything.
+    #[snafu(display("invalid {kind} handle: {handle}"))]
+    InvalidHandle { kind: &'static str, handle: u32 },
+    /// The robot code used a thread local storage index that doesn't exist.
+    #[snafu(display(
+        "thread local storage index out of bounds: index {index} should be at least 0 and less \
+        than {NUM_THREAD_LOCAL_STORAGE_POINTERS}"
+    ))]
+    InvalidThreadLocalIndex { index: i32 },
+    /// The robot code's allocator (`wasm_memalign`) couldn't allocate memory for the simulator.
+    #[snafu(display("robot code failed to allocate {size} bytes"))]
+    Allocation { size: usize },
+    /// The WebAssembly runtime failed.
+    #[snafu(display("WebAssembly runtime error"))]
+    Runtime {
+        #[snafu(source(from(anyhow::Error, Into::into)))]
+        source: BoxedError,
+    },
+}
+
+impl SimulatorError {
+    /// Converts an error returned by a task into a simulator error. Errors raised by the
+    /// simulator's API are kept as they are, and anything else (like a trap) is treated as a
+    /// robot code error.
+    pub(crate) fn from_task_error(error: anyhow::Error) -> Self {
+        match error.downcast::<SimulatorError>() {
+            Ok(error) => error,
+            Err(error) => RobotCodeError::from(error).into(),
+        }
+    }
+
+    /// Converts an error returned by the WebAssembly runtime into a simulator error.
+    pub(crate) fn from_runtime_error(error: anyhow::Error) -> Self {
+        match error.downcast::<SimulatorError>() {
+            Ok(error) => error,
+            Err(error) => RuntimeSnafu.into_error(error),
+        }
+    }
+}
+
+/// The robot code aborted (for example, by panicking) or crashed.
+#[derive(Debug, Clone, PartialEq, Eq, Snafu)]
+#[snafu(display("robot code error: {message}"))]
+pub struct RobotCodeError {
+    /// A description of what went wrong.
+    pub message: String,
+    /// The WebAssembly call stack of the task that caused the error.
+    #[snafu(backtrace(false))]
+    pub backtrace: String,
+}
+
+impl From<anyhow::Error> for RobotCodeError {
+    /// Describes an error that was returned from a task, which could be a trap, an abort, or a
+    /// failed call to the simulator's API.
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<RobotCodeError>() {
+            Ok(error) => error,
+            Err(error) => {
+                // wasmtime attaches the backtrace to the error as context, so it has to be
+                // removed from the message
+                let backtrace = error
+                    .downcast_ref::<WasmBacktrace>()
+                    .map(ToString::to_string)
+                    .unwrap_or_default();
+                let message = error
+                    .chain()
+                    .map(ToString::to_string)
+                    .filter(|cause| *cause != backtrace)
+                    .collect::<Vec<_>>()
+                    .join(": ");
+                Self { message, backtrace }
+            }
+        }
+    }
+}
@@ -1,26 +1,63 @@
+pub mod adi;
+pub mod battery;
+pub mod clock;
 pub mod controllers;
+pub mod devices;
+pub mod distance;
+pub mod field;
+pub mod font;
+pub mod gps;
+pub mod imu;
 pub mod lcd;
 pub mod memory;
+pub mod motors;
 pub mod multitasking;
+pub mod noise;
+pub mod optical;
+pub mod physics;
+pub mod pose;
+pub mod printf;
+pub mod rotation;
+pub mod screen;
 pub mod task;
 pub mod thread_local;
+pub mod vision;
+pub mod world;
 
-use std::{alloc::Layout, sync::Arc, time::Instant};
+use std::{alloc::Layout, sync::Arc};
 
 use async_trait::async_trait;
 use lcd::Lcd;
-use pros_simulator_interface::CompetitionPhase;
+use pros_simulator_interface::{config::RobotConfig, world::World, CompetitionPhase};
+use snafu::{ensure, OptionExt};
 use tokio::sync::{Mutex, MutexGuard};
 use wasmtime::{
-    AsContext, AsContextMut, Caller, Engine, Instance, Module, SharedMemory, TypedFunc,
+    AsContext, AsContextMut, Caller, Engine, Instance, Module, SharedMemory, TypedFunc, WasmParams,
+    WasmResults,
 };
 
 use self::{
+    adi::Adi,
+    battery::Battery,
+    clock::Clock,
     controllers::Controllers,
+    distance::DistanceSensors,
+    gps::GpsSensors,
+    imu::Imus,
+    motors::Motors,
     multitasking::MutexPool,
+    optical::OpticalSensors,
+    physics::Physics,
+    pose::PoseModel,
+    rotation::RotationSensors,
+    screen::Screen,
     task::{TaskHandle, TaskPool},
+    vision::VisionSensors,
+};
+use crate::{
+    error::{AllocationSnafu, InvalidEntrypointSnafu, MissingEntrypointSnafu, SimulatorError},
+    interface::SimulatorInterface,
 };
-use crate::interface::SimulatorInterface;
 
 /// This struct contains the functions necessary to send buffers to the sandbox.
 /// By letting the sandboxed allocator know that we want to write a buffer
@@ -36,37 +73,59 @@ pub struct WasmAllocator {
 }
 
 impl WasmAllocator {
-    pub fn new(mut store: impl AsContextMut, instance: &Instance) -> Self {
-        Self {
-            wasm_memalign: instance
-                .get_typed_func::<(u32, u32), u32>(&mut store, "wasm_memalign")
-                .unwrap(),
-            wasm_free: instance
-                .get_typed_func::<u32, ()>(&mut store, "wasm_free")
-                .unwrap(),
-        }
+    pub fn new(mut store: impl AsContextMut, instance: &Instance) -> Result<Self, SimulatorError> {
+        Ok(Self {
+            wasm_memalign: Self::get_func(
+                &mut store,
+                instance,
+                "wasm_memalign",
+                "(u32, u32) -> u32",
+            )?,
+            wasm_free: Self::get_func(&mut store, instance, "wasm_free", "(u32) -> ()")?,
+        })
+    }
+
+    fn get_func<Params: WasmParams, Results: WasmResults>(
+        mut store: impl AsContextMut,
+        instance: &Instance,
+        name: &str,
+        signature: &str,
+    ) -> Result<TypedFunc<Params, Results>, SimulatorError> {
+        instance
+            .get_func(&mut store, name)
+            .context(MissingEntrypointSnafu { name })?
+            .typed(&store)
+            .ok()
+            .context(InvalidEntrypointSnafu { name, signature })
     }
 
     pub async fn memalign(
         &self,
         mut store: impl AsContextMut<Data = impl Send>,
         layout: Layout,
-    ) -> u32 {
-        let size = layout.size().try_into().unwrap();
-        let alignment = layout.align().try_into().unwrap();
+    ) -> Result<u32, SimulatorError> {
+        let size = layout.size();
+        let (Ok(size_u32), Ok(alignment)) = (size.try_into(), layout.align().try_into()) else {
+            return AllocationSnafu { size }.fail();
+        };
         let ptr = self
             .wasm_memalign
-            .call_async(&mut store, (alignment, size))
+            .call_async(&mut store, (alignment, size_u32))
             .await
-            .unwrap();
-        if ptr == 0 {
-            panic!("wasm_memalign failed");
-        }
-        ptr
+            .map_err(SimulatorError::from_task_error)?;
+        ensure!(ptr != 0, AllocationSnafu { size });
+        Ok(ptr)
     }
 
-    pub async fn free(&self, mut store: impl AsContextMut<Data = impl Send>, ptr: u32) {
-        self.wasm_free.call_async(&mut store, ptr).await.unwrap()
+    pub async fn free(
+        &self,
+        mut store: impl AsContextMut<Data = impl Send>,
+        ptr: u32,
+    ) -> Result<(), SimulatorError> {
+        self.wasm_free
+            .call_async(&mut store, ptr)
+            .await
+            .map_err(SimulatorError::from_task_error)
     }
 }
 
@@ -77,12 +136,28 @@ pub struct Host {
     /// Interface for simulator output (e.g. log messages)
     interface: SimulatorInterface,
     lcd: Arc<Mutex<Lcd>>,
+    /// The brain's touch screen
+    screen: Arc<Mutex<Screen>>,
     /// Pointers to mutexes created with mutex_create
     mutexes: Arc<Mutex<MutexPool>>,
     tasks: Arc<Mutex<TaskPool>>,
     controllers: Arc<Mutex<Controllers>>,
+    motors: Arc<Mutex<Motors>>,
+    imus: Arc<Mutex<Imus>>,
+    rotation_sensors: Arc<Mutex<RotationSensors>>,
+    distance_sensors: Arc<Mutex<DistanceSensors>>,
+    optical_sensors: Arc<Mutex<OpticalSensors>>,
+    vision_sensors: Arc<Mutex<VisionSensors>>,
+    gps_sensors: Arc<Mutex<GpsSensors>>,
+    /// Three-wire ports on the brain and on three-wire expanders
+    adi: Arc<Mutex<Adi>>,
+    /// Where the robot is on the field
+    pose: Arc<Mutex<PoseModel>>,
+    /// Moves the robot according to its motors, and runs the world around it
+    physics: Arc<Mutex<Physics>>,
+    battery: Arc<Mutex<Battery>>,
     competition_phase: Arc<Mutex<CompetitionPhase>>,
-    start_time: Instant,
+    clock: Clock,
 }
 
 impl Host {
@@ -91,22 +166,47 @@ impl Host {
         memory: SharedMemory,
         interface: SimulatorInterface,
         module: Module,
+        robot_config: &RobotConfig,
+        world: Option<&World>,
     ) -> anyhow::Result<Self> {
         let lcd = Lcd::new(interface.clone());
+        let screen = Screen::new(interface.clone());
         let mutexes = MutexPool::default();
-        let tasks = TaskPool::new(engine, memory.clone(), interface.clone())?;
-        let controllers = Controllers::new(None, None);
+        let clock = Clock::default();
+        let tasks = TaskPool::new(engine, memory.clone(), interface.clone(), clock.clone())?;
+        let controllers = Controllers::new(interface.clone(), None, None);
+        let motors = Motors::new(interface.clone(), robot_config);
+        let imus = Imus::new(robot_config);
+        let rotation_sensors = RotationSensors::new(robot_config);
+        let distance_sensors = DistanceSensors::new(robot_config);
+        let optical_sensors = OpticalSensors::new(robot_config);
+        let vision_sensors = VisionSensors::new(robot_config);
+        let gps_sensors = GpsSensors::new(robot_config);
+        let adi = Adi::new(interface.clone(), robot_config);
+        let physics = Physics::new(interface.clone(), robot_config, world);
 
         Ok(Self {
             memory,
             module,
             interface,
             lcd: Arc::new(Mutex::new(lcd)),
+            screen: Arc::new(Mutex::new(screen)),
             mutexes: Arc::new(Mutex::new(mutexes)),
             tasks: Arc::new(Mutex::new(tasks)),
             controllers: Arc::new(Mutex::new(controllers)),
+            motors: Arc::new(Mutex::new(motors)),
+            imus: Arc::new(Mutex::new(imus)),
+            rotation_sensors: Arc::new(Mutex::new(rotation_sensors)),
+            distance_sensors: Arc::new(Mutex::new(distance_sensors)),
+            optical_sensors: Arc::new(Mutex::new(optical_sensors)),
+            vision_sensors: Arc::new(Mutex::new(vision_sensors)),
+            gps_sensors: Arc::new(Mutex::new(gps_sensors)),
+            adi: Arc::new(Mutex::new(adi)),
+            pose: Default::default(),
+            physics: Arc::new(Mutex::new(physics)),
+            battery: Default::default(),
             competition_phase: Default::default(),
-            start_time: Instant::now(),
+            clock,
         })
     }
 }
@@ -118,14 +218,38 @@ pub trait HostCtx {
     fn interface(&self) -> SimulatorInterface;
     fn lcd(&self) -> Arc<Mutex<Lcd>>;
     async fn lcd_lock(&self) -> MutexGuard<'_, Lcd>;
+    fn screen(&self) -> Arc<Mutex<Screen>>;
+    async fn screen_lock(&self) -> MutexGuard<'_, Screen>;
     fn mutexes(&self) -> Arc<Mutex<MutexPool>>;
     async fn mutexes_lock(&self) -> MutexGuard<'_, MutexPool>;
     fn tasks(&self) -> Arc<Mutex<TaskPool>>;
     async fn tasks_lock(&self) -> MutexGuard<'_, TaskPool>;
-    fn start_time(&self) -> Instant;
+    fn clock(&self) -> Clock;
     async fn current_task(&self) -> TaskHandle;
     fn controllers(&self) -> Arc<Mutex<Controllers>>;
     async fn controllers_lock(&self) -> MutexGuard<'_, Controllers>;
+    fn motors(&self) -> Arc<Mutex<Motors>>;
+    async fn motors_lock(&self) -> MutexGuard<'_, Motors>;
+    fn imus(&self) -> Arc<Mutex<Imus>>;
+    async fn imus_lock(&self) -> MutexGuard<'_, Imus>;
+    fn rotation_sensors(&self) -> Arc<Mutex<RotationSensors>>;
+    async fn rotation_sensors_lock(&self) -> MutexGuard<'_, RotationSensors>;
+    fn distance_sensors(&self) -> Arc<Mutex<DistanceSensors>>;
+    async fn distance_sensors_lock(&self) -> MutexGuard<'_, DistanceSensors>;
+    fn optical_sensors(&self) -> Arc<Mutex<OpticalSensors>>;
+    async fn optical_sensors_lock(&self) -> MutexGuard<'_, OpticalSensors>;
+    fn vision_sensors(&self) -> Arc<Mutex<VisionSensors>>;
+    async fn vision_sensors_lock(&self) -> MutexGuard<'_, VisionSensors>;
+    fn gps_sensors(&self) -> Arc<Mutex<GpsSensors>>;
+    async fn gps_sensors_lock(&self) -> MutexGuard<'_, GpsSensors>;
+    fn adi(&self) -> Arc<Mutex<Adi>>;
+    async fn adi_lock(&self) -> MutexGuard<'_, Adi>;
+    fn pose(&self) -> Arc<Mutex<PoseModel>>;
+    async fn pose_lock(&self) -> MutexGuard<'_, PoseModel>;
+    fn physics(&self) -> Arc<Mutex<Physics>>;
+    async fn physics_lock(&self) -> MutexGuard<'_, Physics>;
+    fn battery(&self) -> Arc<Mutex<Battery>>;
+    async fn battery_lock(&self) -> MutexGuard<'_, Battery>;
     fn competition_phase(&self) -> Arc<Mutex<CompetitionPhase>>;
     async fn competition_phase_lock(&self) -> MutexGuard<'_, CompetitionPhase>;
 }
@@ -152,6 +276,14 @@ impl HostCtx for Host {
         self.lcd.lock().await
     }
 
+    fn screen(&self) -> Arc<Mutex<Screen>> {
+        self.screen.clone()
+    }
+
+    async fn screen_lock(&self) -> MutexGuard<'_, Screen> {
+        self.screen.lock().await
+    }
+
     fn mutexes(&self) -> Arc<Mutex<MutexPool>> {
         self.mutexes.clone()
     }
@@ -168,8 +300,8 @@ impl HostCtx for Host {
         self.tasks.lock().await
     }
 
-    fn start_time(&self) -> Instant {
-        self.start_time
+    fn clock(&self) -> Clock {
+        self.clock.clone()
     }
 
     async fn current_task(&self) -> TaskHandle {
@@ -184,6 +316,94 @@ impl HostCtx for Host {
         self.controllers.lock().await
     }
 
+    fn motors(&self) -> Arc<Mutex<Motors>> {
+        self.motors.clone()
+    }
+
+    async fn motors_lock(&self) -> MutexGuard<'_, Motors> {
+        self.motors.lock().await
+    }
+
+    fn imus(&self) -> Arc<Mutex<Imus>> {
+        self.imus.clone()
+    }
+
+    async fn imus_lock(&self) -> MutexGuard<'_, Imus> {
+        self.imus.lock().await
+    }
+
+    fn rotation_sensors(&self) -> Arc<Mutex<RotationSensors>> {
+        self.rotation_sensors.clone()
+    }
+
+    async fn rotation_sensors_lock(&self) -> MutexGuard<'_, RotationSensors> {
+        self.rotation_sensors.lock().await
+    }
+
+    fn distance_sensors(&self) -> Arc<Mutex<DistanceSensors>> {
+        self.distance_sensors.clone()
+    }
+
+    async fn distance_sensors_lock(&self) -> MutexGuard<'_, DistanceSensors> {
+        self.distance_sensors.lock().await
+    }
+
+    fn optical_sensors(&self) -> Arc<Mutex<OpticalSensors>> {
+        self.optical_sensors.clone()
+    }
+
+    async fn optical_sensors_lock(&self) -> MutexGuard<'_, OpticalSensors> {
+        self.optical_sensors.lock().await
+    }
+
+    fn vision_sensors(&self) -> Arc<Mutex<VisionSensors>> {
+        self.vision_sensors.clone()
+    }
+
+    async fn vision_sensors_lock(&self) -> MutexGuard<'_, VisionSensors> {
+        self.vision_sensors.lock().await
+    }
+
+    fn gps_sensors(&self) -> Arc<Mutex<GpsSensors>> {
+        self.gps_sensors.clone()
+    }
+
+    async fn gps_sensors_lock(&self) -> MutexGuard<'_, GpsSensors> {
+        self.gps_sensors.lock().await
+    }
+
+    fn adi(&self) -> Arc<Mutex<Adi>> {
+        self.adi.clone()
+    }
+
+    async fn adi_lock(&self) -> MutexGuard<'_, Adi> {
+        self.adi.lock().await
+    }
+
+    fn pose(&self) -> Arc<Mutex<PoseModel>> {
+        self.pose.clone()
+    }
+
+    async fn pose_lock(&self) -> MutexGuard<'_, PoseModel> {
+        self.pose.lock().await
+    }
+
+    fn physics(&self) -> Arc<Mutex<Physics>> {
+        self.physics.clone()
+    }
+
+    async fn physics_lock(&self) -> MutexGuard<'_, Physics> {
+        self.physics.lock().await
+    }
+
+    fn battery(&self) -> Arc<Mutex<Battery>> {
+        self.battery.clone()
+    }
+
+    async fn battery_lock(&self) -> MutexGuard<'_, Battery> {
+        self.battery.lock().await
+    }
+
     fn competition_phase(&self) -> Arc<Mutex<CompetitionPhase>> {
         self.competition_phase.clone()
     }
@@ -218,6 +438,14 @@ where
         self.as_context().data().lcd_lock().await
     }
 
+    fn screen(&self) -> Arc<Mutex<Screen>> {
+        self.as_context().data().screen()
+    }
+
+    async fn screen_lock(&self) -> MutexGuard<'_, Screen> {
+        self.as_context().data().screen_lock().await
+    }
+
     fn mutexes(&self) -> Arc<Mutex<MutexPool>> {
         self.as_context().data().mutexes()
     }
@@ -234,8 +462,8 @@ where
         self.as_context().data().tasks_lock().await
     }
 
-    fn start_time(&self) -> Instant {
-        self.as_context().data().start_time()
+    fn clock(&self) -> Clock {
+        self.as_context().data().clock()
     }
 
     async fn current_task(&self) -> TaskHandle {
@@ -250,6 +478,94 @@ where
         self.as_context().data().controllers_lock().await
     }
 
+    fn motors(&self) -> Arc<Mutex<Motors>> {
+        self.as_context().data().motors()
+    }
+
+    async fn motors_lock(&self) -> MutexGuard<'_, Motors> {
+        self.as_context().data().motors_lock().await
+    }
+
+    fn imus(&self) -> Arc<Mutex<Imus>> {
+        self.as_context().data().imus()
+    }
+
+    async fn imus_lock(&self) -> MutexGuard<'_, Imus> {
+        self.as_context().data().imus_lock().await
+    }
+
+    fn rotation_sensors(&self) -> Arc<Mutex<RotationSensors>> {
+        self.as_context().data().rotation_sensors()
+    }
+
+    async fn rotation_sensors_lock(&self) -> MutexGuard<'_, RotationSensors> {
+        self.as_context().data().rotation_sensors_lock().await
+    }
+
+    fn distance_sensors(&self) -> Arc<Mutex<DistanceSensors>> {
+        self.as_context().data().distance_sensors()
+    }
+
+    async fn distance_sensors_lock(&self) -> MutexGuard<'_, DistanceSensors> {
+        self.as_context().data().distance_sensors_lock().await
+    }
+
+    fn optical_sensors(&self) -> Arc<Mutex<OpticalSensors>> {
+        self.as_context().data().optical_sensors()
+    }
+
+    async fn optical_sensors_lock(&self) -> MutexGuard<'_, OpticalSensors> {
+        self.as_context().data().optical_sensors_lock().await
+    }
+
+    fn vision_sensors(&self) -> Arc<Mutex<VisionSensors>> {
+        self.as_context().data().vision_sensors()
+    }
+
+    async fn vision_sensors_lock(&self) -> MutexGuard<'_, VisionSensors> {
+        self.as_context().data().vision_sensors_lock().await
+    }
+
+    fn gps_sensors(&self) -> Arc<Mutex<GpsSensors>> {
+        self.as_context().data().gps_sensors()
+    }
+
+    async fn gps_sensors_lock(&self) -> MutexGuard<'_, GpsSensors> {
+        self.as_context().data().gps_sensors_lock().await
+    }
+
+    fn adi(&self) -> Arc<Mutex<Adi>> {
+        self.as_context().data().adi()
+    }
+
+    async fn adi_lock(&self) -> MutexGuard<'_, Adi> {
+        self.as_context().data().adi_lock().await
+    }
+
+    fn pose(&self) -> Arc<Mutex<PoseModel>> {
+        self.as_context().data().pose()
+    }
+
+    async fn pose_lock(&self) -> MutexGuard<'_, PoseModel> {
+        self.as_context().data().pose_lock().await
+    }
+
+    fn physics(&self) -> Arc<Mutex<Physics>> {
+        self.as_context().data().physics()
+    }
+
+    async fn physics_lock(&self) -> MutexGuard<'_, Physics> {
+        self.as_context().data().physics_lock().await
+    }
+
+    fn battery(&self) -> Arc<Mutex<Battery>> {
+        self.as_context().data().battery()
+    }
+
+    async fn battery_lock(&self) -> MutexGuard<'_, Battery> {
+        self.as_context().data().battery_lock().await
+    }
+
     fn competition_phase(&self) -> Arc<Mutex<CompetitionPhase>> {
         self.as_context().data().competition_phase()
     }
@@ -263,7 +579,7 @@ where
 pub trait ContextExt {
     /// Sets the task's errno value to the given code.
     async fn set_errno(&mut self, code: i32);
-    async fn errno_address(&mut self) -> u32;
+    async fn errno_address(&mut self) -> Result<u32, SimulatorError>;
 }
 
 #[async_trait]
@@ -273,13 +589,13 @@ where
 {
     async fn set_errno(&mut self, code: i32) {
         let current_task = self.current_task().await;
-        let errno = current_task.lock().await.errno(&mut *self).await;
-        errno.set(&self.memory(), code);
+        current_task.lock().await.set_errno(&self.memory(), code);
     }
-    async fn errno_address(&mut self) -> u32 {
+    async fn errno_address(&mut self) -> Result<u32, SimulatorError> {
         let current_task = self.current_task().await;
-        let errno = current_task.lock().await.errno(self).await;
-        errno.address()
+        let memory = self.memory();
+        let errno = current_task.lock().await.errno(&mut *self, &memory).await?;
+        Ok(errno.address())
     }
 }
 
@@ -0,0 +1,519 @@
+use pros_simulator_interface::{
+    config::{AdiDevice, RobotConfig, SmartDevice},
+    SimulatorEvent,
+};
+use pros_sys::{
+    EINVAL, ENXIO, E_ADI_ANALOG_IN, E_ADI_ANALOG_OUT, E_ADI_DIGITAL_IN, E_ADI_DIGITAL_OUT,
+    E_ADI_LEGACY_ENCODER, E_ADI_LEGACY_PWM, E_ADI_LEGACY_SERVO, E_ADI_LEGACY_ULTRASONIC,
+    E_ADI_POT_EDR, E_ADI_POT_V2, E_ADI_TYPE_UNDEFINED, INPUT, INPUT_ANALOG, INTERNAL_ADI_PORT,
+    NUM_ADI_PORTS, OUTPUT, OUTPUT_ANALOG,
+};
+
+use super::devices::DeviceTable;
+use crate::interface::SimulatorInterface;
+
+/// "Address already in use" error code, used when a three-wire port is configured as a different
+/// kind of device. Missing from `pros_sys`; this is the value from newlib.
+pub const EADDRINUSE: i32 = 112;
+
+/// The highest value an analog input or output can have (12 bits).
+const MAX_ANALOG_VALUE: i32 = 4095;
+
+/// Converts a three-wire port number (1-8, 'a'-'h' or 'A'-'H') into an index from 0 to 7, failing
+/// with `ENXIO` if it isn't a valid port.
+fn port_index(port: u32) -> Result<usize, i32> {
+    let port = match char::from_u32(port) {
+        Some(letter @ 'a'..='h') => letter as u32 - 'a' as u32 + 1,
+        Some(letter @ 'A'..='H') => letter as u32 - 'A' as u32 + 1,
+        _ => port,
+    };
+    if port == 0 || port > NUM_ADI_PORTS as u32 {
+        tracing::error!("Port {port} is not a valid three-wire port");
+        return Err(ENXIO);
+    }
+    Ok(port as usize - 1)
+}
+
+fn port_letter(index: usize) -> char {
+    (b'A' + index as u8) as char
+}
+
+/// Whether the robot code can write to a port with the given configuration.
+fn is_output(config: i32) -> bool {
+    matches!(
+        config,
+        E_ADI_ANALOG_OUT | E_ADI_DIGITAL_OUT | E_ADI_LEGACY_PWM | E_ADI_LEGACY_SERVO
+    )
+}
+
+/// The configuration a port starts with, based on the device plugged into it.
+///
+/// Like on a real brain, empty ports start as analog inputs. Encoders and ultrasonic sensors use
+/// two ports, so the port before this one is checked too.
+fn default_config(device: Option<&AdiDevice>, previous: Option<&AdiDevice>) -> i32 {
+    match (device, previous) {
+        (Some(AdiDevice::DigitalIn), _) => E_ADI_DIGITAL_IN,
+        (Some(AdiDevice::DigitalOut), _) => E_ADI_DIGITAL_OUT,
+        (Some(AdiDevice::AnalogIn | AdiDevice::LineTracker { .. }), _) => E_ADI_ANALOG_IN,
+        (Some(AdiDevice::AnalogOut), _) => E_ADI_ANALOG_OUT,
+        (Some(AdiDevice::Encoder), _) | (None, Some(AdiDevice::Encoder)) => E_ADI_LEGACY_ENCODER,
+        (Some(AdiDevice::Ultrasonic), _) | (None, Some(AdiDevice::Ultrasonic)) => {
+            E_ADI_LEGACY_ULTRASONIC
+        }
+        (None, _) => E_ADI_ANALOG_IN,
+    }
+}
+
+/// The state of a single three-wire port.
+#[derive(Debug, Clone)]
+pub struct AdiPort {
+    /// The port's `adi_port_config_e_t` value.
+    config: i32,
+    /// The port's raw value, either read from a sensor or written by the robot code.
+    value: i32,
+    /// The value saved by `adi_analog_calibrate`.
+    calibration: i32,
+    /// Whether the digital input was pressed the last time `adi_digital_get_new_press` was
+    /// called.
+    was_pressed: bool,
+    /// The raw value that an encoder reads as zero.
+    encoder_zero: i32,
+    encoder_reversed: bool,
+    /// The port's `adi_potentiometer_type_e_t` value.
+    potentiometer_type: i32,
+}
+
+impl AdiPort {
+    pub fn new(config: i32) -> Self {
+        Self {
+            config,
+            value: 0,
+            calibration: 0,
+            was_pressed: false,
+            encoder_zero: 0,
+            encoder_reversed: false,
+            potentiometer_type: E_ADI_POT_EDR,
+        }
+    }
+
+    pub fn config(&self) -> i32 {
+        self.config
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    /// Fails with `EADDRINUSE` unless the port has the given configuration.
+    fn expect_config(&self, config: i32) -> Result<&Self, i32> {
+        if self.config != config {
+            return Err(EADDRINUSE);
+        }
+        Ok(self)
+    }
+
+    /// The reading of an analog input, relative to its calibration value.
+    pub fn analog_read_calibrated(&self) -> i32 {
+        self.value - self.calibration
+    }
+
+    /// The reading of an analog input relative to its calibration value, in 16 times the
+    /// resolution of a normal reading.
+    pub fn analog_read_calibrated_hr(&self) -> i32 {
+        (self.value - self.calibration) * 16
+    }
+
+    /// The number of ticks an encoder has counted since it was last reset.
+    pub fn encoder_ticks(&self) -> i32 {
+        let ticks = self.value - self.encoder_zero;
+        if self.encoder_reversed {
+            -ticks
+        } else {
+            ticks
+        }
+    }
+
+    /// Makes an encoder read zero ticks at its current position.
+    pub fn reset_encoder(&mut self) {
+        self.encoder_zero = self.value;
+    }
+
+    /// The angle of a potentiometer in degrees.
+    pub fn potentiometer_angle(&self) -> f64 {
+        let range = if self.potentiometer_type == E_ADI_POT_V2 {
+            330.0
+        } else {
+            250.0
+        };
+        self.value as f64 * range / MAX_ANALOG_VALUE as f64
+    }
+}
+
+/// The eight ports on the brain or on a three-wire expander.
+type AdiBank = [AdiPort; NUM_ADI_PORTS as usize];
+
+fn new_bank(device: impl Fn(char) -> Option<AdiDevice>) -> AdiBank {
+    std::array::from_fn(|index| {
+        let previous = index
+            .checked_sub(1)
+            .and_then(|index| device(port_letter(index)));
+        AdiPort::new(default_config(
+            device(port_letter(index)).as_ref(),
+            previous.as_ref(),
+        ))
+    })
+}
+
+/// Stores the state of every three-wire port on the brain and its three-wire expanders.
+///
+/// Ports are addressed with a smart port number and a three-wire port number. The brain's
+/// built-in ports use the smart port number `INTERNAL_ADI_PORT`, and using a smart port without an
+/// expander plugged into it fails with `ENODEV`.
+pub struct Adi {
+    internal: AdiBank,
+    expanders: DeviceTable<AdiBank>,
+    interface: SimulatorInterface,
+}
+
+impl Adi {
+    pub fn new(interface: SimulatorInterface, robot_config: &RobotConfig) -> Self {
+        Self {
+            internal: new_bank(|port| robot_config.adi_device(port).cloned()),
+            expanders: DeviceTable::new(|smart_port| {
+                let device = robot_config.smart_device(smart_port)?;
+                matches!(device, SmartDevice::AdiExpander { .. })
+                    .then(|| new_bank(|port| device.adi_device(port).cloned()))
+            }),
+            interface,
+        }
+    }
+
+    fn bank(&mut self, smart_port: u32) -> Result<&mut AdiBank, i32> {
+        if smart_port == INTERNAL_ADI_PORT as u32 {
+            Ok(&mut self.internal)
+        } else {
+            self.expanders.get_mut(smart_port)
+        }
+    }
+
+    fn port_at(&mut self, smart_port: u32, index: usize) -> Result<&mut AdiPort, i32> {
+        self.bank(smart_port)?.get_mut(index).ok_or(ENXIO)
+    }
+
+    fn port_mut(&mut self, smart_port: u32, adi_port: u32) -> Result<&mut AdiPort, i32> {
+        let index = port_index(adi_port)?;
+        self.port_at(smart_port, index)
+    }
+
+    /// Returns the port with the given smart port and three-wire port numbers.
+    pub fn port(&mut self, smart_port: u32, adi_port: u32) -> Result<&AdiPort, i32> {
+        self.port_mut(smart_port, adi_port).map(|port| &*port)
+    }
+
+    /// Returns the port with the given numbers, failing with `EADDRINUSE` unless it has the
+    /// given configuration.
+    pub fn typed_port(
+        &mut self,
+        smart_port: u32,
+        adi_port: u32,
+        config: i32,
+    ) -> Result<&AdiPort, i32> {
+        self.port(smart_port, adi_port)?.expect_config(config)
+    }
+
+    /// Changes a port's configuration, which resets its value.
+    pub fn set_config(&mut self, smart_port: u32, adi_port: u32, config: i32) -> Result<(), i32> {
+        if !matches!(
+            config,
+            E_ADI_ANALOG_IN
+                | E_ADI_ANALOG_OUT
+                | E_ADI_DIGITAL_IN
+                | E_ADI_DIGITAL_OUT
+                | E_ADI_LEGACY_SERVO
+                | E_ADI_LEGACY_PWM
+                | E_ADI_LEGACY_ENCODER
+                | E_ADI_LEGACY_ULTRASONIC
+                | E_ADI_TYPE_UNDEFINED
+        ) {
+            return Err(EINVAL);
+        }
+        let port = self.port_mut(smart_port, adi_port)?;
+        if port.config != config {
+            let value = port.value;
+            *port = AdiPort::new(config);
+            // inputs keep reading whatever the sensor sees
+            if !is_output(config) {
+                port.value = value;
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes a port's configuration using an Arduino-style pin mode.
+    pub fn pin_mode(&mut self, smart_port: u32, adi_port: u32, mode: i32) -> Result<(), i32> {
+        let config = match mode {
+            INPUT => E_ADI_DIGITAL_IN,
+            OUTPUT => E_ADI_DIGITAL_OUT,
+            INPUT_ANALOG => E_ADI_ANALOG_IN,
+            OUTPUT_ANALOG => E_ADI_ANALOG_OUT,
+            _ => return Err(EINVAL),
+        };
+        self.set_config(smart_port, adi_port, config)
+    }
+
+    /// Sets the value of an output port, notifying the simulator interface if it changes.
+    /// Values are clamped to the range of the port's configuration.
+    ///
+    /// Fails with `EADDRINUSE` if the port isn't configured as an output.
+    pub fn set_value(&mut self, smart_port: u32, adi_port: u32, value: i32) -> Result<(), i32> {
+        let index = port_index(adi_port)?;
+        let port = &mut self.bank(smart_port)?[index];
+        let value = match port.config {
+            E_ADI_DIGITAL_OUT => (value != 0).into(),
+            E_ADI_ANALOG_OUT => value.clamp(0, MAX_ANALOG_VALUE),
+            E_ADI_LEGACY_PWM | E_ADI_LEGACY_SERVO => value.clamp(-127, 127),
+            _ => return Err(EADDRINUSE),
+        };
+        if port.value != value {
+            port.value = value;
+            self.interface.send(SimulatorEvent::AdiOutput {
+                port: port_letter(index),
+                expander: (smart_port != INTERNAL_ADI_PORT as u32).then_some(smart_port as u8),
+                value,
+            });
+        }
+        Ok(())
+    }
+
+    /// Saves the current value of an analog input as its calibration value, and returns it.
+    pub fn analog_calibrate(&mut self, smart_port: u32, adi_port: u32) -> Result<i32, i32> {
+        let port = self.port_mut(smart_port, adi_port)?;
+        port.expect_config(E_ADI_ANALOG_IN)?;
+        port.calibration = port.value;
+        Ok(port.calibration)
+    }
+
+    /// Returns 1 if a digital input has started being pressed since the last time this was
+    /// called, or 0 otherwise.
+    pub fn digital_get_new_press(&mut self, smart_port: u32, adi_port: u32) -> Result<i32, i32> {
+        let port = self.port_mut(smart_port, adi_port)?;
+        port.expect_config(E_ADI_DIGITAL_IN)?;
+        let pressed = port.value != 0;
+        let new_press = pressed && !port.was_pressed;
+        port.was_pressed = pressed;
+        Ok(new_press.into())
+    }
+
+    /// Configures two adjacent ports for a two-wire legacy sensor, returning the index of the
+    /// first one. The first port must be A, C, E or G.
+    fn init_pair(
+        &mut self,
+        smart_port: u32,
+        first: u32,
+        second: u32,
+        config: i32,
+    ) -> Result<usize, i32> {
+        let first = port_index(first)?;
+        let second = port_index(second)?;
+        if first % 2 != 0 || second != first + 1 {
+            return Err(ENXIO);
+        }
+        let bank = self.bank(smart_port)?;
+        for port in &mut bank[first..=second] {
+            if port.config != config {
+                let value = port.value;
+                *port = AdiPort::new(config);
+                port.value = value;
+            }
+        }
+        Ok(first)
+    }
+
+    /// Configures an encoder and returns a handle to it. The encoder's value is zeroed.
+    pub fn encoder_init(
+        &mut self,
+        smart_port: u32,
+        top: u32,
+        bottom: u32,
+        reversed: bool,
+    ) -> Result<i32, i32> {
+        let index = self.init_pair(smart_port, top, bottom, E_ADI_LEGACY_ENCODER)?;
+        let port = &mut self.bank(smart_port)?[index];
+        port.encoder_zero = port.value;
+        port.encoder_reversed = reversed;
+        Ok(handle(smart_port, index))
+    }
+
+    /// Configures an ultrasonic sensor and returns a handle to it.
+    pub fn ultrasonic_init(&mut self, smart_port: u32, ping: u32, echo: u32) -> Result<i32, i32> {
+        let index = self.init_pair(smart_port, ping, echo, E_ADI_LEGACY_ULTRASONIC)?;
+        Ok(handle(smart_port, index))
+    }
+
+    /// Configures a potentiometer and returns a handle to it.
+    pub fn potentiometer_init(
+        &mut self,
+        smart_port: u32,
+        adi_port: u32,
+        potentiometer_type: i32,
+    ) -> Result<i32, i32> {
+        if !matches!(potentiometer_type, E_ADI_POT_EDR | E_ADI_POT_V2) {
+            return Err(EINVAL);
+        }
+        self.set_config(smart_port, adi_port, E_ADI_ANALOG_IN)?;
+        let index = port_index(adi_port)?;
+        self.bank(smart_port)?[index].potentiometer_type = potentiometer_type;
+        Ok(handle(smart_port, index))
+    }
+
+    /// Returns the port that a handle from one of the `init` functions refers to, failing with
+    /// `EADDRINUSE` unless it has the given configuration.
+    pub fn handle_port(&mut self, handle: i32, config: i32) -> Result<&mut AdiPort, i32> {
+        let (smart_port, index) = split_handle(handle)?;
+        let port = self.port_at(smart_port, index)?;
+        port.expect_config(config)?;
+        Ok(port)
+    }
+
+    /// Disables a two-wire legacy sensor, voiding the configuration of both of its ports.
+    pub fn shutdown_pair(&mut self, handle: i32, config: i32) -> Result<(), i32> {
+        self.handle_port(handle, config)?;
+        let (smart_port, index) = split_handle(handle)?;
+        for port in self.bank(smart_port)?.iter_mut().skip(index).take(2) {
+            *port = AdiPort::new(E_ADI_TYPE_UNDEFINED);
+        }
+        Ok(())
+    }
+
+    /// Updates the reading of a sensor plugged into the given port (A-H). `expander` is the
+    /// smart port of the three-wire expander the sensor is plugged into, if any.
+    ///
+    /// Values written by the robot code to output ports are left alone.
+    pub fn set_input(&mut self, expander: Option<u8>, port: char, value: i32) -> Result<(), i32> {
+        let smart_port = expander.map_or(INTERNAL_ADI_PORT as u32, u32::from);
+        let port = self.port_mut(smart_port, port as u32)?;
+        if is_output(port.config) {
+            return Err(EADDRINUSE);
+        }
+        port.value = match port.config {
+            E_ADI_DIGITAL_IN => (value != 0).into(),
+            E_ADI_ANALOG_IN => value.clamp(0, MAX_ANALOG_VALUE),
+            _ => value,
+        };
+        Ok(())
+    }
+}
+
+/// Creates a handle to a legacy sensor, encoding the smart port and the three-wire port it's
+/// plugged into like PROS does.
+fn handle(smart_port: u32, index: usize) -> i32 {
+    ((smart_port as i32 - 1) << 8) | index as i32
+}
+
+/// Splits a handle into a smart port number and the index of a three-wire port, failing with
+/// `ENXIO` if it's negative.
+fn split_handle(handle: i32) -> Result<(u32, usize), i32> {
+    let smart_port = u32::try_from(handle >> 8)
+        .ok()
+        .and_then(|port| port.checked_add(1))
+        .ok_or(ENXIO)?;
+    Ok((smart_port, (handle & 0xFF) as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_sys::ENODEV;
+
+    use super::*;
+
+    const INTERNAL: u32 = INTERNAL_ADI_PORT as u32;
+
+    fn adi() -> Adi {
+        Adi::new(SimulatorInterface::from(|_| {}), &RobotConfig::default())
+    }
+
+    fn port(letter: char) -> u32 {
+        letter as u32
+    }
+
+    #[test]
+    fn pairs_start_on_odd_ports() {
+        let mut adi = adi();
+        assert!(adi
+            .encoder_init(INTERNAL, port('A'), port('B'), false)
+            .is_ok());
+        assert!(adi.ultrasonic_init(INTERNAL, 7, 8).is_ok());
+        assert_eq!(
+            adi.encoder_init(INTERNAL, port('b'), port('c'), false),
+            Err(ENXIO)
+        );
+        assert_eq!(
+            adi.ultrasonic_init(INTERNAL, port('C'), port('E')),
+            Err(ENXIO)
+        );
+        assert_eq!(adi.ultrasonic_init(INTERNAL, port('H'), 9), Err(ENXIO));
+        assert_eq!(adi.encoder_init(1, 1, 2, false), Err(ENODEV));
+    }
+
+    #[test]
+    fn handles_round_trip() {
+        for smart_port in [1, 21, INTERNAL] {
+            for index in 0..NUM_ADI_PORTS as usize {
+                assert_eq!(
+                    split_handle(handle(smart_port, index)),
+                    Ok((smart_port, index))
+                );
+            }
+        }
+
+        let mut adi = adi();
+        let encoder = adi
+            .encoder_init(INTERNAL, port('C'), port('D'), false)
+            .unwrap();
+        assert_eq!(encoder, handle(INTERNAL, 2));
+        assert!(adi.handle_port(encoder, E_ADI_LEGACY_ENCODER).is_ok());
+    }
+
+    #[test]
+    fn negative_handles_are_invalid() {
+        assert_eq!(split_handle(-1), Err(ENXIO));
+        assert_eq!(split_handle(i32::MIN), Err(ENXIO));
+        assert_eq!(
+            adi().handle_port(-1, E_ADI_LEGACY_ENCODER).map(|_| ()),
+            Err(ENXIO)
+        );
+    }
+
+    #[test]
+    fn using_a_port_as_the_wrong_device_fails() {
+        let mut adi = adi();
+        let encoder = adi
+            .encoder_init(INTERNAL, port('A'), port('B'), false)
+            .unwrap();
+        assert_eq!(
+            adi.handle_port(encoder, E_ADI_LEGACY_ULTRASONIC)
+                .map(|_| ()),
+            Err(EADDRINUSE)
+        );
+        assert_eq!(
+            adi.shutdown_pair(encoder, E_ADI_LEGACY_ULTRASONIC),
+            Err(EADDRINUSE)
+        );
+
+        // empty ports start as analog inputs
+        assert_eq!(
+            adi.typed_port(INTERNAL, port('C'), E_ADI_DIGITAL_IN)
+                .map(|_| ()),
+            Err(EADDRINUSE)
+        );
+        assert_eq!(adi.set_value(INTERNAL, port('C'), 1), Err(EADDRINUSE));
+        assert_eq!(
+            adi.digital_get_new_press(INTERNAL, port('C')),
+            Err(EADDRINUSE)
+        );
+
+        adi.pin_mode(INTERNAL, port('C'), OUTPUT).unwrap();
+        assert_eq!(adi.set_input(None, 'C', 1), Err(EADDRINUSE));
+        assert_eq!(adi.analog_calibrate(INTERNAL, port('C')), Err(EADDRINUSE));
+    }
+}
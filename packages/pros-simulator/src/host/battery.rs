@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use super::motors::Motors;
+
+/// The charge a full V5 Robot Battery holds, in amp hours.
+const CAPACITY: f64 = 1.1;
+/// The voltage of a full battery with nothing plugged in, in volts.
+const FULL_VOLTAGE: f64 = 13.0;
+/// The voltage of an empty battery with nothing plugged in, in volts.
+const EMPTY_VOLTAGE: f64 = 11.5;
+/// The resistance of the battery's cells and wiring in ohms, which makes the voltage drop when
+/// the motors draw a lot of current.
+const INTERNAL_RESISTANCE: f64 = 0.1;
+/// The current drawn by the brain itself, in amps.
+const BRAIN_CURRENT: f64 = 0.3;
+/// The temperature of a battery that isn't doing any work, in degrees Celsius.
+const AMBIENT_TEMPERATURE: f64 = 25.0;
+/// How many degrees Celsius warmer than the air the battery gets for each watt of heat.
+const THERMAL_RESISTANCE: f64 = 2.0;
+/// How much heat it takes to warm the battery by one degree Celsius, in joules.
+const HEAT_CAPACITY: f64 = 200.0;
+
+/// A simulated V5 Robot Battery.
+///
+/// The battery drains as the brain and motors draw current from it, and its voltage drops both
+/// as it empties and while it's under load. Motors can't apply more voltage than the battery
+/// supplies, so a low battery makes the robot slower.
+#[derive(Debug, Clone)]
+pub struct Battery {
+    /// Remaining charge, from 0 to 1.
+    charge: f64,
+    /// Current drawn from the battery in amps.
+    current: f64,
+    /// Temperature in degrees Celsius.
+    temperature: f64,
+    last_update: Duration,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Self {
+            charge: 1.0,
+            current: BRAIN_CURRENT,
+            temperature: AMBIENT_TEMPERATURE,
+            last_update: Duration::ZERO,
+        }
+    }
+}
+
+impl Battery {
+    /// Drains the battery by the current drawn since the last update, then works out the new
+    /// current from the motors' outputs and tells them the voltage they have to work with. `now`
+    /// is the amount of time that has passed since the simulator started.
+    pub fn update(&mut self, now: Duration, motors: &mut Motors) {
+        let seconds = now.saturating_sub(self.last_update).as_secs_f64();
+        self.last_update = now;
+
+        self.charge = (self.charge - self.current * seconds / 3600.0 / CAPACITY).max(0.0);
+        let heat = self.current.powi(2) * INTERNAL_RESISTANCE;
+        let cooling = (self.temperature - AMBIENT_TEMPERATURE) / THERMAL_RESISTANCE;
+        self.temperature += (heat - cooling) / HEAT_CAPACITY * seconds;
+
+        self.current = BRAIN_CURRENT + motors.supply_current();
+        motors.set_supply_voltage(self.voltage());
+    }
+
+    /// The voltage of the battery with nothing plugged in, which only depends on how much
+    /// charge is left.
+    fn open_circuit_voltage(&self) -> f64 {
+        EMPTY_VOLTAGE + (FULL_VOLTAGE - EMPTY_VOLTAGE) * self.charge
+    }
+
+    /// The voltage at the battery's terminals in volts, after the drop caused by the current
+    /// being drawn.
+    pub fn voltage(&self) -> f64 {
+        (self.open_circuit_voltage() - self.current * INTERNAL_RESISTANCE).max(0.0)
+    }
+
+    /// The current drawn from the battery in amps.
+    pub fn current(&self) -> f64 {
+        self.current
+    }
+
+    /// The remaining charge as a percentage.
+    pub fn capacity(&self) -> f64 {
+        self.charge * 100.0
+    }
+
+    /// Sets the remaining charge as a percentage.
+    pub fn set_capacity(&mut self, capacity: f64) {
+        self.charge = (capacity / 100.0).clamp(0.0, 1.0);
+    }
+
+    /// The temperature in degrees Celsius.
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    pub fn set_temperature(&mut self, temperature: f64) {
+        self.temperature = temperature;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::config::{RobotConfig, SmartDevice, SmartPortConfig};
+
+    use super::*;
+    use crate::interface::SimulatorInterface;
+
+    fn motors(count: u8) -> Motors {
+        let robot_config = RobotConfig {
+            smart_ports: (1..=count)
+                .map(|port| SmartPortConfig {
+                    port,
+                    device: SmartDevice::Motor {
+                        gearset: Default::default(),
+                        model: Default::default(),
+                    },
+                })
+                .collect(),
+            ..Default::default()
+        };
+        Motors::new(SimulatorInterface::from(|_| {}), &robot_config)
+    }
+
+    #[test]
+    fn voltage_sags_under_load() {
+        let mut idle = Battery::default();
+        idle.update(Duration::ZERO, &mut motors(0));
+        assert!(
+            (idle.voltage() - (FULL_VOLTAGE - BRAIN_CURRENT * INTERNAL_RESISTANCE)).abs() < 1e-9
+        );
+
+        let mut battery = Battery::default();
+        let mut motors = motors(4);
+        for port in 1..=4 {
+            motors.move_voltage(port, 12_000).unwrap();
+        }
+        motors.update(Duration::from_millis(10));
+        battery.update(Duration::from_millis(10), &mut motors);
+        assert!(battery.current() > BRAIN_CURRENT + 4.0);
+        assert!(battery.voltage() < idle.voltage() - 0.4);
+
+        // the voltage recovers once the motors stop drawing current
+        for port in 1..=4 {
+            motors.move_voltage(port, 0).unwrap();
+        }
+        motors.update(Duration::from_secs(1));
+        battery.update(Duration::from_secs(1), &mut motors);
+        assert!(battery.voltage() > idle.voltage() - 0.01);
+    }
+
+    #[test]
+    fn drains_by_the_current_drawn() {
+        let mut battery = Battery::default();
+        battery.update(Duration::from_secs(3600), &mut motors(0));
+        let expected = 100.0 * (1.0 - BRAIN_CURRENT / CAPACITY);
+        assert!((battery.capacity() - expected).abs() < 1e-9);
+
+        let mut loaded = Battery::default();
+        let mut motors = motors(2);
+        for port in 1..=2 {
+            motors.move_voltage(port, 12_000).unwrap();
+        }
+        for second in 1..=3600 {
+            let now = Duration::from_secs(second);
+            motors.update(now);
+            loaded.update(now, &mut motors);
+        }
+        assert!(loaded.capacity() < battery.capacity());
+    }
+
+    #[test]
+    fn an_empty_battery_stays_empty() {
+        let mut battery = Battery::default();
+        battery.set_capacity(0.0);
+        battery.update(Duration::from_secs(60), &mut motors(0));
+        assert_eq!(battery.capacity(), 0.0);
+        assert!(battery.voltage() < EMPTY_VOLTAGE);
+    }
+}
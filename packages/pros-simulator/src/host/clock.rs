@@ -0,0 +1,105 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use pros_simulator_interface::SimulationSpeed;
+
+/// The simulator's virtual clock, which measures how much time has passed inside the simulation.
+///
+/// Time only moves forward when the scheduler advances it, which mostly happens when every task is
+/// waiting for something. This means that robot code will see the exact same timings every time
+/// it is run, no matter how fast the host computer is.
+///
+/// So that tasks which poll the clock or yield in a loop don't wait forever, time also moves
+/// forward by a microsecond each time robot code reads the clock and each time the scheduler
+/// switches between tasks that are ready to run.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    micros: Arc<AtomicU64>,
+}
+
+impl Clock {
+    /// The amount of simulated time that has passed since the simulator started.
+    pub fn now(&self) -> Duration {
+        Duration::from_micros(self.micros())
+    }
+
+    /// The number of simulated milliseconds that have passed since the simulator started.
+    pub fn millis(&self) -> u32 {
+        (self.micros() / 1000) as u32
+    }
+
+    /// The number of simulated microseconds that have passed since the simulator started.
+    pub fn micros(&self) -> u64 {
+        self.micros.load(Ordering::Acquire)
+    }
+
+    /// Reads the number of simulated milliseconds for robot code, moving the clock forward by a
+    /// microsecond afterwards.
+    pub fn read_millis(&self) -> u32 {
+        (self.read_micros() / 1000) as u32
+    }
+
+    /// Reads the number of simulated microseconds for robot code, moving the clock forward by a
+    /// microsecond afterwards.
+    pub fn read_micros(&self) -> u64 {
+        self.micros.fetch_add(1, Ordering::AcqRel)
+    }
+
+    /// Moves the clock forward to the given time. The clock will never move backwards.
+    pub fn advance_to(&self, time: Duration) {
+        self.micros
+            .fetch_max(time.as_micros() as u64, Ordering::AcqRel);
+    }
+}
+
+/// Keeps the virtual clock from getting ahead of real time, scaled by the simulation speed.
+#[derive(Debug)]
+pub struct Pacer {
+    speed: SimulationSpeed,
+    /// The real and virtual times at which the speed was last changed.
+    epoch: (Instant, Duration),
+}
+
+impl Pacer {
+    pub fn new(speed: SimulationSpeed) -> Self {
+        Self {
+            speed,
+            epoch: (Instant::now(), Duration::ZERO),
+        }
+    }
+
+    pub fn speed(&self) -> SimulationSpeed {
+        self.speed
+    }
+
+    /// Changes the simulation speed, starting from the given virtual time.
+    pub fn set_speed(&mut self, speed: SimulationSpeed, now: Duration) {
+        assert!(speed.is_valid(), "invalid simulation speed: {speed:?}");
+        self.speed = speed;
+        self.reset(now);
+    }
+
+    /// Starts pacing from the given virtual time, forgetting about any time that passed before
+    /// now (like while the simulator was paused).
+    pub fn reset(&mut self, now: Duration) {
+        self.epoch = (Instant::now(), now);
+    }
+
+    /// Waits until enough real time has passed for the virtual clock to be advanced to `time`.
+    pub async fn wait_until(&self, time: Duration) {
+        let SimulationSpeed::Factor(factor) = self.speed else {
+            return;
+        };
+        let (real_epoch, virtual_epoch) = self.epoch;
+        let Some(elapsed) = time.checked_sub(virtual_epoch) else {
+            return;
+        };
+        let deadline = real_epoch + elapsed.div_f64(factor);
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+}
@@ -1,14 +1,26 @@
-use std::mem;
+use std::{mem, time::Duration};
 
-use pros_simulator_interface::{ControllerState, DigitalControllerState};
+use pros_simulator_interface::{
+    ControllerId, ControllerScreenLines, ControllerState, DigitalControllerState, SimulatorEvent,
+    CONTROLLER_SCREEN_HEIGHT, CONTROLLER_SCREEN_WIDTH,
+};
 use pros_sys::{
-    misc::E_CONTROLLER_DIGITAL_R1, EINVAL, E_CONTROLLER_ANALOG_LEFT_X, E_CONTROLLER_ANALOG_LEFT_Y,
-    E_CONTROLLER_ANALOG_RIGHT_X, E_CONTROLLER_ANALOG_RIGHT_Y, E_CONTROLLER_DIGITAL_A,
-    E_CONTROLLER_DIGITAL_B, E_CONTROLLER_DIGITAL_DOWN, E_CONTROLLER_DIGITAL_L1,
-    E_CONTROLLER_DIGITAL_L2, E_CONTROLLER_DIGITAL_LEFT, E_CONTROLLER_DIGITAL_R2,
-    E_CONTROLLER_DIGITAL_RIGHT, E_CONTROLLER_DIGITAL_UP, E_CONTROLLER_DIGITAL_X,
-    E_CONTROLLER_DIGITAL_Y, E_CONTROLLER_MASTER, E_CONTROLLER_PARTNER,
+    misc::E_CONTROLLER_DIGITAL_R1, EAGAIN, EINVAL, E_CONTROLLER_ANALOG_LEFT_X,
+    E_CONTROLLER_ANALOG_LEFT_Y, E_CONTROLLER_ANALOG_RIGHT_X, E_CONTROLLER_ANALOG_RIGHT_Y,
+    E_CONTROLLER_DIGITAL_A, E_CONTROLLER_DIGITAL_B, E_CONTROLLER_DIGITAL_DOWN,
+    E_CONTROLLER_DIGITAL_L1, E_CONTROLLER_DIGITAL_L2, E_CONTROLLER_DIGITAL_LEFT,
+    E_CONTROLLER_DIGITAL_R2, E_CONTROLLER_DIGITAL_RIGHT, E_CONTROLLER_DIGITAL_UP,
+    E_CONTROLLER_DIGITAL_X, E_CONTROLLER_DIGITAL_Y, E_CONTROLLER_MASTER, E_CONTROLLER_PARTNER,
 };
+
+use crate::interface::SimulatorInterface;
+
+/// How often a controller accepts changes to its screen or rumble motor. Writing to it more
+/// often than this fails with `EAGAIN`.
+const SCREEN_UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+/// The longest rumble pattern a controller supports, in characters.
+const MAX_RUMBLE_LENGTH: usize = 8;
+
 struct Controller {
     state: ControllerState,
     new_presses: DigitalControllerState,
@@ -44,17 +56,81 @@ impl Controller {
     }
 }
 
+/// The screen on the front of a controller, which shows 3 lines of 19 characters.
+struct Screen {
+    lines: [Vec<char>; CONTROLLER_SCREEN_HEIGHT as usize],
+    /// When the robot code last wrote to the controller, for rate limiting.
+    last_write: Option<Duration>,
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        let mut screen = Self {
+            lines: Default::default(),
+            last_write: None,
+        };
+        screen.clear();
+        screen
+    }
+}
+
+impl Screen {
+    fn clear(&mut self) {
+        for line in 0..CONTROLLER_SCREEN_HEIGHT as usize {
+            self.clear_line(line);
+        }
+    }
+
+    fn clear_line(&mut self, line: usize) {
+        self.lines[line] = vec![' '; CONTROLLER_SCREEN_WIDTH as usize];
+    }
+
+    fn lines(&self) -> ControllerScreenLines {
+        self.lines.clone().map(|line| line.into_iter().collect())
+    }
+
+    /// Starts a write to the controller, failing with `EAGAIN` if the last one was too recent.
+    fn begin_write(&mut self, now: Duration) -> Result<(), i32> {
+        if let Some(last_write) = self.last_write {
+            if now < last_write + SCREEN_UPDATE_INTERVAL {
+                return Err(EAGAIN);
+            }
+        }
+        self.last_write = Some(now);
+        Ok(())
+    }
+}
+
+/// Checks that a line number is on the controller's screen.
+fn screen_line(line: u8) -> Result<usize, i32> {
+    if u32::from(line) < CONTROLLER_SCREEN_HEIGHT {
+        Ok(line.into())
+    } else {
+        Err(EINVAL)
+    }
+}
+
 /// Stores state of VEX V5 master and partner controllers.
 pub struct Controllers {
     master: Option<Controller>,
     partner: Option<Controller>,
+    master_screen: Screen,
+    partner_screen: Screen,
+    interface: SimulatorInterface,
 }
 
 impl Controllers {
-    pub fn new(master: Option<ControllerState>, partner: Option<ControllerState>) -> Self {
+    pub fn new(
+        interface: SimulatorInterface,
+        master: Option<ControllerState>,
+        partner: Option<ControllerState>,
+    ) -> Self {
         Self {
             master: master.map(|v| v.into()),
             partner: partner.map(|v| v.into()),
+            master_screen: Screen::default(),
+            partner_screen: Screen::default(),
+            interface,
         }
     }
 
@@ -88,6 +164,108 @@ impl Controllers {
         }
     }
 
+    /// Converts a controller ID from the robot code. Fails with EINVAL if the controller ID is
+    /// invalid.
+    fn controller_id(controller_id: u32) -> Result<ControllerId, i32> {
+        match controller_id {
+            E_CONTROLLER_MASTER => Ok(ControllerId::Master),
+            E_CONTROLLER_PARTNER => Ok(ControllerId::Partner),
+            _ => Err(EINVAL),
+        }
+    }
+
+    /// Starts a write to a controller's screen or rumble motor, failing with EINVAL if the
+    /// controller ID is invalid or EAGAIN if the controller was written to too recently.
+    fn begin_write(&mut self, controller_id: u32, now: Duration) -> Result<ControllerId, i32> {
+        let id = Self::controller_id(controller_id)?;
+        let screen = match id {
+            ControllerId::Master => &mut self.master_screen,
+            ControllerId::Partner => &mut self.partner_screen,
+        };
+        screen.begin_write(now)?;
+        Ok(id)
+    }
+
+    /// Changes the text on a controller's screen and notifies the simulator interface.
+    fn update_screen(&mut self, id: ControllerId, update: impl FnOnce(&mut Screen)) {
+        let screen = match id {
+            ControllerId::Master => &mut self.master_screen,
+            ControllerId::Partner => &mut self.partner_screen,
+        };
+        update(screen);
+        self.interface
+            .send(SimulatorEvent::ControllerScreenUpdated {
+                controller: id,
+                lines: screen.lines(),
+            });
+    }
+
+    /// Writes text to a controller's screen, starting at the given line and column and
+    /// replacing the characters already there. Text that runs past the edge of the screen is
+    /// cut off.
+    ///
+    /// Fails with EINVAL if the controller ID, line or column is invalid, or EAGAIN if the
+    /// controller was written to in the last 50 milliseconds.
+    pub fn set_text(
+        &mut self,
+        controller_id: u32,
+        now: Duration,
+        line: u8,
+        column: u8,
+        text: &str,
+    ) -> Result<(), i32> {
+        let line = screen_line(line)?;
+        let column = usize::from(column);
+        if column >= CONTROLLER_SCREEN_WIDTH as usize {
+            return Err(EINVAL);
+        }
+        let id = self.begin_write(controller_id, now)?;
+        self.update_screen(id, |screen| {
+            for (cell, c) in screen.lines[line][column..].iter_mut().zip(text.chars()) {
+                *cell = c;
+            }
+        });
+        Ok(())
+    }
+
+    /// Clears one line of a controller's screen.
+    ///
+    /// Fails with EINVAL if the controller ID or line is invalid, or EAGAIN if the controller
+    /// was written to in the last 50 milliseconds.
+    pub fn clear_line(&mut self, controller_id: u32, now: Duration, line: u8) -> Result<(), i32> {
+        let line = screen_line(line)?;
+        let id = self.begin_write(controller_id, now)?;
+        self.update_screen(id, |screen| screen.clear_line(line));
+        Ok(())
+    }
+
+    /// Clears every line of a controller's screen.
+    ///
+    /// Fails with EINVAL if the controller ID is invalid, or EAGAIN if the controller was
+    /// written to in the last 50 milliseconds.
+    pub fn clear(&mut self, controller_id: u32, now: Duration) -> Result<(), i32> {
+        let id = self.begin_write(controller_id, now)?;
+        self.update_screen(id, Screen::clear);
+        Ok(())
+    }
+
+    /// Makes a controller rumble with the given pattern of `.` (short rumble), `-` (long
+    /// rumble) and ` ` (pause). Patterns longer than 8 characters are cut off.
+    ///
+    /// Fails with EINVAL if the controller ID or pattern is invalid, or EAGAIN if the controller
+    /// was written to in the last 50 milliseconds.
+    pub fn rumble(&mut self, controller_id: u32, now: Duration, pattern: &str) -> Result<(), i32> {
+        if !pattern.chars().all(|c| matches!(c, '.' | '-' | ' ')) {
+            return Err(EINVAL);
+        }
+        let id = self.begin_write(controller_id, now)?;
+        self.interface.send(SimulatorEvent::ControllerRumble {
+            controller: id,
+            pattern: pattern.chars().take(MAX_RUMBLE_LENGTH).collect(),
+        });
+        Ok(())
+    }
+
     /// Get the state of a controller by ID. Fails with EINVAL if the controller ID is invalid.
     fn get_controller_state(&self, controller_id: u32) -> Result<Option<&Controller>, i32> {
         match controller_id {
@@ -246,3 +424,82 @@ impl Controllers {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// Controllers, and the events they've sent.
+    fn controllers() -> (Controllers, Arc<Mutex<Vec<SimulatorEvent>>>) {
+        let events = Arc::new(Mutex::new(vec![]));
+        let interface = {
+            let events = events.clone();
+            SimulatorInterface::from(move |event| events.lock().unwrap().push(event))
+        };
+        (Controllers::new(interface, None, None), events)
+    }
+
+    #[test]
+    fn writes_are_rate_limited() {
+        let (mut controllers, events) = controllers();
+        let master = E_CONTROLLER_MASTER;
+        assert_eq!(controllers.set_text(master, millis(0), 0, 0, "hi"), Ok(()));
+        assert_eq!(
+            controllers.set_text(master, millis(49), 0, 0, "hi"),
+            Err(EAGAIN)
+        );
+        assert_eq!(controllers.clear_line(master, millis(20), 0), Err(EAGAIN));
+        assert_eq!(controllers.clear(master, millis(30)), Err(EAGAIN));
+        assert_eq!(controllers.rumble(master, millis(40), "."), Err(EAGAIN));
+        assert_eq!(events.lock().unwrap().len(), 1);
+
+        // each controller has its own limit, and failed writes don't restart it
+        assert_eq!(controllers.clear(E_CONTROLLER_PARTNER, millis(40)), Ok(()));
+        assert_eq!(controllers.rumble(master, millis(50), "-"), Ok(()));
+        assert_eq!(controllers.clear(master, millis(99)), Err(EAGAIN));
+        assert_eq!(controllers.clear(master, millis(100)), Ok(()));
+    }
+
+    #[test]
+    fn invalid_writes_do_not_count_towards_the_limit() {
+        let (mut controllers, _) = controllers();
+        let master = E_CONTROLLER_MASTER;
+        assert_eq!(
+            controllers.set_text(master, millis(0), 0, CONTROLLER_SCREEN_WIDTH as u8, "hi"),
+            Err(EINVAL)
+        );
+        assert_eq!(controllers.rumble(master, millis(0), "x"), Err(EINVAL));
+        assert_eq!(controllers.clear_line(master, millis(0), 3), Err(EINVAL));
+        assert_eq!(controllers.clear(5, millis(0)), Err(EINVAL));
+        assert_eq!(controllers.set_text(master, millis(1), 0, 0, "hi"), Ok(()));
+    }
+
+    #[test]
+    fn text_and_rumble_patterns_are_cut_off() {
+        let (mut controllers, events) = controllers();
+        let column = CONTROLLER_SCREEN_WIDTH as u8 - 2;
+        controllers
+            .set_text(E_CONTROLLER_MASTER, millis(0), 1, column, "hello")
+            .unwrap();
+        controllers
+            .rumble(E_CONTROLLER_MASTER, millis(50), ". - . - . -")
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        let SimulatorEvent::ControllerScreenUpdated { lines, .. } = &events[0] else {
+            panic!("expected a screen update, got {:?}", events[0]);
+        };
+        assert!(lines[1].ends_with(" he"));
+        assert_eq!(lines[1].chars().count(), CONTROLLER_SCREEN_WIDTH as usize);
+        let SimulatorEvent::ControllerRumble { pattern, .. } = &events[1] else {
+            panic!("expected a rumble, got {:?}", events[1]);
+        };
+        assert_eq!(pattern, ". - . - ");
+    }
+}
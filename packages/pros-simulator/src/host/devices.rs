@@ -0,0 +1,220 @@
+use pros_simulator_interface::config::{AdiDevice, AdiPortConfig, RobotConfig, SmartDevice};
+use pros_sys::{ENODEV, ENXIO};
+use snafu::ensure;
+
+use crate::error::{InvalidRobotConfigSnafu, SimulatorError};
+
+/// The number of smart ports on a V5 brain.
+pub const NUM_SMART_PORTS: u8 = 21;
+
+/// Stores the state of one kind of smart device for every smart port on the brain.
+///
+/// Ports are numbered from 1 to 21, like in the PROS API. Accessing a port that doesn't exist
+/// fails with `ENXIO`, and accessing a port that doesn't have this kind of device plugged into it
+/// fails with `ENODEV`.
+pub struct DeviceTable<T> {
+    ports: [Option<T>; NUM_SMART_PORTS as usize],
+}
+
+impl<T> DeviceTable<T> {
+    /// Creates a device table, calling `device` with each port number to decide what device (if
+    /// any) is plugged into it.
+    pub fn new(mut device: impl FnMut(u8) -> Option<T>) -> Self {
+        Self {
+            ports: std::array::from_fn(|index| device(index as u8 + 1)),
+        }
+    }
+
+    fn index(port: u32) -> Result<usize, i32> {
+        if port == 0 || port > NUM_SMART_PORTS as u32 {
+            tracing::error!("Port {port} is not a valid smart port");
+            return Err(ENXIO);
+        }
+        Ok(port as usize - 1)
+    }
+
+    pub fn get(&self, port: u32) -> Result<&T, i32> {
+        self.ports[Self::index(port)?].as_ref().ok_or(ENODEV)
+    }
+
+    pub fn get_mut(&mut self, port: u32) -> Result<&mut T, i32> {
+        self.ports[Self::index(port)?].as_mut().ok_or(ENODEV)
+    }
+
+    /// Returns an iterator over every plugged in device and its port number.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &T)> {
+        self.ports
+            .iter()
+            .enumerate()
+            .filter_map(|(index, device)| Some((index as u8 + 1, device.as_ref()?)))
+    }
+
+    /// Returns a mutable iterator over every plugged in device and its port number.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u8, &mut T)> {
+        self.ports
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, device)| Some((index as u8 + 1, device.as_mut()?)))
+    }
+}
+
+/// Checks that every device in a robot configuration is plugged into a port that exists, and that
+/// no two devices share a port. Two-wire three-wire devices (encoders and ultrasonics) must be in
+/// port A, C, E or G, and also take up the port after it.
+pub fn check_robot_config(config: &RobotConfig) -> Result<(), SimulatorError> {
+    for (index, smart_port) in config.smart_ports.iter().enumerate() {
+        let port = smart_port.port;
+        ensure!(
+            (1..=NUM_SMART_PORTS).contains(&port),
+            InvalidRobotConfigSnafu {
+                message: format!("smart port {port} does not exist (expected 1-{NUM_SMART_PORTS})")
+            }
+        );
+        ensure!(
+            !config.smart_ports[..index]
+                .iter()
+                .any(|other| other.port == port),
+            InvalidRobotConfigSnafu {
+                message: format!("more than one device is plugged into smart port {port}")
+            }
+        );
+        if let SmartDevice::AdiExpander { adi_ports } = &smart_port.device {
+            check_adi_ports(adi_ports)?;
+        }
+    }
+    check_adi_ports(&config.adi_ports)
+}
+
+fn check_adi_ports(adi_ports: &[AdiPortConfig]) -> Result<(), SimulatorError> {
+    for (index, adi_port) in adi_ports.iter().enumerate() {
+        let port = adi_port.port;
+        ensure!(
+            ('A'..='H').contains(&port.to_ascii_uppercase()),
+            InvalidRobotConfigSnafu {
+                message: format!("three-wire port {port} does not exist (expected A-H)")
+            }
+        );
+        ensure!(
+            !adi_ports[..index]
+                .iter()
+                .any(|other| other.port.eq_ignore_ascii_case(&port)),
+            InvalidRobotConfigSnafu {
+                message: format!("more than one device is plugged into three-wire port {port}")
+            }
+        );
+    }
+
+    for adi_port in adi_ports {
+        if !matches!(adi_port.device, AdiDevice::Encoder | AdiDevice::Ultrasonic) {
+            continue;
+        }
+        let port = adi_port.port.to_ascii_uppercase();
+        ensure!(
+            matches!(port, 'A' | 'C' | 'E' | 'G'),
+            InvalidRobotConfigSnafu {
+                message: format!(
+                    "the two-wire device in three-wire port {port} must be in port A, C, E or G"
+                )
+            }
+        );
+        let partner = (port as u8 + 1) as char;
+        ensure!(
+            !adi_ports
+                .iter()
+                .any(|other| other.port.eq_ignore_ascii_case(&partner)),
+            InvalidRobotConfigSnafu {
+                message: format!(
+                    "three-wire port {partner} is already used by the two-wire device in port {port}"
+                )
+            }
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::config::SmartPortConfig;
+
+    use super::*;
+
+    fn adi(port: char, device: AdiDevice) -> AdiPortConfig {
+        AdiPortConfig { port, device }
+    }
+
+    fn config(adi_ports: Vec<AdiPortConfig>) -> RobotConfig {
+        RobotConfig {
+            adi_ports,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn accepts_two_wire_devices_in_odd_ports() {
+        let config = config(vec![
+            adi('A', AdiDevice::Encoder),
+            adi('c', AdiDevice::Ultrasonic),
+            adi('E', AdiDevice::DigitalIn),
+            adi('F', AdiDevice::DigitalOut),
+            adi('G', AdiDevice::Encoder),
+        ]);
+        assert!(check_robot_config(&config).is_ok());
+    }
+
+    #[test]
+    fn rejects_two_wire_devices_in_even_ports() {
+        for port in ['B', 'd', 'H'] {
+            let config = config(vec![adi(port, AdiDevice::Encoder)]);
+            assert!(check_robot_config(&config).is_err(), "port {port}");
+        }
+    }
+
+    #[test]
+    fn rejects_devices_in_a_two_wire_devices_second_port() {
+        let config = config(vec![
+            adi('C', AdiDevice::DigitalIn),
+            adi('D', AdiDevice::DigitalOut),
+            adi('a', AdiDevice::Ultrasonic),
+            adi('b', AdiDevice::DigitalIn),
+        ]);
+        assert!(check_robot_config(&config).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_and_shared_ports() {
+        assert!(check_robot_config(&config(vec![adi('I', AdiDevice::DigitalIn)])).is_err());
+        assert!(check_robot_config(&config(vec![
+            adi('a', AdiDevice::DigitalIn),
+            adi('A', AdiDevice::DigitalOut),
+        ]))
+        .is_err());
+
+        for ports in [vec![0], vec![22], vec![3, 3]] {
+            let config = RobotConfig {
+                smart_ports: ports
+                    .into_iter()
+                    .map(|port| SmartPortConfig {
+                        port,
+                        device: SmartDevice::AdiExpander { adi_ports: vec![] },
+                    })
+                    .collect(),
+                ..Default::default()
+            };
+            assert!(check_robot_config(&config).is_err());
+        }
+    }
+
+    #[test]
+    fn checks_adi_expander_ports() {
+        let config = RobotConfig {
+            smart_ports: vec![SmartPortConfig {
+                port: 1,
+                device: SmartDevice::AdiExpander {
+                    adi_ports: vec![adi('H', AdiDevice::Encoder)],
+                },
+            }],
+            ..Default::default()
+        };
+        assert!(check_robot_config(&config).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use pros_simulator_interface::{
+    config::{RobotConfig, SensorMount, SmartDevice},
+    DistanceReading,
+};
+
+use super::{
+    devices::DeviceTable,
+    pose::{mount_pose, PoseModel},
+    world::{RayHit, WorldModel},
+};
+
+/// The reading of a sensor with nothing in range.
+const NOTHING_IN_RANGE: DistanceReading = DistanceReading {
+    distance: 9999,
+    confidence: 0,
+    object_size: 0,
+    object_velocity: 0.0,
+};
+/// The furthest a V5 Distance Sensor can see, in millimeters.
+const MAX_RANGE: u32 = 2000;
+/// Objects closer than this (in millimeters) are always reported with low confidence.
+const LOW_CONFIDENCE_RANGE: u32 = 200;
+/// The largest object size the sensor reports, which is how large the field perimeter looks.
+const MAX_OBJECT_SIZE: u32 = 400;
+/// How large objects look to the sensor for each meter of their width.
+const OBJECT_SIZE_PER_METER: f64 = 500.0;
+/// How far ahead to look when estimating how fast the distance to an object is changing, in
+/// seconds.
+const VELOCITY_LOOKAHEAD: f64 = 0.01;
+
+/// A simulated V5 Distance Sensor.
+///
+/// If the sensor has a mount position, it measures the distance to the nearest obstacle, game
+/// piece or wall in the direction it's facing. Readings from `DistanceInput` simulator messages
+/// take priority.
+#[derive(Debug, Clone)]
+pub struct DistanceSensor {
+    mount: Option<SensorMount>,
+    input: Option<DistanceReading>,
+}
+
+impl DistanceSensor {
+    pub fn new(mount: Option<SensorMount>) -> Self {
+        Self { mount, input: None }
+    }
+
+    /// Replaces the sensor's readings with the given values, or goes back to automatic readings
+    /// if `input` is `None`.
+    pub fn set_input(&mut self, input: Option<DistanceReading>) {
+        self.input = input;
+    }
+
+    /// Finds the object the sensor is looking at, if the sensor is mounted on the robot.
+    fn look(&self, pose: &PoseModel, world: &WorldModel, lookahead: f64) -> Option<RayHit> {
+        let mount = self.mount.as_ref()?;
+        Some(world.raycast(mount_pose(pose.predict(lookahead), mount)))
+    }
+
+    /// What the sensor currently sees.
+    pub fn reading(&self, pose: &PoseModel, world: &WorldModel) -> DistanceReading {
+        if let Some(input) = self.input {
+            return input;
+        }
+        let Some(RayHit { distance, size }) = self.look(pose, world, 0.0) else {
+            return NOTHING_IN_RANGE;
+        };
+        let millimeters = (distance * 1000.0).round() as u32;
+        if millimeters > MAX_RANGE {
+            return NOTHING_IN_RANGE;
+        }
+
+        let future_distance = self
+            .look(pose, world, VELOCITY_LOOKAHEAD)
+            .map_or(distance, |hit| hit.distance);
+        DistanceReading {
+            distance: millimeters,
+            confidence: if millimeters > LOW_CONFIDENCE_RANGE {
+                63
+            } else {
+                10
+            },
+            object_size: (size * OBJECT_SIZE_PER_METER).min(MAX_OBJECT_SIZE as f64) as u32,
+            object_velocity: (future_distance - distance) / VELOCITY_LOOKAHEAD,
+        }
+    }
+}
+
+/// Stores the state of every V5 Distance Sensor plugged into the brain.
+pub struct DistanceSensors {
+    sensors: DeviceTable<DistanceSensor>,
+}
+
+impl DistanceSensors {
+    pub fn new(robot_config: &RobotConfig) -> Self {
+        Self {
+            sensors: DeviceTable::new(|port| match robot_config.smart_device(port) {
+                Some(SmartDevice::Distance { mount }) => Some(DistanceSensor::new(*mount)),
+                _ => None,
+            }),
+        }
+    }
+
+    pub fn get(&mut self, port: u32) -> Result<&mut DistanceSensor, i32> {
+        self.sensors.get_mut(port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pros_simulator_interface::{
+        config::SmartPortConfig, world::World, RobotPose, RobotVelocity,
+    };
+    use pros_sys::ENODEV;
+
+    use super::*;
+    use crate::host::field::FIELD_SIZE;
+
+    /// A sensor on the front of the robot, facing forwards.
+    fn front_sensor() -> DistanceSensor {
+        DistanceSensor::new(Some(SensorMount {
+            forward: 0.1,
+            left: 0.0,
+            heading: 0.0,
+        }))
+    }
+
+    fn empty_field() -> WorldModel {
+        WorldModel::new(&World::default(), &RobotConfig::default())
+    }
+
+    /// A robot at the given y coordinate, facing the far wall.
+    fn robot_at(y: f64) -> PoseModel {
+        let mut pose = PoseModel::default();
+        pose.set_pose(RobotPose {
+            x: 0.0,
+            y,
+            heading: 0.0,
+        });
+        pose
+    }
+
+    #[test]
+    fn measures_distance_to_wall() {
+        let reading = front_sensor().reading(&robot_at(0.0), &empty_field());
+        let expected = ((FIELD_SIZE / 2.0 - 0.1) * 1000.0).round() as u32;
+        assert_eq!(reading.distance, expected);
+        assert_eq!(reading.confidence, 63);
+        assert_eq!(reading.object_size, MAX_OBJECT_SIZE);
+        assert_eq!(reading.object_velocity, 0.0);
+    }
+
+    #[test]
+    fn close_objects_have_low_confidence() {
+        let reading = front_sensor().reading(&robot_at(1.6), &empty_field());
+        assert!(reading.distance <= LOW_CONFIDENCE_RANGE);
+        assert_eq!(reading.confidence, 10);
+    }
+
+    #[test]
+    fn nothing_in_range_past_two_meters() {
+        let reading = front_sensor().reading(&robot_at(-1.0), &empty_field());
+        assert_eq!(reading, NOTHING_IN_RANGE);
+    }
+
+    #[test]
+    fn unmounted_sensor_sees_nothing() {
+        let reading = DistanceSensor::new(None).reading(&robot_at(0.0), &empty_field());
+        assert_eq!(reading, NOTHING_IN_RANGE);
+    }
+
+    #[test]
+    fn reports_approach_velocity() {
+        let mut pose = robot_at(0.0);
+        pose.update(Duration::ZERO);
+        pose.set_velocity(RobotVelocity {
+            x: 0.0,
+            y: 0.5,
+            angular: 0.0,
+        });
+        let reading = front_sensor().reading(&pose, &empty_field());
+        assert!((reading.object_velocity + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn input_overrides_automatic_readings() {
+        let input = DistanceReading {
+            distance: 150,
+            confidence: 40,
+            object_size: 75,
+            object_velocity: 0.0,
+        };
+        let mut sensor = front_sensor();
+        sensor.set_input(Some(input));
+        assert_eq!(sensor.reading(&robot_at(0.0), &empty_field()), input);
+        sensor.set_input(None);
+        assert_ne!(sensor.reading(&robot_at(0.0), &empty_field()), input);
+    }
+
+    #[test]
+    fn only_configured_ports_have_sensors() {
+        let mut sensors = DistanceSensors::new(&RobotConfig {
+            smart_ports: vec![SmartPortConfig {
+                port: 5,
+                device: SmartDevice::Distance { mount: None },
+            }],
+            ..Default::default()
+        });
+        assert!(sensors.get(5).is_ok());
+        assert_eq!(sensors.get(6).unwrap_err(), ENODEV);
+    }
+}
@@ -0,0 +1,25 @@
+use pros_simulator_interface::RobotPose;
+
+/// The length of each side of the field's perimeter, in meters (12 feet).
+pub const FIELD_SIZE: f64 = 3.6576;
+
+/// Measures the distance from a point on the field to the perimeter, looking in the direction of
+/// `pose.heading`. Returns zero if the point is outside the field.
+pub fn distance_to_perimeter(pose: RobotPose) -> f64 {
+    let half = FIELD_SIZE / 2.0;
+    if pose.x.abs() > half || pose.y.abs() > half {
+        return 0.0;
+    }
+    let (dx, dy) = pose.heading.to_radians().sin_cos();
+    // distance to the wall the ray hits along each axis
+    let along = |position: f64, direction: f64| {
+        if direction > 0.0 {
+            (half - position) / direction
+        } else if direction < 0.0 {
+            (-half - position) / direction
+        } else {
+            f64::INFINITY
+        }
+    };
+    along(pose.x, dx).min(along(pose.y, dy))
+}
@@ -0,0 +1,281 @@
+use pros_simulator_interface::{
+    config::{RobotConfig, SensorMount, SmartDevice},
+    RobotPose,
+};
+
+use super::{
+    devices::DeviceTable,
+    noise::Noise,
+    pose::{mount_pose, PoseModel, GRAVITY},
+};
+
+/// The data rate of a GPS sensor when it is first plugged in, in milliseconds.
+pub const DEFAULT_DATA_RATE: u32 = 10;
+/// The fastest data rate a GPS sensor supports, in milliseconds.
+const MINIMUM_DATA_RATE: u32 = 5;
+
+/// Wraps an angle in degrees to the range [-180, 180).
+fn wrap_degrees(angle: f64) -> f64 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// A simulated V5 GPS Sensor.
+///
+/// The sensor can always see the field strips, so it reports its absolute position on the field
+/// plus a configurable amount of random error. Like on a real robot, the robot code tells the
+/// sensor where it's mounted with `gps_set_offset`, so that it reports the position of the
+/// robot's center of rotation instead of its own.
+#[derive(Debug, Clone)]
+pub struct Gps {
+    /// Where the sensor is physically mounted on the robot.
+    mount: SensorMount,
+    /// RMS position error in meters.
+    error: f64,
+    noise: Noise,
+    /// The sensor's offset from the center of turning as set by the robot code, in meters. `x`
+    /// points to the sensor's right and `y` points the way it's facing.
+    offset: (f64, f64),
+    rotation_offset: f64,
+    /// Data rate in milliseconds.
+    data_rate: u32,
+}
+
+impl Gps {
+    pub fn new(port: u8, mount: SensorMount, error: f64) -> Self {
+        Self {
+            mount,
+            error,
+            noise: Noise::new(port.into()),
+            offset: (0.0, 0.0),
+            rotation_offset: 0.0,
+            data_rate: DEFAULT_DATA_RATE,
+        }
+    }
+
+    /// The sensor's unwrapped heading, in degrees clockwise from the positive y axis.
+    pub fn heading_raw(&self, pose: &PoseModel) -> f64 {
+        pose.pose().heading + self.mount.heading
+    }
+
+    /// The sensor's heading in degrees, from 0 to 360.
+    pub fn heading(&self, pose: &PoseModel) -> f64 {
+        self.heading_raw(pose).rem_euclid(360.0)
+    }
+
+    /// The position of the center of turning, as measured by the sensor, in meters from the
+    /// center of the field.
+    pub fn position(&mut self, pose: &PoseModel) -> (f64, f64) {
+        let RobotPose { x, y, heading } = mount_pose(pose.pose(), &self.mount);
+        // the sensor assumes the robot faces the same way it does
+        let (sin, cos) = heading.to_radians().sin_cos();
+        let (right, forward) = self.offset;
+        // forwards is (sin, cos) and right is (cos, -sin) in field coordinates
+        let center_x = x - forward * sin - right * cos;
+        let center_y = y - forward * cos + right * sin;
+        // split the RMS error evenly between the two axes
+        let std_dev = self.error / 2f64.sqrt();
+        (
+            center_x + self.noise.gaussian(std_dev),
+            center_y + self.noise.gaussian(std_dev),
+        )
+    }
+
+    /// The values in `gps_status_s_t`: x and y position, pitch, roll and yaw.
+    pub fn status(&mut self, pose: &PoseModel) -> [f64; 5] {
+        let (x, y) = self.position(pose);
+        [x, y, 0.0, 0.0, wrap_degrees(self.heading_raw(pose))]
+    }
+
+    /// The possible RMS error of the position readings, in meters.
+    pub fn error(&self) -> f64 {
+        self.error
+    }
+
+    /// The total number of degrees the sensor has turned clockwise.
+    pub fn rotation(&self, pose: &PoseModel) -> f64 {
+        self.heading_raw(pose) + self.rotation_offset
+    }
+
+    /// Makes the sensor's rotation read as the given value.
+    pub fn set_rotation(&mut self, pose: &PoseModel, rotation: f64) {
+        self.rotation_offset = rotation - self.heading_raw(pose);
+    }
+
+    pub fn offset(&self) -> (f64, f64) {
+        self.offset
+    }
+
+    /// Sets the sensor's offset from the center of turning, in meters. `x` points to the
+    /// sensor's right and `y` points the way it's facing.
+    pub fn set_offset(&mut self, x: f64, y: f64) {
+        self.offset = (x, y);
+    }
+
+    /// The sensor's rate of rotation around its x, y and z axes, in degrees per second.
+    pub fn gyro_rate(&self, pose: &PoseModel) -> [f64; 3] {
+        [0.0, 0.0, pose.velocity().angular]
+    }
+
+    /// The sensor's acceleration along its x, y and z axes, in multiples of standard gravity.
+    pub fn accel(&self, pose: &PoseModel) -> [f64; 3] {
+        let (forward, left) = pose.to_robot_frame(pose.acceleration());
+        // rotate from the robot's frame into the sensor's
+        let (sin, cos) = self.mount.heading.to_radians().sin_cos();
+        let x = forward * cos - left * sin;
+        let y = forward * sin + left * cos;
+        [x / GRAVITY, y / GRAVITY, 1.0]
+    }
+
+    /// The data rate in milliseconds.
+    pub fn data_rate(&self) -> u32 {
+        self.data_rate
+    }
+
+    /// Sets the data rate, rounding it down to a multiple of 5 milliseconds.
+    pub fn set_data_rate(&mut self, rate: u32) {
+        self.data_rate = (rate - rate % 5).max(MINIMUM_DATA_RATE);
+    }
+}
+
+/// Stores the state of every V5 GPS Sensor plugged into the brain.
+pub struct GpsSensors {
+    sensors: DeviceTable<Gps>,
+}
+
+impl GpsSensors {
+    pub fn new(robot_config: &RobotConfig) -> Self {
+        Self {
+            sensors: DeviceTable::new(|port| match robot_config.smart_device(port) {
+                Some(SmartDevice::Gps { mount, error }) => Some(Gps::new(port, *mount, *error)),
+                _ => None,
+            }),
+        }
+    }
+
+    pub fn get(&mut self, port: u32) -> Result<&mut Gps, i32> {
+        self.sensors.get_mut(port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::config::SmartPortConfig;
+    use pros_sys::ENODEV;
+
+    use super::*;
+
+    fn pose_at(x: f64, y: f64, heading: f64) -> PoseModel {
+        let mut pose = PoseModel::default();
+        pose.set_pose(RobotPose { x, y, heading });
+        pose
+    }
+
+    /// A sensor mounted 0.2 meters in front of the center of turning.
+    fn front_gps(error: f64) -> Gps {
+        Gps::new(
+            1,
+            SensorMount {
+                forward: 0.2,
+                left: 0.0,
+                heading: 0.0,
+            },
+            error,
+        )
+    }
+
+    fn assert_near((x, y): (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (x - expected.0).abs() < 1e-9 && (y - expected.1).abs() < 1e-9,
+            "expected {expected:?}, got {:?}",
+            (x, y)
+        );
+    }
+
+    #[test]
+    fn reports_its_own_position_without_offset() {
+        let mut gps = front_gps(0.0);
+        assert_near(gps.position(&pose_at(1.0, -0.5, 90.0)), (1.2, -0.5));
+    }
+
+    #[test]
+    fn offset_gives_center_of_turning() {
+        let mut gps = front_gps(0.0);
+        gps.set_offset(0.0, 0.2);
+        assert_eq!(gps.offset(), (0.0, 0.2));
+        assert_near(gps.position(&pose_at(1.0, -0.5, 90.0)), (1.0, -0.5));
+        assert_near(gps.position(&pose_at(1.0, -0.5, 225.0)), (1.0, -0.5));
+    }
+
+    #[test]
+    fn heading_includes_mount_direction() {
+        let gps = Gps::new(
+            1,
+            SensorMount {
+                forward: 0.0,
+                left: 0.0,
+                heading: 90.0,
+            },
+            0.0,
+        );
+        let pose = pose_at(0.0, 0.0, 300.0);
+        assert_eq!(gps.heading_raw(&pose), 390.0);
+        assert_eq!(gps.heading(&pose), 30.0);
+    }
+
+    #[test]
+    fn status_wraps_yaw() {
+        let mut gps = front_gps(0.0);
+        let status = gps.status(&pose_at(0.0, 0.0, 270.0));
+        assert_eq!(status[2..], [0.0, 0.0, -90.0]);
+    }
+
+    #[test]
+    fn set_rotation_offsets_later_readings() {
+        let mut gps = front_gps(0.0);
+        gps.set_rotation(&pose_at(0.0, 0.0, 100.0), 0.0);
+        assert_eq!(gps.rotation(&pose_at(0.0, 0.0, 100.0)), 0.0);
+        assert_eq!(gps.rotation(&pose_at(0.0, 0.0, 460.0)), 360.0);
+    }
+
+    #[test]
+    fn position_error_matches_configured_rms() {
+        let mut gps = front_gps(0.05);
+        assert_eq!(gps.error(), 0.05);
+        let pose = pose_at(0.0, 0.0, 0.0);
+        let samples = 10_000;
+        let squared_error = (0..samples)
+            .map(|_| {
+                let (x, y) = gps.position(&pose);
+                x.powi(2) + (y - 0.2).powi(2)
+            })
+            .sum::<f64>();
+        let rms = (squared_error / samples as f64).sqrt();
+        assert!((rms - 0.05).abs() < 0.002, "rms error was {rms}");
+    }
+
+    #[test]
+    fn data_rate_is_rounded_down() {
+        let mut gps = front_gps(0.0);
+        assert_eq!(gps.data_rate(), DEFAULT_DATA_RATE);
+        gps.set_data_rate(27);
+        assert_eq!(gps.data_rate(), 25);
+        gps.set_data_rate(2);
+        assert_eq!(gps.data_rate(), MINIMUM_DATA_RATE);
+    }
+
+    #[test]
+    fn only_configured_ports_have_sensors() {
+        let mut sensors = GpsSensors::new(&RobotConfig {
+            smart_ports: vec![SmartPortConfig {
+                port: 9,
+                device: SmartDevice::Gps {
+                    mount: SensorMount::default(),
+                    error: 0.0,
+                },
+            }],
+            ..Default::default()
+        });
+        assert!(sensors.get(9).is_ok());
+        assert_eq!(sensors.get(10).unwrap_err(), ENODEV);
+    }
+}
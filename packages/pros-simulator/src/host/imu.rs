@@ -0,0 +1,293 @@
+use std::time::Duration;
+
+use pros_simulator_interface::config::{RobotConfig, SmartDevice};
+use pros_sys::{EAGAIN, E_IMU_STATUS_CALIBRATING, IMU_MINIMUM_DATA_RATE};
+
+use super::{
+    devices::DeviceTable,
+    noise::Noise,
+    pose::{PoseModel, GRAVITY},
+};
+
+/// How long a V5 Inertial Sensor takes to calibrate.
+pub const CALIBRATION_TIME: Duration = Duration::from_secs(2);
+/// The data rate of an inertial sensor when it is first plugged in, in milliseconds.
+pub const DEFAULT_DATA_RATE: u32 = 10;
+
+/// Wraps an angle in degrees to the range [-180, 180).
+fn wrap_degrees(angle: f64) -> f64 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// A simulated V5 Inertial Sensor.
+///
+/// The sensor measures the heading of the simulated robot, plus a configurable amount of drift
+/// and noise. The field is flat, so pitch and roll are always zero unless the robot code changes
+/// them.
+#[derive(Debug, Clone)]
+pub struct Imu {
+    /// Drift in degrees per minute.
+    drift: f64,
+    /// Standard deviation of the noise in each reading, in degrees.
+    noise_level: f64,
+    noise: Noise,
+    /// The time at which the sensor finishes (or finished) calibrating.
+    calibration_end: Duration,
+    /// The robot's heading when the sensor was last calibrated.
+    zero_heading: f64,
+    rotation_offset: f64,
+    heading_offset: f64,
+    pitch_offset: f64,
+    roll_offset: f64,
+    yaw_offset: f64,
+    /// Data rate in milliseconds.
+    data_rate: u32,
+}
+
+impl Imu {
+    /// Creates an inertial sensor that starts calibrating as soon as the simulator starts, like
+    /// a real sensor does when the brain is turned on.
+    pub fn new(port: u8, drift: f64, noise: f64) -> Self {
+        Self {
+            drift,
+            noise_level: noise,
+            noise: Noise::new(port.into()),
+            calibration_end: CALIBRATION_TIME,
+            zero_heading: 0.0,
+            rotation_offset: 0.0,
+            heading_offset: 0.0,
+            pitch_offset: 0.0,
+            roll_offset: 0.0,
+            yaw_offset: 0.0,
+            data_rate: DEFAULT_DATA_RATE,
+        }
+    }
+
+    pub fn is_calibrating(&self, now: Duration) -> bool {
+        now < self.calibration_end
+    }
+
+    /// The sensor's `imu_status_e_t` value.
+    pub fn status(&self, now: Duration) -> u32 {
+        if self.is_calibrating(now) {
+            E_IMU_STATUS_CALIBRATING
+        } else {
+            0
+        }
+    }
+
+    /// Starts calibrating the sensor, which resets all of its readings to zero.
+    pub fn reset(&mut self, pose: &PoseModel, now: Duration) -> Result<(), i32> {
+        if self.is_calibrating(now) {
+            return Err(EAGAIN);
+        }
+        self.calibration_end = now + CALIBRATION_TIME;
+        self.zero_heading = pose.pose().heading;
+        self.rotation_offset = 0.0;
+        self.heading_offset = 0.0;
+        self.pitch_offset = 0.0;
+        self.roll_offset = 0.0;
+        self.yaw_offset = 0.0;
+        Ok(())
+    }
+
+    /// The number of degrees the sensor has turned clockwise since it was calibrated, before any
+    /// offsets or noise are applied.
+    fn raw_rotation(&self, pose: &PoseModel, now: Duration) -> f64 {
+        let minutes = now.saturating_sub(self.calibration_end).as_secs_f64() / 60.0;
+        pose.pose().heading - self.zero_heading + self.drift * minutes
+    }
+
+    fn noise(&mut self) -> f64 {
+        self.noise.gaussian(self.noise_level)
+    }
+
+    /// The total number of degrees the sensor has turned clockwise.
+    pub fn rotation(&mut self, pose: &PoseModel, now: Duration) -> f64 {
+        self.raw_rotation(pose, now) + self.rotation_offset + self.noise()
+    }
+
+    /// The sensor's heading in degrees, from 0 to 360.
+    pub fn heading(&mut self, pose: &PoseModel, now: Duration) -> f64 {
+        (self.raw_rotation(pose, now) + self.heading_offset + self.noise()).rem_euclid(360.0)
+    }
+
+    /// The sensor's pitch, roll and yaw in degrees, each from -180 to 180.
+    pub fn euler(&mut self, pose: &PoseModel, now: Duration) -> [f64; 3] {
+        [
+            wrap_degrees(self.pitch_offset + self.noise()),
+            wrap_degrees(self.roll_offset + self.noise()),
+            wrap_degrees(self.raw_rotation(pose, now) + self.yaw_offset + self.noise()),
+        ]
+    }
+
+    /// The sensor's orientation as an `[x, y, z, w]` quaternion.
+    pub fn quaternion(&mut self, pose: &PoseModel, now: Duration) -> [f64; 4] {
+        let [pitch, roll, yaw] = self.euler(pose, now).map(|angle| angle.to_radians() / 2.0);
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        let (sin_roll, cos_roll) = roll.sin_cos();
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        [
+            sin_roll * cos_pitch * cos_yaw - cos_roll * sin_pitch * sin_yaw,
+            cos_roll * sin_pitch * cos_yaw + sin_roll * cos_pitch * sin_yaw,
+            cos_roll * cos_pitch * sin_yaw - sin_roll * sin_pitch * cos_yaw,
+            cos_roll * cos_pitch * cos_yaw + sin_roll * sin_pitch * sin_yaw,
+        ]
+    }
+
+    /// The sensor's rate of rotation around its x, y and z axes, in degrees per second.
+    pub fn gyro_rate(&mut self, pose: &PoseModel) -> [f64; 3] {
+        let rate = pose.velocity().angular + self.drift / 60.0;
+        [self.noise(), self.noise(), rate + self.noise()]
+    }
+
+    /// The sensor's acceleration along its x, y and z axes, in multiples of standard gravity.
+    /// The x axis points towards the front of the robot and the z axis points up, so gravity
+    /// reads as +1 on the z axis.
+    pub fn accel(&self, pose: &PoseModel) -> [f64; 3] {
+        let (x, y) = pose.to_robot_frame(pose.acceleration());
+        [x / GRAVITY, y / GRAVITY, 1.0]
+    }
+
+    /// Makes the sensor's rotation read as the given value.
+    pub fn set_rotation(&mut self, pose: &PoseModel, now: Duration, rotation: f64) {
+        self.rotation_offset = rotation - self.raw_rotation(pose, now);
+    }
+
+    /// Makes the sensor's heading read as the given value, which is clamped to [0, 360].
+    pub fn set_heading(&mut self, pose: &PoseModel, now: Duration, heading: f64) {
+        self.heading_offset = heading.clamp(0.0, 360.0) - self.raw_rotation(pose, now);
+    }
+
+    pub fn set_pitch(&mut self, pitch: f64) {
+        self.pitch_offset = pitch.clamp(-180.0, 180.0);
+    }
+
+    pub fn set_roll(&mut self, roll: f64) {
+        self.roll_offset = roll.clamp(-180.0, 180.0);
+    }
+
+    /// Makes the sensor's yaw read as the given value, which is clamped to [-180, 180].
+    pub fn set_yaw(&mut self, pose: &PoseModel, now: Duration, yaw: f64) {
+        self.yaw_offset = yaw.clamp(-180.0, 180.0) - self.raw_rotation(pose, now);
+    }
+
+    /// The data rate in milliseconds.
+    pub fn data_rate(&self) -> u32 {
+        self.data_rate
+    }
+
+    /// Sets the data rate, rounding it down to a multiple of 5 milliseconds.
+    pub fn set_data_rate(&mut self, rate: u32) {
+        self.data_rate = (rate - rate % 5).max(IMU_MINIMUM_DATA_RATE);
+    }
+}
+
+/// Stores the state of every V5 Inertial Sensor plugged into the brain.
+pub struct Imus {
+    imus: DeviceTable<Imu>,
+}
+
+impl Imus {
+    pub fn new(robot_config: &RobotConfig) -> Self {
+        Self {
+            imus: DeviceTable::new(|port| match robot_config.smart_device(port) {
+                Some(SmartDevice::Imu { drift, noise }) => Some(Imu::new(port, *drift, *noise)),
+                _ => None,
+            }),
+        }
+    }
+
+    /// Returns the sensor on the given port, even if it is calibrating.
+    pub fn get(&mut self, port: u32) -> Result<&mut Imu, i32> {
+        self.imus.get_mut(port)
+    }
+
+    /// Returns the sensor on the given port, failing with `EAGAIN` if it is still calibrating.
+    pub fn get_calibrated(&mut self, port: u32, now: Duration) -> Result<&mut Imu, i32> {
+        let imu = self.imus.get_mut(port)?;
+        if imu.is_calibrating(now) {
+            return Err(EAGAIN);
+        }
+        Ok(imu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::{
+        config::{SmartDevice, SmartPortConfig},
+        RobotPose,
+    };
+    use pros_sys::ENODEV;
+
+    use super::*;
+
+    fn imus() -> Imus {
+        Imus::new(&RobotConfig {
+            smart_ports: vec![SmartPortConfig {
+                port: 1,
+                device: SmartDevice::Imu {
+                    drift: 0.0,
+                    noise: 0.0,
+                },
+            }],
+            ..Default::default()
+        })
+    }
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn calibrates_for_two_seconds_at_startup() {
+        let imu = Imu::new(1, 0.0, 0.0);
+        assert!(imu.is_calibrating(Duration::ZERO));
+        assert_eq!(imu.status(millis(1999)), E_IMU_STATUS_CALIBRATING);
+        assert!(!imu.is_calibrating(CALIBRATION_TIME));
+        assert_eq!(imu.status(CALIBRATION_TIME), 0);
+    }
+
+    #[test]
+    fn readings_wait_for_calibration() {
+        let mut imus = imus();
+        assert_eq!(
+            imus.get_calibrated(1, millis(1000)).map(|_| ()),
+            Err(EAGAIN)
+        );
+        assert!(imus.get(1).is_ok());
+        assert!(imus.get_calibrated(1, millis(2000)).is_ok());
+        assert_eq!(
+            imus.get_calibrated(2, millis(2000)).map(|_| ()),
+            Err(ENODEV)
+        );
+
+        // resetting starts calibrating again, and can't be done while calibrating
+        let mut pose = PoseModel::default();
+        pose.set_pose(RobotPose {
+            x: 0.0,
+            y: 0.0,
+            heading: 90.0,
+        });
+        let imu = imus.get(1).unwrap();
+        assert_eq!(imu.reset(&pose, millis(1000)), Err(EAGAIN));
+        assert_eq!(imu.reset(&pose, millis(3000)), Ok(()));
+        assert_eq!(
+            imus.get_calibrated(1, millis(4999)).map(|_| ()),
+            Err(EAGAIN)
+        );
+        let imu = imus.get_calibrated(1, millis(5000)).unwrap();
+        assert_eq!(imu.rotation(&pose, millis(5000)), 0.0);
+    }
+
+    #[test]
+    fn data_rate_is_rounded_down_to_5ms() {
+        let mut imu = Imu::new(1, 0.0, 0.0);
+        assert_eq!(imu.data_rate(), DEFAULT_DATA_RATE);
+        for (rate, expected) in [(12, 10), (15, 15), (19, 15), (3, 5), (0, 5)] {
+            imu.set_data_rate(rate);
+            assert_eq!(imu.data_rate(), expected, "rate {rate}");
+        }
+    }
+}
@@ -1,24 +1,37 @@
 use std::mem::replace;
 
 use pros_simulator_interface::{LcdLines, SimulatorEvent, LCD_HEIGHT, LCD_WIDTH};
-use pros_sys::error as errno;
+use pros_sys::{error as errno, LCD_BTN_CENTER, LCD_BTN_LEFT, LCD_BTN_RIGHT};
 use tokio::sync::Mutex;
 use wasmtime::{AsContextMut, Table};
 
+use super::task::get_function_pointer;
 use crate::interface::SimulatorInterface;
 
 #[derive(Debug)]
 pub struct AlreadyInitializedError;
 
+/// LCD colors, in the same `0xAARRGGBB` format as the PROS `COLOR_*` constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LcdColors {
     pub background: u32,
     pub foreground: u32,
 }
 
+impl Default for LcdColors {
+    fn default() -> Self {
+        Self {
+            background: 0x005A5A5A,
+            foreground: 0x00FFFFFF,
+        }
+    }
+}
+
 pub struct Lcd {
     lines: LcdLines,
     interface: SimulatorInterface,
     initialized: bool,
+    colors: LcdColors,
     button_presses: [bool; 3],
     button_callbacks: [Option<u32>; 3],
 }
@@ -29,6 +42,7 @@ impl Lcd {
             lines: Default::default(),
             interface,
             initialized: false,
+            colors: LcdColors::default(),
             button_presses: [false; 3],
             button_callbacks: [None; 3],
         }
@@ -69,6 +83,21 @@ impl Lcd {
         Ok(())
     }
 
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// Turns off the LCD, erasing its text and button callbacks. It can be initialized again
+    /// afterwards.
+    pub fn shutdown(&mut self) -> Result<(), i32> {
+        self.assert_initialized()?;
+        self.initialized = false;
+        self.lines = Default::default();
+        self.colors = LcdColors::default();
+        self.interface.send(SimulatorEvent::LcdShutdown);
+        Ok(())
+    }
+
     pub fn set_line(&mut self, line: i32, text: &str) -> Result<(), i32> {
         self.assert_initialized()?;
         self.assert_line_in_bounds(line)?;
@@ -100,6 +129,39 @@ impl Lcd {
         Ok(())
     }
 
+    /// Changes the LCD's colors, leaving out any that are `None`.
+    pub fn set_colors(
+        &mut self,
+        background: Option<u32>,
+        foreground: Option<u32>,
+    ) -> Result<(), i32> {
+        self.assert_initialized()?;
+        let colors = LcdColors {
+            background: background.unwrap_or(self.colors.background),
+            foreground: foreground.unwrap_or(self.colors.foreground),
+        };
+        if colors != self.colors {
+            self.colors = colors;
+            self.interface.send(SimulatorEvent::LcdColorsUpdated {
+                foreground: colors.foreground,
+                background: colors.background,
+            });
+        }
+        Ok(())
+    }
+
+    /// The buttons that are currently pressed, as a bit mask of `LCD_BTN_LEFT`,
+    /// `LCD_BTN_CENTER` and `LCD_BTN_RIGHT`.
+    pub fn read_buttons(&self) -> Result<u8, i32> {
+        self.assert_initialized()?;
+        let masks = [LCD_BTN_LEFT, LCD_BTN_CENTER, LCD_BTN_RIGHT];
+        Ok(masks
+            .into_iter()
+            .zip(self.button_presses)
+            .filter(|(_, pressed)| *pressed)
+            .fold(0, |buttons, (mask, _)| buttons | mask as u8))
+    }
+
     pub fn set_btn_press_callback(&mut self, button: usize, callback: u32) -> Result<(), i32> {
         self.assert_initialized()?;
 
@@ -123,9 +185,8 @@ impl Lcd {
         for (index, button_pressed) in buttons.iter().enumerate() {
             if *button_pressed && !previous_presses[index] {
                 if let Some(cb_index) = &callbacks[index] {
-                    let callback = callback_table.get(&mut store, *cb_index).unwrap();
-                    let callback = callback.funcref().unwrap().unwrap();
-                    let callback = callback.typed::<(), ()>(&mut store).unwrap();
+                    let callback =
+                        get_function_pointer::<(), ()>(&mut store, callback_table, *cb_index)?;
                     callback.call_async(&mut store, ()).await?;
                 }
             }
@@ -134,3 +195,70 @@ impl Lcd {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use wasmtime::{Config, Engine, MemoryType, SharedMemory};
+
+    use super::*;
+    use crate::host::{memory::SharedMemoryExt, printf::sprintf};
+
+    /// An initialized LCD, and the text it has displayed.
+    fn lcd() -> (Lcd, Arc<Mutex<Vec<LcdLines>>>) {
+        let updates = Arc::new(Mutex::new(vec![]));
+        let interface = {
+            let updates = updates.clone();
+            SimulatorInterface::from(move |event| {
+                if let SimulatorEvent::LcdUpdated(lines) = event {
+                    updates.lock().unwrap().push(lines);
+                }
+            })
+        };
+        let mut lcd = Lcd::new(interface);
+        lcd.initialize().unwrap();
+        (lcd, updates)
+    }
+
+    /// Formats text like `lcd_print` does, with a single `int` argument.
+    fn print(lcd: &mut Lcd, line: i32, format: &str, arg: i32) -> Result<(), i32> {
+        let engine = Engine::new(Config::new().wasm_threads(true)).unwrap();
+        let memory = SharedMemory::new(&engine, MemoryType::shared(1, 1)).unwrap();
+        memory.write_relaxed(0, &arg.to_le_bytes()).unwrap();
+        let text = sprintf(&memory, format, 0).unwrap();
+        lcd.set_line(line, &text)
+    }
+
+    #[test]
+    fn printing_needs_an_initialized_lcd() {
+        let mut lcd = Lcd::new(SimulatorInterface::from(|_| {}));
+        assert_eq!(print(&mut lcd, 0, "%d", 1), Err(errno::ENXIO));
+    }
+
+    #[test]
+    fn prints_formatted_text() {
+        let (mut lcd, updates) = lcd();
+        print(&mut lcd, 2, "%03d apples", 7).unwrap();
+        let updates = updates.lock().unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0][2], "007 apples");
+        assert!(updates[0][0].is_empty());
+    }
+
+    #[test]
+    fn printing_out_of_bounds_fails() {
+        let (mut lcd, updates) = lcd();
+        assert_eq!(print(&mut lcd, -1, "%d", 1), Err(errno::EINVAL));
+        assert_eq!(
+            print(&mut lcd, LCD_HEIGHT as i32, "%d", 1),
+            Err(errno::EINVAL)
+        );
+
+        // a huge field width is capped while formatting, then rejected for being too wide
+        assert_eq!(print(&mut lcd, 0, "%2147483647d", 1), Err(errno::EINVAL));
+        let width = LCD_WIDTH as i32;
+        assert_eq!(print(&mut lcd, 0, "%*d", width + 1), Err(errno::EINVAL));
+        assert!(updates.lock().unwrap().is_empty());
+    }
+}
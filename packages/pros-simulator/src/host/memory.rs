@@ -1,35 +1,38 @@
-use anyhow::Context;
-use snafu::Snafu;
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
 use wasmtime::SharedMemory;
 
+use crate::error::{InvalidPointerSnafu, InvalidStringSnafu, SimulatorError};
+
 #[derive(Debug, Snafu)]
 pub struct OutOfBoundsError;
 
 pub trait SharedMemoryExt {
-    fn read_c_str(&self, ptr: u32) -> anyhow::Result<String>;
+    fn read_c_str(&self, ptr: u32) -> Result<String, SimulatorError>;
     fn write_relaxed(&self, offset: usize, buffer: &[u8]) -> Result<(), OutOfBoundsError>;
     fn read_relaxed(&self, offset: usize, length: usize) -> Result<Vec<u8>, OutOfBoundsError>;
 }
 
 impl SharedMemoryExt for SharedMemory {
-    fn read_c_str(&self, ptr: u32) -> anyhow::Result<String> {
+    fn read_c_str(&self, ptr: u32) -> Result<String, SimulatorError> {
+        ensure!(ptr != 0, InvalidPointerSnafu { pointer: ptr });
         let data = self
             .data()
             .get(ptr as usize..)
-            .with_context(|| format!("invalid pointer: {}", ptr))?;
+            .context(InvalidPointerSnafu { pointer: ptr })?;
         for (index, cell) in data.iter().enumerate() {
             if unsafe { cell.get().read() } == 0 {
-                return Ok(String::from_utf8(
+                return String::from_utf8(
                     data[..index]
                         .iter()
                         .map(|c| unsafe { c.get().read() })
                         .collect::<Vec<_>>(),
                 )
-                .expect("invalid UTF-8 string"));
+                .context(InvalidStringSnafu { pointer: ptr });
             }
         }
 
-        Err(anyhow::anyhow!("C string must be null-terminated"))
+        // C strings must be null-terminated
+        InvalidPointerSnafu { pointer: ptr }.fail()
     }
     fn write_relaxed(&self, offset: usize, buffer: &[u8]) -> Result<(), OutOfBoundsError> {
         let Some(data) = self.data().get(offset..offset + buffer.len()) else {
@@ -0,0 +1,845 @@
+use std::{f64::consts::TAU, time::Duration};
+
+use pros_simulator_interface::{
+    config::{MotorModel, RobotConfig, SmartDevice},
+    MotorBrakeMode, MotorCommand, MotorGearset, SimulatorEvent,
+};
+use pros_sys::{
+    EINVAL, E_MOTOR_BRAKE_BRAKE, E_MOTOR_BRAKE_COAST, E_MOTOR_BRAKE_HOLD, E_MOTOR_ENCODER_COUNTS,
+    E_MOTOR_ENCODER_DEGREES, E_MOTOR_ENCODER_ROTATIONS, E_MOTOR_FAULT_MOTOR_OVER_TEMP,
+    E_MOTOR_FAULT_NO_FAULTS, E_MOTOR_FAULT_OVER_CURRENT, E_MOTOR_FLAGS_NONE,
+    E_MOTOR_FLAGS_ZERO_POSITION, E_MOTOR_FLAGS_ZERO_VELOCITY, E_MOTOR_GEARSET_06,
+    E_MOTOR_GEARSET_18, E_MOTOR_GEARSET_36,
+};
+
+use super::devices::DeviceTable;
+use crate::interface::SimulatorInterface;
+
+/// The highest voltage a V5 Smart Motor can output, in millivolts.
+pub const MAX_VOLTAGE: i32 = 12000;
+/// The voltage a motor's [`MotorModel`] is measured at, in volts.
+const NOMINAL_VOLTAGE: f64 = 12.0;
+/// The current limit of a V5 Smart Motor when it is first plugged in, in milliamps.
+pub const DEFAULT_CURRENT_LIMIT: i32 = 2500;
+/// The temperature of a motor that isn't doing any work, in degrees Celsius.
+pub const AMBIENT_TEMPERATURE: f64 = 25.0;
+/// The temperature at which a motor starts limiting its current to protect itself, in degrees
+/// Celsius. The current limit is halved for every 5 degrees above this.
+pub const OVER_TEMPERATURE: f64 = 55.0;
+/// How fast a motor approaches its target position, in RPM per degree of remaining distance.
+const POSITION_GAIN: f64 = 1.0;
+/// The longest time step used to simulate a motor that isn't part of the drivetrain. Longer
+/// updates are split into multiple steps so that the simulation stays stable.
+const MAX_STEP: Duration = Duration::from_millis(1);
+
+/// The units used by the robot code to measure a motor's position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncoderUnits {
+    #[default]
+    Degrees,
+    Rotations,
+    /// Raw encoder ticks. The number of ticks per rotation depends on the motor's gearset.
+    Counts,
+}
+
+impl TryFrom<i32> for EncoderUnits {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            E_MOTOR_ENCODER_DEGREES => Ok(Self::Degrees),
+            E_MOTOR_ENCODER_ROTATIONS => Ok(Self::Rotations),
+            E_MOTOR_ENCODER_COUNTS => Ok(Self::Counts),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+impl From<EncoderUnits> for i32 {
+    fn from(units: EncoderUnits) -> Self {
+        match units {
+            EncoderUnits::Degrees => E_MOTOR_ENCODER_DEGREES,
+            EncoderUnits::Rotations => E_MOTOR_ENCODER_ROTATIONS,
+            EncoderUnits::Counts => E_MOTOR_ENCODER_COUNTS,
+        }
+    }
+}
+
+/// Converts a PROS `motor_gearset_e_t` value into a gearset, failing with `EINVAL` if it is invalid.
+pub fn gearset_from_raw(gearset: i32) -> Result<MotorGearset, i32> {
+    match gearset {
+        E_MOTOR_GEARSET_36 => Ok(MotorGearset::Red),
+        E_MOTOR_GEARSET_18 => Ok(MotorGearset::Green),
+        E_MOTOR_GEARSET_06 => Ok(MotorGearset::Blue),
+        _ => Err(EINVAL),
+    }
+}
+
+/// Converts a gearset into its PROS `motor_gearset_e_t` value.
+pub fn gearset_to_raw(gearset: MotorGearset) -> i32 {
+    match gearset {
+        MotorGearset::Red => E_MOTOR_GEARSET_36,
+        MotorGearset::Green => E_MOTOR_GEARSET_18,
+        MotorGearset::Blue => E_MOTOR_GEARSET_06,
+    }
+}
+
+/// Converts a PROS `motor_brake_mode_e_t` value into a brake mode, failing with `EINVAL` if it is
+/// invalid.
+pub fn brake_mode_from_raw(mode: i32) -> Result<MotorBrakeMode, i32> {
+    match mode {
+        E_MOTOR_BRAKE_COAST => Ok(MotorBrakeMode::Coast),
+        E_MOTOR_BRAKE_BRAKE => Ok(MotorBrakeMode::Brake),
+        E_MOTOR_BRAKE_HOLD => Ok(MotorBrakeMode::Hold),
+        _ => Err(EINVAL),
+    }
+}
+
+/// Converts a brake mode into its PROS `motor_brake_mode_e_t` value.
+pub fn brake_mode_to_raw(mode: MotorBrakeMode) -> i32 {
+    match mode {
+        MotorBrakeMode::Coast => E_MOTOR_BRAKE_COAST,
+        MotorBrakeMode::Brake => E_MOTOR_BRAKE_BRAKE,
+        MotorBrakeMode::Hold => E_MOTOR_BRAKE_HOLD,
+    }
+}
+
+/// The number of encoder ticks in one rotation of a motor's output shaft.
+fn counts_per_rotation(gearset: MotorGearset) -> f64 {
+    match gearset {
+        MotorGearset::Red => 1800.0,
+        MotorGearset::Green => 900.0,
+        MotorGearset::Blue => 300.0,
+    }
+}
+
+/// Converts a velocity in RPM to radians per second.
+fn rpm_to_radians(rpm: f64) -> f64 {
+    rpm / 60.0 * TAU
+}
+
+/// Converts a velocity in radians per second to RPM.
+fn radians_to_rpm(radians: f64) -> f64 {
+    radians / TAU * 60.0
+}
+
+/// A simulated V5 Smart Motor.
+///
+/// The motor is modeled as a DC motor following its [`MotorModel`]. The voltage applied to it
+/// either comes straight from the robot code, or from the motor's internal controller when it's
+/// given a velocity or position to reach. The current it draws is limited by its current limit,
+/// and heats the motor up over time.
+///
+/// Motors accelerate the load described by their model, except for motors that drive the robot's
+/// wheels, whose speed is decided by the [drivetrain](super::physics::Drivetrain).
+///
+/// Positions, velocities and commands are stored from the robot code's point of view, meaning
+/// that they are flipped when the motor is reversed.
+#[derive(Debug, Clone)]
+pub struct Motor {
+    gearset: MotorGearset,
+    model: MotorModel,
+    brake_mode: MotorBrakeMode,
+    encoder_units: EncoderUnits,
+    reversed: bool,
+    current_limit: i32,
+    /// Voltage limit in volts, or 0 if there is no limit.
+    voltage_limit: i32,
+    command: MotorCommand,
+    /// Position of the output shaft in degrees, relative to the zero position.
+    position: f64,
+    /// Position of the output shaft in degrees, ignoring the reversed flag and zero position.
+    raw_position: f64,
+    /// Velocity of the output shaft in RPM.
+    velocity: f64,
+    /// Velocity of the output shaft in RPM as seen from outside the robot, if the motor turns
+    /// one of the drivetrain's wheels. The drivetrain decides how fast these motors actually
+    /// turn.
+    drive_velocity: Option<f64>,
+    /// The velocity error accumulated by the internal velocity controller, in RPM seconds.
+    velocity_integral: f64,
+    /// Voltage applied by the motor's controller in volts, or `None` if the motor is coasting.
+    applied_voltage: Option<f64>,
+    /// Current flowing through the motor in amps.
+    current: f64,
+    /// Whether the current was limited during the last update.
+    current_limited: bool,
+    /// Temperature in degrees Celsius.
+    temperature: f64,
+    /// The voltage of the battery powering the motor, in volts.
+    supply_voltage: f64,
+}
+
+impl Motor {
+    pub fn new(gearset: MotorGearset, model: MotorModel) -> Self {
+        Self {
+            gearset,
+            model,
+            brake_mode: Default::default(),
+            encoder_units: Default::default(),
+            reversed: false,
+            current_limit: DEFAULT_CURRENT_LIMIT,
+            voltage_limit: 0,
+            command: MotorCommand::Brake(Default::default()),
+            position: 0.0,
+            raw_position: 0.0,
+            velocity: 0.0,
+            drive_velocity: None,
+            velocity_integral: 0.0,
+            applied_voltage: None,
+            current: 0.0,
+            current_limited: false,
+            temperature: AMBIENT_TEMPERATURE,
+            supply_voltage: NOMINAL_VOLTAGE,
+        }
+    }
+
+    fn direction(&self) -> f64 {
+        if self.reversed {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    fn max_rpm(&self) -> f64 {
+        self.gearset.max_rpm() as f64
+    }
+
+    fn degrees_to_units(&self, degrees: f64) -> f64 {
+        match self.encoder_units {
+            EncoderUnits::Degrees => degrees,
+            EncoderUnits::Rotations => degrees / 360.0,
+            EncoderUnits::Counts => degrees / 360.0 * counts_per_rotation(self.gearset),
+        }
+    }
+
+    fn units_to_degrees(&self, units: f64) -> f64 {
+        match self.encoder_units {
+            EncoderUnits::Degrees => units,
+            EncoderUnits::Rotations => units * 360.0,
+            EncoderUnits::Counts => units / counts_per_rotation(self.gearset) * 360.0,
+        }
+    }
+
+    /// Clamps a voltage in millivolts to the range the motor is allowed to output.
+    fn limit_voltage(&self, voltage: i32) -> i32 {
+        let mut max_voltage = MAX_VOLTAGE;
+        if self.voltage_limit > 0 {
+            max_voltage = max_voltage.min(self.voltage_limit * 1000);
+        }
+        voltage.clamp(-max_voltage, max_voltage)
+    }
+
+    /// The largest voltage the motor can apply, in volts, which is less than usual when the
+    /// battery is low.
+    fn max_voltage(&self) -> f64 {
+        (self.limit_voltage(MAX_VOLTAGE) as f64 / 1000.0).min(self.supply_voltage)
+    }
+
+    /// The resistance of the motor's windings in ohms.
+    fn resistance(&self) -> f64 {
+        NOMINAL_VOLTAGE / self.model.stall_current
+    }
+
+    /// The torque produced per amp of current at the output shaft, in newton meters, which
+    /// depends on the gearset.
+    fn torque_constant(&self) -> f64 {
+        self.model.stall_torque / self.model.stall_current * 100.0 / self.max_rpm()
+    }
+
+    /// The most current the motor will draw in amps, taking into account overheating.
+    fn effective_current_limit(&self) -> f64 {
+        let mut limit = self.current_limit as f64 / 1000.0;
+        if self.temperature >= OVER_TEMPERATURE {
+            let steps = ((self.temperature - OVER_TEMPERATURE) / 5.0).floor() + 1.0;
+            limit /= 2f64.powf(steps);
+        }
+        limit
+    }
+
+    /// The speed in RPM the motor's controller is trying to reach, or `None` if the robot code
+    /// controls the voltage directly.
+    fn target_speed(&self) -> Option<f64> {
+        let max_rpm = self.max_rpm();
+        match self.command {
+            MotorCommand::Brake(MotorBrakeMode::Hold) => Some(0.0),
+            MotorCommand::Brake(_) | MotorCommand::Voltage(_) => None,
+            MotorCommand::Velocity(velocity) => Some((velocity as f64).clamp(-max_rpm, max_rpm)),
+            MotorCommand::Position { target, velocity } => {
+                // slow down when close to the target so the motor doesn't overshoot
+                let remaining = target - self.position;
+                let speed = (velocity.unsigned_abs() as f64).min(max_rpm);
+                Some((remaining * POSITION_GAIN).clamp(-speed, speed))
+            }
+        }
+    }
+
+    /// Works out the voltage to apply to the motor in volts, running the internal velocity
+    /// controller for `seconds` seconds if it's in use. Returns `None` if the motor is coasting.
+    fn controller_voltage(&mut self, seconds: f64) -> Option<f64> {
+        let max_rpm = self.max_rpm();
+        let max_voltage = self.max_voltage();
+        let Some(target) = self.target_speed() else {
+            self.velocity_integral = 0.0;
+            return match self.command {
+                MotorCommand::Brake(MotorBrakeMode::Coast) => None,
+                MotorCommand::Voltage(voltage) => {
+                    Some((voltage as f64 / 1000.0).clamp(-max_voltage, max_voltage))
+                }
+                _ => Some(0.0),
+            };
+        };
+
+        let MotorModel {
+            velocity_kp,
+            velocity_ki,
+            ..
+        } = self.model;
+        let error = target - self.velocity;
+        self.velocity_integral += error * seconds;
+        if velocity_ki > 0.0 {
+            // stop the integral from growing past what the motor can output
+            let max_integral = 2.0 * max_rpm / velocity_ki;
+            self.velocity_integral = self.velocity_integral.clamp(-max_integral, max_integral);
+        }
+        // the velocity is used as a feedforward term, so the controller only corrects errors
+        let output = target + velocity_kp * error + velocity_ki * self.velocity_integral;
+        Some((output / max_rpm * NOMINAL_VOLTAGE).clamp(-max_voltage, max_voltage))
+    }
+
+    /// Runs the motor for `seconds` seconds at its current velocity, updating its current and
+    /// temperature, and returns the torque it produces in newton meters.
+    fn run(&mut self, seconds: f64) -> f64 {
+        let voltage = self.controller_voltage(seconds);
+        let resistance = self.resistance();
+        // the back EMF cancels out the full voltage at the gearset's free speed
+        let back_emf = self.velocity / self.max_rpm() * NOMINAL_VOLTAGE;
+        let current = match voltage {
+            Some(voltage) => (voltage - back_emf) / resistance,
+            None => 0.0,
+        };
+        let limit = self.effective_current_limit();
+        self.current_limited = current.abs() > limit;
+        self.current = current.clamp(-limit, limit);
+        self.applied_voltage = voltage;
+
+        let heat = self.current.powi(2) * resistance;
+        let cooling = (self.temperature - AMBIENT_TEMPERATURE) / self.model.thermal_resistance;
+        self.temperature += (heat - cooling) / self.model.heat_capacity * seconds;
+
+        self.current * self.torque_constant()
+    }
+
+    /// Moves the output shaft by the given number of degrees.
+    fn advance(&mut self, distance: f64) {
+        self.position += distance;
+        self.raw_position += distance * self.direction();
+    }
+
+    /// Advances the motor's position by the given amount of time.
+    fn update(&mut self, elapsed: Duration) {
+        if let Some(drive_velocity) = self.drive_velocity {
+            // the drivetrain runs these motors itself
+            self.velocity = drive_velocity * self.direction();
+            self.advance(self.velocity * 360.0 * elapsed.as_secs_f64() / 60.0);
+            return;
+        }
+
+        let mut remaining = elapsed;
+        while !remaining.is_zero() {
+            let step = remaining.min(MAX_STEP);
+            remaining -= step;
+            let seconds = step.as_secs_f64();
+
+            let torque = self.run(seconds);
+            let friction = self.model.free_current * self.torque_constant();
+            let inertia = self.model.inertia;
+            let mut velocity = self.velocity + radians_to_rpm(torque / inertia * seconds);
+            // friction slows the motor down, but never makes it turn the other way
+            let slowdown = radians_to_rpm(friction / inertia * seconds);
+            velocity = if velocity.abs() <= slowdown {
+                0.0
+            } else {
+                velocity - slowdown.copysign(velocity)
+            };
+
+            let average_velocity = (self.velocity + velocity) / 2.0;
+            self.velocity = velocity;
+            self.advance(average_velocity * 360.0 * seconds / 60.0);
+        }
+    }
+
+    /// Runs a drivetrain motor for `seconds` seconds while it turns at `velocity` RPM, as seen
+    /// from outside the robot, and returns the torque it produces in newton meters.
+    pub fn drive_torque(&mut self, velocity: f64, seconds: f64) -> f64 {
+        self.velocity = velocity * self.direction();
+        self.run(seconds) * self.direction()
+    }
+
+    /// Makes the motor turn at the given speed in RPM, as seen from outside the robot, until the
+    /// next call. The motor should be [updated](Motors::update) to the current time first.
+    pub fn set_drive_velocity(&mut self, velocity: f64) {
+        self.drive_velocity = Some(velocity);
+    }
+
+    /// The command currently being executed by the motor, as seen from outside the robot.
+    fn physical_command(&self) -> MotorCommand {
+        if !self.reversed {
+            return self.command;
+        }
+        match self.command {
+            MotorCommand::Brake(mode) => MotorCommand::Brake(mode),
+            MotorCommand::Voltage(voltage) => MotorCommand::Voltage(-voltage),
+            MotorCommand::Velocity(velocity) => MotorCommand::Velocity(-velocity),
+            MotorCommand::Position { target, velocity } => MotorCommand::Position {
+                target: -target,
+                velocity,
+            },
+        }
+    }
+
+    pub fn gearset(&self) -> MotorGearset {
+        self.gearset
+    }
+
+    pub fn set_gearset(&mut self, gearset: MotorGearset) {
+        self.gearset = gearset;
+    }
+
+    pub fn brake_mode(&self) -> MotorBrakeMode {
+        self.brake_mode
+    }
+
+    pub fn set_brake_mode(&mut self, mode: MotorBrakeMode) {
+        self.brake_mode = mode;
+        if let MotorCommand::Brake(_) = self.command {
+            self.command = MotorCommand::Brake(mode);
+        }
+    }
+
+    pub fn encoder_units(&self) -> EncoderUnits {
+        self.encoder_units
+    }
+
+    pub fn set_encoder_units(&mut self, units: EncoderUnits) {
+        self.encoder_units = units;
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    pub fn set_reversed(&mut self, reversed: bool) {
+        if self.reversed != reversed {
+            self.position = -self.position;
+            self.velocity = -self.velocity;
+            self.velocity_integral = -self.velocity_integral;
+            self.current = -self.current;
+            self.applied_voltage = self.applied_voltage.map(|voltage| -voltage);
+        }
+        self.reversed = reversed;
+    }
+
+    /// The current limit in milliamps.
+    pub fn current_limit(&self) -> i32 {
+        self.current_limit
+    }
+
+    pub fn set_current_limit(&mut self, limit: i32) {
+        self.current_limit = limit;
+    }
+
+    /// The voltage limit in volts, or 0 if there is no limit.
+    pub fn voltage_limit(&self) -> i32 {
+        self.voltage_limit
+    }
+
+    pub fn set_voltage_limit(&mut self, limit: i32) {
+        self.voltage_limit = limit;
+    }
+
+    /// The position of the motor in its encoder units.
+    pub fn position(&self) -> f64 {
+        self.degrees_to_units(self.position)
+    }
+
+    /// The position of the motor in encoder counts, ignoring its zero position and reversed flag.
+    pub fn raw_position(&self) -> i32 {
+        (self.raw_position / 360.0 * counts_per_rotation(self.gearset)) as i32
+    }
+
+    /// Makes the given position (in encoder units) the motor's zero position, so its current
+    /// position reads as the old one minus `position`.
+    pub fn set_zero_position(&mut self, position: f64) {
+        self.shift_position(-self.units_to_degrees(position));
+    }
+
+    /// Makes the motor's current position its zero position.
+    pub fn tare_position(&mut self) {
+        self.shift_position(-self.position);
+    }
+
+    /// Adds `offset` degrees to the motor's position and position target.
+    fn shift_position(&mut self, offset: f64) {
+        self.position += offset;
+        if let MotorCommand::Position { target, .. } = &mut self.command {
+            *target += offset;
+        }
+    }
+
+    /// The target position of the motor in its encoder units.
+    pub fn target_position(&self) -> f64 {
+        match self.command {
+            MotorCommand::Position { target, .. } => self.degrees_to_units(target),
+            _ => self.position(),
+        }
+    }
+
+    /// The velocity commanded to the motor in RPM.
+    pub fn target_velocity(&self) -> i32 {
+        match self.command {
+            MotorCommand::Velocity(velocity) | MotorCommand::Position { velocity, .. } => velocity,
+            _ => 0,
+        }
+    }
+
+    /// The velocity of the motor in RPM.
+    pub fn actual_velocity(&self) -> f64 {
+        self.velocity
+    }
+
+    /// The velocity of the motor in RPM, as seen from outside the robot.
+    pub fn physical_velocity(&self) -> f64 {
+        self.velocity * self.direction()
+    }
+
+    /// 1 if the motor is moving forwards (or not moving), -1 if it is moving backwards.
+    pub fn direction_of_travel(&self) -> i32 {
+        if self.velocity < 0.0 {
+            -1
+        } else {
+            1
+        }
+    }
+
+    /// The voltage being applied to the motor in millivolts.
+    pub fn voltage(&self) -> i32 {
+        (self.applied_voltage.unwrap_or(0.0) * 1000.0) as i32
+    }
+
+    /// The current drawn by the motor in milliamps.
+    pub fn current_draw(&self) -> i32 {
+        (self.current.abs() * 1000.0) as i32
+    }
+
+    /// The power drawn by the motor in watts.
+    pub fn power(&self) -> f64 {
+        (self.applied_voltage.unwrap_or(0.0) * self.current).abs()
+    }
+
+    /// The current the motor draws from the battery in amps. This is less than the current
+    /// through the motor when it applies less than the battery's full voltage, and motors that
+    /// are being back-driven don't charge the battery.
+    fn supply_current(&self) -> f64 {
+        (self.applied_voltage.unwrap_or(0.0) * self.current).max(0.0) / self.supply_voltage
+    }
+
+    /// The torque generated by the motor in newton meters.
+    pub fn torque(&self) -> f64 {
+        (self.current * self.torque_constant()).abs()
+    }
+
+    /// The efficiency of the motor as a percentage: how much of the power it draws turns into
+    /// motion.
+    pub fn efficiency(&self) -> f64 {
+        let power = self.power();
+        if power == 0.0 {
+            return 0.0;
+        }
+        let output = self.current * self.torque_constant() * rpm_to_radians(self.velocity);
+        (output / power * 100.0).clamp(0.0, 100.0)
+    }
+
+    /// The temperature of the motor in degrees Celsius.
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    pub fn is_over_current(&self) -> bool {
+        self.current_limited
+    }
+
+    pub fn is_over_temp(&self) -> bool {
+        self.temperature >= OVER_TEMPERATURE
+    }
+
+    /// A bitfield of `motor_fault_e_t` values.
+    pub fn faults(&self) -> u32 {
+        let mut faults = E_MOTOR_FAULT_NO_FAULTS;
+        if self.is_over_temp() {
+            faults |= E_MOTOR_FAULT_MOTOR_OVER_TEMP;
+        }
+        if self.is_over_current() {
+            faults |= E_MOTOR_FAULT_OVER_CURRENT;
+        }
+        faults
+    }
+
+    /// A bitfield of `motor_flag_e_t` values.
+    pub fn flags(&self) -> u32 {
+        let mut flags = E_MOTOR_FLAGS_NONE;
+        if self.velocity == 0.0 {
+            flags |= E_MOTOR_FLAGS_ZERO_VELOCITY;
+        }
+        if self.position == 0.0 {
+            flags |= E_MOTOR_FLAGS_ZERO_POSITION;
+        }
+        flags
+    }
+}
+
+/// Stores the state of every V5 Smart Motor plugged into the brain.
+pub struct Motors {
+    motors: DeviceTable<Motor>,
+    interface: SimulatorInterface,
+    last_update: Duration,
+}
+
+impl Motors {
+    pub fn new(interface: SimulatorInterface, robot_config: &RobotConfig) -> Self {
+        Self {
+            motors: DeviceTable::new(|port| match robot_config.smart_device(port) {
+                Some(SmartDevice::Motor { gearset, model }) => {
+                    Some(Motor::new(*gearset, robot_config.motor_model(port, *model)))
+                }
+                _ => None,
+            }),
+            interface,
+            last_update: Duration::ZERO,
+        }
+    }
+
+    /// Brings the position of every motor up to date. `now` is the amount of time that has
+    /// passed since the simulator started.
+    pub fn update(&mut self, now: Duration) {
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+        for (_, motor) in self.motors.iter_mut() {
+            motor.update(elapsed);
+        }
+    }
+
+    pub fn get(&self, port: u32) -> Result<&Motor, i32> {
+        self.motors.get(port)
+    }
+
+    /// The total current all motors draw from the battery, in amps.
+    pub fn supply_current(&self) -> f64 {
+        self.motors
+            .iter()
+            .map(|(_, motor)| motor.supply_current())
+            .sum()
+    }
+
+    /// Sets the voltage of the battery powering the motors, in volts.
+    pub fn set_supply_voltage(&mut self, voltage: f64) {
+        for (_, motor) in self.motors.iter_mut() {
+            motor.supply_voltage = voltage;
+        }
+    }
+
+    /// Returns the motor on the given port without notifying the simulator interface of any
+    /// changes. Only for use by the simulator itself.
+    pub fn get_mut(&mut self, port: u32) -> Result<&mut Motor, i32> {
+        self.motors.get_mut(port)
+    }
+
+    /// Modifies the motor on the given port, notifying the simulator interface if that changes
+    /// the motor's output.
+    pub fn modify<T>(
+        &mut self,
+        port: u32,
+        modify: impl FnOnce(&mut Motor) -> Result<T, i32>,
+    ) -> Result<T, i32> {
+        let motor = self.motors.get_mut(port)?;
+        let previous_command = motor.physical_command();
+        let res = modify(motor)?;
+
+        let command = motor.physical_command();
+        if command != previous_command {
+            self.interface.send(SimulatorEvent::MotorCommanded {
+                port: port as u8,
+                command,
+            });
+        }
+        Ok(res)
+    }
+
+    fn command(
+        &mut self,
+        port: u32,
+        command: impl FnOnce(&Motor) -> MotorCommand,
+    ) -> Result<(), i32> {
+        self.modify(port, |motor| {
+            motor.command = command(motor);
+            Ok(())
+        })
+    }
+
+    /// Sets the voltage of the motor in millivolts.
+    pub fn move_voltage(&mut self, port: u32, voltage: i32) -> Result<(), i32> {
+        self.command(port, |_| {
+            MotorCommand::Voltage(voltage.clamp(-MAX_VOLTAGE, MAX_VOLTAGE))
+        })
+    }
+
+    /// Sets the velocity of the motor in RPM.
+    pub fn move_velocity(&mut self, port: u32, velocity: i32) -> Result<(), i32> {
+        self.command(port, |motor| {
+            let max_rpm = motor.gearset.max_rpm() as i32;
+            MotorCommand::Velocity(velocity.clamp(-max_rpm, max_rpm))
+        })
+    }
+
+    /// Moves the motor to an absolute position in its encoder units.
+    pub fn move_absolute(&mut self, port: u32, position: f64, velocity: i32) -> Result<(), i32> {
+        self.command(port, |motor| MotorCommand::Position {
+            target: motor.units_to_degrees(position),
+            velocity,
+        })
+    }
+
+    /// Moves the motor by the given distance in its encoder units.
+    pub fn move_relative(&mut self, port: u32, distance: f64, velocity: i32) -> Result<(), i32> {
+        self.command(port, |motor| MotorCommand::Position {
+            target: motor.position + motor.units_to_degrees(distance),
+            velocity,
+        })
+    }
+
+    /// Changes the maximum velocity of the motor's current profiled movement, if any.
+    pub fn modify_profiled_velocity(&mut self, port: u32, velocity: i32) -> Result<(), i32> {
+        self.command(port, |motor| match motor.command {
+            MotorCommand::Position { target, .. } => MotorCommand::Position { target, velocity },
+            command => command,
+        })
+    }
+
+    /// Stops the motor using its current brake mode.
+    pub fn brake(&mut self, port: u32) -> Result<(), i32> {
+        self.command(port, |motor| MotorCommand::Brake(motor.brake_mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A motor that has been running forwards at full voltage for a second.
+    fn running_motor() -> Motor {
+        let mut motor = Motor::new(MotorGearset::Green, MotorModel::default());
+        motor.command = MotorCommand::Voltage(MAX_VOLTAGE);
+        motor.update(Duration::from_secs(1));
+        motor
+    }
+
+    #[test]
+    fn voltage_spins_up_to_free_speed() {
+        let motor = running_motor();
+        let free_speed = motor.max_rpm();
+        // friction keeps the motor just under its free speed
+        assert!(motor.actual_velocity() < free_speed);
+        assert!(motor.actual_velocity() > free_speed * 0.95);
+        assert!(!motor.is_over_current());
+    }
+
+    #[test]
+    fn stalled_motor_is_current_limited() {
+        let mut motor = Motor::new(MotorGearset::Green, MotorModel::default());
+        motor.command = MotorCommand::Voltage(MAX_VOLTAGE);
+        let torque = motor.drive_torque(0.0, 1.0);
+        assert!(motor.is_over_current());
+        assert_eq!(motor.current_draw(), DEFAULT_CURRENT_LIMIT);
+        // the published stall torque at the current limit, geared for 200 RPM
+        assert!((torque - 2.1 / 2.0).abs() < 1e-9);
+        assert!(motor.temperature() > AMBIENT_TEMPERATURE);
+    }
+
+    #[test]
+    fn overheating_halves_the_current_limit() {
+        let mut motor = Motor::new(MotorGearset::Green, MotorModel::default());
+        motor.command = MotorCommand::Voltage(MAX_VOLTAGE);
+        motor.temperature = OVER_TEMPERATURE + 1.0;
+        motor.drive_torque(0.0, 0.001);
+        assert!(motor.is_over_temp());
+        assert_eq!(motor.current_draw(), DEFAULT_CURRENT_LIMIT / 2);
+    }
+
+    #[test]
+    fn velocity_controller_reaches_its_target() {
+        let mut motor = Motor::new(MotorGearset::Blue, MotorModel::default());
+        motor.command = MotorCommand::Velocity(-200);
+        motor.update(Duration::from_secs(2));
+        assert!((motor.actual_velocity() + 200.0).abs() < 2.0);
+
+        motor.command = MotorCommand::Brake(MotorBrakeMode::Hold);
+        motor.update(Duration::from_secs(2));
+        assert!(motor.actual_velocity().abs() < 1.0);
+    }
+
+    #[test]
+    fn low_battery_limits_voltage() {
+        let mut motor = Motor::new(MotorGearset::Green, MotorModel::default());
+        motor.supply_voltage = 9.0;
+        motor.command = MotorCommand::Voltage(MAX_VOLTAGE);
+        motor.update(Duration::from_millis(10));
+        assert_eq!(motor.voltage(), 9000);
+    }
+
+    #[test]
+    fn reversing_flips_everything_seen_by_the_robot_code() {
+        let mut motor = running_motor();
+        let (position, velocity, voltage) =
+            (motor.position(), motor.actual_velocity(), motor.voltage());
+        let physical_velocity = motor.physical_velocity();
+        motor.set_reversed(true);
+        assert_eq!(motor.position(), -position);
+        assert_eq!(motor.actual_velocity(), -velocity);
+        assert_eq!(motor.voltage(), -voltage);
+        assert_eq!(motor.physical_velocity(), physical_velocity);
+    }
+
+    #[test]
+    fn zero_position_is_subtracted() {
+        let mut motor = running_motor();
+        let position = motor.position();
+        assert!(position > 0.0);
+
+        motor.set_zero_position(100.0);
+        assert_eq!(motor.position(), position - 100.0);
+        motor.set_encoder_units(EncoderUnits::Rotations);
+        motor.set_zero_position(1.0);
+        assert_eq!(motor.position(), (position - 460.0) / 360.0);
+    }
+
+    #[test]
+    fn zero_position_moves_position_targets() {
+        let mut motor = running_motor();
+        motor.command = MotorCommand::Position {
+            target: motor.position + 90.0,
+            velocity: 100,
+        };
+        motor.set_zero_position(30.0);
+        assert_eq!(motor.target_position(), motor.position() + 90.0);
+    }
+
+    #[test]
+    fn tare_zeroes_the_current_position() {
+        let mut motor = running_motor();
+        let raw_position = motor.raw_position();
+        motor.tare_position();
+        assert_eq!(motor.position(), 0.0);
+        assert_eq!(motor.raw_position(), raw_position);
+    }
+}
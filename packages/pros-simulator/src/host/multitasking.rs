@@ -1,10 +1,12 @@
 // use std::sync::Mutex;
-use std::{sync::Arc, time::Instant};
+use std::sync::Arc;
 
-use futures::{future::pending, FutureExt};
 use slab::Slab;
+use snafu::OptionExt;
 use tokio::sync::{Mutex, OwnedMutexGuard};
 
+use crate::error::{InvalidHandleSnafu, SimulatorError};
+
 #[derive(Debug, Default)]
 pub struct HostMutex {
     inner: Arc<Mutex<()>>,
@@ -21,32 +23,40 @@ impl MutexPool {
     pub fn create_mutex(&mut self) -> usize {
         self.mutexes.insert(HostMutex::default())
     }
-    /// Creates a mutex, returning its ID.
-    pub fn delete_mutex(&mut self, mutex_id: usize) {
-        self.mutexes.remove(mutex_id);
+    /// Deletes a mutex by ID.
+    pub fn delete_mutex(&mut self, mutex_id: usize) -> Result<(), SimulatorError> {
+        self.mutexes
+            .try_remove(mutex_id)
+            .context(InvalidHandleSnafu {
+                kind: "mutex",
+                handle: mutex_id as u32,
+            })?;
+        Ok(())
+    }
+
+    fn get_mut(&mut self, mutex_id: usize) -> Result<&mut HostMutex, SimulatorError> {
+        self.mutexes.get_mut(mutex_id).context(InvalidHandleSnafu {
+            kind: "mutex",
+            handle: mutex_id as u32,
+        })
     }
 
-    /// Locks a mutex by ID, cancelling on timeout, and returning a boolean of whether the lock was
+    /// Attempts to lock a mutex by ID without waiting, returning a boolean of whether the lock was
     /// successful.
-    pub async fn lock(&mut self, mutex_id: usize, timeout: Option<Instant>) -> bool {
-        let sleep = timeout.map_or_else(
-            || pending().boxed(),
-            |i| tokio::time::sleep_until(i.into()).boxed(),
-        );
-
-        let mutex = self.mutexes.get_mut(mutex_id).unwrap();
-        tokio::select! {
-            biased;
-            lock = mutex.inner.clone().lock_owned() => {
+    pub fn try_lock(&mut self, mutex_id: usize) -> Result<bool, SimulatorError> {
+        let mutex = self.get_mut(mutex_id)?;
+        match mutex.inner.clone().try_lock_owned() {
+            Ok(lock) => {
                 mutex.lock = Some(lock);
-                true
+                Ok(true)
             }
-            _ = sleep => false,
+            Err(_) => Ok(false),
         }
     }
 
-    pub fn unlock(&mut self, mutex_id: usize) {
-        let mutex = self.mutexes.get_mut(mutex_id).unwrap();
-        mutex.lock.take().unwrap();
+    /// Unlocks a mutex by ID, returning a boolean of whether it was locked.
+    pub fn unlock(&mut self, mutex_id: usize) -> Result<bool, SimulatorError> {
+        let mutex = self.get_mut(mutex_id)?;
+        Ok(mutex.lock.take().is_some())
     }
 }
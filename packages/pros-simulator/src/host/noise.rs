@@ -0,0 +1,40 @@
+use std::f64::consts::TAU;
+
+/// A small deterministic random number generator for simulating sensor noise.
+///
+/// Every sensor is seeded from its port number, so simulations with noise are still repeatable.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    state: u64,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            // xorshift gets stuck at zero, so mix the seed with a constant
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    /// Returns a random number between 0 (inclusive) and 1 (exclusive).
+    pub fn uniform(&mut self) -> f64 {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let bits = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (bits >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a normally distributed random number with a mean of 0 and the given standard
+    /// deviation.
+    pub fn gaussian(&mut self, std_dev: f64) -> f64 {
+        if std_dev == 0.0 {
+            return 0.0;
+        }
+        // Box-Muller transform
+        let radius = (-2.0 * (1.0 - self.uniform()).ln()).sqrt();
+        let angle = TAU * self.uniform();
+        radius * angle.cos() * std_dev
+    }
+}
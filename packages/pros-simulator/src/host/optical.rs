@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use pros_simulator_interface::{
+    config::{RobotConfig, SmartDevice},
+    OpticalGesture, OpticalReading,
+};
+use pros_sys::EINVAL;
+
+use super::devices::DeviceTable;
+
+// `optical_direction_e_t` values. The optical sensor API is missing from `pros_sys`, so these are
+// copied from the PROS headers.
+pub const OPTICAL_NO_GESTURE: i32 = 0;
+pub const OPTICAL_UP: i32 = 1;
+pub const OPTICAL_DOWN: i32 = 2;
+pub const OPTICAL_RIGHT: i32 = 3;
+pub const OPTICAL_LEFT: i32 = 4;
+
+/// The integration time of an optical sensor when it is first plugged in, in milliseconds.
+pub const DEFAULT_INTEGRATION_TIME: f64 = 100.0;
+/// The range of integration times an optical sensor supports, in milliseconds.
+const INTEGRATION_TIME_RANGE: (f64, f64) = (3.0, 712.0);
+
+/// The raw data of the most recent gesture, like `optical_gesture_s_t`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GestureData {
+    /// The `optical_direction_e_t` of the gesture.
+    pub direction: i32,
+    /// The number of gestures detected since gesture detection was enabled.
+    pub count: u16,
+    /// When the gesture was detected, in milliseconds since the simulator started.
+    pub time: u32,
+}
+
+/// A simulated V5 Optical Sensor.
+///
+/// The sensor reports the color set by the most recent `OpticalInput` simulator message.
+#[derive(Debug, Clone)]
+pub struct OpticalSensor {
+    reading: OpticalReading,
+    /// LED brightness from 0 to 100.
+    led_pwm: i32,
+    /// Integration time in milliseconds.
+    integration_time: f64,
+    gestures_enabled: bool,
+    gesture: GestureData,
+}
+
+impl Default for OpticalSensor {
+    fn default() -> Self {
+        Self {
+            reading: Default::default(),
+            led_pwm: 0,
+            integration_time: DEFAULT_INTEGRATION_TIME,
+            gestures_enabled: false,
+            gesture: Default::default(),
+        }
+    }
+}
+
+impl OpticalSensor {
+    pub fn reading(&self) -> OpticalReading {
+        self.reading
+    }
+
+    pub fn set_reading(&mut self, reading: OpticalReading) {
+        self.reading = OpticalReading {
+            hue: reading.hue.rem_euclid(360.0),
+            saturation: reading.saturation.clamp(0.0, 1.0),
+            brightness: reading.brightness.clamp(0.0, 1.0),
+            ..reading
+        };
+    }
+
+    /// The detected color as red, green and blue values from 0 to 255.
+    pub fn rgb(&self) -> [f64; 3] {
+        let OpticalReading {
+            hue,
+            saturation,
+            brightness,
+            ..
+        } = self.reading;
+        let chroma = brightness * saturation;
+        let sector = hue / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (red, green, blue) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let min = brightness - chroma;
+        [red, green, blue].map(|channel| (channel + min) * 255.0)
+    }
+
+    pub fn led_pwm(&self) -> i32 {
+        self.led_pwm
+    }
+
+    /// Sets the LED brightness, failing with `EINVAL` if it isn't between 0 and 100.
+    pub fn set_led_pwm(&mut self, pwm: i32) -> Result<(), i32> {
+        if !(0..=100).contains(&pwm) {
+            return Err(EINVAL);
+        }
+        self.led_pwm = pwm;
+        Ok(())
+    }
+
+    pub fn integration_time(&self) -> f64 {
+        self.integration_time
+    }
+
+    /// Sets the integration time in milliseconds, clamping it to the range the sensor supports.
+    pub fn set_integration_time(&mut self, time: f64) {
+        let (min, max) = INTEGRATION_TIME_RANGE;
+        self.integration_time = time.clamp(min, max);
+    }
+
+    pub fn set_gestures_enabled(&mut self, enabled: bool) {
+        self.gestures_enabled = enabled;
+        self.gesture = Default::default();
+    }
+
+    /// Records a gesture, if gesture detection is enabled.
+    pub fn detect_gesture(&mut self, gesture: OpticalGesture, now: Duration) {
+        if !self.gestures_enabled {
+            return;
+        }
+        self.gesture = GestureData {
+            direction: match gesture {
+                OpticalGesture::Up => OPTICAL_UP,
+                OpticalGesture::Down => OPTICAL_DOWN,
+                OpticalGesture::Left => OPTICAL_LEFT,
+                OpticalGesture::Right => OPTICAL_RIGHT,
+            },
+            count: self.gesture.count.wrapping_add(1),
+            time: now.as_millis() as u32,
+        };
+    }
+
+    /// The most recent gesture, or `OPTICAL_NO_GESTURE` if gesture detection is disabled or
+    /// there hasn't been one yet.
+    pub fn gesture(&self) -> GestureData {
+        self.gesture
+    }
+}
+
+/// Stores the state of every V5 Optical Sensor plugged into the brain.
+pub struct OpticalSensors {
+    sensors: DeviceTable<OpticalSensor>,
+}
+
+impl OpticalSensors {
+    pub fn new(robot_config: &RobotConfig) -> Self {
+        Self {
+            sensors: DeviceTable::new(|port| {
+                matches!(robot_config.smart_device(port), Some(SmartDevice::Optical))
+                    .then(OpticalSensor::default)
+            }),
+        }
+    }
+
+    pub fn get(&mut self, port: u32) -> Result<&mut OpticalSensor, i32> {
+        self.sensors.get_mut(port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::config::SmartPortConfig;
+    use pros_sys::ENODEV;
+
+    use super::*;
+
+    fn color(hue: f64, saturation: f64, brightness: f64) -> OpticalReading {
+        OpticalReading {
+            hue,
+            saturation,
+            brightness,
+            proximity: 0,
+        }
+    }
+
+    fn rgb(reading: OpticalReading) -> [f64; 3] {
+        let mut sensor = OpticalSensor::default();
+        sensor.set_reading(reading);
+        sensor.rgb()
+    }
+
+    #[test]
+    fn readings_are_normalized() {
+        let mut sensor = OpticalSensor::default();
+        sensor.set_reading(color(-30.0, 1.5, -0.5));
+        let reading = sensor.reading();
+        assert_eq!(reading.hue, 330.0);
+        assert_eq!(reading.saturation, 1.0);
+        assert_eq!(reading.brightness, 0.0);
+    }
+
+    #[test]
+    fn converts_colors_to_rgb() {
+        assert_eq!(rgb(color(0.0, 1.0, 1.0)), [255.0, 0.0, 0.0]);
+        assert_eq!(rgb(color(120.0, 1.0, 1.0)), [0.0, 255.0, 0.0]);
+        assert_eq!(rgb(color(240.0, 1.0, 1.0)), [0.0, 0.0, 255.0]);
+        assert_eq!(rgb(color(60.0, 1.0, 0.5)), [127.5, 127.5, 0.0]);
+        assert_eq!(rgb(color(200.0, 0.0, 0.4)), [102.0, 102.0, 102.0]);
+    }
+
+    #[test]
+    fn led_pwm_must_be_a_percentage() {
+        let mut sensor = OpticalSensor::default();
+        assert_eq!(sensor.set_led_pwm(101), Err(EINVAL));
+        assert_eq!(sensor.set_led_pwm(-1), Err(EINVAL));
+        assert_eq!(sensor.set_led_pwm(40), Ok(()));
+        assert_eq!(sensor.led_pwm(), 40);
+    }
+
+    #[test]
+    fn integration_time_is_clamped() {
+        let mut sensor = OpticalSensor::default();
+        assert_eq!(sensor.integration_time(), DEFAULT_INTEGRATION_TIME);
+        sensor.set_integration_time(1.0);
+        assert_eq!(sensor.integration_time(), 3.0);
+        sensor.set_integration_time(1000.0);
+        assert_eq!(sensor.integration_time(), 712.0);
+    }
+
+    #[test]
+    fn gestures_are_ignored_until_enabled() {
+        let mut sensor = OpticalSensor::default();
+        sensor.detect_gesture(OpticalGesture::Up, Duration::from_millis(10));
+        assert_eq!(sensor.gesture().direction, OPTICAL_NO_GESTURE);
+        assert_eq!(sensor.gesture().count, 0);
+    }
+
+    #[test]
+    fn records_most_recent_gesture() {
+        let mut sensor = OpticalSensor::default();
+        sensor.set_gestures_enabled(true);
+        sensor.detect_gesture(OpticalGesture::Up, Duration::from_millis(10));
+        sensor.detect_gesture(OpticalGesture::Left, Duration::from_millis(25));
+        let gesture = sensor.gesture();
+        assert_eq!(gesture.direction, OPTICAL_LEFT);
+        assert_eq!(gesture.count, 2);
+        assert_eq!(gesture.time, 25);
+
+        // disabling gestures forgets the last one
+        sensor.set_gestures_enabled(false);
+        assert_eq!(sensor.gesture().direction, OPTICAL_NO_GESTURE);
+        assert_eq!(sensor.gesture().count, 0);
+    }
+
+    #[test]
+    fn only_configured_ports_have_sensors() {
+        let mut sensors = OpticalSensors::new(&RobotConfig {
+            smart_ports: vec![SmartPortConfig {
+                port: 2,
+                device: SmartDevice::Optical,
+            }],
+            ..Default::default()
+        });
+        assert!(sensors.get(2).is_ok());
+        assert_eq!(sensors.get(1).unwrap_err(), ENODEV);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    f64::consts::{FRAC_1_SQRT_2, TAU},
+    time::Duration,
+};
+
+use pros_simulator_interface::{
+    config::{DrivetrainConfig, DrivetrainLayout, RobotConfig},
+    world::World,
+    GamePieceState, RobotPose, RobotVelocity, SimulatorEvent,
+};
+
+use super::{adi::Adi, motors::Motors, pose::PoseModel, world::WorldModel};
+use crate::interface::SimulatorInterface;
+
+/// The longest time step used to integrate the drivetrain's motion. Longer updates are split
+/// into multiple steps so that the simulation stays stable.
+const MAX_STEP: Duration = Duration::from_millis(1);
+/// How often the robot's pose is reported to the simulator interface.
+const POSE_REPORT_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A velocity in the robot's frame of reference: meters per second forwards and to the left, and
+/// radians per second clockwise.
+type ChassisVelocity = [f64; 3];
+
+/// A powered wheel on the drivetrain.
+#[derive(Debug, Clone)]
+struct Wheel {
+    /// The smart ports of the motors that turn the wheel.
+    motors: Vec<u8>,
+    /// How fast the wheel's rim moves for each component of the chassis velocity. Also how much
+    /// of the wheel's force goes into each component.
+    kinematics: ChassisVelocity,
+}
+
+impl Wheel {
+    fn new(motors: &[u8], kinematics: ChassisVelocity) -> Self {
+        Self {
+            motors: motors.to_vec(),
+            kinematics,
+        }
+    }
+
+    /// The speed of the wheel's rim in meters per second, when the robot moves at the given
+    /// velocity.
+    fn rim_speed(&self, velocity: ChassisVelocity) -> f64 {
+        self.kinematics
+            .iter()
+            .zip(velocity)
+            .map(|(factor, velocity)| factor * velocity)
+            .sum()
+    }
+}
+
+/// A drivetrain that moves the robot by turning its wheels with motors.
+///
+/// Each motor turns its wheel with the torque it produces at the wheel's current speed, and the
+/// forces from every wheel accelerate the robot. The robot's wheels never slip, so tank drives
+/// can't move sideways.
+#[derive(Debug, Clone)]
+pub struct Drivetrain {
+    wheels: Vec<Wheel>,
+    wheel_radius: f64,
+    gear_ratio: f64,
+    mass: f64,
+    /// Moment of inertia around the center of rotation, in kilogram square meters.
+    inertia: f64,
+}
+
+impl Drivetrain {
+    pub fn new(config: &DrivetrainConfig) -> Self {
+        let half_track = config.track_width / 2.0;
+        let half_base = config.wheelbase / 2.0;
+        // wheel order: front left, front right, back left, back right
+        let corners = |motors: [&Vec<u8>; 4], forward: f64, left: f64, turn: f64| {
+            let kinematics = [
+                [forward, -left, turn],
+                [forward, left, -turn],
+                [forward, left, turn],
+                [forward, -left, -turn],
+            ];
+            motors
+                .into_iter()
+                .zip(kinematics)
+                .map(|(motors, kinematics)| Wheel::new(motors, kinematics))
+                .collect()
+        };
+        let wheels = match &config.layout {
+            DrivetrainLayout::Tank { left, right } => vec![
+                Wheel::new(left, [1.0, 0.0, half_track]),
+                Wheel::new(right, [1.0, 0.0, -half_track]),
+            ],
+            DrivetrainLayout::XDrive {
+                front_left,
+                front_right,
+                back_left,
+                back_right,
+            } => corners(
+                [front_left, front_right, back_left, back_right],
+                FRAC_1_SQRT_2,
+                FRAC_1_SQRT_2,
+                half_track.hypot(half_base),
+            ),
+            DrivetrainLayout::Mecanum {
+                front_left,
+                front_right,
+                back_left,
+                back_right,
+            } => corners(
+                [front_left, front_right, back_left, back_right],
+                1.0,
+                1.0,
+                half_track + half_base,
+            ),
+        };
+
+        Self {
+            wheels,
+            wheel_radius: config.wheel_diameter / 2.0,
+            gear_ratio: config.gear_ratio,
+            mass: config.mass,
+            // treat the robot as a uniform rectangle
+            inertia: config.mass * (config.track_width.powi(2) + config.wheelbase.powi(2)) / 12.0,
+        }
+    }
+
+    /// Converts the speed of a wheel's rim in meters per second to the speed of its motors in
+    /// RPM.
+    fn motor_rpm(&self, rim_speed: f64) -> f64 {
+        rim_speed / self.wheel_radius / self.gear_ratio / TAU * 60.0
+    }
+
+    /// Accelerates the robot for `seconds` seconds using the force from each motor.
+    fn step(
+        &self,
+        velocity: ChassisVelocity,
+        seconds: f64,
+        motors: &mut Motors,
+    ) -> ChassisVelocity {
+        let mut force = [0.0; 3];
+        for wheel in &self.wheels {
+            let motor_rpm = self.motor_rpm(wheel.rim_speed(velocity));
+            let mut wheel_force = 0.0;
+            for &port in &wheel.motors {
+                if let Ok(motor) = motors.get_mut(port.into()) {
+                    let torque = motor.drive_torque(motor_rpm, seconds);
+                    wheel_force += torque / self.gear_ratio / self.wheel_radius;
+                }
+            }
+            for (force, factor) in force.iter_mut().zip(wheel.kinematics) {
+                *force += wheel_force * factor;
+            }
+        }
+
+        let [forward, left, angular] = velocity;
+        [
+            forward + force[0] / self.mass * seconds,
+            left + force[1] / self.mass * seconds,
+            angular + force[2] / self.inertia * seconds,
+        ]
+    }
+
+    /// Moves the robot for the given amount of time, and makes each motor turn at the speed of
+    /// its wheel. The robot keeps any velocity it already had, like from a `SetRobotVelocity`
+    /// message. The pose and motors should be updated to the current time first.
+    pub fn update(&self, elapsed: Duration, motors: &mut Motors, pose: &mut PoseModel) {
+        let RobotVelocity { x, y, angular } = pose.velocity();
+        let (forward, left) = pose.to_robot_frame((x, y));
+        let mut velocity = [forward, left, angular.to_radians()];
+
+        let mut remaining = elapsed;
+        while !remaining.is_zero() {
+            let step = remaining.min(MAX_STEP);
+            velocity = self.step(velocity, step.as_secs_f64(), motors);
+            remaining -= step;
+        }
+
+        for wheel in &self.wheels {
+            let motor_rpm = self.motor_rpm(wheel.rim_speed(velocity));
+            for &port in &wheel.motors {
+                if let Ok(motor) = motors.get_mut(port.into()) {
+                    motor.set_drive_velocity(motor_rpm);
+                }
+            }
+        }
+
+        let [forward, left, angular] = velocity;
+        let (sin, cos) = pose.pose().heading.to_radians().sin_cos();
+        // forwards is (sin, cos) and left is (-cos, sin) in field coordinates
+        pose.set_velocity(RobotVelocity {
+            x: forward * sin - left * cos,
+            y: forward * cos + left * sin,
+            angular: angular.to_degrees(),
+        });
+    }
+}
+
+/// Moves the simulated robot according to its motors, and reports where it is.
+pub struct Physics {
+    drivetrain: Option<Drivetrain>,
+    world: WorldModel,
+    /// Whether to send [`SimulatorEvent::WorldState`] events, which only happens if the
+    /// simulator was given a world.
+    report_world: bool,
+    interface: SimulatorInterface,
+    last_update: Duration,
+    last_report: Option<(Duration, RobotPose)>,
+    last_world_state: Option<(RobotPose, Vec<GamePieceState>)>,
+}
+
+impl Physics {
+    pub fn new(
+        interface: SimulatorInterface,
+        robot_config: &RobotConfig,
+        world: Option<&World>,
+    ) -> Self {
+        Self {
+            drivetrain: robot_config.drivetrain.as_ref().map(Drivetrain::new),
+            world: WorldModel::new(world.unwrap_or(&World::default()), robot_config),
+            report_world: world.is_some(),
+            interface,
+            last_update: Duration::ZERO,
+            last_report: None,
+            last_world_state: None,
+        }
+    }
+
+    /// The field and the objects on it.
+    pub fn world(&self) -> &WorldModel {
+        &self.world
+    }
+
+    /// Updates the readings of sensors that look at the field, like line trackers.
+    pub fn read_sensors(&self, pose: &PoseModel, adi: &mut Adi) {
+        self.world.read_line_trackers(pose, adi);
+    }
+
+    /// Advances the simulation to the given time, which is the amount of time that has passed
+    /// since the simulator started.
+    pub fn update(&mut self, now: Duration, motors: &mut Motors, pose: &mut PoseModel) {
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+        motors.update(now);
+        pose.update(now);
+        self.world.update(elapsed.as_secs_f64(), motors, pose);
+        if let Some(drivetrain) = &self.drivetrain {
+            drivetrain.update(elapsed, motors, pose);
+        }
+        self.report_pose(now, pose.pose());
+        if self.report_world {
+            self.report_world_state(pose.pose());
+        }
+    }
+
+    /// Sends a [`SimulatorEvent::RobotPose`] event if the robot has moved since the last one,
+    /// unless one was sent recently.
+    fn report_pose(&mut self, now: Duration, pose: RobotPose) {
+        if let Some((time, last_pose)) = self.last_report {
+            if now < time + POSE_REPORT_INTERVAL || pose == last_pose {
+                return;
+            }
+        }
+        self.last_report = Some((now, pose));
+        self.interface.send(SimulatorEvent::RobotPose {
+            x: pose.x,
+            y: pose.y,
+            heading: pose.heading,
+        });
+    }
+
+    /// Sends a [`SimulatorEvent::WorldState`] event if anything has moved since the last one.
+    fn report_world_state(&mut self, robot: RobotPose) {
+        let game_pieces = self.world.game_pieces();
+        let state = (robot, game_pieces);
+        if self.last_world_state.as_ref() == Some(&state) {
+            return;
+        }
+        self.last_world_state = Some(state.clone());
+        let (robot, game_pieces) = state;
+        self.interface
+            .send(SimulatorEvent::WorldState { robot, game_pieces });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::{
+        config::{MotorCurve, MotorModel, SmartDevice, SmartPortConfig},
+        MotorGearset,
+    };
+
+    use super::*;
+
+    /// A robot with 0.1 meter wheels, driven by green motors.
+    fn robot_config(layout: DrivetrainLayout, motor_curve: MotorCurve) -> RobotConfig {
+        #[allow(deprecated)]
+        let drivetrain = DrivetrainConfig {
+            layout,
+            wheel_diameter: 0.1,
+            track_width: 0.3,
+            wheelbase: 0.3,
+            gear_ratio: 1.0,
+            mass: 6.0,
+            motor_curve,
+        };
+        let smart_ports = drivetrain
+            .layout
+            .motors()
+            .into_iter()
+            .map(|port| SmartPortConfig {
+                port,
+                device: SmartDevice::Motor {
+                    gearset: MotorGearset::Green,
+                    model: MotorModel::default(),
+                },
+            })
+            .collect();
+        RobotConfig {
+            smart_ports,
+            drivetrain: Some(drivetrain),
+            ..Default::default()
+        }
+    }
+
+    fn tank() -> RobotConfig {
+        let layout = DrivetrainLayout::Tank {
+            left: vec![1],
+            right: vec![2],
+        };
+        robot_config(layout, MotorCurve::default())
+    }
+
+    fn x_drive() -> RobotConfig {
+        let layout = DrivetrainLayout::XDrive {
+            front_left: vec![1],
+            front_right: vec![2],
+            back_left: vec![3],
+            back_right: vec![4],
+        };
+        robot_config(layout, MotorCurve::default())
+    }
+
+    /// Drives the robot with the given voltages (in millivolts, by port) for `duration`.
+    fn drive(config: &RobotConfig, voltages: &[(u32, i32)], duration: Duration) -> PoseModel {
+        let mut motors = Motors::new(SimulatorInterface::from(|_| {}), config);
+        for &(port, voltage) in voltages {
+            motors.move_voltage(port, voltage).unwrap();
+        }
+        let drivetrain = Drivetrain::new(config.drivetrain.as_ref().unwrap());
+        let mut pose = PoseModel::default();
+        let step = Duration::from_millis(10);
+        let mut now = Duration::ZERO;
+        while now < duration {
+            now += step;
+            motors.update(now);
+            pose.update(now);
+            drivetrain.update(step, &mut motors, &mut pose);
+        }
+        pose
+    }
+
+    #[test]
+    fn motor_speed_matches_wheel_speed() {
+        let drivetrain = Drivetrain::new(tank().drivetrain.as_ref().unwrap());
+        // one turn of a 0.1 meter wheel per second
+        let rpm = drivetrain.motor_rpm(0.1 * std::f64::consts::PI);
+        assert!((rpm - 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tank_drive_moves_forwards() {
+        let pose = drive(&tank(), &[(1, 12000), (2, 12000)], Duration::from_secs(2));
+        // close to the wheels' free speed of 200 RPM
+        let speed = pose.velocity().y;
+        assert!(speed > 0.95 && speed < 200.0 / 60.0 * 0.1 * std::f64::consts::PI);
+        assert!(pose.pose().y > 1.5);
+        assert!(pose.pose().x.abs() < 1e-9);
+        assert!(pose.pose().heading.abs() < 1e-9);
+    }
+
+    #[test]
+    fn tank_drive_turns_in_place() {
+        let pose = drive(&tank(), &[(1, 6000), (2, -6000)], Duration::from_secs(1));
+        // headings are clockwise
+        assert!(pose.pose().heading > 90.0);
+        assert!(pose.pose().x.abs() < 1e-9);
+        assert!(pose.pose().y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn x_drive_strafes() {
+        let voltages = [(1, 12000), (2, -12000), (3, -12000), (4, 12000)];
+        let pose = drive(&x_drive(), &voltages, Duration::from_secs(1));
+        // facing along the y axis, so the robot's right is along the x axis
+        assert!(pose.pose().x > 0.5);
+        assert!(pose.pose().y.abs() < 1e-9);
+        assert!(pose.pose().heading.abs() < 1e-9);
+    }
+
+    #[test]
+    fn motor_curve_scales_drive_motors() {
+        let layout = DrivetrainLayout::Tank {
+            left: vec![1],
+            right: vec![2],
+        };
+        let config = robot_config(layout, MotorCurve { stall_torque: 4.2 });
+        let model = MotorModel::default();
+        assert_eq!(
+            config.motor_model(1, model).stall_torque,
+            model.stall_torque * 2.0
+        );
+        assert_eq!(config.motor_model(3, model), model);
+
+        // twice the torque accelerates the robot faster
+        let voltages = [(1, 12000), (2, 12000)];
+        let duration = Duration::from_millis(20);
+        let stronger = drive(&config, &voltages, duration);
+        let normal = drive(&tank(), &voltages, duration);
+        assert!(stronger.velocity().y > normal.velocity().y * 1.5);
+    }
+}
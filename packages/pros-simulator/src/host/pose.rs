@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+use pros_simulator_interface::{config::SensorMount, RobotPose, RobotVelocity};
+
+/// Standard gravity, in meters per second squared.
+pub const GRAVITY: f64 = 9.80665;
+
+/// How far the robot has moved since the simulator started, measured in its own frame of
+/// reference. Unlike the robot's pose, this doesn't change when the robot is moved by hand, so
+/// it's what a tracking wheel would measure.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Odometry {
+    /// Distance travelled forwards, in meters.
+    pub forward: f64,
+    /// Distance travelled to the left, in meters.
+    pub left: f64,
+    /// Degrees turned clockwise.
+    pub rotation: f64,
+}
+
+/// Tracks where the simulated robot is on the field.
+///
+/// The robot moves at a constant velocity between updates. Sensors that depend on the robot's
+/// motion, like the inertial sensor, read their values from here.
+#[derive(Debug, Default)]
+pub struct PoseModel {
+    pose: RobotPose,
+    velocity: RobotVelocity,
+    odometry: Odometry,
+    /// Linear acceleration in meters per second squared, along the field's x and y axes.
+    acceleration: (f64, f64),
+    last_update: Duration,
+    /// When the velocity was last changed.
+    last_velocity_change: Duration,
+}
+
+impl PoseModel {
+    /// Moves the robot along its current velocity. `now` is the amount of time that has passed
+    /// since the simulator started.
+    pub fn update(&mut self, now: Duration) {
+        let elapsed = now.saturating_sub(self.last_update).as_secs_f64();
+        self.last_update = now;
+        if elapsed > 0.0 {
+            // the velocity step has been applied, and the velocity stays the same until the next
+            // one
+            self.acceleration = (0.0, 0.0);
+        }
+        let turned = self.velocity.angular * elapsed;
+        // measure the distance travelled at the average heading over this update
+        let (sin, cos) = (self.pose.heading + turned / 2.0).to_radians().sin_cos();
+        let (x, y) = (self.velocity.x * elapsed, self.velocity.y * elapsed);
+        self.odometry.forward += x * sin + y * cos;
+        self.odometry.left += -x * cos + y * sin;
+        self.odometry.rotation += turned;
+
+        self.pose = self.predict(elapsed);
+    }
+
+    /// Where the robot will be in `seconds` seconds if its velocity doesn't change.
+    pub fn predict(&self, seconds: f64) -> RobotPose {
+        RobotPose {
+            x: self.pose.x + self.velocity.x * seconds,
+            y: self.pose.y + self.velocity.y * seconds,
+            heading: self.pose.heading + self.velocity.angular * seconds,
+        }
+    }
+
+    pub fn pose(&self) -> RobotPose {
+        self.pose
+    }
+
+    pub fn set_pose(&mut self, pose: RobotPose) {
+        self.pose = pose;
+    }
+
+    pub fn velocity(&self) -> RobotVelocity {
+        self.velocity
+    }
+
+    pub fn odometry(&self) -> Odometry {
+        self.odometry
+    }
+
+    /// Changes the robot's velocity, estimating its acceleration from the previous velocity.
+    /// The acceleration lasts until the model is next [updated](Self::update) to a later time.
+    /// The model should be updated to the current time first.
+    pub fn set_velocity(&mut self, velocity: RobotVelocity) {
+        let elapsed = self
+            .last_update
+            .saturating_sub(self.last_velocity_change)
+            .as_secs_f64();
+        self.acceleration = if elapsed > 0.0 {
+            (
+                (velocity.x - self.velocity.x) / elapsed,
+                (velocity.y - self.velocity.y) / elapsed,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        self.velocity = velocity;
+        self.last_velocity_change = self.last_update;
+    }
+
+    /// Moves the robot `depth` meters in the direction of `normal` (a unit vector along the
+    /// field's axes) to get it out of something it ran into, and stops it from moving any
+    /// further into it.
+    pub fn push(&mut self, normal: (f64, f64), depth: f64) {
+        self.pose.x += normal.0 * depth;
+        self.pose.y += normal.1 * depth;
+        let speed = self.velocity.x * normal.0 + self.velocity.y * normal.1;
+        if speed < 0.0 {
+            self.velocity.x -= speed * normal.0;
+            self.velocity.y -= speed * normal.1;
+        }
+    }
+
+    /// The robot's linear acceleration along the field's x and y axes, in meters per second
+    /// squared.
+    pub fn acceleration(&self) -> (f64, f64) {
+        self.acceleration
+    }
+
+    /// Converts a vector along the field's axes into the robot's frame of reference, where x
+    /// points forwards and y points to the robot's left.
+    pub fn to_robot_frame(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let (sin, cos) = self.pose.heading.to_radians().sin_cos();
+        // forwards is (sin, cos) and left is (-cos, sin) in field coordinates
+        (x * sin + y * cos, -x * cos + y * sin)
+    }
+
+    /// The speed of a point on the robot in the given direction, in meters per second. Both the
+    /// point and the direction are given by `mount`.
+    pub fn speed_at(&self, mount: &SensorMount) -> f64 {
+        let (forward, left) = self.to_robot_frame((self.velocity.x, self.velocity.y));
+        let angular = self.velocity.angular.to_radians();
+        along_mount(
+            mount,
+            forward + angular * mount.left,
+            left - angular * mount.forward,
+        )
+    }
+
+    /// The distance a point on the robot has moved in the given direction since the simulator
+    /// started, in meters, ignoring any times the robot was moved by hand. Both the point and
+    /// the direction are given by `mount`.
+    pub fn distance_at(&self, mount: &SensorMount) -> f64 {
+        let odometry = self.odometry;
+        let rotation = odometry.rotation.to_radians();
+        along_mount(
+            mount,
+            odometry.forward + rotation * mount.left,
+            odometry.left - rotation * mount.forward,
+        )
+    }
+}
+
+/// Projects a vector in the robot's frame of reference onto the direction a sensor is facing.
+fn along_mount(mount: &SensorMount, forward: f64, left: f64) -> f64 {
+    let (sin, cos) = mount.heading.to_radians().sin_cos();
+    // headings are clockwise, so a heading of 90 degrees points to the robot's right
+    forward * cos - left * sin
+}
+
+/// Finds where a sensor is on the field and which way it's facing, given the robot's pose.
+pub fn mount_pose(pose: RobotPose, mount: &SensorMount) -> RobotPose {
+    let (sin, cos) = pose.heading.to_radians().sin_cos();
+    // forwards is (sin, cos) and left is (-cos, sin) in field coordinates
+    RobotPose {
+        x: pose.x + mount.forward * sin - mount.left * cos,
+        y: pose.y + mount.forward * cos + mount.left * sin,
+        heading: pose.heading + mount.heading,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn velocity(x: f64) -> RobotVelocity {
+        RobotVelocity {
+            x,
+            y: 0.0,
+            angular: 0.0,
+        }
+    }
+
+    #[test]
+    fn acceleration_is_estimated_from_velocity_steps() {
+        let mut pose = PoseModel::default();
+        pose.update(Duration::from_millis(500));
+        pose.set_velocity(velocity(1.0));
+        assert_eq!(pose.acceleration(), (2.0, 0.0));
+
+        // reading at the same time keeps the estimate
+        pose.update(Duration::from_millis(500));
+        assert_eq!(pose.acceleration(), (2.0, 0.0));
+    }
+
+    #[test]
+    fn acceleration_is_cleared_once_the_step_is_applied() {
+        let mut pose = PoseModel::default();
+        pose.update(Duration::from_millis(500));
+        pose.set_velocity(velocity(1.0));
+        pose.update(Duration::from_millis(600));
+        assert_eq!(pose.acceleration(), (0.0, 0.0));
+        assert_eq!(pose.pose().x, 0.1);
+
+        pose.set_velocity(velocity(0.0));
+        assert_eq!(pose.acceleration(), (-10.0, 0.0));
+    }
+}
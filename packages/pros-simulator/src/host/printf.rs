@@ -0,0 +1,551 @@
+//! Formatting for the printf-style API functions, like `controller_print` and `lcd_print`.
+//!
+//! The robot code passes variadic arguments as a pointer to a buffer in its memory, where each
+//! argument is stored at the next offset aligned to its size. Integers smaller than `int` are
+//! promoted to `int` and floats to `double`, so every argument takes 4, 8 or (for `long double`)
+//! 16 bytes.
+
+use std::{iter::Peekable, str::Chars};
+
+use snafu::OptionExt;
+use wasmtime::SharedMemory;
+
+use super::memory::SharedMemoryExt;
+use crate::error::{InvalidPointerSnafu, SimulatorError};
+
+/// The precision used for floating point conversions when the format doesn't give one.
+const DEFAULT_PRECISION: usize = 6;
+/// The largest width or precision a conversion can have. Everything printed with these functions
+/// ends up on a small screen, and this stops robot code from making the simulator allocate huge
+/// strings.
+const MAX_FIELD_WIDTH: usize = 256;
+
+/// Reads variadic arguments from the robot code's memory in order.
+struct Arguments<'a> {
+    memory: &'a SharedMemory,
+    pointer: u32,
+}
+
+impl Arguments<'_> {
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], SimulatorError> {
+        let size = N as u32;
+        let pointer = self
+            .pointer
+            .checked_next_multiple_of(size)
+            .context(InvalidPointerSnafu {
+                pointer: self.pointer,
+            })?;
+        let bytes = self
+            .memory
+            .read_relaxed(pointer as usize, N)
+            .ok()
+            .context(InvalidPointerSnafu { pointer })?;
+        self.pointer = pointer
+            .checked_add(size)
+            .context(InvalidPointerSnafu { pointer })?;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn int(&mut self) -> Result<i32, SimulatorError> {
+        self.read().map(i32::from_le_bytes)
+    }
+
+    fn long_long(&mut self) -> Result<i64, SimulatorError> {
+        self.read().map(i64::from_le_bytes)
+    }
+
+    fn double(&mut self) -> Result<f64, SimulatorError> {
+        self.read().map(f64::from_le_bytes)
+    }
+
+    fn long_double(&mut self) -> Result<f64, SimulatorError> {
+        self.read()
+            .map(|bytes| f128_to_f64(u128::from_le_bytes(bytes)))
+    }
+}
+
+/// Converts the bits of an IEEE 754 quadruple precision float, which is what `long double` is in
+/// WebAssembly, to the nearest `f64` (rounding towards zero).
+fn f128_to_f64(bits: u128) -> f64 {
+    let sign = if bits >> 127 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 112) & 0x7fff) as i32;
+    let fraction = bits & ((1 << 112) - 1);
+    match exponent {
+        0x7fff if fraction == 0 => return sign * f64::INFINITY,
+        0x7fff => return f64::NAN,
+        // subnormal long doubles are far too small to be anything but zero as a double
+        0 => return sign * 0.0,
+        _ => {}
+    }
+    let exponent = exponent - 16383;
+    // the top 52 bits of the fraction, like a double's
+    let mantissa = 1.0 + (fraction >> 60) as f64 / (1u64 << 52) as f64;
+    // far enough outside of a double's range to become infinity or zero, and then scaled in two
+    // steps so that the powers of two can't overflow or underflow by themselves
+    let exponent = exponent.clamp(-1100, 1100);
+    let half = exponent / 2;
+    sign * mantissa * 2f64.powi(half) * 2f64.powi(exponent - half)
+}
+
+/// How wide an integer argument is, from the conversion's length modifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Length {
+    Char,
+    Short,
+    Int,
+    LongLong,
+}
+
+/// The flags, width and precision of a single conversion.
+#[derive(Debug, Default)]
+struct Spec {
+    left_align: bool,
+    zero_pad: bool,
+    plus_sign: bool,
+    space_sign: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /// Pads `body` to the conversion's width. `prefix` is the sign or radix prefix, which goes
+    /// before any zero padding.
+    fn pad(&self, prefix: &str, body: &str, zero_pad: bool) -> String {
+        let len = prefix.chars().count() + body.chars().count();
+        let padding = self.width.saturating_sub(len);
+        if self.left_align {
+            format!("{prefix}{body}{}", " ".repeat(padding))
+        } else if zero_pad {
+            format!("{prefix}{}{body}", "0".repeat(padding))
+        } else {
+            format!("{}{prefix}{body}", " ".repeat(padding))
+        }
+    }
+
+    fn sign(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else if self.plus_sign {
+            "+"
+        } else if self.space_sign {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    /// Formats an integer's digits, applying the precision as a minimum number of digits.
+    fn integer(&self, prefix: &str, digits: String) -> String {
+        let digits = match self.precision {
+            Some(0) if digits == "0" => String::new(),
+            Some(precision) => format!("{digits:0>precision$}"),
+            None => digits,
+        };
+        self.pad(prefix, &digits, self.zero_pad && self.precision.is_none())
+    }
+
+    /// Formats a floating point number with one of the `f`, `e` or `g` conversions.
+    fn float(&self, value: f64, conversion: char) -> String {
+        let upper = conversion.is_ascii_uppercase();
+        let sign = self.sign(value.is_sign_negative() && !value.is_nan());
+        if !value.is_finite() {
+            let body = if value.is_nan() { "nan" } else { "inf" };
+            let body = if upper {
+                body.to_uppercase()
+            } else {
+                body.to_string()
+            };
+            return self.pad(sign, &body, false);
+        }
+
+        let value = value.abs();
+        let precision = self.precision.unwrap_or(DEFAULT_PRECISION);
+        let body = match conversion.to_ascii_lowercase() {
+            'f' => format!("{value:.precision$}"),
+            'e' => exponential(value, precision),
+            _ => {
+                let precision = precision.max(1);
+                let exponent = exponential(value, precision - 1)
+                    .split_once('e')
+                    .map_or(0, |(_, exponent)| exponent.parse::<i32>().unwrap());
+                let body = if exponent < -4 || exponent >= precision as i32 {
+                    exponential(value, precision - 1)
+                } else {
+                    let decimals = (precision as i32 - 1 - exponent) as usize;
+                    format!("{value:.decimals$}")
+                };
+                if self.alternate {
+                    body
+                } else {
+                    strip_trailing_zeros(&body)
+                }
+            }
+        };
+        let body = if self.alternate && !body.contains('.') {
+            match body.split_once('e') {
+                Some((mantissa, exponent)) => format!("{mantissa}.e{exponent}"),
+                None => format!("{body}."),
+            }
+        } else {
+            body
+        };
+        let body = if upper { body.to_uppercase() } else { body };
+        self.pad(sign, &body, self.zero_pad)
+    }
+}
+
+/// Formats a non-negative number like C's `%e`, with at least two digits in the exponent.
+fn exponential(value: f64, precision: usize) -> String {
+    let formatted = format!("{value:.precision$e}");
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent = exponent.parse::<i32>().unwrap();
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{mantissa}e{sign}{:02}", exponent.abs())
+}
+
+/// Removes zeros after the decimal point, and the point itself if nothing is left after it.
+fn strip_trailing_zeros(number: &str) -> String {
+    let (mantissa, exponent) = match number.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa, format!("e{exponent}")),
+        None => (number, String::new()),
+    };
+    let mantissa = if mantissa.contains('.') {
+        mantissa.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mantissa
+    };
+    format!("{mantissa}{exponent}")
+}
+
+/// Reads a width or precision from the format string, or from the arguments if it's `*`.
+fn read_number(
+    chars: &mut Peekable<Chars>,
+    args: &mut Arguments,
+) -> Result<Option<i32>, SimulatorError> {
+    if chars.next_if_eq(&'*').is_some() {
+        return args.int().map(Some);
+    }
+    let mut number = None::<i32>;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        chars.next();
+        number = Some(
+            number
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit as i32),
+        );
+    }
+    Ok(number)
+}
+
+/// Formats a printf-style format string with the variadic arguments stored at `args` in the
+/// robot code's memory.
+///
+/// Supports the standard flags, widths, precisions and length modifiers, and the `d`, `i`, `u`,
+/// `o`, `x`, `X`, `c`, `s`, `p`, `f`, `F`, `e`, `E`, `g`, `G` and `%` conversions. Unknown
+/// conversions are copied to the output unchanged.
+pub fn sprintf(memory: &SharedMemory, format: &str, args: u32) -> Result<String, SimulatorError> {
+    let mut args = Arguments {
+        memory,
+        pointer: args,
+    };
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+
+        let mut spec = Spec::default();
+        while let Some(flag) = chars.next_if(|c| "-0+ #".contains(*c)) {
+            match flag {
+                '-' => spec.left_align = true,
+                '0' => spec.zero_pad = true,
+                '+' => spec.plus_sign = true,
+                ' ' => spec.space_sign = true,
+                _ => spec.alternate = true,
+            }
+        }
+        if let Some(width) = read_number(&mut chars, &mut args)? {
+            // a negative width from `*` means left alignment
+            spec.left_align |= width < 0;
+            spec.width = (width.unsigned_abs() as usize).min(MAX_FIELD_WIDTH);
+        }
+        if chars.next_if_eq(&'.').is_some() {
+            // a negative precision from `*` is ignored
+            spec.precision = match read_number(&mut chars, &mut args)? {
+                Some(precision) => usize::try_from(precision)
+                    .ok()
+                    .map(|precision| precision.min(MAX_FIELD_WIDTH)),
+                None => Some(0),
+            };
+        }
+        spec.zero_pad &= !spec.left_align;
+
+        let mut modifiers = String::new();
+        while let Some(modifier) = chars.next_if(|c| "hljztL".contains(*c)) {
+            modifiers.push(modifier);
+        }
+        // long, size_t and ptrdiff_t are 32 bits
+        let length = match modifiers.as_str() {
+            "hh" => Length::Char,
+            "h" => Length::Short,
+            "ll" | "j" => Length::LongLong,
+            _ => Length::Int,
+        };
+
+        let Some(conversion) = chars.next() else {
+            output.push('%');
+            break;
+        };
+        let formatted = match conversion {
+            '%' => "%".to_string(),
+            'd' | 'i' => {
+                let value = match length {
+                    Length::Char => args.int()? as i8 as i64,
+                    Length::Short => args.int()? as i16 as i64,
+                    Length::Int => args.int()? as i64,
+                    Length::LongLong => args.long_long()?,
+                };
+                let sign = spec.sign(value < 0);
+                spec.integer(sign, value.unsigned_abs().to_string())
+            }
+            'u' | 'o' | 'x' | 'X' => {
+                let value = match length {
+                    Length::Char => args.int()? as u8 as u64,
+                    Length::Short => args.int()? as u16 as u64,
+                    Length::Int => args.int()? as u32 as u64,
+                    Length::LongLong => args.long_long()? as u64,
+                };
+                let (prefix, digits) = match conversion {
+                    'u' => ("", value.to_string()),
+                    'o' if spec.alternate && value != 0 => ("0", format!("{value:o}")),
+                    'o' => ("", format!("{value:o}")),
+                    'x' if spec.alternate && value != 0 => ("0x", format!("{value:x}")),
+                    'x' => ("", format!("{value:x}")),
+                    _ if spec.alternate && value != 0 => ("0X", format!("{value:X}")),
+                    _ => ("", format!("{value:X}")),
+                };
+                spec.integer(prefix, digits)
+            }
+            'p' => {
+                let value = args.int()? as u32;
+                spec.pad("0x", &format!("{value:x}"), false)
+            }
+            'c' => {
+                let value = args.int()? as u8;
+                spec.pad("", &char::from(value).to_string(), false)
+            }
+            's' => {
+                let pointer = args.int()? as u32;
+                let string = if pointer == 0 {
+                    "(null)".to_string()
+                } else {
+                    memory.read_c_str(pointer)?
+                };
+                let string = match spec.precision {
+                    Some(precision) => string.chars().take(precision).collect(),
+                    None => string,
+                };
+                spec.pad("", &string, false)
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let value = if modifiers == "L" {
+                    args.long_double()?
+                } else {
+                    args.double()?
+                };
+                spec.float(value, conversion)
+            }
+            other => format!("%{other}"),
+        };
+        output.push_str(&formatted);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime::{Config, Engine, MemoryType};
+
+    use super::*;
+
+    /// Where the formatted arguments start in memory.
+    const ARGS: u32 = 1024;
+    /// Where strings passed with [`Arg::Str`] are stored.
+    const STRINGS: u32 = 4096;
+
+    enum Arg<'a> {
+        Int(i32),
+        LongLong(i64),
+        Double(f64),
+        LongDouble(u128),
+        Str(&'a str),
+    }
+
+    fn memory() -> SharedMemory {
+        let engine = Engine::new(Config::new().wasm_threads(true)).unwrap();
+        SharedMemory::new(&engine, MemoryType::shared(1, 1)).unwrap()
+    }
+
+    /// Stores the arguments in memory like a variadic call would, and formats them.
+    fn format(format: &str, args: &[Arg]) -> String {
+        let memory = memory();
+        let mut pointer = ARGS as usize;
+        let mut strings = STRINGS as usize;
+        for arg in args {
+            let bytes = match arg {
+                Arg::Int(value) => value.to_le_bytes().to_vec(),
+                Arg::LongLong(value) => value.to_le_bytes().to_vec(),
+                Arg::Double(value) => value.to_le_bytes().to_vec(),
+                Arg::LongDouble(bits) => bits.to_le_bytes().to_vec(),
+                Arg::Str(string) => {
+                    let address = strings as u32;
+                    memory.write_relaxed(strings, string.as_bytes()).unwrap();
+                    memory.write_relaxed(strings + string.len(), &[0]).unwrap();
+                    strings += string.len() + 1;
+                    address.to_le_bytes().to_vec()
+                }
+            };
+            pointer = pointer.next_multiple_of(bytes.len());
+            memory.write_relaxed(pointer, &bytes).unwrap();
+            pointer += bytes.len();
+        }
+        sprintf(&memory, format, ARGS).unwrap()
+    }
+
+    #[test]
+    fn integers() {
+        use Arg::Int;
+        assert_eq!(
+            format(
+                "%d|%5d|%-5d|%05d|%+d|% d",
+                &[Int(-7), Int(42), Int(42), Int(-42), Int(3), Int(3)]
+            ),
+            "-7|   42|42   |-0042|+3| 3"
+        );
+        assert_eq!(format("%u %i", &[Int(-1), Int(12)]), "4294967295 12");
+        assert_eq!(format("%.3d|%.0d", &[Int(7), Int(0)]), "007|");
+        assert_eq!(format("%hhd %hu", &[Int(300), Int(-1)]), "44 65535");
+        assert_eq!(
+            format("%lld %llx", &[Arg::LongLong(-1 << 40), Arg::LongLong(-1)]),
+            "-1099511627776 ffffffffffffffff"
+        );
+    }
+
+    #[test]
+    fn alternate_forms() {
+        use Arg::Int;
+        assert_eq!(
+            format(
+                "%#x %#X %#o %#x %x",
+                &[Int(255), Int(255), Int(8), Int(0), Int(255)]
+            ),
+            "0xff 0XFF 010 0 ff"
+        );
+        assert_eq!(format("%#08x", &[Int(255)]), "0x0000ff");
+        assert_eq!(format("%p", &[Int(0x1234)]), "0x1234");
+    }
+
+    #[test]
+    fn star_width_and_precision() {
+        use Arg::{Double, Int};
+        assert_eq!(format("[%*d]", &[Int(6), Int(42)]), "[    42]");
+        // a negative width means left alignment
+        assert_eq!(format("[%*d]", &[Int(-6), Int(42)]), "[42    ]");
+        assert_eq!(format("%.*f", &[Int(2), Double(1.005)]), "1.00");
+        // a negative precision is ignored
+        assert_eq!(format("%.*f", &[Int(-1), Double(1.5)]), "1.500000");
+        assert_eq!(format("%.*s", &[Int(-1), Arg::Str("hello")]), "hello");
+    }
+
+    #[test]
+    fn floats() {
+        use Arg::Double;
+        assert_eq!(format("%f", &[Double(-2.5)]), "-2.500000");
+        assert_eq!(format("%08.3f", &[Double(-2.5)]), "-002.500");
+        assert_eq!(format("%e", &[Double(12345.678)]), "1.234568e+04");
+        assert_eq!(format("%E", &[Double(0.000123)]), "1.230000E-04");
+        assert_eq!(
+            format("%F %f", &[Double(f64::INFINITY), Double(f64::NAN)]),
+            "INF nan"
+        );
+        assert_eq!(format("%#.0f", &[Double(3.0)]), "3.");
+    }
+
+    #[test]
+    fn general_floats() {
+        use Arg::Double;
+        assert_eq!(format("%g", &[Double(0.0001)]), "0.0001");
+        assert_eq!(format("%g", &[Double(0.00001)]), "1e-05");
+        assert_eq!(format("%g", &[Double(100000.0)]), "100000");
+        assert_eq!(format("%g", &[Double(1000000.0)]), "1e+06");
+        assert_eq!(format("%g", &[Double(123456789.0)]), "1.23457e+08");
+        assert_eq!(format("%.3g", &[Double(1.23456)]), "1.23");
+        assert_eq!(format("%G", &[Double(1.5e-10)]), "1.5E-10");
+        assert_eq!(format("%#g", &[Double(1.0)]), "1.00000");
+        assert_eq!(format("%g", &[Double(0.0)]), "0");
+    }
+
+    #[test]
+    fn strings_and_characters() {
+        use Arg::{Int, Str};
+        assert_eq!(
+            format(
+                "%s|%5s|%-5s|%.2s",
+                &[Str("hi"), Str("hi"), Str("hi"), Str("hello")]
+            ),
+            "hi|   hi|hi   |he"
+        );
+        assert_eq!(format("%s", &[Int(0)]), "(null)");
+        assert_eq!(
+            format("%c%c 100%%", &[Int('o' as i32), Int('k' as i32)]),
+            "ok 100%"
+        );
+        // unknown conversions are copied to the output
+        assert_eq!(format("%k", &[]), "%k");
+    }
+
+    #[test]
+    fn long_double_arguments() {
+        // 2.5 as a quadruple precision float: 1.25 * 2^1
+        let two_and_a_half = (16384u128 << 112) | (1 << 110);
+        assert_eq!(
+            format("%Lf %d", &[Arg::LongDouble(two_and_a_half), Arg::Int(7)]),
+            "2.500000 7"
+        );
+        assert_eq!(f128_to_f64(1 << 127), -0.0);
+        assert_eq!(f128_to_f64(0x7fff << 112), f64::INFINITY);
+        assert!(f128_to_f64((0x7fff << 112) | 1).is_nan());
+        // too large and too small for a double
+        assert_eq!(f128_to_f64(0x7ffe << 112), f64::INFINITY);
+        assert_eq!(f128_to_f64(1 << 112), 0.0);
+        assert_eq!(f128_to_f64((16383 - 1074) << 112), f64::from_bits(1));
+    }
+
+    #[test]
+    fn field_widths_are_limited() {
+        use Arg::{Double, Int};
+        assert_eq!(format("%2147483647d", &[Int(1)]).len(), MAX_FIELD_WIDTH);
+        assert_eq!(
+            format("%*d", &[Int(i32::MIN), Int(1)]).len(),
+            MAX_FIELD_WIDTH
+        );
+        assert_eq!(
+            format("%.2147483647f", &[Double(1.0)]).len(),
+            "1.".len() + MAX_FIELD_WIDTH
+        );
+    }
+
+    #[test]
+    fn arguments_past_the_end_of_memory() {
+        let memory = memory();
+        assert!(sprintf(&memory, "%d", u32::MAX - 1).is_err());
+        assert!(sprintf(&memory, "%f", u32::MAX - 3).is_err());
+        assert!(sprintf(&memory, "%d", 65536).is_err());
+    }
+}
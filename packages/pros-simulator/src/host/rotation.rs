@@ -0,0 +1,256 @@
+use std::{f64::consts::PI, time::Duration};
+
+use pros_simulator_interface::config::{RobotConfig, SmartDevice, TrackingWheel};
+use pros_sys::ROTATION_MINIMUM_DATA_RATE;
+
+use super::{devices::DeviceTable, pose::PoseModel};
+
+/// The data rate of a rotation sensor when it is first plugged in, in milliseconds.
+pub const DEFAULT_DATA_RATE: u32 = 10;
+
+/// A simulated V5 Rotation Sensor.
+///
+/// The sensor's shaft is turned by `RotationInput` simulator messages, plus the tracking wheel
+/// it's attached to (if any). Readings are in centidegrees, like in the PROS API.
+#[derive(Debug, Clone)]
+pub struct RotationSensor {
+    tracking_wheel: Option<TrackingWheel>,
+    /// The shaft's angle in degrees at `input_time`, not counting the tracking wheel.
+    input_position: f64,
+    /// The speed the shaft is being turned at, in degrees per second.
+    input_velocity: f64,
+    input_time: Duration,
+    reversed: bool,
+    /// Added to the position reading, in centidegrees.
+    position_offset: f64,
+    /// Data rate in milliseconds.
+    data_rate: u32,
+}
+
+impl RotationSensor {
+    pub fn new(tracking_wheel: Option<TrackingWheel>) -> Self {
+        Self {
+            tracking_wheel,
+            input_position: 0.0,
+            input_velocity: 0.0,
+            input_time: Duration::ZERO,
+            reversed: false,
+            position_offset: 0.0,
+            data_rate: DEFAULT_DATA_RATE,
+        }
+    }
+
+    fn direction(&self) -> f64 {
+        if self.reversed {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Converts a distance rolled by the tracking wheel into degrees.
+    fn wheel_degrees(wheel: &TrackingWheel, distance: f64) -> f64 {
+        distance / (PI * wheel.diameter) * 360.0
+    }
+
+    /// The number of degrees the tracking wheel has turned the shaft.
+    fn wheel_angle(&self, pose: &PoseModel) -> f64 {
+        self.tracking_wheel.map_or(0.0, |wheel| {
+            Self::wheel_degrees(&wheel, pose.distance_at(&wheel.mount))
+        })
+    }
+
+    /// The angle of the shaft in degrees, ignoring the reversed flag and position offset.
+    fn raw_angle(&self, pose: &PoseModel, now: Duration) -> f64 {
+        let elapsed = now.saturating_sub(self.input_time).as_secs_f64();
+        self.input_position + self.input_velocity * elapsed + self.wheel_angle(pose)
+    }
+
+    /// Turns the shaft to the given angle in degrees, and keeps it turning at the given velocity
+    /// in degrees per second.
+    pub fn set_input(&mut self, pose: &PoseModel, now: Duration, position: f64, velocity: f64) {
+        self.input_position = position - self.wheel_angle(pose);
+        self.input_velocity = velocity;
+        self.input_time = now;
+    }
+
+    /// The total number of centidegrees the sensor has turned.
+    pub fn position(&self, pose: &PoseModel, now: Duration) -> i32 {
+        (self.raw_angle(pose, now) * 100.0 * self.direction() + self.position_offset).round() as i32
+    }
+
+    /// Makes the sensor's position read as the given value, in centidegrees.
+    pub fn set_position(&mut self, pose: &PoseModel, now: Duration, position: i32) {
+        self.position_offset =
+            position as f64 - self.raw_angle(pose, now) * 100.0 * self.direction();
+    }
+
+    /// The angle of the sensor's shaft in centidegrees, from 0 to 36000.
+    pub fn angle(&self, pose: &PoseModel, now: Duration) -> i32 {
+        (self.raw_angle(pose, now) * 100.0 * self.direction()).rem_euclid(36000.0) as i32
+    }
+
+    /// The speed the sensor is turning at, in centidegrees per second.
+    pub fn velocity(&self, pose: &PoseModel) -> i32 {
+        let wheel = self.tracking_wheel.map_or(0.0, |wheel| {
+            Self::wheel_degrees(&wheel, pose.speed_at(&wheel.mount))
+        });
+        ((self.input_velocity + wheel) * 100.0 * self.direction()) as i32
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    pub fn set_reversed(&mut self, reversed: bool) {
+        self.reversed = reversed;
+    }
+
+    /// The data rate in milliseconds.
+    pub fn data_rate(&self) -> u32 {
+        self.data_rate
+    }
+
+    /// Sets the data rate, rounding it down to a multiple of 5 milliseconds.
+    pub fn set_data_rate(&mut self, rate: u32) {
+        self.data_rate = (rate - rate % 5).max(ROTATION_MINIMUM_DATA_RATE);
+    }
+}
+
+/// Stores the state of every V5 Rotation Sensor plugged into the brain.
+pub struct RotationSensors {
+    sensors: DeviceTable<RotationSensor>,
+}
+
+impl RotationSensors {
+    pub fn new(robot_config: &RobotConfig) -> Self {
+        Self {
+            sensors: DeviceTable::new(|port| match robot_config.smart_device(port) {
+                Some(SmartDevice::Rotation { tracking_wheel }) => {
+                    Some(RotationSensor::new(*tracking_wheel))
+                }
+                _ => None,
+            }),
+        }
+    }
+
+    pub fn get(&mut self, port: u32) -> Result<&mut RotationSensor, i32> {
+        self.sensors.get_mut(port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::{
+        config::{SensorMount, SmartPortConfig},
+        RobotVelocity,
+    };
+    use pros_sys::ENODEV;
+
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    /// A tracking wheel that rolls 0.1 meters per turn as the robot drives forwards.
+    fn forward_wheel() -> TrackingWheel {
+        TrackingWheel {
+            diameter: 0.1 / PI,
+            mount: SensorMount::default(),
+        }
+    }
+
+    /// Drives the robot forwards at `speed` meters per second for one second.
+    fn drive_forward(pose: &mut PoseModel, speed: f64) {
+        pose.update(secs(0));
+        pose.set_velocity(RobotVelocity {
+            x: 0.0,
+            y: speed,
+            angular: 0.0,
+        });
+        pose.update(secs(1));
+    }
+
+    #[test]
+    fn input_turns_the_shaft_at_the_given_velocity() {
+        let pose = PoseModel::default();
+        let mut sensor = RotationSensor::new(None);
+        sensor.set_input(&pose, secs(0), 350.0, 20.0);
+        assert_eq!(sensor.position(&pose, secs(0)), 35000);
+        assert_eq!(sensor.position(&pose, secs(1)), 37000);
+        assert_eq!(sensor.angle(&pose, secs(1)), 1000);
+        assert_eq!(sensor.velocity(&pose), 2000);
+    }
+
+    #[test]
+    fn reversing_negates_readings() {
+        let pose = PoseModel::default();
+        let mut sensor = RotationSensor::new(None);
+        sensor.set_input(&pose, secs(0), 90.0, 10.0);
+        sensor.set_reversed(true);
+        assert!(sensor.is_reversed());
+        assert_eq!(sensor.position(&pose, secs(0)), -9000);
+        assert_eq!(sensor.angle(&pose, secs(0)), 27000);
+        assert_eq!(sensor.velocity(&pose), -1000);
+    }
+
+    #[test]
+    fn set_position_offsets_later_readings() {
+        let pose = PoseModel::default();
+        let mut sensor = RotationSensor::new(None);
+        sensor.set_input(&pose, secs(0), 90.0, 10.0);
+        sensor.set_position(&pose, secs(0), 0);
+        assert_eq!(sensor.position(&pose, secs(0)), 0);
+        assert_eq!(sensor.position(&pose, secs(2)), 2000);
+        // the angle still follows the shaft
+        assert_eq!(sensor.angle(&pose, secs(0)), 9000);
+    }
+
+    #[test]
+    fn tracking_wheel_follows_robot() {
+        let mut pose = PoseModel::default();
+        let sensor = RotationSensor::new(Some(forward_wheel()));
+        drive_forward(&mut pose, 0.15);
+        assert_eq!(sensor.position(&pose, secs(1)), 54000);
+        assert_eq!(sensor.angle(&pose, secs(1)), 18000);
+        assert_eq!(sensor.velocity(&pose), 54000);
+    }
+
+    #[test]
+    fn input_replaces_tracking_wheel_position() {
+        let mut pose = PoseModel::default();
+        let mut sensor = RotationSensor::new(Some(forward_wheel()));
+        drive_forward(&mut pose, 0.1);
+        sensor.set_input(&pose, secs(1), 0.0, 0.0);
+        assert_eq!(sensor.position(&pose, secs(1)), 0);
+        // the wheel keeps turning the shaft afterwards
+        pose.update(secs(2));
+        assert_eq!(sensor.position(&pose, secs(2)), 36000);
+    }
+
+    #[test]
+    fn data_rate_is_rounded_down() {
+        let mut sensor = RotationSensor::new(None);
+        assert_eq!(sensor.data_rate(), DEFAULT_DATA_RATE);
+        sensor.set_data_rate(23);
+        assert_eq!(sensor.data_rate(), 20);
+        sensor.set_data_rate(1);
+        assert_eq!(sensor.data_rate(), ROTATION_MINIMUM_DATA_RATE);
+    }
+
+    #[test]
+    fn only_configured_ports_have_sensors() {
+        let mut sensors = RotationSensors::new(&RobotConfig {
+            smart_ports: vec![SmartPortConfig {
+                port: 3,
+                device: SmartDevice::Rotation {
+                    tracking_wheel: None,
+                },
+            }],
+            ..Default::default()
+        });
+        assert!(sensors.get(3).is_ok());
+        assert_eq!(sensors.get(4).unwrap_err(), ENODEV);
+    }
+}
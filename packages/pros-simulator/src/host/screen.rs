@@ -0,0 +1,650 @@
+use std::mem;
+
+use pros_simulator_interface::{ScreenRegion, SimulatorEvent, SCREEN_HEIGHT, SCREEN_WIDTH};
+use pros_sys::EINVAL;
+use tokio::sync::Mutex;
+use wasmtime::{AsContextMut, Table};
+
+use super::{font, task::get_function_pointer};
+use crate::interface::SimulatorInterface;
+
+const WIDTH: i32 = SCREEN_WIDTH as i32;
+const HEIGHT: i32 = SCREEN_HEIGHT as i32;
+/// How many separate changed regions are tracked between updates before they're combined into
+/// one region that covers all of them.
+const MAX_DIRTY_REGIONS: usize = 8;
+const DEFAULT_PEN: u32 = 0x00FFFFFF;
+const DEFAULT_ERASER: u32 = 0x00000000;
+
+/// A rectangle of pixels, with inclusive bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl Rect {
+    /// Creates a rectangle from any two opposite corners.
+    fn new(x0: i32, y0: i32, x1: i32, y1: i32) -> Self {
+        Self {
+            x0: x0.min(x1),
+            y0: y0.min(y1),
+            x1: x0.max(x1),
+            y1: y0.max(y1),
+        }
+    }
+
+    fn screen() -> Self {
+        Self::new(0, 0, WIDTH - 1, HEIGHT - 1)
+    }
+
+    /// The part of the rectangle that's on the screen, if any.
+    fn clip(self) -> Option<Self> {
+        let clipped = Self {
+            x0: self.x0.max(0),
+            y0: self.y0.max(0),
+            x1: self.x1.min(WIDTH - 1),
+            y1: self.y1.min(HEIGHT - 1),
+        };
+        (clipped.x0 <= clipped.x1 && clipped.y0 <= clipped.y1).then_some(clipped)
+    }
+
+    /// Whether the rectangles overlap or share an edge.
+    fn touches(&self, other: &Self) -> bool {
+        self.x0 <= other.x1 + 1
+            && other.x0 <= self.x1 + 1
+            && self.y0 <= other.y1 + 1
+            && other.y0 <= self.y1 + 1
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+
+    fn width(&self) -> i32 {
+        self.x1 - self.x0 + 1
+    }
+}
+
+/// The size and alignment of text drawn with `screen_print`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    Small,
+    Medium,
+    Large,
+    MediumCenter,
+    LargeCenter,
+}
+
+impl TryFrom<i32> for TextFormat {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Small),
+            1 => Ok(Self::Medium),
+            2 => Ok(Self::Large),
+            3 => Ok(Self::MediumCenter),
+            4 => Ok(Self::LargeCenter),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+impl TextFormat {
+    /// How many screen pixels each pixel of the font takes up.
+    fn scale(self) -> usize {
+        match self {
+            Self::Small => 1,
+            Self::Medium | Self::MediumCenter => 2,
+            Self::Large | Self::LargeCenter => 3,
+        }
+    }
+
+    /// The distance between lines of text, in pixels.
+    fn line_height(self) -> i32 {
+        match self {
+            Self::Small => 12,
+            Self::Medium | Self::MediumCenter => 20,
+            Self::Large | Self::LargeCenter => 30,
+        }
+    }
+
+    fn centered(self) -> bool {
+        matches!(self, Self::MediumCenter | Self::LargeCenter)
+    }
+}
+
+/// What the touch screen is doing, as in `last_touch_e_t`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TouchEvent {
+    #[default]
+    Released,
+    Pressed,
+    Held,
+}
+
+impl TryFrom<i32> for TouchEvent {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Released),
+            1 => Ok(Self::Pressed),
+            2 => Ok(Self::Held),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+impl From<TouchEvent> for i32 {
+    fn from(event: TouchEvent) -> Self {
+        match event {
+            TouchEvent::Released => 0,
+            TouchEvent::Pressed => 1,
+            TouchEvent::Held => 2,
+        }
+    }
+}
+
+/// The values in `screen_touch_status_s_t`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TouchStatus {
+    pub event: TouchEvent,
+    /// Position of the last touch in pixels.
+    pub x: i16,
+    pub y: i16,
+    /// How many times the screen has been pressed.
+    pub press_count: i32,
+    /// How many times the screen has been released.
+    pub release_count: i32,
+}
+
+/// The V5 Brain's 480x272 touch screen.
+///
+/// Drawing functions change the screen's framebuffer straight away, and the regions that changed
+/// are sent to the simulator interface in a [`SimulatorEvent::ScreenUpdated`] event on the next
+/// [`flush`](Self::flush). Anything drawn off the edge of the screen is cut off.
+///
+/// Coordinates are `i16`s, like in the PROS API, so that no shape is large enough for the
+/// drawing math to overflow.
+pub struct Screen {
+    /// Colors in `0x00RRGGBB` format, row by row from the top left corner.
+    pixels: Vec<u32>,
+    pen: u32,
+    eraser: u32,
+    dirty: Vec<Rect>,
+    touch: TouchStatus,
+    touch_callbacks: Vec<(TouchEvent, u32)>,
+    interface: SimulatorInterface,
+}
+
+impl Screen {
+    pub fn new(interface: SimulatorInterface) -> Self {
+        Self {
+            pixels: vec![DEFAULT_ERASER; (WIDTH * HEIGHT) as usize],
+            pen: DEFAULT_PEN,
+            eraser: DEFAULT_ERASER,
+            dirty: Vec::new(),
+            touch: TouchStatus::default(),
+            touch_callbacks: Vec::new(),
+            interface,
+        }
+    }
+
+    /// Colors in `0x00RRGGBB` format, row by row from the top left corner.
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn pen(&self) -> u32 {
+        self.pen
+    }
+
+    pub fn set_pen(&mut self, color: u32) {
+        self.pen = color;
+    }
+
+    pub fn eraser(&self) -> u32 {
+        self.eraser
+    }
+
+    pub fn set_eraser(&mut self, color: u32) {
+        self.eraser = color;
+    }
+
+    /// Records that part of the screen changed, so that it's sent in the next update.
+    fn mark_dirty(&mut self, rect: Rect) {
+        let Some(mut rect) = rect.clip() else {
+            return;
+        };
+        // merge with any regions it touches, which might then touch other regions
+        while let Some(index) = self.dirty.iter().position(|other| other.touches(&rect)) {
+            rect = rect.union(&self.dirty.swap_remove(index));
+        }
+        self.dirty.push(rect);
+        if self.dirty.len() > MAX_DIRTY_REGIONS {
+            let bounds = self
+                .dirty
+                .iter()
+                .fold(rect, |bounds, other| bounds.union(other));
+            self.dirty = vec![bounds];
+        }
+    }
+
+    /// Sets a pixel without marking it as changed, ignoring pixels off the screen.
+    fn plot(&mut self, x: i32, y: i32, color: u32) {
+        if (0..WIDTH).contains(&x) && (0..HEIGHT).contains(&y) {
+            self.pixels[(y * WIDTH + x) as usize] = color;
+        }
+    }
+
+    fn fill(&mut self, rect: Rect, color: u32) {
+        let Some(clipped) = rect.clip() else {
+            return;
+        };
+        for y in clipped.y0..=clipped.y1 {
+            let start = (y * WIDTH + clipped.x0) as usize;
+            self.pixels[start..start + clipped.width() as usize].fill(color);
+        }
+        self.mark_dirty(clipped);
+    }
+
+    /// Fills the whole screen with the eraser color.
+    pub fn erase(&mut self) {
+        self.fill(Rect::screen(), self.eraser);
+    }
+
+    /// Colors one pixel with the pen color, or the eraser color if `erase` is set.
+    pub fn draw_pixel(&mut self, x: i16, y: i16, erase: bool) {
+        let color = if erase { self.eraser } else { self.pen };
+        let (x, y) = (x.into(), y.into());
+        self.plot(x, y, color);
+        self.mark_dirty(Rect::new(x, y, x, y));
+    }
+
+    /// Draws a 1 pixel wide line between two points, including both ends.
+    pub fn draw_line(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, erase: bool) {
+        let color = if erase { self.eraser } else { self.pen };
+        let (x0, y0, x1, y1) = (x0.into(), y0.into(), x1.into(), y1.into());
+        if Rect::new(x0, y0, x1, y1).clip().is_none() {
+            return;
+        }
+        // Bresenham's line algorithm
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+        let mut was_on_screen = false;
+        loop {
+            let on_screen = (0..WIDTH).contains(&x) && (0..HEIGHT).contains(&y);
+            if was_on_screen && !on_screen {
+                // a line can't come back onto the screen once it has left it
+                break;
+            }
+            was_on_screen = on_screen;
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled_error = 2 * error;
+            if doubled_error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled_error <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+        self.mark_dirty(Rect::new(x0, y0, x1, y1));
+    }
+
+    /// Draws the outline of a rectangle with the pen color, or the eraser color if `erase` is
+    /// set.
+    pub fn draw_rect(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, erase: bool) {
+        let color = if erase { self.eraser } else { self.pen };
+        let rect = Rect::new(x0.into(), y0.into(), x1.into(), y1.into());
+        self.fill(Rect::new(rect.x0, rect.y0, rect.x1, rect.y0), color);
+        self.fill(Rect::new(rect.x0, rect.y1, rect.x1, rect.y1), color);
+        self.fill(Rect::new(rect.x0, rect.y0, rect.x0, rect.y1), color);
+        self.fill(Rect::new(rect.x1, rect.y0, rect.x1, rect.y1), color);
+    }
+
+    /// Fills a rectangle with the pen color, or the eraser color if `erase` is set.
+    pub fn fill_rect(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, erase: bool) {
+        let color = if erase { self.eraser } else { self.pen };
+        self.fill(Rect::new(x0.into(), y0.into(), x1.into(), y1.into()), color);
+    }
+
+    /// Draws the outline of a circle with the pen color, or the eraser color if `erase` is set.
+    pub fn draw_circle(&mut self, x: i16, y: i16, radius: i16, erase: bool) {
+        let color = if erase { self.eraser } else { self.pen };
+        let (x, y, radius) = (i32::from(x), i32::from(y), i32::from(radius).abs());
+        let bounds = Rect::new(x - radius, y - radius, x + radius, y + radius);
+        if bounds.clip().is_none() {
+            return;
+        }
+        // midpoint circle algorithm, drawing all 8 octants at once
+        let (mut dx, mut dy, mut error) = (radius, 0, 1 - radius);
+        while dx >= dy {
+            for (px, py) in [
+                (dx, dy),
+                (dy, dx),
+                (-dy, dx),
+                (-dx, dy),
+                (-dx, -dy),
+                (-dy, -dx),
+                (dy, -dx),
+                (dx, -dy),
+            ] {
+                self.plot(x + px, y + py, color);
+            }
+            dy += 1;
+            if error < 0 {
+                error += 2 * dy + 1;
+            } else {
+                dx -= 1;
+                error += 2 * (dy - dx) + 1;
+            }
+        }
+        self.mark_dirty(bounds);
+    }
+
+    /// Fills a circle with the pen color, or the eraser color if `erase` is set.
+    pub fn fill_circle(&mut self, x: i16, y: i16, radius: i16, erase: bool) {
+        let color = if erase { self.eraser } else { self.pen };
+        let (x, y, radius) = (i32::from(x), i32::from(y), i32::from(radius).abs());
+        // only the rows that are on the screen
+        for dy in (-radius).max(-y)..=radius.min(HEIGHT - 1 - y) {
+            let half_width = ((radius * radius - dy * dy) as f64).sqrt().round() as i32;
+            self.fill(
+                Rect::new(x - half_width, y + dy, x + half_width, y + dy),
+                color,
+            );
+        }
+    }
+
+    /// Moves the pixels in a rectangle up by `lines` pixels, or down if it's negative, filling
+    /// the space left behind with the eraser color.
+    pub fn scroll_area(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, lines: i16) {
+        let Some(area) = Rect::new(x0.into(), y0.into(), x1.into(), y1.into()).clip() else {
+            return;
+        };
+        let lines = i32::from(lines);
+        let width = area.width() as usize;
+        let rows: Box<dyn Iterator<Item = i32>> = if lines >= 0 {
+            Box::new(area.y0..=area.y1)
+        } else {
+            Box::new((area.y0..=area.y1).rev())
+        };
+        for y in rows {
+            let destination = (y * WIDTH + area.x0) as usize;
+            let source_y = y + lines;
+            if (area.y0..=area.y1).contains(&source_y) {
+                let source = (source_y * WIDTH + area.x0) as usize;
+                self.pixels.copy_within(source..source + width, destination);
+            } else {
+                self.pixels[destination..destination + width].fill(self.eraser);
+            }
+        }
+        self.mark_dirty(area);
+    }
+
+    /// How many pixels of the buffer passed to [`copy_area`](Self::copy_area) are drawn on the
+    /// screen, counting from the start of the buffer to the last pixel that's used. Returns
+    /// `None` if that doesn't fit in a `usize`.
+    pub fn copy_area_len(x0: i16, y0: i16, x1: i16, y1: i16, stride: usize) -> Option<usize> {
+        let rect = Rect::new(x0.into(), y0.into(), x1.into(), y1.into());
+        let Some(clipped) = rect.clip() else {
+            return Some(0);
+        };
+        let rows = (clipped.y1 - rect.y0) as usize;
+        let columns = (clipped.x1 - rect.x0 + 1) as usize;
+        rows.checked_mul(stride)?.checked_add(columns)
+    }
+
+    /// Copies pixels onto a rectangle of the screen. `stride` is the number of pixels from the
+    /// start of one row of `pixels` to the next. Pixels missing from the end of the buffer are
+    /// left unchanged.
+    pub fn copy_area(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, pixels: &[u32], stride: usize) {
+        let rect = Rect::new(x0.into(), y0.into(), x1.into(), y1.into());
+        let Some(clipped) = rect.clip() else {
+            return;
+        };
+        for y in clipped.y0..=clipped.y1 {
+            for x in clipped.x0..=clipped.x1 {
+                let (row, column) = ((y - rect.y0) as usize, (x - rect.x0) as usize);
+                let index = row
+                    .checked_mul(stride)
+                    .and_then(|start| start.checked_add(column));
+                if let Some(color) = index.and_then(|index| pixels.get(index)) {
+                    self.plot(x, y, *color);
+                }
+            }
+        }
+        self.mark_dirty(clipped);
+    }
+
+    /// Draws a line of text with the pen color, with its top left corner at the given point.
+    /// Centered formats center the text on `x` instead.
+    pub fn print_at(&mut self, format: TextFormat, x: i16, y: i16, text: &str) {
+        let (x, y) = (i32::from(x), i32::from(y));
+        let scale = format.scale();
+        let width = i32::try_from(font::text_width(text, scale)).unwrap_or(i32::MAX);
+        let x = if format.centered() { x - width / 2 } else { x };
+        let pen = self.pen;
+        font::draw_text(text, scale, |dx, dy| {
+            let dx = i32::try_from(dx).unwrap_or(i32::MAX);
+            self.plot(x.saturating_add(dx), y + dy as i32, pen);
+        });
+        let height = (font::CHAR_HEIGHT * scale) as i32;
+        self.mark_dirty(Rect::new(x, y, x.saturating_add(width - 1), y + height - 1));
+    }
+
+    /// Draws a line of text with the pen color on the given line of the screen, where the
+    /// height of a line depends on the text size. Centered formats center the text across the
+    /// screen.
+    pub fn print(&mut self, format: TextFormat, line: i16, text: &str) {
+        let x = if format.centered() { WIDTH / 2 } else { 0 };
+        // lines that start below the bottom of the screen can't be seen anyway
+        let y = (i32::from(line) * format.line_height()).clamp(i16::MIN.into(), HEIGHT);
+        self.print_at(format, x as i16, y as i16, text);
+    }
+
+    pub fn touch_status(&self) -> TouchStatus {
+        self.touch
+    }
+
+    /// Registers a function in the robot code's function table to be called when the screen
+    /// is touched in the given way.
+    pub fn register_touch_callback(&mut self, callback: u32, event: TouchEvent) {
+        self.touch_callbacks.push((event, callback));
+    }
+
+    /// Sends the regions of the screen that changed since the last update to the simulator
+    /// interface, if there are any.
+    pub fn flush(&mut self) {
+        if self.dirty.is_empty() {
+            return;
+        }
+        let regions = mem::take(&mut self.dirty)
+            .into_iter()
+            .map(|rect| ScreenRegion {
+                x: rect.x0 as u32,
+                y: rect.y0 as u32,
+                width: rect.width() as u32,
+                height: (rect.y1 - rect.y0 + 1) as u32,
+                pixels: (rect.y0..=rect.y1)
+                    .flat_map(|y| {
+                        let start = (y * WIDTH + rect.x0) as usize;
+                        self.pixels[start..start + rect.width() as usize].to_vec()
+                    })
+                    .collect(),
+            })
+            .collect();
+        self.interface
+            .send(SimulatorEvent::ScreenUpdated { regions });
+    }
+
+    /// Touches the screen at the given position, or releases it. Calls the robot code's touch
+    /// callbacks for the resulting event.
+    pub async fn touch(
+        screen: &Mutex<Self>,
+        mut store: impl AsContextMut<Data = impl Send>,
+        callback_table: Table,
+        x: i16,
+        y: i16,
+        pressed: bool,
+    ) -> anyhow::Result<()> {
+        let mut screen = screen.lock().await;
+        let touch = &mut screen.touch;
+        let event = match (pressed, touch.event) {
+            (false, TouchEvent::Released) => return Ok(()),
+            (false, _) => {
+                touch.release_count += 1;
+                TouchEvent::Released
+            }
+            (true, TouchEvent::Released) => {
+                touch.press_count += 1;
+                TouchEvent::Pressed
+            }
+            (true, _) => TouchEvent::Held,
+        };
+        touch.event = event;
+        touch.x = x;
+        touch.y = y;
+        let callbacks = screen
+            .touch_callbacks
+            .iter()
+            .filter(|(callback_event, _)| *callback_event == event)
+            .map(|(_, callback)| *callback)
+            .collect::<Vec<_>>();
+        drop(screen);
+
+        for callback in callbacks {
+            let callback = get_function_pointer::<(), ()>(&mut store, callback_table, callback)?;
+            callback.call_async(&mut store, ()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    const RED: u32 = 0x00FF0000;
+
+    fn screen() -> (Screen, Arc<Mutex<Vec<SimulatorEvent>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let interface = {
+            let events = events.clone();
+            SimulatorInterface::from(move |event| events.lock().unwrap().push(event))
+        };
+        let mut screen = Screen::new(interface);
+        screen.set_pen(RED);
+        (screen, events)
+    }
+
+    fn pixel(screen: &Screen, x: i32, y: i32) -> u32 {
+        screen.pixels()[(y * WIDTH + x) as usize]
+    }
+
+    #[test]
+    fn fill_rect_is_clipped() {
+        let (mut screen, events) = screen();
+        screen.fill_rect(-10, -10, 5, 5, false);
+        assert_eq!(pixel(&screen, 0, 0), RED);
+        assert_eq!(pixel(&screen, 5, 5), RED);
+        assert_eq!(pixel(&screen, 6, 6), DEFAULT_ERASER);
+
+        screen.flush();
+        let events = events.lock().unwrap();
+        let [SimulatorEvent::ScreenUpdated { regions }] = events.as_slice() else {
+            panic!("expected one screen update, got {events:?}");
+        };
+        assert_eq!(regions.len(), 1);
+        let region = &regions[0];
+        assert_eq!(
+            (region.x, region.y, region.width, region.height),
+            (0, 0, 6, 6)
+        );
+        assert_eq!(region.pixels.len(), 36);
+    }
+
+    #[test]
+    fn draw_line_includes_both_ends() {
+        let (mut screen, _) = screen();
+        screen.draw_line(10, 10, 20, 15, false);
+        assert_eq!(pixel(&screen, 10, 10), RED);
+        assert_eq!(pixel(&screen, 20, 15), RED);
+        assert_eq!(pixel(&screen, 15, 10), DEFAULT_ERASER);
+    }
+
+    #[test]
+    fn extreme_coordinates_do_not_overflow() {
+        let (mut screen, _) = screen();
+        screen.draw_line(i16::MIN, i16::MIN, i16::MAX, i16::MAX, false);
+        screen.draw_line(i16::MAX, i16::MIN, i16::MIN, i16::MAX, false);
+        screen.draw_rect(i16::MIN, i16::MIN, i16::MAX, i16::MAX, false);
+        screen.draw_circle(0, 0, i16::MIN, false);
+        screen.draw_circle(i16::MAX, i16::MAX, i16::MAX, false);
+        screen.fill_circle(i16::MIN, i16::MAX, i16::MIN, false);
+        screen.fill_circle(100, 100, i16::MAX, false);
+        screen.scroll_area(i16::MIN, i16::MIN, i16::MAX, i16::MAX, i16::MIN);
+        screen.scroll_area(i16::MIN, i16::MIN, i16::MAX, i16::MAX, i16::MAX);
+        screen.copy_area(i16::MIN, i16::MIN, i16::MAX, i16::MAX, &[RED], usize::MAX);
+        screen.print_at(TextFormat::LargeCenter, i16::MAX, i16::MAX, "overflow");
+        screen.print_at(TextFormat::Small, i16::MIN, i16::MIN, "overflow");
+        screen.print(TextFormat::Large, i16::MAX, "overflow");
+        screen.print(TextFormat::Large, i16::MIN, "overflow");
+        screen.flush();
+    }
+
+    #[test]
+    fn filled_circle_covers_the_screen() {
+        let (mut screen, _) = screen();
+        screen.fill_circle(240, 136, i16::MAX, false);
+        assert!(screen.pixels().iter().all(|&color| color == RED));
+    }
+
+    #[test]
+    fn copy_area_len_is_clipped() {
+        // only the on-screen rows and columns of the buffer are read
+        assert_eq!(Screen::copy_area_len(0, 0, 9, 9, 10), Some(100));
+        assert_eq!(Screen::copy_area_len(470, 0, 499, 1, 30), Some(40));
+        assert_eq!(Screen::copy_area_len(-100, -100, -1, -1, 100), Some(0));
+        assert_eq!(
+            Screen::copy_area_len(i16::MIN, i16::MIN, i16::MAX, i16::MAX, usize::MAX),
+            None
+        );
+    }
+
+    #[test]
+    fn copy_area_uses_stride() {
+        let (mut screen, _) = screen();
+        let buffer = [1, 2, 0, 3, 4, 0];
+        screen.copy_area(-1, 0, 0, 1, &buffer, 3);
+        assert_eq!(pixel(&screen, 0, 0), 2);
+        assert_eq!(pixel(&screen, 0, 1), 4);
+    }
+
+    #[test]
+    fn scroll_area_moves_pixels_up() {
+        let (mut screen, _) = screen();
+        screen.draw_pixel(0, 10, false);
+        screen.scroll_area(0, 0, 9, 19, 4);
+        assert_eq!(pixel(&screen, 0, 6), RED);
+        assert_eq!(pixel(&screen, 0, 10), DEFAULT_ERASER);
+    }
+}
@@ -2,20 +2,38 @@ use std::{
     collections::{HashMap, HashSet},
     future::Future,
     pin::Pin,
-    sync::Arc,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc,
+    },
     task::Poll,
+    time::Duration,
 };
 
-use anyhow::{bail, Context};
-use pros_simulator_interface::SimulatorEvent;
+use anyhow::bail;
+use pros_simulator_interface::{SimulationSpeed, SimulatorEvent, SimulatorMessage};
+use pros_sys::{
+    error::EINVAL, E_NOTIFY_ACTION_BITS, E_NOTIFY_ACTION_INCR, E_NOTIFY_ACTION_NONE,
+    E_NOTIFY_ACTION_NO_OWRITE, E_NOTIFY_ACTION_OWRITE,
+};
+use snafu::OptionExt;
 use tokio::sync::{Mutex, MutexGuard};
 use wasmtime::{
     AsContextMut, Caller, Engine, Func, Instance, Linker, Module, SharedMemory, Store, Table,
-    TypedFunc, WasmParams,
+    TypedFunc, WasmBacktrace, WasmParams, WasmResults,
 };
 
-use super::{memory::SharedMemoryExt, thread_local::TaskStorage, Host, HostCtx, WasmAllocator};
-use crate::{api::configure_api, interface::SimulatorInterface};
+use super::{
+    clock::{Clock, Pacer},
+    memory::SharedMemoryExt,
+    thread_local::TaskStorage,
+    Host, HostCtx, WasmAllocator,
+};
+use crate::{
+    api::configure_api,
+    error::{InvalidEntrypointSnafu, InvalidPointerSnafu, MissingEntrypointSnafu, SimulatorError},
+    interface::SimulatorInterface,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TaskState {
@@ -25,18 +43,138 @@ pub enum TaskState {
     Ready,
     /// Finished executing and will be removed from the task pool
     Finished,
+    /// Waiting for the virtual clock to reach the task's wake time, for a notification, or for
+    /// another task to finish
     Blocked,
-    // Suspended,
+    /// Stopped by `task_suspend` and won't run again until it's resumed
+    Suspended,
     Deleted,
 }
 
 pub const TASK_PRIORITIES: u32 = 16;
 
+/// What sending a notification does to the receiving task's notification value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyAction {
+    /// Leaves the value unchanged.
+    None,
+    /// Sets the bits of the value that are set in the sent value.
+    SetBits,
+    /// Increments the value, ignoring the sent value.
+    Increment,
+    /// Replaces the value with the sent value.
+    Overwrite,
+    /// Replaces the value with the sent value, unless a notification is already pending.
+    NoOverwrite,
+}
+
+impl TryFrom<u32> for NotifyAction {
+    type Error = i32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            E_NOTIFY_ACTION_NONE => Ok(Self::None),
+            E_NOTIFY_ACTION_BITS => Ok(Self::SetBits),
+            E_NOTIFY_ACTION_INCR => Ok(Self::Increment),
+            E_NOTIFY_ACTION_OWRITE => Ok(Self::Overwrite),
+            E_NOTIFY_ACTION_NO_OWRITE => Ok(Self::NoOverwrite),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+/// A task's notification value, and whether a notification has been sent that the task hasn't
+/// taken yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Notification {
+    value: u32,
+    pending: bool,
+}
+
+impl Notification {
+    /// Performs a notification's action on the value. Returns the value from before the action
+    /// was performed, and whether it was performed (which only fails for
+    /// [`NotifyAction::NoOverwrite`]).
+    fn notify(&mut self, value: u32, action: NotifyAction) -> (u32, bool) {
+        let previous = self.value;
+        match action {
+            NotifyAction::None => {}
+            NotifyAction::SetBits => self.value |= value,
+            NotifyAction::Increment => self.value = self.value.wrapping_add(1),
+            NotifyAction::Overwrite => self.value = value,
+            NotifyAction::NoOverwrite if self.pending => return (previous, false),
+            NotifyAction::NoOverwrite => self.value = value,
+        }
+        self.pending = true;
+        (previous, true)
+    }
+
+    /// Takes the value, either clearing it or decrementing it depending on `clear_on_exit`.
+    /// Returns the value from before it was taken.
+    fn take(&mut self, clear_on_exit: bool) -> u32 {
+        let value = self.value;
+        self.value = if clear_on_exit {
+            0
+        } else {
+            value.saturating_sub(1)
+        };
+        self.pending = false;
+        value
+    }
+
+    /// Marks the notification as taken without changing the value. Returns whether a
+    /// notification was pending.
+    fn clear(&mut self) -> bool {
+        std::mem::replace(&mut self.pending, false)
+    }
+}
+
+/// Looks up a function pointer from robot code in its indirect function table.
+pub fn get_function_pointer<Params: WasmParams, Results: WasmResults>(
+    mut store: impl AsContextMut,
+    table: Table,
+    pointer: u32,
+) -> Result<TypedFunc<Params, Results>, SimulatorError> {
+    table
+        .get(&mut store, pointer)
+        .and_then(|function| function.funcref().flatten().copied())
+        .context(InvalidPointerSnafu { pointer })?
+        .typed(&store)
+        .ok()
+        .context(InvalidEntrypointSnafu {
+            name: format!("function pointer {pointer:#x}"),
+            signature: format!(
+                "{} -> {}",
+                std::any::type_name::<Params>(),
+                std::any::type_name::<Results>()
+            ),
+        })
+}
+
+/// How often the scheduler checks for new messages while paused.
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How much virtual time passes each time the scheduler picks a task while some are ready to run.
+/// Without this, tasks that yield in a loop waiting for a sleeping task would never let it wake.
+const CONTEXT_SWITCH_TIME: Duration = Duration::from_micros(1);
+
+/// Whether the scheduler is allowed to run tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunState {
+    Running,
+    Paused,
+    /// Running until the virtual clock reaches the given time, then pausing.
+    Stepping {
+        until: Duration,
+    },
+}
+
 pub struct TaskOptions {
     priority: u32,
     store: Store<Host>,
     entrypoint: TypedFunc<(), ()>,
     name: Option<String>,
+    system: bool,
 }
 
 impl TaskOptions {
@@ -60,21 +198,12 @@ impl TaskOptions {
         Self::new_closure(pool, host, move |mut caller| {
             let args = args.clone();
             Box::new(async move {
-                let entrypoint = {
+                let table = {
                     let task_handle = caller.current_task().await;
                     let current_task = task_handle.lock().await;
-                    current_task
-                        .indirect_call_table
-                        .get(&mut caller, task_start)
-                        .context("Task entrypoint is out of bounds")?
+                    current_task.indirect_call_table
                 };
-
-                let entrypoint = entrypoint
-                    .funcref()
-                    .context("Task entrypoint is not a function")?
-                    .context("Task entrypoint is NULL")?
-                    .typed::<P, ()>(&mut caller)
-                    .context("Task entrypoint has invalid signature")?;
+                let entrypoint = get_function_pointer::<P, ()>(&mut caller, table, task_start)?;
 
                 entrypoint
                     .call_async(&mut caller, args.lock().await.take().unwrap())
@@ -115,6 +244,7 @@ impl TaskOptions {
             entrypoint,
             store,
             name: None,
+            system: false,
         })
     }
 
@@ -132,12 +262,16 @@ impl TaskOptions {
                     this_task.instance
                 };
 
-                let func = instance.get_func(&mut caller, func_name).with_context(|| {
-                    format!("entrypoint missing: expected {func_name} to be defined")
-                })?;
+                let func = instance
+                    .get_func(&mut caller, func_name)
+                    .context(MissingEntrypointSnafu { name: func_name })?;
                 let func = func
                     .typed(&mut caller)
-                    .with_context(|| format!("invalid {func_name} signature: expected () -> ()"))?;
+                    .ok()
+                    .context(InvalidEntrypointSnafu {
+                        name: func_name,
+                        signature: "() -> ()",
+                    })?;
 
                 func.call_async(&mut caller, ()).await
             })
@@ -154,6 +288,13 @@ impl TaskOptions {
         self.priority = priority;
         self
     }
+
+    /// Marks the task as part of the simulator rather than the robot code, so that it isn't
+    /// included in [`TaskPool::task_count`].
+    pub fn system(mut self) -> Self {
+        self.system = true;
+        self
+    }
 }
 
 pub struct Task {
@@ -163,59 +304,95 @@ pub struct Task {
     task_impl: TypedFunc<(), ()>,
     priority: u32,
     errno: Option<Errno>,
+    /// The task's errno, kept here until the robot code asks where errno is and it's allocated.
+    errno_value: i32,
     pub instance: Instance,
     allocator: WasmAllocator,
     pub indirect_call_table: Table,
     store: Arc<Mutex<Store<Host>>>,
     state: TaskState,
+    wake_time: Option<Duration>,
     marked_for_delete: bool,
+    notification: Notification,
+    /// Whether the task is blocked in `task_notify_take`, and should be woken by a notification.
+    waiting_for_notification: bool,
+    /// The task that this task is blocked in `task_join` waiting for.
+    joining: Option<u32>,
 }
 
 impl Task {
-    fn new(
+    async fn new(
         id: u32,
         name: String,
         mut store: Store<Host>,
         instance: Instance,
         task_impl: TypedFunc<(), ()>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, SimulatorError> {
+        let allocator = WasmAllocator::new(&mut store, &instance)?;
+        let indirect_call_table = instance
+            .get_table(&mut store, "__indirect_function_table")
+            .context(MissingEntrypointSnafu {
+                name: "__indirect_function_table",
+            })?;
+
+        Ok(Self {
             id,
             name,
             local_storage: None,
             task_impl,
             priority: 0,
             errno: None,
-            allocator: WasmAllocator::new(&mut store, &instance),
-            indirect_call_table: instance
-                .get_table(&mut store, "__indirect_function_table")
-                .unwrap(),
+            errno_value: 0,
+            allocator,
+            indirect_call_table,
             instance,
             store: Arc::new(Mutex::new(store)),
             state: TaskState::Ready,
+            wake_time: None,
             marked_for_delete: false,
-        }
+            notification: Notification::default(),
+            waiting_for_notification: false,
+            joining: None,
+        })
     }
 
+    /// The task's thread-local storage, which is allocated with `allocator` the first time it's
+    /// used.
     pub async fn local_storage(
         &mut self,
         store: impl AsContextMut<Data = impl Send>,
-    ) -> TaskStorage {
+        allocator: &WasmAllocator,
+    ) -> Result<TaskStorage, SimulatorError> {
         if let Some(storage) = self.local_storage {
-            return storage;
+            return Ok(storage);
         }
-        let storage = TaskStorage::new(store, &self.allocator).await;
+        let storage = TaskStorage::new(store, allocator).await?;
         self.local_storage = Some(storage);
-        storage
+        Ok(storage)
     }
 
-    pub async fn errno(&mut self, store: impl AsContextMut<Data = impl Send>) -> Errno {
+    /// Where the task's errno is stored in the robot code's memory. It's allocated the first time
+    /// this is called, so `store` must belong to this task.
+    pub async fn errno(
+        &mut self,
+        store: impl AsContextMut<Data = impl Send>,
+        memory: &SharedMemory,
+    ) -> Result<Errno, SimulatorError> {
         if let Some(errno) = self.errno {
-            return errno;
+            return Ok(errno);
         }
-        let errno = Errno::new(store, &self.allocator).await;
+        let errno = Errno::new(store, &self.allocator).await?;
+        errno.set(memory, self.errno_value);
         self.errno = Some(errno);
-        errno
+        Ok(errno)
+    }
+
+    /// Sets the task's errno, without allocating it if the robot code hasn't used it yet.
+    pub fn set_errno(&mut self, memory: &SharedMemory, code: i32) {
+        match self.errno {
+            Some(errno) => errno.set(memory, code),
+            None => self.errno_value = code,
+        }
     }
 
     pub fn id(&self) -> u32 {
@@ -235,6 +412,12 @@ impl Task {
         self.state
     }
 
+    /// The task's priority, from 0 to [`TASK_PRIORITIES`] - 1. Note that PROS priorities are one
+    /// higher than this.
+    pub fn priority(&self) -> u32 {
+        self.priority
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -242,6 +425,48 @@ impl Task {
     pub fn allocator(&self) -> WasmAllocator {
         self.allocator.clone()
     }
+
+    /// Sends a notification to the task, waking it if it's waiting for one. Returns the
+    /// notification value from before the action was performed, and whether the action was
+    /// performed (which only fails for [`NotifyAction::NoOverwrite`]).
+    pub fn notify(&mut self, value: u32, action: NotifyAction) -> (u32, bool) {
+        let (previous, performed) = self.notification.notify(value, action);
+        if !performed {
+            return (previous, false);
+        }
+
+        if self.waiting_for_notification {
+            self.waiting_for_notification = false;
+            self.state = TaskState::Ready;
+            self.wake_time = None;
+        }
+        (previous, true)
+    }
+
+    /// Stops the task from running until [`resume`](Self::resume) is called. Anything the task
+    /// was blocked on is forgotten, so a task that was waiting when it was suspended will check
+    /// whether it's done waiting as soon as it's resumed.
+    fn suspend(&mut self) {
+        self.state = TaskState::Suspended;
+        self.wake_time = None;
+        self.waiting_for_notification = false;
+        self.joining = None;
+    }
+
+    /// Lets a suspended task run again. Returns whether it was suspended.
+    fn resume(&mut self) -> bool {
+        if self.state != TaskState::Suspended {
+            return false;
+        }
+        self.state = TaskState::Ready;
+        true
+    }
+
+    /// Marks the task as having no pending notification, without changing its notification
+    /// value. Returns whether a notification was pending.
+    pub fn clear_notification(&mut self) -> bool {
+        self.notification.clear()
+    }
 }
 impl PartialEq for Task {
     fn eq(&self, other: &Self) -> bool {
@@ -255,6 +480,8 @@ pub type TaskHandle = Arc<Mutex<Task>>;
 pub struct TaskPool {
     pool: HashMap<u32, TaskHandle>,
     deleted_tasks: HashSet<u32>,
+    /// Tasks that were spawned by the simulator, like the system daemon.
+    system_tasks: HashSet<u32>,
     newest_task_id: u32,
     current_task: Option<TaskHandle>,
     engine: Engine,
@@ -263,6 +490,9 @@ pub struct TaskPool {
     yield_pending: bool,
     shutdown_pending: bool,
     interface: SimulatorInterface,
+    clock: Clock,
+    pacer: Pacer,
+    run_state: RunState,
 }
 
 impl TaskPool {
@@ -270,10 +500,12 @@ impl TaskPool {
         engine: Engine,
         shared_memory: SharedMemory,
         interface: SimulatorInterface,
+        clock: Clock,
     ) -> anyhow::Result<Self> {
         Ok(Self {
             pool: HashMap::new(),
             deleted_tasks: HashSet::new(),
+            system_tasks: HashSet::new(),
             newest_task_id: 0,
             current_task: None,
             engine,
@@ -282,6 +514,9 @@ impl TaskPool {
             yield_pending: false,
             shutdown_pending: false,
             interface,
+            clock,
+            pacer: Pacer::new(SimulationSpeed::default()),
+            run_state: RunState::Running,
         })
     }
 
@@ -329,7 +564,7 @@ impl TaskPool {
             entrypoint,
             mut store,
             name,
-            ..
+            system,
         } = opts;
 
         let instance = self.instantiate(&mut store, module, interface).await?;
@@ -343,8 +578,12 @@ impl TaskPool {
             store,
             instance,
             entrypoint,
-        );
+        )
+        .await?;
         task.priority = priority;
+        if system {
+            self.system_tasks.insert(id);
+        }
         let task = Arc::new(Mutex::new(task));
         self.pool.insert(id, task.clone());
         Ok(task)
@@ -376,6 +615,208 @@ impl TaskPool {
         futures_util::pending!();
     }
 
+    /// Blocks the current task until the virtual clock reaches `time`, letting other tasks run in
+    /// the meantime.
+    pub async fn sleep_until(host: &Host, time: Duration) {
+        while host.clock().now() < time {
+            {
+                let tasks = host.tasks_lock().await;
+                let mut task = tasks.current_lock().await;
+                task.state = TaskState::Blocked;
+                task.wake_time = Some(time);
+            }
+            Self::yield_now().await;
+        }
+    }
+
+    /// Blocks the current task for the given amount of virtual time.
+    pub async fn sleep(host: &Host, duration: Duration) {
+        Self::sleep_until(host, host.clock().now() + duration).await;
+    }
+
+    /// Waits for the current task's notification value to be nonzero, for at most `timeout` (or
+    /// forever if it's `None`). The task is blocked while it waits, so the scheduler won't run it
+    /// until it's notified or the timeout passes.
+    ///
+    /// Returns the notification value from before it was taken. Taking it either clears it or
+    /// decrements it, depending on `clear_on_exit`.
+    pub async fn notify_take(host: &Host, clear_on_exit: bool, timeout: Option<Duration>) -> u32 {
+        let wake_time = timeout.map(|timeout| host.clock().now() + timeout);
+        loop {
+            {
+                let tasks = host.tasks_lock().await;
+                let mut task = tasks.current_lock().await;
+                let timed_out = wake_time.is_some_and(|time| host.clock().now() >= time);
+                if task.notification.value != 0 || timed_out {
+                    task.waiting_for_notification = false;
+                    return task.notification.take(clear_on_exit);
+                }
+                task.state = TaskState::Blocked;
+                task.wake_time = wake_time;
+                task.waiting_for_notification = true;
+            }
+            Self::yield_now().await;
+        }
+    }
+
+    /// Blocks the current task until the task with the given ID has finished or been deleted.
+    pub async fn join(host: &Host, task_id: u32) {
+        loop {
+            {
+                let tasks = host.tasks_lock().await;
+                let mut task = tasks.current_lock().await;
+                if task.id == task_id || !tasks.pool.contains_key(&task_id) {
+                    task.joining = None;
+                    return;
+                }
+                task.state = TaskState::Blocked;
+                task.joining = Some(task_id);
+            }
+            Self::yield_now().await;
+        }
+    }
+
+    /// Stops a task from running until it's resumed with [`resume_task`](Self::resume_task),
+    /// yielding if it's the current task.
+    pub async fn suspend_task(host: &Host, task: TaskHandle) {
+        let is_current = {
+            let tasks = host.tasks_lock().await;
+            let current = tasks.current();
+            let is_current = Arc::ptr_eq(&task, &current);
+            task.lock().await.suspend();
+            is_current
+        };
+        if is_current {
+            Self::yield_now().await;
+        }
+    }
+
+    /// Lets a suspended task run again, yielding if it has a higher priority than the current
+    /// task.
+    pub async fn resume_task(host: &Host, task: TaskHandle) {
+        let should_yield = {
+            let tasks = host.tasks_lock().await;
+            let current_priority = tasks.current_lock().await.priority;
+            let mut task = task.lock().await;
+            task.resume() && task.priority > current_priority
+        };
+        if should_yield {
+            Self::yield_now().await;
+        }
+    }
+
+    /// Changes a task's priority, yielding if a different task should now be running.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the priority is not less than [`TASK_PRIORITIES`].
+    pub async fn set_task_priority(host: &Host, task: TaskHandle, priority: u32) {
+        assert!(priority < TASK_PRIORITIES);
+        let should_yield = {
+            let tasks = host.tasks_lock().await;
+            let current = tasks.current();
+            if Arc::ptr_eq(&task, &current) {
+                let mut task = task.lock().await;
+                let lowered = priority < task.priority;
+                task.priority = priority;
+                lowered
+            } else {
+                let current_priority = current.lock().await.priority;
+                let mut task = task.lock().await;
+                task.priority = priority;
+                task.state == TaskState::Ready && priority > current_priority
+            }
+        };
+        if should_yield {
+            Self::yield_now().await;
+        }
+    }
+
+    /// The number of robot code tasks that haven't finished or been deleted yet. Tasks spawned by
+    /// the simulator, like the system daemon, aren't counted.
+    pub fn task_count(&self) -> usize {
+        self.pool
+            .keys()
+            .filter(|id| !self.system_tasks.contains(id))
+            .count()
+    }
+
+    /// Finds a task by name, returning its ID.
+    pub async fn find_by_name(&self, name: &str) -> Option<u32> {
+        for task in self.pool.values() {
+            let task = task.lock().await;
+            if task.name == name {
+                return Some(task.id);
+            }
+        }
+        None
+    }
+
+    pub fn speed(&self) -> SimulationSpeed {
+        self.pacer.speed()
+    }
+
+    /// Changes how fast the virtual clock advances compared to real time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the speed is not valid (see [`SimulationSpeed::is_valid`]).
+    pub fn set_speed(&mut self, speed: SimulationSpeed) {
+        self.pacer.set_speed(speed, self.clock.now());
+    }
+
+    /// Stops running tasks until `resume` or `step` is called.
+    pub fn pause(&mut self) {
+        self.run_state = RunState::Paused;
+        self.interface.send(SimulatorEvent::Paused {
+            time: self.clock.millis(),
+        });
+    }
+
+    pub fn resume(&mut self) {
+        self.run_state = RunState::Running;
+        self.pacer.reset(self.clock.now());
+    }
+
+    /// Runs tasks until the virtual clock has advanced by `duration`, then pauses.
+    pub fn step(&mut self, duration: Duration) {
+        let until = self.clock.now() + duration;
+        self.run_state = RunState::Stepping { until };
+        self.pacer.reset(self.clock.now());
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.run_state == RunState::Paused
+    }
+
+    /// Moves the virtual clock forward by `duration`, without going past the end of a step.
+    fn pass_time(&self, duration: Duration) {
+        let mut time = self.clock.now() + duration;
+        if let RunState::Stepping { until } = self.run_state {
+            time = time.min(until);
+        }
+        self.clock.advance_to(time);
+    }
+
+    /// Handles messages that control the scheduler, and forwards the rest to the system daemon.
+    fn handle_messages(
+        &mut self,
+        messages: &[Receiver<SimulatorMessage>],
+        daemon_messages: &Sender<SimulatorMessage>,
+    ) {
+        for message in messages.iter().flat_map(Receiver::try_iter) {
+            match message {
+                SimulatorMessage::Stop => self.start_shutdown(),
+                SimulatorMessage::Pause => self.pause(),
+                SimulatorMessage::Resume => self.resume(),
+                SimulatorMessage::Step { millis } => {
+                    self.step(Duration::from_millis(millis.into()));
+                }
+                message => _ = daemon_messages.send(message),
+            }
+        }
+    }
+
     /// Prevent context switches from happening until `resume_all` is called.
     pub fn suspend_all(&mut self) {
         self.scheduler_suspended += 1;
@@ -404,6 +845,9 @@ impl TaskPool {
         let mut highest_priority_tasks = vec![];
         for task in self.pool.values() {
             let task = task.lock().await;
+            if matches!(task.state, TaskState::Blocked | TaskState::Suspended) {
+                continue;
+            }
             if task.priority > highest_priority {
                 highest_priority = task.priority;
                 highest_priority_tasks.clear();
@@ -416,15 +860,64 @@ impl TaskPool {
         highest_priority_tasks
     }
 
+    /// Unblocks every task whose wake time has been reached or that was joining a task that has
+    /// finished, and returns the earliest wake time of the tasks that are still blocked.
+    async fn wake_tasks(&self) -> Option<Duration> {
+        let now = self.clock.now();
+        let mut next_wake_time = None::<Duration>;
+        for task in self.pool.values() {
+            let mut task = task.lock().await;
+            if let Some(joined) = task.joining {
+                if !self.pool.contains_key(&joined) {
+                    task.state = TaskState::Ready;
+                    task.joining = None;
+                }
+                continue;
+            }
+            let Some(wake_time) = task.wake_time else {
+                continue;
+            };
+            if wake_time <= now {
+                task.state = TaskState::Ready;
+                task.wake_time = None;
+            } else {
+                next_wake_time = Some(next_wake_time.map_or(wake_time, |next| next.min(wake_time)));
+            }
+        }
+        next_wake_time
+    }
+
     /// Switches to the next task in the task pool, if any. Returns whether there are running
     /// tasks remaining.
     ///
     /// This function will loop through the tasks in a round-robin fashion, giving each task a
     /// chance to run before looping back around to the beginning. Only tasks with the highest
-    /// priority will be considered.
+    /// priority that aren't blocked or suspended will be considered.
+    ///
+    /// If every task is blocked, the virtual clock is advanced to the time when the next task
+    /// wakes up. Unless the simulation speed is [`SimulationSpeed::Max`], the scheduler first
+    /// waits for the corresponding amount of real time to pass. If a step would finish before
+    /// then, the clock is advanced to the end of the step and the scheduler is paused instead.
+    /// If some tasks are ready, the clock is only advanced by [`CONTEXT_SWITCH_TIME`].
     pub async fn cycle_tasks(&mut self) -> bool {
+        if let RunState::Stepping { until } = self.run_state {
+            if self.clock.now() >= until {
+                self.pause();
+                return true;
+            }
+        }
+
         if self.scheduler_suspended != 0 {
-            if self.current_task.is_some() {
+            if let Some(current_task) = &self.current_task {
+                // No other task is allowed to run, so there's nothing to do until the current
+                // task wakes up.
+                let mut task = current_task.lock().await;
+                if let Some(wake_time) = task.wake_time.take() {
+                    self.clock.advance_to(wake_time);
+                    task.state = TaskState::Ready;
+                } else {
+                    self.pass_time(CONTEXT_SWITCH_TIME);
+                }
                 self.yield_pending = true;
                 return true;
             } else {
@@ -433,7 +926,31 @@ impl TaskPool {
         }
         self.yield_pending = false;
 
-        let task_candidates = self.highest_priority_task_ids().await;
+        let task_candidates = loop {
+            let next_wake_time = self.wake_tasks().await;
+            let task_candidates = self.highest_priority_task_ids().await;
+            if !task_candidates.is_empty() {
+                self.pass_time(CONTEXT_SWITCH_TIME);
+                break task_candidates;
+            }
+
+            let Some(next_wake_time) = next_wake_time else {
+                // either every task has finished or the remaining ones will never wake up
+                self.current_task = None;
+                return false;
+            };
+            if let RunState::Stepping { until } = self.run_state {
+                if next_wake_time > until {
+                    self.pacer.wait_until(until).await;
+                    self.clock.advance_to(until);
+                    self.pause();
+                    return true;
+                }
+            }
+            self.pacer.wait_until(next_wake_time).await;
+            self.clock.advance_to(next_wake_time);
+        };
+
         let current_task_id = if let Some(task) = &self.current_task {
             task.lock().await.id
         } else {
@@ -448,15 +965,42 @@ impl TaskPool {
         self.current_task.is_some()
     }
 
-    pub async fn run_to_completion(host: &Host) -> anyhow::Result<()> {
+    /// Runs tasks until they have all finished, the robot code exits, or a stop message is
+    /// received.
+    ///
+    /// If a task aborts, crashes or misuses the simulator's API, a
+    /// [`SimulatorEvent::RobotCodeError`] event is sent and the error is returned.
+    ///
+    /// Messages are read from every receiver in `messages`. Stop, pause, resume and step messages
+    /// are handled here so that they work even when no tasks are running. All other messages are
+    /// forwarded to the system daemon through `daemon_messages`.
+    pub async fn run_to_completion(
+        host: &Host,
+        messages: Vec<Receiver<SimulatorMessage>>,
+        daemon_messages: Sender<SimulatorMessage>,
+    ) -> Result<(), SimulatorError> {
         let mut futures =
             HashMap::<u32, Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>>::new();
         loop {
             let mut tasks = host.tasks_lock().await;
+            tasks.handle_messages(&messages, &daemon_messages);
+            if tasks.shutdown_pending {
+                break Ok(());
+            }
+            if tasks.is_paused() {
+                drop(tasks);
+                tokio::time::sleep(PAUSED_POLL_INTERVAL).await;
+                continue;
+            }
+
             let running = tasks.cycle_tasks().await;
             if !running {
                 break Ok(());
             }
+            if tasks.is_paused() {
+                // a step just finished
+                continue;
+            }
 
             let mut task = tasks.current_lock().await;
             let id = task.id();
@@ -464,7 +1008,9 @@ impl TaskPool {
             drop(task);
             drop(tasks);
 
-            let result = futures::poll!(future);
+            // A task returning `Pending` is treated as a yield, so tokio's cooperative scheduling
+            // budget must not be allowed to interrupt it partway through an operation.
+            let result = futures::poll!(tokio::task::unconstrained(future));
 
             let tasks = host.tasks();
             let mut tasks = tasks
@@ -482,7 +1028,23 @@ impl TaskPool {
             if let Poll::Ready(result) = result {
                 task.marked_for_delete = true;
                 task.state = TaskState::Finished;
-                result?;
+                if let Err(error) = result {
+                    let backtrace = error
+                        .downcast_ref::<WasmBacktrace>()
+                        .map(ToString::to_string)
+                        .unwrap_or_default();
+                    let error = SimulatorError::from_task_error(error);
+                    let (message, backtrace) = match &error {
+                        SimulatorError::RobotCode { source } => {
+                            (source.message.clone(), source.backtrace.clone())
+                        }
+                        error => (error.to_string(), backtrace),
+                    };
+                    tasks
+                        .interface
+                        .send(SimulatorEvent::RobotCodeError { message, backtrace });
+                    break Err(error);
+                }
             } else if task.marked_for_delete {
                 task.state = TaskState::Deleted;
             }
@@ -500,20 +1062,27 @@ impl TaskPool {
                 tasks.scheduler_suspended = 0;
                 futures.remove(&id);
                 tasks.pool.remove(&id);
+                tasks.deleted_tasks.insert(id);
             }
         }
     }
 
+    /// The state of the task with the given ID, or `None` if there has never been a task with
+    /// that ID. The current task is always [`TaskState::Running`].
     pub async fn task_state(&self, task_id: u32) -> Option<TaskState> {
         if self.deleted_tasks.contains(&task_id) {
             return Some(TaskState::Deleted);
         }
-        if let Some(task) = self.pool.get(&task_id) {
-            let task = task.lock().await;
-            Some(task.state)
-        } else {
-            None
+        let task = self.by_id(task_id)?;
+        if self
+            .current_task
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, &task))
+        {
+            return Some(TaskState::Running);
         }
+        let task = task.lock().await;
+        Some(task.state)
     }
 
     pub async fn delete_task(&mut self, task_id: u32) {
@@ -547,11 +1116,11 @@ impl Errno {
     pub async fn new(
         store: impl AsContextMut<Data = impl Send>,
         allocator: &WasmAllocator,
-    ) -> Self {
+    ) -> Result<Self, SimulatorError> {
         let address = allocator
             .memalign(store, std::alloc::Layout::new::<i32>())
-            .await;
-        Self { address }
+            .await?;
+        Ok(Self { address })
     }
     pub fn address(&self) -> u32 {
         self.address
@@ -563,3 +1132,517 @@ impl Errno {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, OnceLock,
+        },
+        thread,
+    };
+
+    use pros_simulator_interface::config::RobotConfig;
+    use wasmtime::{Config, MemoryType};
+
+    use super::*;
+    use crate::system::system_daemon::system_daemon_initialize;
+
+    // A robot program with nothing but the exports that every task needs:
+    //
+    // (module
+    //   (table (export "__indirect_function_table") 0 funcref)
+    //   (func (export "wasm_memalign") (param i32 i32) (result i32) i32.const 0)
+    //   (func (export "wasm_free") (param i32)))
+    const EMPTY_MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0b, 0x02, 0x60, 0x02, 0x7f, 0x7f,
+        0x01, 0x7f, 0x60, 0x01, 0x7f, 0x00, 0x03, 0x03, 0x02, 0x00, 0x01, 0x04, 0x04, 0x01, 0x70,
+        0x00, 0x00, 0x07, 0x39, 0x03, 0x19, 0x5f, 0x5f, 0x69, 0x6e, 0x64, 0x69, 0x72, 0x65, 0x63,
+        0x74, 0x5f, 0x66, 0x75, 0x6e, 0x63, 0x74, 0x69, 0x6f, 0x6e, 0x5f, 0x74, 0x61, 0x62, 0x6c,
+        0x65, 0x01, 0x00, 0x0d, 0x77, 0x61, 0x73, 0x6d, 0x5f, 0x6d, 0x65, 0x6d, 0x61, 0x6c, 0x69,
+        0x67, 0x6e, 0x00, 0x00, 0x09, 0x77, 0x61, 0x73, 0x6d, 0x5f, 0x66, 0x72, 0x65, 0x65, 0x00,
+        0x01, 0x0a, 0x09, 0x02, 0x04, 0x00, 0x41, 0x00, 0x0b, 0x02, 0x00, 0x0b,
+    ];
+
+    /// Things that tasks did, and the virtual time in milliseconds when they did them.
+    type Log = Arc<std::sync::Mutex<Vec<(&'static str, u32)>>>;
+
+    fn log(log: &Log, host: &Host, event: &'static str) {
+        log.lock().unwrap().push((event, host.clock().millis()));
+    }
+
+    /// A host running at max speed, without a system daemon.
+    async fn host() -> Host {
+        host_with_interface(SimulatorInterface::from(|_| {})).await
+    }
+
+    async fn host_with_interface(interface: SimulatorInterface) -> Host {
+        let mut config = Config::new();
+        config.async_support(true).wasm_threads(true);
+        let engine = Engine::new(&config).unwrap();
+        let module = Module::new(&engine, EMPTY_MODULE).unwrap();
+        let memory = SharedMemory::new(&engine, MemoryType::shared(1, 1)).unwrap();
+        let host = Host::new(
+            engine,
+            memory,
+            interface,
+            module,
+            &RobotConfig::default(),
+            None,
+        )
+        .unwrap();
+        host.tasks_lock().await.set_speed(SimulationSpeed::Max);
+        host
+    }
+
+    /// Spawns a task that runs `body` instead of robot code.
+    async fn spawn<F>(
+        host: &Host,
+        name: &str,
+        priority: u32,
+        body: impl FnOnce(Host) -> F + Send + 'static,
+    ) -> TaskHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut tasks = host.tasks_lock().await;
+        let opts = TaskOptions::new_closure(&mut tasks, host, move |caller| {
+            let host = caller.data().clone();
+            Box::new(async move {
+                body(host).await;
+                Ok(())
+            })
+        })
+        .unwrap()
+        .name(name)
+        .priority(priority);
+        tasks
+            .spawn(opts, &host.module(), &host.interface())
+            .await
+            .unwrap()
+    }
+
+    /// Finds a task by name, returning its ID and handle.
+    async fn find(host: &Host, name: &str) -> (u32, TaskHandle) {
+        let tasks = host.tasks_lock().await;
+        let id = tasks.find_by_name(name).await.unwrap();
+        (id, tasks.by_id(id).unwrap())
+    }
+
+    async fn state(host: &Host, task_id: u32) -> Option<TaskState> {
+        host.tasks_lock().await.task_state(task_id).await
+    }
+
+    /// Runs tasks until they have all finished, handling any control messages that are sent.
+    async fn run(host: &Host, messages: Receiver<SimulatorMessage>) {
+        let (daemon_messages, _) = mpsc::channel();
+        TaskPool::run_to_completion(host, vec![messages], daemon_messages)
+            .await
+            .unwrap();
+    }
+
+    fn notified(value: u32) -> Notification {
+        Notification {
+            value,
+            pending: true,
+        }
+    }
+
+    #[test]
+    fn notify_actions() {
+        let mut notification = Notification {
+            value: 0b0101,
+            pending: false,
+        };
+        assert_eq!(
+            notification.notify(0b0011, NotifyAction::SetBits),
+            (0b0101, true)
+        );
+        assert_eq!(notification, notified(0b0111));
+
+        assert_eq!(
+            notification.notify(100, NotifyAction::Increment),
+            (0b0111, true)
+        );
+        assert_eq!(notification.value, 0b1000);
+
+        assert_eq!(
+            notification.notify(42, NotifyAction::Overwrite),
+            (0b1000, true)
+        );
+        assert_eq!(notification.value, 42);
+
+        assert_eq!(notification.notify(7, NotifyAction::None), (42, true));
+        assert_eq!(notification, notified(42));
+
+        notification.value = u32::MAX;
+        notification.notify(0, NotifyAction::Increment);
+        assert_eq!(notification.value, 0);
+    }
+
+    #[test]
+    fn no_overwrite_fails_while_a_notification_is_pending() {
+        let mut notification = notified(5);
+        assert_eq!(
+            notification.notify(9, NotifyAction::NoOverwrite),
+            (5, false)
+        );
+        assert_eq!(notification, notified(5));
+
+        assert!(notification.clear());
+        assert_eq!(notification.notify(9, NotifyAction::NoOverwrite), (5, true));
+        assert_eq!(notification, notified(9));
+    }
+
+    #[test]
+    fn taking_clears_or_decrements() {
+        let mut notification = notified(3);
+        assert_eq!(notification.take(false), 3);
+        assert_eq!(
+            notification,
+            Notification {
+                value: 2,
+                pending: false,
+            }
+        );
+        assert_eq!(notification.take(true), 2);
+        assert_eq!(notification, Notification::default());
+        assert_eq!(notification.take(false), 0);
+        assert_eq!(notification.value, 0);
+    }
+
+    #[test]
+    fn clearing_keeps_the_value() {
+        let mut notification = notified(8);
+        assert!(notification.clear());
+        assert!(!notification.clear());
+        assert_eq!(notification.value, 8);
+    }
+
+    #[test]
+    fn notify_actions_from_pros() {
+        assert_eq!(
+            NotifyAction::try_from(E_NOTIFY_ACTION_NO_OWRITE),
+            Ok(NotifyAction::NoOverwrite)
+        );
+        assert_eq!(
+            NotifyAction::try_from(E_NOTIFY_ACTION_BITS),
+            Ok(NotifyAction::SetBits)
+        );
+        assert_eq!(NotifyAction::try_from(5), Err(EINVAL));
+    }
+
+    #[tokio::test]
+    async fn busy_polling_the_clock_lets_time_pass() {
+        let host = host().await;
+        let events = Log::default();
+        let task_events = events.clone();
+        spawn(&host, "poller", 7, move |host| async move {
+            let start = host.clock().read_millis();
+            while host.clock().read_millis() < start + 100 {}
+            log(&task_events, &host, "done");
+        })
+        .await;
+
+        run(&host, mpsc::channel().1).await;
+        assert_eq!(*events.lock().unwrap(), [("done", 100)]);
+    }
+
+    #[tokio::test]
+    async fn yielding_tasks_let_sleeping_tasks_wake() {
+        let host = host().await;
+        let events = Log::default();
+        let ready = Arc::new(AtomicBool::new(false));
+
+        let sleeper_ready = ready.clone();
+        spawn(&host, "sleeper", 7, move |host| async move {
+            TaskPool::sleep(&host, Duration::from_millis(10)).await;
+            sleeper_ready.store(true, Ordering::Release);
+        })
+        .await;
+        for name in ["first", "second"] {
+            let ready = ready.clone();
+            let events = events.clone();
+            spawn(&host, name, 7, move |host| async move {
+                while !ready.load(Ordering::Acquire) {
+                    TaskPool::yield_now().await;
+                }
+                log(&events, &host, name);
+            })
+            .await;
+        }
+
+        run(&host, mpsc::channel().1).await;
+        assert_eq!(*events.lock().unwrap(), [("first", 10), ("second", 10)]);
+    }
+
+    #[tokio::test]
+    async fn suspended_tasks_are_skipped() {
+        let host = host().await;
+        let events = Log::default();
+
+        let main_events = events.clone();
+        spawn(&host, "main", 7, move |host| async move {
+            let (main_id, _) = find(&host, "main").await;
+            let (worker_id, worker) = find(&host, "worker").await;
+            assert_eq!(state(&host, main_id).await, Some(TaskState::Running));
+            assert_eq!(state(&host, worker_id).await, Some(TaskState::Ready));
+
+            TaskPool::suspend_task(&host, worker.clone()).await;
+            assert_eq!(state(&host, worker_id).await, Some(TaskState::Suspended));
+            for _ in 0..2 {
+                log(&main_events, &host, "main");
+                TaskPool::yield_now().await;
+            }
+            TaskPool::resume_task(&host, worker).await;
+            log(&main_events, &host, "main resumed worker");
+        })
+        .await;
+        let worker_events = events.clone();
+        spawn(&host, "worker", 7, move |host| async move {
+            log(&worker_events, &host, "worker");
+        })
+        .await;
+
+        run(&host, mpsc::channel().1).await;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ("main", 0),
+                ("main", 0),
+                ("main resumed worker", 0),
+                ("worker", 0)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn resuming_a_higher_priority_task_preempts() {
+        let host = host().await;
+        let events = Log::default();
+
+        let main_events = events.clone();
+        spawn(&host, "main", 7, move |host| async move {
+            let (_, worker) = find(&host, "worker").await;
+            log(&main_events, &host, "main");
+            TaskPool::resume_task(&host, worker).await;
+            log(&main_events, &host, "main resumed worker");
+        })
+        .await;
+        let worker_events = events.clone();
+        spawn(&host, "worker", 8, move |host| async move {
+            log(&worker_events, &host, "worker");
+            let current = host.tasks_lock().await.current();
+            TaskPool::suspend_task(&host, current).await;
+            log(&worker_events, &host, "worker resumed");
+        })
+        .await;
+
+        run(&host, mpsc::channel().1).await;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ("worker", 0),
+                ("main", 0),
+                ("worker resumed", 0),
+                ("main resumed worker", 0)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn joining_waits_for_the_task_to_end() {
+        let host = host().await;
+        let events = Log::default();
+
+        let main_events = events.clone();
+        spawn(&host, "main", 7, move |host| async move {
+            let (worker_id, _) = find(&host, "worker").await;
+            TaskPool::yield_now().await;
+            assert_eq!(state(&host, worker_id).await, Some(TaskState::Blocked));
+
+            TaskPool::join(&host, worker_id).await;
+            log(&main_events, &host, "joined");
+            assert_eq!(state(&host, worker_id).await, Some(TaskState::Deleted));
+        })
+        .await;
+        let worker_events = events.clone();
+        spawn(&host, "worker", 7, move |host| async move {
+            TaskPool::sleep(&host, Duration::from_millis(5)).await;
+            log(&worker_events, &host, "worker done");
+        })
+        .await;
+
+        run(&host, mpsc::channel().1).await;
+        assert_eq!(*events.lock().unwrap(), [("worker done", 5), ("joined", 5)]);
+    }
+
+    #[tokio::test]
+    async fn lowering_the_priority_yields() {
+        let host = host().await;
+        let events = Log::default();
+
+        let main_events = events.clone();
+        spawn(&host, "main", 8, move |host| async move {
+            log(&main_events, &host, "main");
+            let current = host.tasks_lock().await.current();
+            TaskPool::set_task_priority(&host, current, 6).await;
+            log(&main_events, &host, "main lowered");
+        })
+        .await;
+        let worker_events = events.clone();
+        spawn(&host, "worker", 7, move |host| async move {
+            log(&worker_events, &host, "worker");
+        })
+        .await;
+
+        run(&host, mpsc::channel().1).await;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [("main", 0), ("worker", 0), ("main lowered", 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn finding_tasks_by_name() {
+        let host = host().await;
+        let main = spawn(&host, "main", 7, |_| async {}).await;
+
+        let tasks = host.tasks_lock().await;
+        let id = main.lock().await.id();
+        assert_eq!(tasks.find_by_name("main").await, Some(id));
+        assert_eq!(tasks.find_by_name("missing").await, None);
+        assert_eq!(tasks.task_state(id).await, Some(TaskState::Ready));
+        assert_eq!(tasks.task_state(id + 1).await, None);
+    }
+
+    #[tokio::test]
+    async fn the_system_daemon_is_not_counted() {
+        let host = host().await;
+        system_daemon_initialize(&host, mpsc::channel().1)
+            .await
+            .unwrap();
+        spawn(&host, "main", 7, |_| async {}).await;
+
+        let tasks = host.tasks_lock().await;
+        assert!(tasks.find_by_name("PROS System Daemon").await.is_some());
+        assert_eq!(tasks.task_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn notify_take_keeps_waiting_after_being_resumed() {
+        let host = host().await;
+        let events = Log::default();
+
+        let main_events = events.clone();
+        spawn(&host, "main", 7, move |host| async move {
+            let value = TaskPool::notify_take(&host, false, Some(Duration::from_millis(10))).await;
+            assert_eq!(value, 0);
+            log(&main_events, &host, "timed out");
+
+            let value = TaskPool::notify_take(&host, true, None).await;
+            assert_eq!(value, 3);
+            log(&main_events, &host, "notified");
+        })
+        .await;
+        spawn(&host, "helper", 7, move |host| async move {
+            let (_, main) = find(&host, "main").await;
+            TaskPool::sleep(&host, Duration::from_millis(2)).await;
+            TaskPool::suspend_task(&host, main.clone()).await;
+            TaskPool::resume_task(&host, main.clone()).await;
+
+            TaskPool::sleep(&host, Duration::from_millis(13)).await;
+            main.lock().await.notify(3, NotifyAction::Overwrite);
+        })
+        .await;
+
+        run(&host, mpsc::channel().1).await;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [("timed out", 10), ("notified", 15)]
+        );
+    }
+
+    /// Spawns a task that sleeps for 10 milliseconds at a time, logging a tick after each sleep.
+    async fn spawn_ticker(host: &Host, events: &Log, ticks: usize) {
+        let events = events.clone();
+        spawn(host, "ticker", 7, move |host| async move {
+            for _ in 0..ticks {
+                TaskPool::sleep(&host, Duration::from_millis(10)).await;
+                log(&events, &host, "tick");
+            }
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn stepping_stops_exactly_at_the_end_of_the_step() {
+        let (controls, messages) = mpsc::channel();
+        let pauses = Arc::new(std::sync::Mutex::new(vec![]));
+        let interface = {
+            let controls = controls.clone();
+            let pauses = pauses.clone();
+            SimulatorInterface::from(move |event| {
+                if let SimulatorEvent::Paused { time } = event {
+                    pauses.lock().unwrap().push(time);
+                    _ = controls.send(SimulatorMessage::Stop);
+                }
+            })
+        };
+        let host = host_with_interface(interface).await;
+        let events = Log::default();
+        spawn_ticker(&host, &events, 10).await;
+
+        controls
+            .send(SimulatorMessage::Step { millis: 25 })
+            .unwrap();
+        run(&host, messages).await;
+        assert_eq!(*pauses.lock().unwrap(), [25]);
+        assert_eq!(host.clock().now(), Duration::from_millis(25));
+        assert_eq!(*events.lock().unwrap(), [("tick", 10), ("tick", 20)]);
+    }
+
+    #[tokio::test]
+    async fn pausing_stops_the_clock_until_resumed() {
+        let (controls, messages) = mpsc::channel();
+        let clock = Arc::new(OnceLock::<Clock>::new());
+        let paused_times = Arc::new(std::sync::Mutex::new(vec![]));
+        let interface = {
+            let controls = controls.clone();
+            let clock = clock.clone();
+            let paused_times = paused_times.clone();
+            SimulatorInterface::from(move |event| {
+                if let SimulatorEvent::Paused { .. } = event {
+                    let controls = controls.clone();
+                    let clock = clock.clone();
+                    let paused_times = paused_times.clone();
+                    thread::spawn(move || {
+                        let clock = clock.get().unwrap();
+                        paused_times.lock().unwrap().push(clock.now());
+                        thread::sleep(Duration::from_millis(50));
+                        paused_times.lock().unwrap().push(clock.now());
+                        _ = controls.send(SimulatorMessage::Resume);
+                    });
+                }
+            })
+        };
+        let host = host_with_interface(interface).await;
+        clock.set(host.clock()).unwrap();
+        let events = Log::default();
+        spawn_ticker(&host, &events, 3).await;
+
+        controls
+            .send(SimulatorMessage::Step { millis: 15 })
+            .unwrap();
+        run(&host, messages).await;
+        assert_eq!(
+            *paused_times.lock().unwrap(),
+            [Duration::from_millis(15), Duration::from_millis(15)]
+        );
+        assert_eq!(
+            *events.lock().unwrap(),
+            [("tick", 10), ("tick", 20), ("tick", 30)]
+        );
+    }
+}
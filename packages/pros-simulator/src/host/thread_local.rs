@@ -1,9 +1,13 @@
 use std::mem::size_of;
 
 use async_trait::async_trait;
+use snafu::{ensure, OptionExt};
 use wasmtime::{AsContextMut, SharedMemory};
 
 use super::{memory::SharedMemoryExt, HostCtx, WasmAllocator};
+use crate::error::{
+    InvalidHandleSnafu, InvalidPointerSnafu, InvalidThreadLocalIndexSnafu, SimulatorError,
+};
 
 pub const NUM_THREAD_LOCAL_STORAGE_POINTERS: usize = 5;
 
@@ -38,64 +42,76 @@ impl TaskStorage {
     pub async fn new(
         store: impl AsContextMut<Data = impl Send>,
         allocator: &WasmAllocator,
-    ) -> Self {
+    ) -> Result<Self, SimulatorError> {
         let base_ptr = allocator
             .memalign(
                 store,
                 std::alloc::Layout::new::<[u32; NUM_THREAD_LOCAL_STORAGE_POINTERS]>(),
             )
-            .await;
-        Self { base_ptr }
+            .await?;
+        Ok(Self { base_ptr })
     }
 
-    fn assert_in_bounds(index: i32) {
-        if index < 0 || index as usize >= NUM_THREAD_LOCAL_STORAGE_POINTERS {
-            panic!(
-                "Thread local storage index out of bounds:\n\
-                index {index} should be more than 0 and less than {NUM_THREAD_LOCAL_STORAGE_POINTERS}."
-            );
-        }
+    fn check_in_bounds(index: i32) -> Result<(), SimulatorError> {
+        ensure!(
+            index >= 0 && (index as usize) < NUM_THREAD_LOCAL_STORAGE_POINTERS,
+            InvalidThreadLocalIndexSnafu { index }
+        );
+        Ok(())
     }
-    pub fn get_address(&self, index: i32) -> u32 {
-        Self::assert_in_bounds(index);
+    pub fn get_address(&self, index: i32) -> Result<u32, SimulatorError> {
+        Self::check_in_bounds(index)?;
 
-        self.base_ptr + (index as u32 * size_of::<u32>() as u32)
+        Ok(self.base_ptr + (index as u32 * size_of::<u32>() as u32))
     }
-    pub fn get(&self, memory: SharedMemory, index: i32) -> u32 {
-        Self::assert_in_bounds(index);
-        let address = self.get_address(index);
+    pub fn get(&self, memory: SharedMemory, index: i32) -> Result<u32, SimulatorError> {
+        let address = self.get_address(index)?;
         let buffer = memory
             .read_relaxed(address as usize, size_of::<u32>())
-            .unwrap();
-        u32::from_le_bytes(buffer.try_into().unwrap())
+            .ok()
+            .context(InvalidPointerSnafu { pointer: address })?;
+        Ok(u32::from_le_bytes(buffer.try_into().unwrap()))
     }
-    pub fn set(&mut self, memory: SharedMemory, index: i32, value: u32) {
-        Self::assert_in_bounds(index);
-        let address = self.get_address(index);
+    pub fn set(
+        &mut self,
+        memory: SharedMemory,
+        index: i32,
+        value: u32,
+    ) -> Result<(), SimulatorError> {
+        let address = self.get_address(index)?;
         let buffer = value.to_le_bytes();
-        memory.write_relaxed(address as usize, &buffer).unwrap();
+        memory
+            .write_relaxed(address as usize, &buffer)
+            .ok()
+            .context(InvalidPointerSnafu { pointer: address })
     }
 }
 
 #[async_trait]
 pub trait GetTaskStorage {
-    async fn task_storage(&mut self, task_handle: u32) -> TaskStorage;
+    async fn task_storage(&mut self, task_handle: u32) -> Result<TaskStorage, SimulatorError>;
 }
 
 #[async_trait]
 impl<T, D> GetTaskStorage for T
 where
-    T: HostCtx + wasmtime::AsContextMut<Data = D> + Send,
+    T: HostCtx + AsContextMut<Data = D> + Send,
     D: Send,
 {
-    async fn task_storage(&mut self, task_handle: u32) -> TaskStorage {
+    async fn task_storage(&mut self, task_handle: u32) -> Result<TaskStorage, SimulatorError> {
+        // memory is shared between tasks, so any task's storage can be allocated by the task
+        // that's running
+        let allocator = self.current_task().await.lock().await.allocator();
         let task = self
             .tasks_lock()
             .await
             .by_id(task_handle)
-            .expect("invalid task handle");
+            .context(InvalidHandleSnafu {
+                kind: "task",
+                handle: task_handle,
+            })?;
 
         let mut task = task.lock().await;
-        task.local_storage(self).await
+        task.local_storage(&mut *self, &allocator).await
     }
 }
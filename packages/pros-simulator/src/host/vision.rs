@@ -0,0 +1,419 @@
+use pros_simulator_interface::{
+    config::{RobotConfig, SmartDevice},
+    VisionObject, VisionObjectType,
+};
+use pros_sys::{
+    EAGAIN, EDOM, EINVAL, E_VISION_OBJECT_COLOR_CODE, E_VISION_OBJECT_LINE, E_VISION_OBJECT_NORMAL,
+    E_VISION_ZERO_CENTER, E_VISION_ZERO_TOPLEFT, VISION_FOV_HEIGHT, VISION_FOV_WIDTH,
+    VISION_OBJECT_ERR_SIG,
+};
+
+use super::devices::DeviceTable;
+
+/// The number of signatures a vision sensor can store.
+pub const NUM_SIGNATURES: usize = 7;
+/// The exposure of a vision sensor when it is first plugged in, from 0 to 150.
+pub const DEFAULT_EXPOSURE: i32 = 50;
+/// The size of `vision_signature_s_t`, in bytes.
+pub const SIGNATURE_SIZE: usize = 40;
+/// The size of `vision_object_s_t`, in bytes.
+pub const OBJECT_SIZE: usize = 20;
+
+/// A `vision_signature_s_t` stored on the sensor, kept as the raw bytes the robot code gave us.
+pub type Signature = [u8; SIGNATURE_SIZE];
+
+/// Encodes an object in the `vision_object_s_t` format. Coordinates are measured from the
+/// sensor's zero point, which is either the top left corner or the center of the field of view.
+fn object_bytes(object: &VisionObject, zero_point: u32) -> [u8; OBJECT_SIZE] {
+    let (x_offset, y_offset) = if zero_point == E_VISION_ZERO_CENTER {
+        (
+            (VISION_FOV_WIDTH / 2) as i16,
+            (VISION_FOV_HEIGHT / 2) as i16,
+        )
+    } else {
+        (0, 0)
+    };
+    let object_type = match object.object_type {
+        VisionObjectType::Normal => E_VISION_OBJECT_NORMAL,
+        VisionObjectType::ColorCode => E_VISION_OBJECT_COLOR_CODE,
+        VisionObjectType::Line => E_VISION_OBJECT_LINE,
+    };
+    let mut bytes = [0; OBJECT_SIZE];
+    bytes[0..2].copy_from_slice(&object.signature.to_le_bytes());
+    bytes[2..6].copy_from_slice(&object_type.to_le_bytes());
+    let fields = [
+        object.left - x_offset,
+        object.top - y_offset,
+        object.width,
+        object.height,
+        object.angle,
+        object.left + object.width / 2 - x_offset,
+        object.top + object.height / 2 - y_offset,
+    ];
+    for (index, field) in fields.into_iter().enumerate() {
+        let start = 6 + index * 2;
+        bytes[start..start + 2].copy_from_slice(&field.to_le_bytes());
+    }
+    bytes
+}
+
+/// The `vision_object_s_t` that PROS returns in place of an object when an error occurs.
+pub fn error_object_bytes() -> [u8; OBJECT_SIZE] {
+    let mut bytes = [0; OBJECT_SIZE];
+    bytes[0..2].copy_from_slice(&VISION_OBJECT_ERR_SIG.to_le_bytes());
+    bytes
+}
+
+/// Combines up to 5 signature IDs into a color code, like `vision_create_color_code`. Unused
+/// IDs should be 0. Fails with `EINVAL` if fewer than 2 IDs are given or an ID is out of range.
+pub fn color_code(ids: [u32; 5]) -> Result<u16, i32> {
+    let ids = ids
+        .into_iter()
+        .take_while(|&id| id != 0)
+        .collect::<Vec<_>>();
+    if ids.len() < 2 || ids.iter().any(|&id| id > NUM_SIGNATURES as u32) {
+        return Err(EINVAL);
+    }
+    Ok(ids.into_iter().fold(0, |code, id| (code << 3) | id as u16))
+}
+
+/// What a vision sensor looks for in the objects it detects.
+#[derive(Debug, Clone, Copy)]
+pub enum ObjectFilter {
+    All,
+    Signature(u16),
+    ColorCode(u16),
+}
+
+impl ObjectFilter {
+    /// Creates a filter for the given signature ID, failing with `EINVAL` if it isn't between 1
+    /// and 7.
+    pub fn signature(id: u32) -> Result<Self, i32> {
+        if !(1..=NUM_SIGNATURES as u32).contains(&id) {
+            return Err(EINVAL);
+        }
+        Ok(Self::Signature(id as u16))
+    }
+
+    fn matches(&self, object: &VisionObject) -> bool {
+        match *self {
+            Self::All => true,
+            Self::Signature(id) => {
+                object.object_type == VisionObjectType::Normal && object.signature == id
+            }
+            Self::ColorCode(code) => {
+                object.object_type == VisionObjectType::ColorCode && object.signature == code
+            }
+        }
+    }
+}
+
+/// A simulated V5 Vision Sensor.
+///
+/// The sensor sees the objects set by the most recent `VisionObjects` simulator message.
+#[derive(Debug, Clone)]
+pub struct VisionSensor {
+    /// Objects in view, sorted from largest to smallest.
+    objects: Vec<VisionObject>,
+    signatures: [Option<Signature>; NUM_SIGNATURES],
+    exposure: i32,
+    white_balance: i32,
+    auto_white_balance: bool,
+    /// LED color as an RGB code, or `None` if it shows the most prominent signature's color.
+    led: Option<i32>,
+    /// A `vision_zero_e_t` value.
+    zero_point: u32,
+    wifi_enabled: bool,
+}
+
+impl Default for VisionSensor {
+    fn default() -> Self {
+        Self {
+            objects: Vec::new(),
+            signatures: [None; NUM_SIGNATURES],
+            exposure: DEFAULT_EXPOSURE,
+            white_balance: 0,
+            auto_white_balance: true,
+            led: None,
+            zero_point: E_VISION_ZERO_TOPLEFT,
+            wifi_enabled: false,
+        }
+    }
+}
+
+impl VisionSensor {
+    pub fn set_objects(&mut self, mut objects: Vec<VisionObject>) {
+        objects.sort_by_key(|object| {
+            std::cmp::Reverse(i32::from(object.width) * i32::from(object.height))
+        });
+        self.objects = objects;
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Encodes up to `count` objects matching the filter, skipping the `size_id` largest ones.
+    /// Fails with `EDOM` if there are no objects to return.
+    pub fn read_objects(
+        &self,
+        filter: ObjectFilter,
+        size_id: u32,
+        count: u32,
+    ) -> Result<Vec<[u8; OBJECT_SIZE]>, i32> {
+        let objects = self
+            .objects
+            .iter()
+            .filter(|object| filter.matches(object))
+            .skip(size_id as usize)
+            .take(count as usize)
+            .map(|object| object_bytes(object, self.zero_point))
+            .collect::<Vec<_>>();
+        if objects.is_empty() && count > 0 {
+            return Err(EDOM);
+        }
+        Ok(objects)
+    }
+
+    /// Returns the signature with the given ID (1-7). Fails with `EINVAL` if the ID is out of
+    /// range, or `EAGAIN` if the signature hasn't been set.
+    pub fn signature(&self, id: u32) -> Result<Signature, i32> {
+        let index = Self::signature_index(id)?;
+        self.signatures[index].ok_or(EAGAIN)
+    }
+
+    /// Stores a signature with the given ID (1-7), failing with `EINVAL` if the ID is out of
+    /// range. The signature's `id` field is changed to match.
+    pub fn set_signature(&mut self, id: u32, mut signature: Signature) -> Result<(), i32> {
+        let index = Self::signature_index(id)?;
+        signature[0] = id as u8;
+        self.signatures[index] = Some(signature);
+        Ok(())
+    }
+
+    fn signature_index(id: u32) -> Result<usize, i32> {
+        if !(1..=NUM_SIGNATURES as u32).contains(&id) {
+            return Err(EINVAL);
+        }
+        Ok(id as usize - 1)
+    }
+
+    pub fn exposure(&self) -> i32 {
+        self.exposure
+    }
+
+    /// Sets the exposure, which is clamped to the range 0 to 150.
+    pub fn set_exposure(&mut self, exposure: i32) {
+        self.exposure = exposure.clamp(0, 150);
+    }
+
+    pub fn white_balance(&self) -> i32 {
+        self.white_balance
+    }
+
+    /// Sets the white balance to an RGB code, turning off automatic white balance.
+    pub fn set_white_balance(&mut self, rgb: i32) {
+        self.white_balance = rgb;
+        self.auto_white_balance = false;
+    }
+
+    pub fn auto_white_balance(&self) -> bool {
+        self.auto_white_balance
+    }
+
+    /// Turns automatic white balance on or off, failing with `EINVAL` unless `enabled` is 0 or 1.
+    pub fn set_auto_white_balance(&mut self, enabled: u32) -> Result<(), i32> {
+        match enabled {
+            0 | 1 => {
+                self.auto_white_balance = enabled == 1;
+                Ok(())
+            }
+            _ => Err(EINVAL),
+        }
+    }
+
+    /// The LED color as an RGB code, or `None` if it shows the most prominent signature's color.
+    pub fn led(&self) -> Option<i32> {
+        self.led
+    }
+
+    pub fn set_led(&mut self, rgb: Option<i32>) {
+        self.led = rgb;
+    }
+
+    /// Changes where object coordinates are measured from, failing with `EINVAL` if `zero_point`
+    /// isn't a `vision_zero_e_t` value.
+    pub fn set_zero_point(&mut self, zero_point: u32) -> Result<(), i32> {
+        if !matches!(zero_point, E_VISION_ZERO_TOPLEFT | E_VISION_ZERO_CENTER) {
+            return Err(EINVAL);
+        }
+        self.zero_point = zero_point;
+        Ok(())
+    }
+
+    pub fn is_wifi_enabled(&self) -> bool {
+        self.wifi_enabled
+    }
+
+    pub fn set_wifi_enabled(&mut self, enabled: bool) {
+        self.wifi_enabled = enabled;
+    }
+}
+
+/// Stores the state of every V5 Vision Sensor plugged into the brain.
+pub struct VisionSensors {
+    sensors: DeviceTable<VisionSensor>,
+}
+
+impl VisionSensors {
+    pub fn new(robot_config: &RobotConfig) -> Self {
+        Self {
+            sensors: DeviceTable::new(|port| {
+                matches!(robot_config.smart_device(port), Some(SmartDevice::Vision))
+                    .then(VisionSensor::default)
+            }),
+        }
+    }
+
+    pub fn get(&mut self, port: u32) -> Result<&mut VisionSensor, i32> {
+        self.sensors.get_mut(port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::config::SmartPortConfig;
+    use pros_sys::ENODEV;
+
+    use super::*;
+
+    fn object(signature: u16, object_type: VisionObjectType, size: i16) -> VisionObject {
+        VisionObject {
+            signature,
+            object_type,
+            left: 10,
+            top: 20,
+            width: size,
+            height: size,
+            angle: 0,
+        }
+    }
+
+    /// The signature and width of an encoded object.
+    fn summary(bytes: &[u8; OBJECT_SIZE]) -> (u16, i16) {
+        (
+            u16::from_le_bytes([bytes[0], bytes[1]]),
+            i16::from_le_bytes([bytes[10], bytes[11]]),
+        )
+    }
+
+    fn sensor_with_objects() -> VisionSensor {
+        let mut sensor = VisionSensor::default();
+        sensor.set_objects(vec![
+            object(1, VisionObjectType::Normal, 10),
+            object(2, VisionObjectType::Normal, 30),
+            object(1, VisionObjectType::Normal, 20),
+            object(10, VisionObjectType::ColorCode, 40),
+        ]);
+        sensor
+    }
+
+    #[test]
+    fn objects_are_sorted_largest_first() {
+        let sensor = sensor_with_objects();
+        assert_eq!(sensor.object_count(), 4);
+        let objects = sensor.read_objects(ObjectFilter::All, 1, 2).unwrap();
+        let summaries = objects.iter().map(summary).collect::<Vec<_>>();
+        assert_eq!(summaries, [(2, 30), (1, 20)]);
+    }
+
+    #[test]
+    fn filters_objects() {
+        let sensor = sensor_with_objects();
+        let objects = sensor
+            .read_objects(ObjectFilter::signature(1).unwrap(), 0, 5)
+            .unwrap();
+        let summaries = objects.iter().map(summary).collect::<Vec<_>>();
+        assert_eq!(summaries, [(1, 20), (1, 10)]);
+
+        let objects = sensor
+            .read_objects(ObjectFilter::ColorCode(10), 0, 5)
+            .unwrap();
+        assert_eq!(objects.iter().map(summary).collect::<Vec<_>>(), [(10, 40)]);
+    }
+
+    #[test]
+    fn fails_without_matching_objects() {
+        let sensor = sensor_with_objects();
+        let filter = ObjectFilter::signature(3).unwrap();
+        assert_eq!(sensor.read_objects(filter, 0, 1), Err(EDOM));
+        assert_eq!(sensor.read_objects(filter, 0, 0), Ok(vec![]));
+        assert_eq!(sensor.read_objects(ObjectFilter::All, 4, 1), Err(EDOM));
+        assert_eq!(ObjectFilter::signature(8).unwrap_err(), EINVAL);
+    }
+
+    #[test]
+    fn encodes_objects_from_zero_point() {
+        let mut sensor = VisionSensor::default();
+        sensor.set_objects(vec![object(1, VisionObjectType::Normal, 10)]);
+        let top_left = sensor.read_objects(ObjectFilter::All, 0, 1).unwrap()[0];
+        assert_eq!(i16::from_le_bytes([top_left[6], top_left[7]]), 10);
+        assert_eq!(i16::from_le_bytes([top_left[16], top_left[17]]), 15);
+
+        sensor.set_zero_point(E_VISION_ZERO_CENTER).unwrap();
+        let center = sensor.read_objects(ObjectFilter::All, 0, 1).unwrap()[0];
+        let x_offset = (VISION_FOV_WIDTH / 2) as i16;
+        let y_offset = (VISION_FOV_HEIGHT / 2) as i16;
+        assert_eq!(i16::from_le_bytes([center[6], center[7]]), 10 - x_offset);
+        assert_eq!(i16::from_le_bytes([center[8], center[9]]), 20 - y_offset);
+        assert_eq!(sensor.set_zero_point(5), Err(EINVAL));
+    }
+
+    #[test]
+    fn creates_color_codes() {
+        assert_eq!(color_code([1, 2, 0, 0, 0]), Ok(0o12));
+        assert_eq!(color_code([3, 1, 7, 0, 0]), Ok(0o317));
+        assert_eq!(color_code([1, 0, 2, 0, 0]), Err(EINVAL));
+        assert_eq!(color_code([1, 8, 0, 0, 0]), Err(EINVAL));
+    }
+
+    #[test]
+    fn stores_signatures() {
+        let mut sensor = VisionSensor::default();
+        assert_eq!(sensor.signature(2), Err(EAGAIN));
+        sensor.set_signature(2, [9; SIGNATURE_SIZE]).unwrap();
+        let signature = sensor.signature(2).unwrap();
+        assert_eq!(signature[0], 2);
+        assert_eq!(signature[1], 9);
+        assert_eq!(sensor.set_signature(0, [0; SIGNATURE_SIZE]), Err(EINVAL));
+        assert_eq!(sensor.signature(8), Err(EINVAL));
+    }
+
+    #[test]
+    fn adjusts_camera_settings() {
+        let mut sensor = VisionSensor::default();
+        sensor.set_exposure(200);
+        assert_eq!(sensor.exposure(), 150);
+
+        assert!(sensor.auto_white_balance());
+        sensor.set_white_balance(0x123456);
+        assert_eq!(sensor.white_balance(), 0x123456);
+        assert!(!sensor.auto_white_balance());
+        assert_eq!(sensor.set_auto_white_balance(2), Err(EINVAL));
+        sensor.set_auto_white_balance(1).unwrap();
+        assert!(sensor.auto_white_balance());
+    }
+
+    #[test]
+    fn only_configured_ports_have_sensors() {
+        let mut sensors = VisionSensors::new(&RobotConfig {
+            smart_ports: vec![SmartPortConfig {
+                port: 7,
+                device: SmartDevice::Vision,
+            }],
+            ..Default::default()
+        });
+        assert!(sensors.get(7).is_ok());
+        assert_eq!(sensors.get(8).unwrap_err(), ENODEV);
+    }
+}
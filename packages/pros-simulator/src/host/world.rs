@@ -0,0 +1,832 @@
+use pros_simulator_interface::{
+    config::{
+        AdiDevice, AdiPortConfig, IntakeConfig, RobotConfig, RobotSize, SensorMount, SmartDevice,
+    },
+    world::{FieldLine, Obstacle, Shape, World},
+    GamePieceState, RobotPose,
+};
+
+use super::{
+    adi::Adi,
+    field::{distance_to_perimeter, FIELD_SIZE},
+    motors::Motors,
+    pose::{mount_pose, PoseModel, GRAVITY},
+};
+
+/// How much of their speed game pieces keep when they bounce off something.
+const RESTITUTION: f64 = 0.3;
+/// How far in front of an intake's opening game pieces get picked up, in meters.
+const INTAKE_REACH: f64 = 0.03;
+/// How fast an intake's motors have to spin to pick up or spit out game pieces, as a fraction of
+/// their top speed.
+const INTAKE_THRESHOLD: f64 = 0.1;
+/// How fast game pieces leave an intake, in meters per second.
+const EJECT_SPEED: f64 = 1.0;
+/// What a line tracker reads over a tape line.
+const LINE_READING: i32 = 200;
+/// What a line tracker reads over a field tile.
+const TILE_READING: i32 = 2900;
+
+/// A vector along the field's x and y axes.
+type Vector = (f64, f64);
+
+fn dot(a: Vector, b: Vector) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn length(vector: Vector) -> f64 {
+    vector.0.hypot(vector.1)
+}
+
+/// Unit vectors pointing forwards and to the right of something facing the given heading.
+fn axes(heading: f64) -> (Vector, Vector) {
+    let (sin, cos) = heading.to_radians().sin_cos();
+    ((sin, cos), (cos, -sin))
+}
+
+/// Where two objects overlap.
+#[derive(Debug, Clone, Copy)]
+struct Contact {
+    /// The direction to push the second object to separate them.
+    normal: Vector,
+    /// How far the objects overlap, in meters.
+    depth: f64,
+}
+
+impl Contact {
+    fn flipped(self) -> Self {
+        Self {
+            normal: (-self.normal.0, -self.normal.1),
+            depth: self.depth,
+        }
+    }
+}
+
+/// A rectangle that can be rotated.
+#[derive(Debug, Clone, Copy)]
+struct Rectangle {
+    center: Vector,
+    heading: f64,
+    half_width: f64,
+    half_length: f64,
+}
+
+impl Rectangle {
+    fn new(center: Vector, heading: f64, width: f64, length: f64) -> Self {
+        Self {
+            center,
+            heading,
+            half_width: width / 2.0,
+            half_length: length / 2.0,
+        }
+    }
+
+    fn robot(pose: RobotPose, size: RobotSize) -> Self {
+        Self::new((pose.x, pose.y), pose.heading, size.width, size.length)
+    }
+
+    /// Converts a point on the field into distances to the right of and in front of the
+    /// rectangle's center.
+    fn local_point(&self, point: Vector) -> Vector {
+        let (forward, right) = axes(self.heading);
+        let offset = (point.0 - self.center.0, point.1 - self.center.1);
+        (dot(offset, right), dot(offset, forward))
+    }
+
+    /// Converts a direction relative to the rectangle back into field coordinates.
+    fn field_direction(&self, (right_amount, forward_amount): Vector) -> Vector {
+        let (forward, right) = axes(self.heading);
+        (
+            right.0 * right_amount + forward.0 * forward_amount,
+            right.1 * right_amount + forward.1 * forward_amount,
+        )
+    }
+
+    fn corners(&self) -> [Vector; 4] {
+        [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)].map(|(x, y)| {
+            let (dx, dy) = self.field_direction((x * self.half_width, y * self.half_length));
+            (self.center.0 + dx, self.center.1 + dy)
+        })
+    }
+
+    /// Half the length of the rectangle's shadow on the given axis.
+    fn radius_along(&self, axis: Vector) -> f64 {
+        let (forward, right) = axes(self.heading);
+        self.half_width * dot(right, axis).abs() + self.half_length * dot(forward, axis).abs()
+    }
+
+    /// Finds where a circle overlaps the rectangle, pushing the circle out.
+    fn contact_circle(&self, center: Vector, radius: f64) -> Option<Contact> {
+        let (x, y) = self.local_point(center);
+        let closest = (
+            x.clamp(-self.half_width, self.half_width),
+            y.clamp(-self.half_length, self.half_length),
+        );
+        if closest == (x, y) {
+            // the circle's center is inside, so push it out the nearest side
+            let overlap_x = self.half_width - x.abs();
+            let overlap_y = self.half_length - y.abs();
+            return Some(if overlap_x < overlap_y {
+                Contact {
+                    normal: self.field_direction((x.signum(), 0.0)),
+                    depth: overlap_x + radius,
+                }
+            } else {
+                Contact {
+                    normal: self.field_direction((0.0, y.signum())),
+                    depth: overlap_y + radius,
+                }
+            });
+        }
+        let offset = (x - closest.0, y - closest.1);
+        let distance = length(offset);
+        (distance < radius).then(|| Contact {
+            normal: self.field_direction((offset.0 / distance, offset.1 / distance)),
+            depth: radius - distance,
+        })
+    }
+
+    /// Finds where another rectangle overlaps this one, pushing the other one out.
+    fn contact_rectangle(&self, other: &Rectangle) -> Option<Contact> {
+        let (forward, right) = axes(self.heading);
+        let (other_forward, other_right) = axes(other.heading);
+        let offset = (
+            other.center.0 - self.center.0,
+            other.center.1 - self.center.1,
+        );
+        // separating axis test: the rectangles overlap if their shadows overlap on every axis
+        let mut contact: Option<Contact> = None;
+        for axis in [forward, right, other_forward, other_right] {
+            let distance = dot(offset, axis);
+            let depth = self.radius_along(axis) + other.radius_along(axis) - distance.abs();
+            if depth <= 0.0 {
+                return None;
+            }
+            if contact.is_none_or(|contact| depth < contact.depth) {
+                let sign = if distance < 0.0 { -1.0 } else { 1.0 };
+                contact = Some(Contact {
+                    normal: (axis.0 * sign, axis.1 * sign),
+                    depth,
+                });
+            }
+        }
+        contact
+    }
+
+    /// The distance along a ray to the rectangle's edge, or `None` if the ray misses it.
+    fn raycast(&self, origin: Vector, direction: Vector) -> Option<f64> {
+        let origin = self.local_point(origin);
+        let (forward, right) = axes(self.heading);
+        let direction = (dot(direction, right), dot(direction, forward));
+        let mut enter = f64::NEG_INFINITY;
+        let mut exit = f64::INFINITY;
+        for (origin, direction, half) in [
+            (origin.0, direction.0, self.half_width),
+            (origin.1, direction.1, self.half_length),
+        ] {
+            if direction == 0.0 {
+                if origin.abs() > half {
+                    return None;
+                }
+                continue;
+            }
+            let near = (-half.copysign(direction) - origin) / direction;
+            let far = (half.copysign(direction) - origin) / direction;
+            enter = enter.max(near);
+            exit = exit.min(far);
+        }
+        (enter <= exit && exit >= 0.0).then(|| enter.max(0.0))
+    }
+}
+
+/// Finds where two circles overlap, pushing the second one out.
+fn contact_circles(a: Vector, a_radius: f64, b: Vector, b_radius: f64) -> Option<Contact> {
+    let offset = (b.0 - a.0, b.1 - a.1);
+    let distance = length(offset);
+    let depth = a_radius + b_radius - distance;
+    if depth <= 0.0 {
+        return None;
+    }
+    let normal = if distance > 0.0 {
+        (offset.0 / distance, offset.1 / distance)
+    } else {
+        (0.0, 1.0)
+    };
+    Some(Contact { normal, depth })
+}
+
+/// The distance along a ray to a circle's edge, or `None` if the ray misses it.
+fn raycast_circle(origin: Vector, direction: Vector, center: Vector, radius: f64) -> Option<f64> {
+    let offset = (center.0 - origin.0, center.1 - origin.1);
+    let along = dot(offset, direction);
+    let miss_squared = dot(offset, offset) - along * along;
+    if miss_squared > radius * radius {
+        return None;
+    }
+    let half_chord = (radius * radius - miss_squared).sqrt();
+    (along + half_chord >= 0.0).then(|| (along - half_chord).max(0.0))
+}
+
+fn obstacle_rectangle(obstacle: &Obstacle) -> Option<Rectangle> {
+    match obstacle.shape {
+        Shape::Rectangle { width, length } => Some(Rectangle::new(
+            (obstacle.x, obstacle.y),
+            obstacle.heading,
+            width,
+            length,
+        )),
+        Shape::Circle { .. } => None,
+    }
+}
+
+/// Finds where a circle overlaps an obstacle, pushing the circle out.
+fn obstacle_contact_circle(obstacle: &Obstacle, center: Vector, radius: f64) -> Option<Contact> {
+    match obstacle.shape {
+        Shape::Circle {
+            radius: obstacle_radius,
+        } => contact_circles((obstacle.x, obstacle.y), obstacle_radius, center, radius),
+        Shape::Rectangle { .. } => obstacle_rectangle(obstacle)?.contact_circle(center, radius),
+    }
+}
+
+/// Finds where the robot overlaps an obstacle, pushing the robot out.
+fn obstacle_contact_robot(obstacle: &Obstacle, robot: &Rectangle) -> Option<Contact> {
+    match obstacle.shape {
+        Shape::Circle { radius } => robot
+            .contact_circle((obstacle.x, obstacle.y), radius)
+            .map(Contact::flipped),
+        Shape::Rectangle { .. } => obstacle_rectangle(obstacle)?.contact_rectangle(robot),
+    }
+}
+
+/// The distance along a ray to an obstacle's edge, or `None` if the ray misses it.
+fn obstacle_raycast(obstacle: &Obstacle, origin: Vector, direction: Vector) -> Option<f64> {
+    match obstacle.shape {
+        Shape::Circle { radius } => {
+            raycast_circle(origin, direction, (obstacle.x, obstacle.y), radius)
+        }
+        Shape::Rectangle { .. } => obstacle_rectangle(obstacle)?.raycast(origin, direction),
+    }
+}
+
+/// The size of an obstacle as seen by a distance sensor, in meters.
+fn obstacle_size(obstacle: &Obstacle) -> f64 {
+    match obstacle.shape {
+        Shape::Circle { radius } => radius * 2.0,
+        Shape::Rectangle { width, length } => width.max(length),
+    }
+}
+
+/// Whether the given point is on a tape line.
+fn line_covers(line: &FieldLine, point: Vector) -> bool {
+    let direction = (line.end.0 - line.start.0, line.end.1 - line.start.1);
+    let offset = (point.0 - line.start.0, point.1 - line.start.1);
+    let length_squared = dot(direction, direction);
+    let along = if length_squared > 0.0 {
+        (dot(offset, direction) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let closest = (
+        line.start.0 + direction.0 * along,
+        line.start.1 + direction.1 * along,
+    );
+    length((point.0 - closest.0, point.1 - closest.1)) <= line.width / 2.0
+}
+
+/// A game piece and how it's moving.
+#[derive(Debug, Clone)]
+struct Piece {
+    position: Vector,
+    velocity: Vector,
+    radius: f64,
+    mass: f64,
+    friction: f64,
+    /// Whether one of the robot's intakes is holding the game piece.
+    held: bool,
+}
+
+impl Piece {
+    /// Moves the game piece out of something fixed, and makes it bounce off.
+    fn push(&mut self, contact: Contact) {
+        let Contact { normal, depth } = contact;
+        self.position.0 += normal.0 * depth;
+        self.position.1 += normal.1 * depth;
+        self.bounce(normal, (0.0, 0.0));
+    }
+
+    /// Makes the game piece bounce off something moving at `surface_velocity` if it's moving
+    /// towards it.
+    fn bounce(&mut self, normal: Vector, surface_velocity: Vector) {
+        let relative = (
+            self.velocity.0 - surface_velocity.0,
+            self.velocity.1 - surface_velocity.1,
+        );
+        let speed = dot(relative, normal);
+        if speed < 0.0 {
+            self.velocity.0 -= (1.0 + RESTITUTION) * speed * normal.0;
+            self.velocity.1 -= (1.0 + RESTITUTION) * speed * normal.1;
+        }
+    }
+
+    fn state(&self) -> GamePieceState {
+        GamePieceState {
+            x: self.position.0,
+            y: self.position.1,
+            held: self.held,
+        }
+    }
+}
+
+/// An intake and the game pieces it's holding.
+#[derive(Debug, Clone)]
+struct Intake {
+    config: IntakeConfig,
+    /// Indices of the held game pieces, in the order they were picked up.
+    held: Vec<usize>,
+}
+
+impl Intake {
+    /// How fast the intake's motors are spinning, as a fraction of their top speed. Positive
+    /// values pull game pieces in.
+    fn speed(&self, motors: &Motors) -> f64 {
+        let speeds = self
+            .config
+            .motors
+            .iter()
+            .filter_map(|&port| motors.get(port.into()).ok())
+            .map(|motor| motor.physical_velocity() / motor.gearset().max_rpm() as f64)
+            .collect::<Vec<_>>();
+        if speeds.is_empty() {
+            return 0.0;
+        }
+        speeds.iter().sum::<f64>() / speeds.len() as f64
+    }
+}
+
+/// A line tracker that looks at the tape lines on the field.
+#[derive(Debug, Clone)]
+struct LineTracker {
+    /// The smart port of the three-wire expander the sensor is plugged into, if any.
+    expander: Option<u8>,
+    port: char,
+    mount: SensorMount,
+}
+
+/// Something a distance sensor can see.
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    /// The distance to the object, in meters.
+    pub distance: f64,
+    /// How big the object is, in meters. Infinite for the field perimeter.
+    pub size: f64,
+}
+
+/// The field, the objects on it, and how the robot interacts with them.
+///
+/// Every physics tick, game pieces slide across the field and slow down, then everything is
+/// pushed apart so that nothing overlaps. The robot stops when it runs into the field perimeter
+/// or an obstacle, and pushes game pieces out of its way. Game pieces are much lighter than the
+/// robot, so they don't slow it down.
+pub struct WorldModel {
+    obstacles: Vec<Obstacle>,
+    pieces: Vec<Piece>,
+    lines: Vec<FieldLine>,
+    robot_size: RobotSize,
+    intakes: Vec<Intake>,
+    line_trackers: Vec<LineTracker>,
+}
+
+impl WorldModel {
+    pub fn new(world: &World, robot_config: &RobotConfig) -> Self {
+        let mut line_trackers = Vec::new();
+        let mut add_line_trackers = |expander, ports: &[_]| {
+            for adi_port in ports {
+                let AdiPortConfig {
+                    port,
+                    device: AdiDevice::LineTracker { mount: Some(mount) },
+                } = adi_port
+                else {
+                    continue;
+                };
+                line_trackers.push(LineTracker {
+                    expander,
+                    port: *port,
+                    mount: *mount,
+                });
+            }
+        };
+        add_line_trackers(None, &robot_config.adi_ports);
+        for smart_port in &robot_config.smart_ports {
+            if let SmartDevice::AdiExpander { adi_ports } = &smart_port.device {
+                add_line_trackers(Some(smart_port.port), adi_ports);
+            }
+        }
+
+        Self {
+            obstacles: world.obstacles.clone(),
+            pieces: world
+                .game_pieces
+                .iter()
+                .map(|piece| Piece {
+                    position: (piece.x, piece.y),
+                    velocity: (0.0, 0.0),
+                    radius: piece.radius,
+                    mass: piece.mass,
+                    friction: piece.friction,
+                    held: false,
+                })
+                .collect(),
+            lines: world.lines.clone(),
+            robot_size: robot_config.size,
+            intakes: robot_config
+                .intakes
+                .iter()
+                .map(|config| Intake {
+                    config: config.clone(),
+                    held: Vec::new(),
+                })
+                .collect(),
+            line_trackers,
+        }
+    }
+
+    /// Advances the world by `seconds` seconds. The robot's pose should already be updated to
+    /// the current time; it's pushed out of anything it ran into.
+    pub fn update(&mut self, seconds: f64, motors: &Motors, pose: &mut PoseModel) {
+        for piece in self.pieces.iter_mut().filter(|piece| !piece.held) {
+            piece.position.0 += piece.velocity.0 * seconds;
+            piece.position.1 += piece.velocity.1 * seconds;
+            let speed = length(piece.velocity);
+            let slowdown = piece.friction * GRAVITY * seconds;
+            let scale = if speed > slowdown {
+                (speed - slowdown) / speed
+            } else {
+                0.0
+            };
+            piece.velocity = (piece.velocity.0 * scale, piece.velocity.1 * scale);
+        }
+
+        self.run_intakes(motors, pose);
+        self.collide_pieces();
+        self.collide_robot(pose);
+
+        let robot = pose.pose();
+        for piece in self.pieces.iter_mut().filter(|piece| piece.held) {
+            piece.position = (robot.x, robot.y);
+            piece.velocity = (pose.velocity().x, pose.velocity().y);
+        }
+    }
+
+    /// Picks up game pieces in front of intakes that are running forwards, and spits them out
+    /// of intakes that are running backwards.
+    fn run_intakes(&mut self, motors: &Motors, pose: &PoseModel) {
+        let robot = pose.pose();
+        let velocity = pose.velocity();
+        for intake in &mut self.intakes {
+            let speed = intake.speed(motors);
+            let opening = mount_pose(robot, &intake.config.mount);
+            let (facing, side) = axes(opening.heading);
+            // where a game piece is compared to the middle of the opening
+            let relative = |position: Vector| {
+                let offset = (position.0 - opening.x, position.1 - opening.y);
+                (dot(offset, facing), dot(offset, side))
+            };
+            let in_reach = |piece: &Piece| {
+                let (ahead, across) = relative(piece.position);
+                !piece.held
+                    && (-piece.radius..=piece.radius + INTAKE_REACH).contains(&ahead)
+                    && across.abs() <= intake.config.width / 2.0
+            };
+
+            if speed > INTAKE_THRESHOLD {
+                for (index, piece) in self.pieces.iter_mut().enumerate() {
+                    if intake.held.len() >= intake.config.capacity {
+                        break;
+                    }
+                    if in_reach(piece) {
+                        piece.held = true;
+                        intake.held.push(index);
+                    }
+                }
+            } else if speed < -INTAKE_THRESHOLD {
+                // wait for the last game piece to get out of the way first
+                if self.pieces.iter().any(in_reach) {
+                    continue;
+                }
+                let Some(index) = intake.held.pop() else {
+                    continue;
+                };
+                let piece = &mut self.pieces[index];
+                let distance = piece.radius + INTAKE_REACH / 2.0;
+                piece.held = false;
+                piece.position = (
+                    opening.x + facing.0 * distance,
+                    opening.y + facing.1 * distance,
+                );
+                piece.velocity = (
+                    velocity.x + facing.0 * EJECT_SPEED,
+                    velocity.y + facing.1 * EJECT_SPEED,
+                );
+            }
+        }
+    }
+
+    /// Pushes game pieces out of the field perimeter, obstacles and each other.
+    fn collide_pieces(&mut self) {
+        let half = FIELD_SIZE / 2.0;
+        for index in 0..self.pieces.len() {
+            if self.pieces[index].held {
+                continue;
+            }
+            let (before, after) = self.pieces.split_at_mut(index + 1);
+            let piece = &mut before[index];
+            for other in after {
+                if other.held {
+                    continue;
+                }
+                let Some(Contact { normal, depth }) =
+                    contact_circles(piece.position, piece.radius, other.position, other.radius)
+                else {
+                    continue;
+                };
+                // heavier game pieces move less
+                let share = piece.mass / (piece.mass + other.mass);
+                piece.position.0 -= normal.0 * depth * (1.0 - share);
+                piece.position.1 -= normal.1 * depth * (1.0 - share);
+                other.position.0 += normal.0 * depth * share;
+                other.position.1 += normal.1 * depth * share;
+                let relative = (
+                    other.velocity.0 - piece.velocity.0,
+                    other.velocity.1 - piece.velocity.1,
+                );
+                let speed = dot(relative, normal);
+                if speed < 0.0 {
+                    let impulse = (1.0 + RESTITUTION) * speed;
+                    piece.velocity.0 += normal.0 * impulse * (1.0 - share);
+                    piece.velocity.1 += normal.1 * impulse * (1.0 - share);
+                    other.velocity.0 -= normal.0 * impulse * share;
+                    other.velocity.1 -= normal.1 * impulse * share;
+                }
+            }
+
+            let piece = &mut self.pieces[index];
+            for obstacle in &self.obstacles {
+                if let Some(contact) =
+                    obstacle_contact_circle(obstacle, piece.position, piece.radius)
+                {
+                    piece.push(contact);
+                }
+            }
+            let limit = half - piece.radius;
+            for (axis, normal) in [(0, (1.0, 0.0)), (1, (0.0, 1.0))] {
+                let position = if axis == 0 {
+                    piece.position.0
+                } else {
+                    piece.position.1
+                };
+                if position.abs() > limit {
+                    let sign = -position.signum();
+                    piece.push(Contact {
+                        normal: (normal.0 * sign, normal.1 * sign),
+                        depth: position.abs() - limit,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Pushes the robot out of the field perimeter and obstacles, and pushes game pieces out of
+    /// the robot.
+    fn collide_robot(&mut self, pose: &mut PoseModel) {
+        let half = FIELD_SIZE / 2.0;
+        let robot = Rectangle::robot(pose.pose(), self.robot_size);
+        let mut overshoot = [0.0f64; 4];
+        for (x, y) in robot.corners() {
+            overshoot[0] = overshoot[0].max(x - half);
+            overshoot[1] = overshoot[1].max(-half - x);
+            overshoot[2] = overshoot[2].max(y - half);
+            overshoot[3] = overshoot[3].max(-half - y);
+        }
+        let normals = [(-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0)];
+        for (depth, normal) in overshoot.into_iter().zip(normals) {
+            if depth > 0.0 {
+                pose.push(normal, depth);
+            }
+        }
+
+        for obstacle in &self.obstacles {
+            let robot = Rectangle::robot(pose.pose(), self.robot_size);
+            if let Some(Contact { normal, depth }) = obstacle_contact_robot(obstacle, &robot) {
+                pose.push(normal, depth);
+            }
+        }
+
+        let robot = Rectangle::robot(pose.pose(), self.robot_size);
+        let velocity = pose.velocity();
+        let angular = velocity.angular.to_radians();
+        for piece in self.pieces.iter_mut().filter(|piece| !piece.held) {
+            let Some(contact) = robot.contact_circle(piece.position, piece.radius) else {
+                continue;
+            };
+            piece.position.0 += contact.normal.0 * contact.depth;
+            piece.position.1 += contact.normal.1 * contact.depth;
+            // the robot's surface moves faster further from its center when it turns
+            let offset = (
+                piece.position.0 - robot.center.0,
+                piece.position.1 - robot.center.1,
+            );
+            let surface_velocity = (
+                velocity.x + angular * offset.1,
+                velocity.y - angular * offset.0,
+            );
+            piece.bounce(contact.normal, surface_velocity);
+        }
+
+        // game pieces pinned against something fixed push back on the robot instead
+        for piece in self.pieces.iter().filter(|piece| !piece.held) {
+            let pinned = self.obstacles.iter().any(|obstacle| {
+                obstacle_contact_circle(obstacle, piece.position, piece.radius).is_some()
+            }) || piece.position.0.abs() > half - piece.radius
+                || piece.position.1.abs() > half - piece.radius;
+            if !pinned {
+                continue;
+            }
+            let robot = Rectangle::robot(pose.pose(), self.robot_size);
+            if let Some(Contact { normal, depth }) =
+                robot.contact_circle(piece.position, piece.radius)
+            {
+                pose.push((-normal.0, -normal.1), depth);
+            }
+        }
+    }
+
+    /// Finds the nearest thing a distance sensor facing `from.heading` can see, including the
+    /// field perimeter.
+    pub fn raycast(&self, from: RobotPose) -> RayHit {
+        let origin = (from.x, from.y);
+        let (direction, _) = axes(from.heading);
+        let mut hit = RayHit {
+            distance: distance_to_perimeter(from),
+            size: f64::INFINITY,
+        };
+        let mut consider = |distance: Option<f64>, size: f64| {
+            if let Some(distance) = distance {
+                if distance < hit.distance {
+                    hit = RayHit { distance, size };
+                }
+            }
+        };
+        for obstacle in &self.obstacles {
+            consider(
+                obstacle_raycast(obstacle, origin, direction),
+                obstacle_size(obstacle),
+            );
+        }
+        for piece in self.pieces.iter().filter(|piece| !piece.held) {
+            consider(
+                raycast_circle(origin, direction, piece.position, piece.radius),
+                piece.radius * 2.0,
+            );
+        }
+        hit
+    }
+
+    /// Updates the readings of line trackers mounted on the robot, depending on whether
+    /// they're over a tape line.
+    pub fn read_line_trackers(&self, pose: &PoseModel, adi: &mut Adi) {
+        for tracker in &self.line_trackers {
+            let position = mount_pose(pose.pose(), &tracker.mount);
+            let on_line = self
+                .lines
+                .iter()
+                .any(|line| line_covers(line, (position.x, position.y)));
+            let reading = if on_line { LINE_READING } else { TILE_READING };
+            // fails if the robot code is using the port as an output
+            _ = adi.set_input(tracker.expander, tracker.port, reading);
+        }
+    }
+
+    /// Where every game piece is.
+    pub fn game_pieces(&self) -> Vec<GamePieceState> {
+        self.pieces.iter().map(Piece::state).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pros_simulator_interface::{world::GamePiece, RobotVelocity};
+
+    use super::*;
+    use crate::interface::SimulatorInterface;
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    /// A robot on a field, updated one physics tick at a time.
+    struct Sim {
+        world: WorldModel,
+        pose: PoseModel,
+        motors: Motors,
+        now: Duration,
+    }
+
+    impl Sim {
+        fn new(world: World) -> Self {
+            let robot_config = RobotConfig::default();
+            Self {
+                world: WorldModel::new(&world, &robot_config),
+                pose: PoseModel::default(),
+                motors: Motors::new(SimulatorInterface::from(|_| {}), &robot_config),
+                now: Duration::ZERO,
+            }
+        }
+
+        /// Drives the robot at a constant velocity for the given number of physics ticks.
+        fn drive(&mut self, velocity: RobotVelocity, ticks: u32) {
+            for _ in 0..ticks {
+                self.pose.set_velocity(velocity);
+                self.now += TICK;
+                self.pose.update(self.now);
+                self.world
+                    .update(TICK.as_secs_f64(), &self.motors, &mut self.pose);
+            }
+        }
+
+        /// The y coordinate of the front of the robot.
+        fn front(&self) -> f64 {
+            self.pose.pose().y + RobotSize::default().length / 2.0
+        }
+    }
+
+    fn forward(speed: f64) -> RobotVelocity {
+        RobotVelocity {
+            x: 0.0,
+            y: speed,
+            angular: 0.0,
+        }
+    }
+
+    fn game_piece(x: f64, y: f64) -> GamePiece {
+        GamePiece {
+            kind: "ball".into(),
+            x,
+            y,
+            radius: 0.05,
+            mass: 0.2,
+            friction: 0.5,
+        }
+    }
+
+    #[test]
+    fn robot_stops_at_the_field_wall() {
+        let mut sim = Sim::new(World::default());
+        sim.drive(forward(1.0), 300);
+
+        assert!((sim.front() - FIELD_SIZE / 2.0).abs() < 1e-9);
+        assert_eq!(sim.pose.velocity().y, 0.0);
+    }
+
+    #[test]
+    fn robot_stops_at_obstacles() {
+        let mut sim = Sim::new(World {
+            obstacles: vec![Obstacle {
+                x: 0.0,
+                y: 1.0,
+                heading: 0.0,
+                shape: Shape::Rectangle {
+                    width: 0.5,
+                    length: 0.2,
+                },
+            }],
+            ..Default::default()
+        });
+        sim.drive(forward(1.0), 200);
+
+        assert!((sim.front() - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn robot_pushes_game_pieces() {
+        let mut sim = Sim::new(World {
+            game_pieces: vec![game_piece(0.0, 0.5)],
+            ..Default::default()
+        });
+        sim.drive(forward(1.0), 50);
+
+        let [piece] = sim.world.game_pieces()[..] else {
+            panic!("expected one game piece");
+        };
+        assert!(!piece.held);
+        assert_eq!(piece.x, 0.0);
+        assert!(piece.y >= sim.front() + 0.05 - 1e-9);
+
+        // once the robot stops, friction brings the game piece to a stop too
+        sim.drive(forward(0.0), 300);
+        let resting = sim.world.game_pieces()[0].y;
+        sim.drive(forward(0.0), 10);
+        assert!(resting > piece.y);
+        assert_eq!(sim.world.game_pieces()[0].y, resting);
+    }
+}
@@ -1,61 +1,51 @@
-use std::{path::Path, sync::mpsc::Receiver};
+use std::sync::mpsc::Receiver;
 
-use anyhow::Result;
-use host::{task::TaskPool, Host};
+use error::SimulatorError;
 use interface::SimulatorInterface;
-use pros_simulator_interface::{SimulatorEvent, SimulatorMessage};
-use wasmtime::*;
-
-use crate::system::system_daemon::system_daemon_initialize;
+use pros_simulator_interface::{config::RobotConfig, SimulationSpeed, SimulatorMessage};
 
 mod api;
+mod cache;
+pub mod error;
 pub mod host;
 pub mod interface;
+pub mod simulator;
+pub mod snapshot;
 pub mod stream;
 mod system;
 
+pub use simulator::{RobotCode, Simulator, SimulatorBuilder, SimulatorController};
+
 /// Simulate the WebAssembly robot program at the given path.
 ///
+/// This is a shortcut for [`Simulator::builder`]; use the builder for more options, or to pause
+/// and stop the simulation.
+///
 /// # Arguments
 ///
-/// - `robot_code`: The path to the robot program to simulate.
+/// - `robot_code`: The robot program to simulate: a path to a WebAssembly file, the contents of
+///   one, or a precompiled module.
+/// - `robot_config`: The devices plugged into the simulated robot. Robot code will not be able to
+///   use devices that aren't listed here.
+/// - `speed`: How fast simulated time passes compared to real time. This can be changed later
+///   with [`SimulatorMessage::SetSpeed`].
 /// - `interface`: A callback function that will be invoked with any events that occur during
 ///   simulation.
 /// - `messages`: Input message stream to send to the robot program. This can be used to simulate
-///  controller input, LCD touch events, and more.
+///   controller input, LCD touch events, and more.
 pub async fn simulate(
-    robot_code: &Path,
+    robot_code: impl Into<RobotCode>,
+    robot_config: RobotConfig,
+    speed: SimulationSpeed,
     interface: impl Into<SimulatorInterface>,
     messages: Receiver<SimulatorMessage>,
-) -> Result<()> {
-    let interface: SimulatorInterface = interface.into();
-    tracing::info!("Initializing WASM runtime");
-    let engine = Engine::new(
-        Config::new()
-            .async_support(true)
-            .wasm_threads(true)
-            .debug_info(true)
-            .wasm_backtrace_details(WasmBacktraceDetails::Enable),
-    )
-    .unwrap();
-
-    tracing::info!("JIT compiling your robot code... 🚀");
-    interface.send(SimulatorEvent::RobotCodeLoading);
-
-    let module = Module::from_file(&engine, robot_code)?;
-
-    let shared_memory = SharedMemory::new(&engine, MemoryType::shared(18, 16384))?;
-    let host = Host::new(
-        engine.clone(),
-        shared_memory.clone(),
-        interface.clone(),
-        module.clone(),
-    )?;
-
-    system_daemon_initialize(&host, messages).await?;
-
-    TaskPool::run_to_completion(&host).await?;
-    interface.send(SimulatorEvent::RobotCodeFinished);
-
-    Ok(())
+) -> Result<(), SimulatorError> {
+    Simulator::builder()
+        .robot_code(robot_code)
+        .robot_config(robot_config)
+        .speed(speed)
+        .interface(interface)
+        .messages(messages)
+        .start()
+        .await
 }
@@ -0,0 +1,410 @@
+//! Configuring, starting and controlling a simulation.
+
+use std::{
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::mpsc::{self, Receiver, Sender},
+    task::{Context, Poll},
+};
+
+use pros_simulator_interface::{
+    config::RobotConfig, world::World, SimulationSpeed, SimulatorEvent, SimulatorMessage,
+};
+use snafu::{ensure, OptionExt, ResultExt};
+use wasmtime::*;
+
+use crate::{
+    cache::load_module,
+    error::{CompileSnafu, InvalidSpeedSnafu, MissingRobotCodeSnafu, RuntimeSnafu, SimulatorError},
+    host::{devices::check_robot_config, task::TaskPool, Host, HostCtx},
+    interface::SimulatorInterface,
+    system::system_daemon::system_daemon_initialize,
+};
+
+/// The default minimum size of the robot code's memory, in 64 KiB WebAssembly pages.
+pub const DEFAULT_MIN_MEMORY_PAGES: u32 = 18;
+/// The default maximum size of the robot code's memory, in 64 KiB WebAssembly pages (1 GiB).
+pub const DEFAULT_MAX_MEMORY_PAGES: u32 = 16384;
+
+/// The WebAssembly robot program to simulate.
+///
+/// Anything that can be converted into `RobotCode` (like a path, a byte buffer or a compiled
+/// module) can be passed to [`SimulatorBuilder::robot_code`] and [`simulate`](crate::simulate).
+#[derive(Debug, Clone)]
+pub enum RobotCode {
+    /// Load the robot code from a WebAssembly file.
+    Path(PathBuf),
+    /// Robot code that has already been loaded into memory, in WebAssembly binary or text format.
+    Bytes(Vec<u8>),
+    /// Robot code that has already been compiled.
+    ///
+    /// The module's engine is used to run the simulation, so it must have been created with async
+    /// support and the WebAssembly threads proposal enabled. The simulator's compiler settings
+    /// ([`SimulatorBuilder::debug_info`], [`SimulatorBuilder::backtrace_details`] and
+    /// [`SimulatorBuilder::cache_dir`]) have no effect on precompiled modules.
+    Module(Module),
+}
+
+impl RobotCode {
+    /// Returns the engine to run the robot code with, compiling the robot code if needed.
+    fn load(
+        self,
+        config: &Config,
+        cache_dir: Option<&Path>,
+    ) -> Result<(Engine, Module), SimulatorError> {
+        let wasm = match self {
+            RobotCode::Module(module) => return Ok((module.engine().clone(), module)),
+            RobotCode::Path(path) => fs::read(&path)
+                .map_err(|err| {
+                    anyhow::Error::new(err)
+                        .context(format!("failed to read robot code from {}", path.display()))
+                })
+                .context(CompileSnafu)?,
+            RobotCode::Bytes(bytes) => bytes,
+        };
+        tracing::info!("Initializing WASM runtime");
+        let engine = Engine::new(config).context(RuntimeSnafu)?;
+        let module = load_module(&engine, &wasm, cache_dir)?;
+        Ok((engine, module))
+    }
+}
+
+impl From<PathBuf> for RobotCode {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<&Path> for RobotCode {
+    fn from(path: &Path) -> Self {
+        Self::Path(path.to_owned())
+    }
+}
+
+impl From<&PathBuf> for RobotCode {
+    fn from(path: &PathBuf) -> Self {
+        Self::Path(path.clone())
+    }
+}
+
+impl From<Vec<u8>> for RobotCode {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for RobotCode {
+    fn from(bytes: &[u8]) -> Self {
+        Self::Bytes(bytes.to_owned())
+    }
+}
+
+impl From<Module> for RobotCode {
+    fn from(module: Module) -> Self {
+        Self::Module(module)
+    }
+}
+
+/// Configures a simulation. Create one with [`Simulator::builder`].
+///
+/// # Example
+///
+/// ```ignore
+/// let simulator = Simulator::builder()
+///     .robot_code(Path::new("robot.wasm"))
+///     .robot_config(robot_config)
+///     .speed(SimulationSpeed::Max)
+///     .cache_dir("target/pros-simulator")
+///     .interface(|event| println!("{event:?}"))
+///     .start();
+/// let controller = simulator.controller();
+/// simulator.await?;
+/// ```
+pub struct SimulatorBuilder {
+    robot_code: Option<RobotCode>,
+    robot_config: RobotConfig,
+    world: Option<World>,
+    speed: SimulationSpeed,
+    cache_dir: Option<PathBuf>,
+    memory_pages: (u32, u32),
+    debug_info: bool,
+    backtrace_details: bool,
+    interface: Option<SimulatorInterface>,
+    messages: Option<Receiver<SimulatorMessage>>,
+}
+
+impl Default for SimulatorBuilder {
+    fn default() -> Self {
+        Self {
+            robot_code: None,
+            robot_config: RobotConfig::default(),
+            world: None,
+            speed: SimulationSpeed::default(),
+            cache_dir: None,
+            memory_pages: (DEFAULT_MIN_MEMORY_PAGES, DEFAULT_MAX_MEMORY_PAGES),
+            debug_info: true,
+            backtrace_details: true,
+            interface: None,
+            messages: None,
+        }
+    }
+}
+
+impl SimulatorBuilder {
+    /// Sets the robot program to simulate: a path to a WebAssembly file, the contents of one, or
+    /// a precompiled [`Module`].
+    pub fn robot_code(mut self, robot_code: impl Into<RobotCode>) -> Self {
+        self.robot_code = Some(robot_code.into());
+        self
+    }
+
+    /// Sets the devices plugged into the simulated robot. Robot code will not be able to use
+    /// devices that aren't listed here.
+    pub fn robot_config(mut self, robot_config: RobotConfig) -> Self {
+        self.robot_config = robot_config;
+        self
+    }
+
+    /// Sets the objects on the field, like obstacles and game pieces. The simulator sends
+    /// [`SimulatorEvent::WorldState`] events describing where they are if this is set.
+    ///
+    /// Without a world, the field is empty apart from its perimeter.
+    pub fn world(mut self, world: World) -> Self {
+        self.world = Some(world);
+        self
+    }
+
+    /// Sets how fast simulated time passes compared to real time. This can be changed later with
+    /// [`SimulatorController::set_speed`].
+    pub fn speed(mut self, speed: SimulationSpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Caches compiled robot code in the given directory, so that running the same robot code
+    /// again doesn't need to compile it.
+    ///
+    /// The directory must be trusted. Cached code is loaded without being checked, so anyone who
+    /// can write to the directory can run any code they want in this process. Don't use a
+    /// directory that other users can write to.
+    pub fn cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Sets the minimum and maximum size of the robot code's shared memory, in 64 KiB
+    /// WebAssembly pages. The robot code's memory import must accept these limits.
+    ///
+    /// Defaults to [`DEFAULT_MIN_MEMORY_PAGES`] and [`DEFAULT_MAX_MEMORY_PAGES`].
+    pub fn memory_pages(mut self, min: u32, max: u32) -> Self {
+        self.memory_pages = (min, max);
+        self
+    }
+
+    /// Sets whether to compile the robot code with native debug info, so that it can be debugged
+    /// with a native debugger like GDB or LLDB. Enabled by default.
+    pub fn debug_info(mut self, enabled: bool) -> Self {
+        self.debug_info = enabled;
+        self
+    }
+
+    /// Sets whether robot code error backtraces include file names and line numbers. Enabled by
+    /// default; disabling it makes loading robot code faster.
+    pub fn backtrace_details(mut self, enabled: bool) -> Self {
+        self.backtrace_details = enabled;
+        self
+    }
+
+    /// Sets a callback function that will be invoked with any events that occur during
+    /// simulation. Events are discarded if this isn't set.
+    pub fn interface(mut self, interface: impl Into<SimulatorInterface>) -> Self {
+        self.interface = Some(interface.into());
+        self
+    }
+
+    /// Sets an input message stream to send to the robot program. This can be used to simulate
+    /// controller input, LCD touch events, and more.
+    ///
+    /// Messages can also be sent with [`SimulatorController::send`].
+    pub fn messages(mut self, messages: Receiver<SimulatorMessage>) -> Self {
+        self.messages = Some(messages);
+        self
+    }
+
+    /// Starts the simulation.
+    ///
+    /// Nothing happens until the returned [`Simulator`] is awaited (or polled), which runs the
+    /// simulation to completion.
+    pub fn start(mut self) -> Simulator {
+        let (control_tx, control_rx) = mpsc::channel();
+        let mut messages = vec![control_rx];
+        messages.extend(self.messages.take());
+        Simulator {
+            future: Box::pin(self.run(messages)),
+            controller: SimulatorController {
+                messages: control_tx,
+            },
+        }
+    }
+
+    async fn run(self, messages: Vec<Receiver<SimulatorMessage>>) -> Result<(), SimulatorError> {
+        let Self {
+            robot_code,
+            robot_config,
+            world,
+            speed,
+            cache_dir,
+            memory_pages: (min_pages, max_pages),
+            debug_info,
+            backtrace_details,
+            interface,
+            messages: _,
+        } = self;
+        let robot_code = robot_code.context(MissingRobotCodeSnafu)?;
+        let interface =
+            interface.unwrap_or_else(|| SimulatorInterface::from(|_: SimulatorEvent| {}));
+        check_robot_config(&robot_config)?;
+        ensure!(speed.is_valid(), InvalidSpeedSnafu { speed });
+
+        tracing::info!("JIT compiling your robot code... 🚀");
+        interface.send(SimulatorEvent::RobotCodeLoading);
+
+        let mut config = Config::new();
+        config
+            .async_support(true)
+            .wasm_threads(true)
+            .debug_info(debug_info)
+            .wasm_backtrace_details(if backtrace_details {
+                WasmBacktraceDetails::Enable
+            } else {
+                WasmBacktraceDetails::Disable
+            });
+        let (engine, module) = robot_code.load(&config, cache_dir.as_deref())?;
+
+        let shared_memory = SharedMemory::new(&engine, MemoryType::shared(min_pages, max_pages))
+            .context(RuntimeSnafu)?;
+        let host = Host::new(
+            engine.clone(),
+            shared_memory.clone(),
+            interface.clone(),
+            module.clone(),
+            &robot_config,
+            world.as_ref(),
+        )
+        .context(RuntimeSnafu)?;
+
+        host.tasks_lock().await.set_speed(speed);
+        let (daemon_tx, daemon_rx) = mpsc::channel();
+        system_daemon_initialize(&host, daemon_rx)
+            .await
+            .map_err(SimulatorError::from_runtime_error)?;
+
+        TaskPool::run_to_completion(&host, messages, daemon_tx).await?;
+        interface.send(SimulatorEvent::RobotCodeFinished);
+
+        Ok(())
+    }
+}
+
+/// A running simulation.
+///
+/// Awaiting a `Simulator` runs the robot code until it finishes, the robot code exits, or the
+/// simulation is stopped. Dropping it stops the simulation immediately.
+///
+/// # Errors
+///
+/// Resolves to an error if the robot code can't be loaded, or if it aborts, crashes, or misuses
+/// the simulator's API while running. In the latter case, a [`SimulatorEvent::RobotCodeError`]
+/// event is also sent.
+#[must_use = "simulators do nothing unless awaited"]
+pub struct Simulator {
+    future: Pin<Box<dyn Future<Output = Result<(), SimulatorError>> + Send>>,
+    controller: SimulatorController,
+}
+
+impl Simulator {
+    /// Configures a new simulation.
+    pub fn builder() -> SimulatorBuilder {
+        SimulatorBuilder::default()
+    }
+
+    /// Returns a handle that can control the simulation from somewhere else while it is being
+    /// awaited.
+    pub fn controller(&self) -> SimulatorController {
+        self.controller.clone()
+    }
+
+    /// See [`SimulatorController::send`].
+    pub fn send(&self, message: SimulatorMessage) {
+        self.controller.send(message);
+    }
+
+    /// See [`SimulatorController::pause`].
+    pub fn pause(&self) {
+        self.controller.pause();
+    }
+
+    /// See [`SimulatorController::resume`].
+    pub fn resume(&self) {
+        self.controller.resume();
+    }
+
+    /// See [`SimulatorController::stop`].
+    pub fn stop(&self) {
+        self.controller.stop();
+    }
+}
+
+impl Future for Simulator {
+    type Output = Result<(), SimulatorError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Controls a running [`Simulator`].
+///
+/// Controllers can be cloned and sent to other threads. Messages sent after the simulation has
+/// finished are ignored.
+#[derive(Debug, Clone)]
+pub struct SimulatorController {
+    messages: Sender<SimulatorMessage>,
+}
+
+impl SimulatorController {
+    /// Sends a message to the simulator, like it was received from the
+    /// [`messages`](SimulatorBuilder::messages) stream.
+    pub fn send(&self, message: SimulatorMessage) {
+        _ = self.messages.send(message);
+    }
+
+    /// Freezes the simulated clock until [`resume`](Self::resume) is called. A
+    /// [`SimulatorEvent::Paused`] event is sent once the simulation has paused.
+    pub fn pause(&self) {
+        self.send(SimulatorMessage::Pause);
+    }
+
+    /// Continues running robot code after a pause or step.
+    pub fn resume(&self) {
+        self.send(SimulatorMessage::Resume);
+    }
+
+    /// Runs the simulation for exactly this many milliseconds of simulated time, then pauses
+    /// again.
+    pub fn step(&self, millis: u32) {
+        self.send(SimulatorMessage::Step { millis });
+    }
+
+    /// Changes how fast simulated time passes.
+    pub fn set_speed(&self, speed: SimulationSpeed) {
+        self.send(SimulatorMessage::SetSpeed(speed));
+    }
+
+    /// Stops running robot code and ends the simulation.
+    pub fn stop(&self) {
+        self.send(SimulatorMessage::Stop);
+    }
+}
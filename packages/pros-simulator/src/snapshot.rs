@@ -0,0 +1,230 @@
+//! Rendering the LCD and brain screen to PNG images, for screenshots of what the robot code
+//! displayed.
+//!
+//! Everything is drawn on the CPU, so this works without a display. [`Snapshots`] keeps track
+//! of both displays from the events sent to the simulator interface, so it can be used from any
+//! interface callback:
+//!
+//! ```no_run
+//! # use pros_simulator::snapshot::Snapshots;
+//! # use pros_simulator_interface::SimulatorEvent;
+//! let mut snapshots = Snapshots::default();
+//! let interface = move |event: SimulatorEvent| {
+//!     if let Some(display) = snapshots.update(&event) {
+//!         std::fs::write("display.png", snapshots.render(display)).unwrap();
+//!     }
+//! };
+//! ```
+
+use pros_simulator_interface::{
+    LcdLines, SimulatorEvent, LCD_HEIGHT, LCD_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+use crate::host::{font, lcd::LcdColors};
+
+mod png;
+
+pub use png::encode_png;
+
+/// How many times larger than the font's pixels LCD text is drawn.
+const LCD_TEXT_SCALE: usize = 2;
+/// The height of each line of the LCD in pixels. Text is centered vertically in its line.
+const LCD_LINE_HEIGHT: usize = 30;
+pub const LCD_IMAGE_WIDTH: u32 = LCD_WIDTH * (font::CHAR_WIDTH * LCD_TEXT_SCALE) as u32;
+pub const LCD_IMAGE_HEIGHT: u32 = LCD_HEIGHT * LCD_LINE_HEIGHT as u32;
+
+/// Renders the lines of the LCD with the given colors to a PNG image that is
+/// [`LCD_IMAGE_WIDTH`] by [`LCD_IMAGE_HEIGHT`] pixels.
+pub fn lcd_png(lines: &LcdLines, colors: LcdColors) -> Vec<u8> {
+    let width = LCD_IMAGE_WIDTH as usize;
+    // the alpha channel of LCD colors is ignored, like it is on the brain
+    let mut pixels = vec![colors.background & 0xFFFFFF; width * LCD_IMAGE_HEIGHT as usize];
+    let padding = (LCD_LINE_HEIGHT - font::CHAR_HEIGHT * LCD_TEXT_SCALE) / 2;
+    for (index, line) in lines.iter().enumerate() {
+        let top = index * LCD_LINE_HEIGHT + padding;
+        font::draw_text(line, LCD_TEXT_SCALE, |x, y| {
+            if x < width {
+                pixels[(top + y) * width + x] = colors.foreground & 0xFFFFFF;
+            }
+        });
+    }
+    encode_png(LCD_IMAGE_WIDTH, LCD_IMAGE_HEIGHT, &pixels)
+}
+
+/// Renders the brain screen's pixels (in `0x00RRGGBB` format, row by row) to a PNG image that
+/// is [`SCREEN_WIDTH`] by [`SCREEN_HEIGHT`] pixels.
+pub fn screen_png(pixels: &[u32]) -> Vec<u8> {
+    encode_png(SCREEN_WIDTH, SCREEN_HEIGHT, pixels)
+}
+
+/// One of the robot's displays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Display {
+    /// The emulated LLEMU (legacy LCD) text display.
+    Lcd,
+    /// The brain screen, as drawn with the `screen_*` API.
+    Screen,
+}
+
+/// The current contents of the LCD and brain screen, rebuilt from simulator events.
+#[derive(Debug, Clone)]
+pub struct Snapshots {
+    lcd_lines: LcdLines,
+    lcd_colors: LcdColors,
+    screen: Vec<u32>,
+}
+
+impl Default for Snapshots {
+    fn default() -> Self {
+        Self {
+            lcd_lines: Default::default(),
+            lcd_colors: LcdColors::default(),
+            screen: vec![0; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize],
+        }
+    }
+}
+
+impl Snapshots {
+    /// Applies an event sent to the simulator interface, returning the display it changed, if
+    /// any.
+    pub fn update(&mut self, event: &SimulatorEvent) -> Option<Display> {
+        match event {
+            SimulatorEvent::LcdUpdated(lines) => self.lcd_lines = lines.clone(),
+            SimulatorEvent::LcdColorsUpdated {
+                foreground,
+                background,
+            } => {
+                self.lcd_colors = LcdColors {
+                    background: *background,
+                    foreground: *foreground,
+                };
+            }
+            SimulatorEvent::LcdShutdown => {
+                self.lcd_lines = Default::default();
+                self.lcd_colors = LcdColors::default();
+            }
+            SimulatorEvent::ScreenUpdated { regions } => {
+                for region in regions.iter().filter(|region| region.width > 0) {
+                    let width = region.width as usize;
+                    let (x, y) = (region.x as usize, region.y as usize);
+                    // regions come from outside the simulator, so anything off the screen or
+                    // past the end of the pixels is ignored
+                    let visible = width.min((SCREEN_WIDTH as usize).saturating_sub(x));
+                    if visible == 0 {
+                        continue;
+                    }
+                    let rows = region
+                        .pixels
+                        .chunks_exact(width)
+                        .take(region.height as usize)
+                        .zip(y..SCREEN_HEIGHT as usize);
+                    for (pixels, row) in rows {
+                        let start = row * SCREEN_WIDTH as usize + x;
+                        self.screen[start..start + visible].copy_from_slice(&pixels[..visible]);
+                    }
+                }
+                return Some(Display::Screen);
+            }
+            _ => return None,
+        }
+        Some(Display::Lcd)
+    }
+
+    /// Renders a display as it currently looks to a PNG image.
+    pub fn render(&self, display: Display) -> Vec<u8> {
+        match display {
+            Display::Lcd => lcd_png(&self.lcd_lines, self.lcd_colors),
+            Display::Screen => screen_png(&self.screen),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pros_simulator_interface::ScreenRegion;
+
+    use super::*;
+
+    fn update_screen(snapshots: &mut Snapshots, region: ScreenRegion) {
+        let event = SimulatorEvent::ScreenUpdated {
+            regions: vec![region],
+        };
+        assert_eq!(snapshots.update(&event), Some(Display::Screen));
+    }
+
+    #[test]
+    fn regions_are_copied_to_the_screen() {
+        let mut snapshots = Snapshots::default();
+        update_screen(
+            &mut snapshots,
+            ScreenRegion {
+                x: 10,
+                y: 20,
+                width: 2,
+                height: 2,
+                pixels: vec![1, 2, 3, 4],
+            },
+        );
+        let width = SCREEN_WIDTH as usize;
+        assert_eq!(snapshots.screen[20 * width + 10..20 * width + 12], [1, 2]);
+        assert_eq!(snapshots.screen[21 * width + 10..21 * width + 12], [3, 4]);
+        assert_eq!(
+            snapshots.screen.iter().filter(|&&pixel| pixel != 0).count(),
+            4
+        );
+    }
+
+    #[test]
+    fn regions_are_clipped_to_the_screen() {
+        let mut snapshots = Snapshots::default();
+        update_screen(
+            &mut snapshots,
+            ScreenRegion {
+                x: SCREEN_WIDTH - 1,
+                y: SCREEN_HEIGHT - 1,
+                width: 2,
+                height: 2,
+                pixels: vec![1, 2, 3, 4],
+            },
+        );
+        assert_eq!(snapshots.screen.last(), Some(&1));
+        assert_eq!(
+            snapshots.screen.iter().filter(|&&pixel| pixel != 0).count(),
+            1
+        );
+
+        update_screen(
+            &mut snapshots,
+            ScreenRegion {
+                x: u32::MAX,
+                y: u32::MAX,
+                width: u32::MAX,
+                height: u32::MAX,
+                pixels: vec![5; 4],
+            },
+        );
+        assert_eq!(
+            snapshots.screen.iter().filter(|&&pixel| pixel != 0).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn malformed_regions_are_ignored() {
+        let mut snapshots = Snapshots::default();
+        // the pixels stop partway through the second row, and there are more rows than the
+        // height says
+        update_screen(
+            &mut snapshots,
+            ScreenRegion {
+                x: 0,
+                y: 0,
+                width: 3,
+                height: 1,
+                pixels: vec![1, 2, 3, 4, 5],
+            },
+        );
+        assert_eq!(snapshots.screen[..4], [1, 2, 3, 0]);
+        assert_eq!(snapshots.screen[SCREEN_WIDTH as usize], 0);
+    }
+}
@@ -0,0 +1,388 @@
+//! A minimal PNG encoder for 8-bit RGB images.
+//!
+//! Image data is compressed with fixed Huffman codes and a simple greedy LZ77 matcher, which
+//! works well for screens that are mostly flat colors and text.
+
+/// The first 8 bytes of every PNG file.
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// How far back a match can start, as allowed by deflate.
+const WINDOW_SIZE: usize = 32 * 1024;
+/// The shortest and longest matches that deflate can encode, in bytes.
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// The size of the table used to find matches, as a power of two.
+const HASH_BITS: u32 = 15;
+
+/// The smallest length for each length code from 257, and how many extra bits follow it.
+#[rustfmt::skip]
+const LENGTH_BASES: [(u16, u8); 29] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0),
+    (11, 1), (13, 1), (15, 1), (17, 1), (19, 2), (23, 2), (27, 2), (31, 2),
+    (35, 3), (43, 3), (51, 3), (59, 3), (67, 4), (83, 4), (99, 4), (115, 4),
+    (131, 5), (163, 5), (195, 5), (227, 5), (258, 0),
+];
+
+/// The smallest distance for each distance code, and how many extra bits follow it.
+#[rustfmt::skip]
+const DISTANCE_BASES: [(u16, u8); 30] = [
+    (1, 0), (2, 0), (3, 0), (4, 0), (5, 1), (7, 1), (9, 2), (13, 2),
+    (17, 3), (25, 3), (33, 4), (49, 4), (65, 5), (97, 5), (129, 6), (193, 6),
+    (257, 7), (385, 7), (513, 8), (769, 8), (1025, 9), (1537, 9), (2049, 10), (3073, 10),
+    (4097, 11), (6145, 11), (8193, 12), (12289, 12), (16385, 13), (24577, 13),
+];
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1, 0);
+    // 5552 is the most bytes that can be summed before `b` could overflow
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+/// Writes values to a byte buffer starting from the least significant bit, as deflate does.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        self.buffer |= value << self.len;
+        self.len += bits;
+        while self.len >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which is stored starting from its most significant bit.
+    fn write_code(&mut self, code: u32, bits: u32) {
+        self.write(code.reverse_bits() >> (32 - bits), bits);
+    }
+
+    fn literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn copy(&mut self, length: usize, distance: usize) {
+        let code = LENGTH_BASES.partition_point(|&(base, _)| base as usize <= length) - 1;
+        let (base, extra) = LENGTH_BASES[code];
+        self.literal(257 + code as u16);
+        self.write((length - base as usize) as u32, extra as u32);
+
+        let code = DISTANCE_BASES.partition_point(|&(base, _)| base as usize <= distance) - 1;
+        let (base, extra) = DISTANCE_BASES[code];
+        self.write_code(code as u32, 5);
+        self.write((distance - base as usize) as u32, extra as u32);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    (value.wrapping_mul(0x9e3779b1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Compresses data into a single deflate block using the fixed Huffman codes.
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // final block, compressed with fixed codes
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    // the most recent position at which each hash of 3 bytes was seen
+    let mut heads = vec![usize::MAX; 1 << HASH_BITS];
+    let mut position = 0;
+    while position < data.len() {
+        let mut length = 0;
+        if position + MIN_MATCH <= data.len() {
+            let key = hash(&data[position..]);
+            let candidate = heads[key];
+            heads[key] = position;
+            if candidate != usize::MAX && position - candidate <= WINDOW_SIZE {
+                let limit = (data.len() - position).min(MAX_MATCH);
+                length = (0..limit)
+                    .take_while(|&i| data[candidate + i] == data[position + i])
+                    .count();
+                if length >= MIN_MATCH {
+                    writer.copy(length, position - candidate);
+                    for start in position + 1..(position + length).min(data.len() - MIN_MATCH + 1) {
+                        heads[hash(&data[start..])] = start;
+                    }
+                }
+            }
+        }
+        if length >= MIN_MATCH {
+            position += length;
+        } else {
+            writer.literal(data[position] as u16);
+            position += 1;
+        }
+    }
+
+    writer.literal(256);
+    writer.finish()
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// Encodes an image as a PNG file. `pixels` are colors in `0x00RRGGBB` format, row by row
+/// from the top left corner; the top byte is ignored.
+///
+/// # Panics
+///
+/// Panics if there are fewer than `width * height` pixels.
+pub fn encode_png(width: u32, height: u32, pixels: &[u32]) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    assert!(
+        pixels.len() >= width * height,
+        "not enough pixels for image"
+    );
+
+    let mut data = Vec::with_capacity((width * 3 + 1) * height);
+    for row in (0..height).map(|y| &pixels[y * width..(y + 1) * width]) {
+        // rows are stored without a filter
+        data.push(0);
+        for pixel in row {
+            data.extend(&pixel.to_be_bytes()[1..]);
+        }
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    zlib.extend(deflate(&data));
+    zlib.extend(adler32(&data).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, default compression, filtering and no interlacing
+    header.extend([8, 2, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads values from a byte buffer starting from the least significant bit.
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        position: usize,
+    }
+
+    impl BitReader<'_> {
+        fn read(&mut self, bits: u32) -> u32 {
+            (0..bits).fold(0, |value, bit| {
+                let byte = self.bytes[self.position / 8];
+                let set = (byte >> (self.position % 8)) & 1;
+                self.position += 1;
+                value | (set as u32) << bit
+            })
+        }
+
+        /// Reads a Huffman code, which is stored starting from its most significant bit.
+        fn read_code(&mut self, bits: u32) -> u32 {
+            (0..bits).fold(0, |code, _| (code << 1) | self.read(1))
+        }
+
+        fn literal(&mut self) -> u16 {
+            let code = self.read_code(7);
+            if code <= 0x17 {
+                return 256 + code as u16;
+            }
+            let code = (code << 1) | self.read(1);
+            match code {
+                0x30..=0xbf => code as u16 - 0x30,
+                0xc0..=0xc7 => 280 + code as u16 - 0xc0,
+                _ => 144 + ((code << 1) | self.read(1)) as u16 - 0x190,
+            }
+        }
+    }
+
+    /// Decompresses a single deflate block compressed with the fixed Huffman codes.
+    fn inflate(bytes: &[u8]) -> Vec<u8> {
+        let mut reader = BitReader { bytes, position: 0 };
+        assert_eq!(reader.read(1), 1, "not the final block");
+        assert_eq!(reader.read(2), 1, "not compressed with fixed codes");
+
+        let mut data = Vec::new();
+        loop {
+            let symbol = reader.literal();
+            match symbol {
+                0..=255 => data.push(symbol as u8),
+                256 => return data,
+                _ => {
+                    let (base, extra) = LENGTH_BASES[symbol as usize - 257];
+                    let length = base as usize + reader.read(extra as u32) as usize;
+                    let (base, extra) = DISTANCE_BASES[reader.read_code(5) as usize];
+                    let distance = base as usize + reader.read(extra as u32) as usize;
+                    for _ in 0..length {
+                        data.push(data[data.len() - distance]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Splits a PNG file into its chunks, checking their CRCs.
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        assert_eq!(png[..8], SIGNATURE);
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (body, crc) = rest[4..].split_at(len + 4);
+            assert_eq!(
+                crc32(body),
+                u32::from_be_bytes(crc[..4].try_into().unwrap())
+            );
+            chunks.push((body[..4].try_into().unwrap(), &body[4..]));
+            rest = &crc[4..];
+        }
+        chunks
+    }
+
+    /// Decodes a PNG written by [`encode_png`] back to its size and pixels.
+    fn decode_png(png: &[u8]) -> (u32, u32, Vec<u32>) {
+        let chunks = chunks(png);
+        let kinds = chunks.iter().map(|(kind, _)| kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+
+        let header = chunks[0].1;
+        let width = u32::from_be_bytes(header[..4].try_into().unwrap());
+        let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+        assert_eq!(header[8..], [8, 2, 0, 0, 0]);
+
+        let zlib = chunks[1].1;
+        assert_eq!(
+            u16::from_be_bytes([zlib[0], zlib[1]]) % 31,
+            0,
+            "bad zlib header"
+        );
+        let (deflated, checksum) = zlib[2..].split_at(zlib.len() - 6);
+        let data = inflate(deflated);
+        assert_eq!(adler32(&data).to_be_bytes(), checksum);
+
+        let row_len = width as usize * 3 + 1;
+        assert_eq!(data.len(), row_len * height as usize);
+        let mut pixels = Vec::new();
+        for row in data.chunks_exact(row_len) {
+            assert_eq!(row[0], 0, "rows should be unfiltered");
+            for rgb in row[1..].chunks_exact(3) {
+                pixels.push(u32::from_be_bytes([0, rgb[0], rgb[1], rgb[2]]));
+            }
+        }
+        (width, height, pixels)
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn round_trip() {
+        // flat colors with a few stripes, so there are long matches as well as literals
+        let (width, height) = (70, 40);
+        let pixels = (0..width * height)
+            .map(|index| match (index % width, index / width) {
+                (x, _) if x % 17 == 3 => 0xff0000,
+                (_, y) if y > 30 => 0x00ff80,
+                (x, y) => (x * 3 + y * 5) % 7 * 0x010101,
+            })
+            .collect::<Vec<_>>();
+        let png = encode_png(width, height, &pixels);
+        assert_eq!(decode_png(&png), (width, height, pixels));
+    }
+
+    #[test]
+    fn top_byte_is_ignored() {
+        let png = encode_png(2, 1, &[0xff123456, 0x80abcdef]);
+        assert_eq!(decode_png(&png), (2, 1, vec![0x123456, 0xabcdef]));
+    }
+
+    #[test]
+    fn long_runs_use_the_whole_window() {
+        // more than a window of identical bytes, then a repeat of data from too far back to
+        // be matched
+        let stripe = (0..200).map(|i| i * 0x010305).collect::<Vec<_>>();
+        let mut pixels = stripe.clone();
+        pixels.extend(vec![0x202020; 12000]);
+        pixels.extend(stripe);
+        let png = encode_png(pixels.len() as u32, 1, &pixels);
+        let (_, _, decoded) = decode_png(&png);
+        let expected = pixels
+            .iter()
+            .map(|pixel| pixel & 0xffffff)
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "not enough pixels")]
+    fn too_few_pixels() {
+        encode_png(2, 2, &[0; 3]);
+    }
+}
@@ -1,5 +1,4 @@
 use std::{
-    path::PathBuf,
     pin::Pin,
     sync::{mpsc::Receiver, Arc, Mutex},
     task::{Context, Poll},
@@ -7,7 +6,9 @@ use std::{
 
 use anyhow::Result;
 use futures::{executor::block_on, FutureExt, Stream};
-use pros_simulator_interface::{SimulatorEvent, SimulatorMessage};
+use pros_simulator_interface::{
+    config::RobotConfig, SimulationSpeed, SimulatorEvent, SimulatorMessage,
+};
 use tokio::{
     sync::{
         mpsc::{self, UnboundedReceiver},
@@ -16,7 +17,7 @@ use tokio::{
     task::JoinHandle,
 };
 
-use crate::simulate;
+use crate::{simulate, RobotCode};
 
 pub struct StreamedSimulatorEvent {
     pub inner: SimulatorEvent,
@@ -25,10 +26,13 @@ pub struct StreamedSimulatorEvent {
 
 /// Start a simulator in a new tokio task and stream the events from it.
 pub fn start_simulator(
-    robot_code: PathBuf,
+    robot_code: impl Into<RobotCode>,
+    robot_config: RobotConfig,
+    speed: SimulationSpeed,
     require_unpause: bool,
     messages: Receiver<SimulatorMessage>,
 ) -> impl Stream<Item = Result<StreamedSimulatorEvent>> {
+    let robot_code = robot_code.into();
     let (tx, rx) = mpsc::unbounded_channel();
 
     SimulatorStream {
@@ -37,7 +41,9 @@ pub fn start_simulator(
         future: tokio::task::spawn_blocking(move || {
             let tx = Arc::new(Mutex::new(tx));
             let res = block_on(simulate(
-                &robot_code,
+                robot_code,
+                robot_config,
+                speed,
                 {
                     let tx = tx.clone();
                     move |inner| {
@@ -61,7 +67,7 @@ pub fn start_simulator(
                 messages,
             ));
             if let Err(e) = res {
-                tx.lock().unwrap().send(Err(e)).unwrap();
+                tx.lock().unwrap().send(Err(e.into())).unwrap();
             }
         }),
     }
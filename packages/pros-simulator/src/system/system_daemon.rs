@@ -3,18 +3,20 @@ use std::{
     time::Duration,
 };
 
-use pros_simulator_interface::{CompetitionPhase, SimulatorMessage};
+use pros_simulator_interface::{CompetitionPhase, SimulatorEvent, SimulatorMessage};
 use pros_sys::{COMPETITION_AUTONOMOUS, COMPETITION_CONNECTED, COMPETITION_DISABLED};
-use tokio::{
-    sync::Mutex,
-    time::{interval, sleep},
-};
+use tokio::sync::Mutex;
 use wasmtime::Caller;
 
-use crate::host::{
-    lcd::Lcd,
-    task::{Task, TaskOptions, TaskState},
-    Host, HostCtx,
+use crate::{
+    host::{
+        adi::EADDRINUSE,
+        lcd::Lcd,
+        screen::Screen,
+        task::{Task, TaskOptions, TaskPool, TaskState},
+        Host, HostCtx,
+    },
+    interface::SimulatorInterface,
 };
 
 enum UserTask {
@@ -49,6 +51,26 @@ async fn spawn_user_code(
         .await
 }
 
+/// Passes a simulator message on to the sensor it's meant for, which was looked up from a
+/// [`DeviceTable`](crate::host::devices::DeviceTable). If there's no such sensor on the port,
+/// the message is ignored with a warning like "Ignoring {ignored} for port {port}, which has no
+/// {sensor_name}".
+fn with_sensor<T>(
+    interface: &SimulatorInterface,
+    sensor: Result<&mut T, i32>,
+    port: u8,
+    ignored: &str,
+    sensor_name: &str,
+    apply: impl FnOnce(&mut T),
+) {
+    match sensor {
+        Ok(sensor) => apply(sensor),
+        Err(_) => interface.send(SimulatorEvent::Warning(format!(
+            "Ignoring {ignored} for port {port}, which has no {sensor_name}"
+        ))),
+    }
+}
+
 async fn do_background_operations(
     caller: &mut Caller<'_, Host>,
     messages: &mut Receiver<SimulatorMessage>,
@@ -68,13 +90,148 @@ async fn do_background_operations(
 
                 Lcd::press(&caller.lcd(), &mut *caller, cb_table, btns).await?;
             }
+            SimulatorMessage::ScreenTouch { x, y, pressed } => {
+                let cb_table = {
+                    let task_handle = caller.current_task().await;
+                    let current_task = task_handle.lock().await;
+                    current_task.indirect_call_table
+                };
+
+                Screen::touch(&caller.screen(), &mut *caller, cb_table, x, y, pressed).await?;
+            }
             SimulatorMessage::PhaseChange(new_phase) => {
                 let mut phase = caller.competition_phase_lock().await;
                 *phase = new_phase;
             }
+            SimulatorMessage::Stop
+            | SimulatorMessage::Pause
+            | SimulatorMessage::Resume
+            | SimulatorMessage::Step { .. } => {
+                // handled by the scheduler
+            }
+            SimulatorMessage::SetRobotPose(new_pose) => {
+                let mut pose = caller.pose_lock().await;
+                pose.update(caller.clock().now());
+                pose.set_pose(new_pose);
+            }
+            SimulatorMessage::SetRobotVelocity(velocity) => {
+                let mut pose = caller.pose_lock().await;
+                pose.update(caller.clock().now());
+                pose.set_velocity(velocity);
+            }
+            SimulatorMessage::AdiInput {
+                port,
+                expander,
+                value,
+            } => {
+                let res = caller.adi_lock().await.set_input(expander, port, value);
+                if let Err(code) = res {
+                    let reason = if code == EADDRINUSE {
+                        "it is configured as an output"
+                    } else {
+                        "it does not exist"
+                    };
+                    caller.interface().send(SimulatorEvent::Warning(format!(
+                        "Ignoring input for three-wire port {port} because {reason}"
+                    )));
+                }
+            }
+            SimulatorMessage::RotationInput {
+                port,
+                position,
+                velocity,
+            } => {
+                let now = caller.clock().now();
+                let mut pose = caller.pose_lock().await;
+                pose.update(now);
+                let mut sensors = caller.rotation_sensors_lock().await;
+                with_sensor(
+                    &caller.interface(),
+                    sensors.get(port.into()),
+                    port,
+                    "input",
+                    "rotation sensor",
+                    |sensor| sensor.set_input(&pose, now, position, velocity),
+                );
+            }
+            SimulatorMessage::DistanceInput { port, reading } => {
+                let mut sensors = caller.distance_sensors_lock().await;
+                with_sensor(
+                    &caller.interface(),
+                    sensors.get(port.into()),
+                    port,
+                    "input",
+                    "distance sensor",
+                    |sensor| sensor.set_input(reading),
+                );
+            }
+            SimulatorMessage::OpticalInput { port, reading } => {
+                let mut sensors = caller.optical_sensors_lock().await;
+                with_sensor(
+                    &caller.interface(),
+                    sensors.get(port.into()),
+                    port,
+                    "input",
+                    "optical sensor",
+                    |sensor| sensor.set_reading(reading),
+                );
+            }
+            SimulatorMessage::OpticalGesture { port, gesture } => {
+                let now = caller.clock().now();
+                let mut sensors = caller.optical_sensors_lock().await;
+                with_sensor(
+                    &caller.interface(),
+                    sensors.get(port.into()),
+                    port,
+                    "gesture",
+                    "optical sensor",
+                    |sensor| sensor.detect_gesture(gesture, now),
+                );
+            }
+            SimulatorMessage::VisionObjects { port, objects } => {
+                let mut sensors = caller.vision_sensors_lock().await;
+                with_sensor(
+                    &caller.interface(),
+                    sensors.get(port.into()),
+                    port,
+                    "objects",
+                    "vision sensor",
+                    |sensor| sensor.set_objects(objects),
+                );
+            }
+            SimulatorMessage::SetBattery {
+                capacity,
+                temperature,
+            } => {
+                let mut battery = caller.battery_lock().await;
+                if let Some(capacity) = capacity {
+                    battery.set_capacity(capacity);
+                }
+                if let Some(temperature) = temperature {
+                    battery.set_temperature(temperature);
+                }
+            }
+            SimulatorMessage::SetSpeed(speed) => {
+                if speed.is_valid() {
+                    caller.tasks_lock().await.set_speed(speed);
+                } else {
+                    caller.interface().send(SimulatorEvent::Warning(format!(
+                        "Ignoring invalid simulation speed: {speed:?}"
+                    )));
+                }
+            }
         }
     }
 
+    let now = caller.clock().now();
+    let mut motors = caller.motors_lock().await;
+    let mut pose = caller.pose_lock().await;
+    let mut physics = caller.physics_lock().await;
+    physics.update(now, &mut motors, &mut pose);
+    physics.read_sensors(&pose, &mut *caller.adi_lock().await);
+    caller.battery_lock().await.update(now, &mut motors);
+    caller.screen_lock().await.flush();
+
     Ok(())
 }
 
@@ -95,12 +252,10 @@ async fn system_daemon_task(
             .await?
     };
 
-    let mut delay = interval(Duration::from_millis(2));
-
     // wait for initialize to finish
     while competition_task.lock().await.state() != TaskState::Finished {
         do_background_operations(&mut caller, &mut messages).await?;
-        sleep(Duration::from_millis(2)).await;
+        TaskPool::sleep(&host, Duration::from_millis(2)).await;
     }
 
     loop {
@@ -131,7 +286,10 @@ async fn system_daemon_task(
                 };
 
             let task = competition_task.lock().await;
-            if task.state() == TaskState::Ready {
+            if matches!(
+                task.state(),
+                TaskState::Ready | TaskState::Blocked | TaskState::Suspended
+            ) {
                 let id = task.id();
                 let mut tasks = caller.tasks_lock().await;
                 tasks.delete_task(id).await;
@@ -141,7 +299,7 @@ async fn system_daemon_task(
             competition_task = spawn_user_code(&mut caller, &host, state).await?;
         }
 
-        delay.tick().await;
+        TaskPool::sleep(&host, Duration::from_millis(2)).await;
     }
 }
 
@@ -154,7 +312,8 @@ pub async fn system_daemon_initialize(
     let daemon = TaskOptions::new_closure(&mut tasks, host, |caller: Caller<'_, Host>| {
         Box::new(system_daemon_task(caller, messages))
     })?
-    .name("PROS System Daemon");
+    .name("PROS System Daemon")
+    .system();
 
     tasks
         .spawn(daemon, &host.module(), &host.interface())